@@ -965,6 +965,8 @@ fn main() -> std::io::Result<()> {
                                         uvs,
                                         None,
                                         None,
+                                        Vec::new(),
+                                        Vec::new(),
                                     ));
                                     for id in 0..mesh.n_triangles {
                                         let triangle =
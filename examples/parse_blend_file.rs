@@ -281,6 +281,8 @@ impl SceneDescriptionBuilder {
             uv,
             None,
             None,
+            Vec::new(),
+            Vec::new(),
         ));
         self.meshes.push(triangle_mesh);
         self.triangle_colors.push(triangle_colors);
@@ -515,6 +517,7 @@ impl RenderOptions {
                             index: index,
                             bump_map: None,
                             remap_roughness: true,
+                            dispersive_eta: None,
                         })));
                         shapes.push(cylinder.clone());
                         shape_materials.push(glass.clone());
@@ -705,6 +708,7 @@ impl RenderOptions {
                             index: index,
                             bump_map: None,
                             remap_roughness: true,
+                            dispersive_eta: None,
                         })));
                         shapes.push(disk.clone());
                         shape_materials.push(glass.clone());
@@ -895,6 +899,7 @@ impl RenderOptions {
                             index: index,
                             bump_map: None,
                             remap_roughness: true,
+                            dispersive_eta: None,
                         })));
                         shapes.push(sphere.clone());
                         shape_materials.push(glass.clone());
@@ -1095,6 +1100,7 @@ impl RenderOptions {
                             index: index,
                             bump_map: None,
                             remap_roughness: true,
+                            dispersive_eta: None,
                         })));
                         for _i in 0..triangles.len() {
                             shape_materials.push(glass.clone());
@@ -12,9 +12,15 @@ use crate::core::material::Material;
 use crate::core::paramset::ParamSet;
 use crate::core::pbrt::Float;
 use crate::core::primitive::Primitive;
+use crate::core::stats::{Prof, ProfilePhase, StatCounter, StatMemoryCounter, StatRatio};
 
 // see bvh.h
 
+static TREE_BYTES: StatMemoryCounter = StatMemoryCounter::new("Memory/BVH tree");
+static TOTAL_PRIMITIVES: StatRatio = StatRatio::new("BVH/Primitives per leaf node");
+static INTERIOR_NODES: StatCounter = StatCounter::new("BVH/Interior nodes");
+static LEAF_NODES: StatCounter = StatCounter::new("BVH/Leaf nodes");
+
 #[derive(Debug, Clone)]
 pub enum SplitMethod {
     SAH,
@@ -70,6 +76,9 @@ impl<'a> BVHBuildNode<'a> {
         self.bounds = *b;
         self.child1 = None;
         self.child2 = None;
+        LEAF_NODES.inc();
+        TOTAL_PRIMITIVES.add_num(n as u64);
+        TOTAL_PRIMITIVES.add_denom(1);
     }
     pub fn init_interior(&mut self, axis: u8, c0: &'a BVHBuildNode<'a>, c1: &'a BVHBuildNode<'a>) {
         self.n_primitives = 0;
@@ -77,6 +86,7 @@ impl<'a> BVHBuildNode<'a> {
         self.child1 = Some(c0);
         self.child2 = Some(c1);
         self.split_axis = axis;
+        INTERIOR_NODES.inc();
     }
 }
 
@@ -155,6 +165,7 @@ impl BVHAccel {
         // println!("{} seconds for building BVH ...", start.to(end));
         // flatten first
         let mut nodes = vec![LinearBVHNode::default(); total_nodes];
+        TREE_BYTES.add(total_nodes * std::mem::size_of::<LinearBVHNode>());
         let mut offset: usize = 0;
         // println!("BVHAccel::flatten_bvh_tree(...)");
         // let start = PreciseTime::now();
@@ -424,7 +435,7 @@ impl BVHAccel {
         if self.nodes.is_empty() {
             return false;
         }
-        // TODO: ProfilePhase p(Prof::AccelIntersect);
+        let _prof = ProfilePhase::new(Prof::AccelIntersect);
         let mut hit: bool = false;
         let inv_dir: Vector3f = Vector3f {
             x: 1.0 / ray.d.x,
@@ -486,7 +497,7 @@ impl BVHAccel {
         if self.nodes.is_empty() {
             return false;
         }
-        // TODO: ProfilePhase p(Prof::AccelIntersectP);
+        let _prof = ProfilePhase::new(Prof::AccelIntersectP);
         let inv_dir: Vector3f = Vector3f {
             x: 1.0 / ray.d.x,
             y: 1.0 / ray.d.y,
@@ -10,6 +10,7 @@ use crate::core::paramset::ParamSet;
 use crate::core::pbrt::log_2_int_i32;
 use crate::core::pbrt::Float;
 use crate::core::primitive::Primitive;
+use crate::core::stats::{Prof, ProfilePhase};
 
 pub const MAX_TODO: usize = 64;
 
@@ -501,7 +502,7 @@ impl KdTreeAccel {
         self.bounds
     }
     pub fn intersect(&self, ray: &Ray, isect: &mut SurfaceInteraction) -> bool {
-        // TODO: ProfilePhase p(Prof::AccelIntersect);
+        let _prof = ProfilePhase::new(Prof::AccelIntersect);
         if self.nodes.is_empty() {
             return false;
         }
@@ -619,7 +620,7 @@ impl KdTreeAccel {
         hit
     }
     pub fn intersect_p(&self, ray: &Ray) -> bool {
-        // TODO: ProfilePhase p(Prof::AccelIntersectP);
+        let _prof = ProfilePhase::new(Prof::AccelIntersectP);
         if self.nodes.is_empty() {
             return false;
         }
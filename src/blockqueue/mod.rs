@@ -51,6 +51,14 @@ impl BlockQueue {
             next: AtomicUsize::new(0),
         }
     }
+    /// Only keep the blocks for which `f` returns true, e.g. blocks
+    /// which need more samples
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&(u32, u32)) -> bool,
+    {
+        self.blocks.retain(f);
+    }
     /// Get the dimensions of an individual block in the queue
     pub fn block_dim(&self) -> (u32, u32) {
         self.dimensions
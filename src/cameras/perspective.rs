@@ -16,6 +16,7 @@ use crate::core::paramset::ParamSet;
 use crate::core::pbrt::lerp;
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::sampling::concentric_sample_disk;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::core::transform::{AnimatedTransform, Transform};
 
 // see perspective.h
@@ -188,7 +189,7 @@ impl PerspectiveCamera {
     }
     // Camera
     pub fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
-        // TODO: ProfilePhase prof(Prof::GenerateCameraRay);
+        let _prof = ProfilePhase::new(Prof::GenerateCameraRay);
         // compute raster and camera sample positions
         let p_film: Point3f = Point3f {
             x: sample.p_film.x,
@@ -19,11 +19,15 @@ use crate::core::medium::Medium;
 use crate::core::paramset::ParamSet;
 use crate::core::pbrt::{lerp, quadratic};
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progress::ProgressReporter;
 use crate::core::reflection::refract;
+use crate::core::stats::{Prof, ProfilePhase, StatPercent};
 use crate::core::transform::{AnimatedTransform, Transform};
 
 // see realistic.h
 
+static VIGNETTED_RAYS: StatPercent = StatPercent::new("Camera/Rays vignetted by lens system");
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct LensElementInterface {
     pub curvature_radius: Float,
@@ -103,15 +107,15 @@ impl RealisticCamera {
         exit_pupil_bounds.resize(n_samples, Bounds2f::default());
         let num_cores: usize = num_cpus::get();
         let chunk_size: usize = n_samples / num_cores;
+        let progress = ProgressReporter::new(n_samples as u64, "Computing exit pupil bounds");
         {
             let bands: Vec<&mut [Bounds2f]> = exit_pupil_bounds.chunks_mut(chunk_size).collect();
             let camera = &camera;
             let film = &film;
+            let progress = &progress;
             crossbeam::scope(|scope| {
-                let (band_tx, band_rx) = crossbeam_channel::bounded(num_cores);
                 // spawn worker threads
                 for (b, band) in bands.into_iter().enumerate() {
-                    let band_tx = band_tx.clone();
                     scope.spawn(move |_| {
                         for (index, bound) in band.iter_mut().enumerate() {
                             let i: usize = (b * chunk_size) + index;
@@ -120,22 +124,14 @@ impl RealisticCamera {
                             let r1: Float = (i + 1) as Float / n_samples as Float * film.diagonal
                                 / 2.0 as Float;
                             *bound = camera.bound_exit_pupil(r0, r1);
+                            progress.update(1);
                         }
                     });
-                    // send progress through the channel to main thread
-                    band_tx
-                        .send(b)
-                        .unwrap_or_else(|_| panic!("Failed to send progress"));
                 }
-                // spawn thread to report progress
-                scope.spawn(move |_| {
-                    for _ in pbr::PbIter::new(0..num_cores) {
-                        band_rx.recv().unwrap();
-                    }
-                });
             })
             .unwrap();
         }
+        progress.done();
         camera.exit_pupil_bounds = exit_pupil_bounds;
         if camera.simple_weighting {
             println!("WARNING: \"simpleweighting\" option with RealisticCamera no longer necessarily matches regular camera images. Further, pixel values will vary a bit depending on the aperture size. See this discussion for details: https://github.com/mmp/pbrt-v3/issues/162#issuecomment-348625837");
@@ -196,8 +192,8 @@ impl RealisticCamera {
         ))))
     }
     pub fn generate_ray(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
-        // TODO: ProfilePhase prof(Prof::GenerateCameraRay);
-        // ++totalRays;
+        let _prof = ProfilePhase::new(Prof::GenerateCameraRay);
+        VIGNETTED_RAYS.inc_denom();
         // find point on film, _p_film_, corresponding to _sample.p_film_
         let s: Point2f = Point2f {
             x: sample.p_film.x / self.film.full_resolution.x as Float,
@@ -225,7 +221,7 @@ impl RealisticCamera {
         r_film.t_max = Cell::new(std::f32::INFINITY);
         r_film.time = lerp(sample.time, self.shutter_open, self.shutter_close);
         if !self.trace_lenses_from_film(&r_film, Some(ray)) {
-            // ++vignettedRays;
+            VIGNETTED_RAYS.inc_num();
             return 0.0 as Float;
         }
         // finish initialization of _RealisticCamera_ ray
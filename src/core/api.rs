@@ -27,12 +27,15 @@ use crate::core::medium::{Medium, MediumInterface};
 use crate::core::mipmap::ImageWrap;
 use crate::core::paramset::{ParamSet, TextureParams};
 use crate::core::pbrt::lerp;
-use crate::core::pbrt::{Float, Spectrum};
+use crate::core::pbrt::{Float, Options, Spectrum};
 use crate::core::primitive::{GeometricPrimitive, Primitive, TransformedPrimitive};
+use crate::core::progress::set_quiet;
 use crate::core::reflection::FourierBSDFTable;
 use crate::core::sampler::Sampler;
 use crate::core::scene::Scene;
+use crate::core::scenewriter::SceneWriter;
 use crate::core::shape::Shape;
+use crate::core::stats::StatCounter;
 use crate::core::texture::{
     CylindricalMapping2D, IdentityMapping3D, PlanarMapping2D, SphericalMapping2D, Texture,
     TextureMapping2D, TextureMapping3D, UVMapping2D,
@@ -73,22 +76,28 @@ use crate::materials::translucent::TranslucentMaterial;
 use crate::materials::uber::UberMaterial;
 use crate::media::grid::GridDensityMedium;
 use crate::media::homogeneous::HomogeneousMedium;
+use crate::parser::ParseWarning;
 use crate::samplers::halton::HaltonSampler;
 use crate::samplers::maxmin::MaxMinDistSampler;
 use crate::samplers::random::RandomSampler;
 use crate::samplers::sobol::SobolSampler;
 use crate::samplers::stratified::StratifiedSampler;
 use crate::samplers::zerotwosequence::ZeroTwoSequenceSampler;
+use crate::shapes::cone::Cone;
 use crate::shapes::curve::create_curve_shape;
 use crate::shapes::cylinder::Cylinder;
 use crate::shapes::disk::Disk;
+use crate::shapes::heightfield::create_heightfield;
+use crate::shapes::hyperboloid::Hyperboloid;
 use crate::shapes::loopsubdiv::loop_subdivide;
 use crate::shapes::nurbs::nurbs_evaluate_surface;
 use crate::shapes::nurbs::Homogeneous3;
+use crate::shapes::paraboloid::Paraboloid;
 use crate::shapes::plymesh::create_ply_mesh;
 use crate::shapes::sphere::Sphere;
-use crate::shapes::triangle::{Triangle, TriangleMesh};
-use crate::textures::checkerboard::Checkerboard2DTexture;
+use crate::shapes::triangle::{get_alpha_texture, Triangle, TriangleMesh, FACE_UVS_TRIANGLE};
+use crate::textures::bilerp::BilerpTexture;
+use crate::textures::checkerboard::{AAMethod, Checkerboard2DTexture, Checkerboard3DTexture};
 use crate::textures::constant::ConstantTexture;
 use crate::textures::dots::DotsTexture;
 use crate::textures::fbm::FBmTexture;
@@ -96,12 +105,16 @@ use crate::textures::imagemap::ImageTexture;
 use crate::textures::imagemap::{convert_to_float, convert_to_spectrum};
 use crate::textures::marble::MarbleTexture;
 use crate::textures::mix::MixTexture;
+use crate::textures::ptex::PtexTexture;
 use crate::textures::scale::ScaleTexture;
+use crate::textures::uv::UVTexture;
 use crate::textures::windy::WindyTexture;
 use crate::textures::wrinkled::WrinkledTexture;
 
 // see api.cpp
 
+static N_OBJECT_INSTANCES_USED: StatCounter = StatCounter::new("Scene/Object instances used");
+
 pub struct BsdfState {
     pub loaded_bsdfs: HashMap<String, Arc<FourierBSDFTable>>,
 }
@@ -115,23 +128,30 @@ impl Default for BsdfState {
 }
 
 pub struct ApiState {
-    number_of_threads: u8,
+    options: Options,
     pub search_directory: Option<Box<PathBuf>>,
     cur_transform: TransformSet,
     active_transform_bits: u8,
-    named_coordinate_systems: HashMap<&'static str, TransformSet>,
+    named_coordinate_systems: HashMap<String, TransformSet>,
     render_options: RenderOptions,
     graphics_state: GraphicsState,
     pushed_graphics_states: Vec<GraphicsState>,
     pushed_transforms: Vec<TransformSet>,
     pushed_active_transform_bits: Vec<u8>,
     param_set: ParamSet,
+    // object and material IDs (see Aov::ObjectId and Aov::MaterialId)
+    n_shapes: u32,
+    material_ids: HashMap<usize, u32>,
+    // writes the scene instead of rendering it (--cat and --toply)
+    scene_writer: Option<SceneWriter>,
+    // e.g. unused parameters, collected by the parser
+    warnings: Vec<ParseWarning>,
 }
 
 impl Default for ApiState {
     fn default() -> Self {
         ApiState {
-            number_of_threads: 0_u8,
+            options: Options::default(),
             search_directory: None,
             cur_transform: TransformSet {
                 t: [Transform {
@@ -161,6 +181,41 @@ impl Default for ApiState {
             pushed_transforms: Vec::new(),
             pushed_active_transform_bits: Vec::new(),
             param_set: ParamSet::default(),
+            n_shapes: 0_u32,
+            material_ids: HashMap::new(),
+            scene_writer: None,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl ApiState {
+    /// Returns true if the scene gets written instead of rendered.
+    pub fn writes_scene(&self) -> bool {
+        self.scene_writer.is_some()
+    }
+    /// Returns (and forgets) the warnings of the API calls so far,
+    /// which were already printed to standard error.
+    pub fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        std::mem::take(&mut self.warnings)
+    }
+    /// Reports a problem with the current statement (e.g. a shape
+    /// which gets skipped) like an unused parameter.
+    fn warning(&mut self, message: String) {
+        let (file, line, column) = self.param_set.location.clone().unwrap_or_default();
+        self.add_warnings(vec![ParseWarning {
+            file,
+            line,
+            column,
+            message,
+        }]);
+    }
+    fn add_warnings(&mut self, mut warnings: Vec<ParseWarning>) {
+        // in the order of the statements they refer to
+        warnings.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+        for warning in warnings {
+            eprintln!("{}", warning);
+            self.warnings.push(warning);
         }
     }
 }
@@ -420,7 +475,6 @@ impl RenderOptions {
                     let radius: Float = self
                         .integrator_params
                         .find_one_float("radius", 1.0 as Float);
-                    // TODO: if (PbrtOptions.quickRender) nIterations = std::max(1, nIterations / 16);
                     let integrator = Box::new(Integrator::SPPM(SPPMIntegrator::new(
                         camera,
                         n_iterations,
@@ -441,6 +495,17 @@ impl RenderOptions {
         }
         some_integrator
     }
+    /// Reports the unused parameters of the render options, after the
+    /// integrator and the scene were created.
+    pub fn report_unused(&self) -> Vec<ParseWarning> {
+        let mut warnings: Vec<ParseWarning> = self.filter_params.report_unused();
+        warnings.append(&mut self.film_params.report_unused());
+        warnings.append(&mut self.camera_params.report_unused());
+        warnings.append(&mut self.sampler_params.report_unused());
+        warnings.append(&mut self.integrator_params.report_unused());
+        warnings.append(&mut self.accelerator_params.report_unused());
+        warnings
+    }
     pub fn make_scene(&self) -> Scene {
         let some_accelerator = make_accelerator(
             &self.accelerator_name,
@@ -453,6 +518,136 @@ impl RenderOptions {
             panic!("Unable to create accelerator.");
         }
     }
+    pub fn apply_options(&mut self, options: &Options) {
+        // Film
+        if let Some(ref image_file) = options.image_file {
+            let filename: String = self.film_params.find_one_string("filename", String::new());
+            if !filename.is_empty() {
+                println!(
+                    "WARNING: Output filename supplied on command line, \"{}\" is overriding filename provided in scene description file, \"{}\".",
+                    image_file, filename
+                );
+            }
+            self.film_params.erase_string(String::from("filename"));
+            self.film_params
+                .add_string(String::from("filename"), image_file.clone());
+        }
+        if let Some(crop_window) = options.crop_window {
+            self.film_params.erase_float(String::from("cropwindow"));
+            self.film_params
+                .add_floats(String::from("cropwindow"), crop_window.to_vec());
+        }
+        if options.denoise {
+            self.film_params.erase_bool(String::from("denoise"));
+            self.film_params.add_bool(String::from("denoise"), true);
+        }
+        if let Some(ref checkpoint) = options.checkpoint {
+            self.film_params.erase_string(String::from("checkpoint"));
+            self.film_params
+                .add_string(String::from("checkpoint"), checkpoint.clone());
+        }
+        if let Some(checkpoint_interval) = options.checkpoint_interval {
+            self.film_params
+                .erase_float(String::from("checkpointinterval"));
+            self.film_params
+                .add_float(String::from("checkpointinterval"), checkpoint_interval);
+        }
+        if options.resume {
+            self.film_params.erase_bool(String::from("resume"));
+            self.film_params.add_bool(String::from("resume"), true);
+        }
+        if options.progressive {
+            self.film_params.erase_bool(String::from("progressive"));
+            self.film_params.add_bool(String::from("progressive"), true);
+        }
+        if let Some(time_limit) = options.time_limit {
+            self.film_params.erase_float(String::from("timelimit"));
+            self.film_params
+                .add_float(String::from("timelimit"), time_limit);
+        }
+        if options.quick_render {
+            let xres: i32 = self.film_params.find_one_int("xresolution", 1280);
+            let yres: i32 = self.film_params.find_one_int("yresolution", 720);
+            self.film_params.erase_int(String::from("xresolution"));
+            self.film_params.erase_int(String::from("yresolution"));
+            self.film_params
+                .add_int(String::from("xresolution"), std::cmp::max(1, xres / 4));
+            self.film_params
+                .add_int(String::from("yresolution"), std::cmp::max(1, yres / 4));
+        }
+        // Sampler
+        let some_pixel_samples: Option<i32> = if options.pixel_samples.is_some() {
+            options.pixel_samples
+        } else if options.quick_render {
+            Some(1)
+        } else {
+            None
+        };
+        if let Some(pixel_samples) = some_pixel_samples {
+            if self.sampler_name == "stratified" {
+                // factor the sample count into xsamples * ysamples
+                let mut div: i32 = (pixel_samples as Float).sqrt() as i32;
+                while div > 1 && pixel_samples % div != 0 {
+                    div -= 1;
+                }
+                let ysamples: i32 = pixel_samples / std::cmp::max(1, div);
+                let xsamples: i32 = pixel_samples / std::cmp::max(1, ysamples);
+                self.sampler_params.erase_int(String::from("xsamples"));
+                self.sampler_params.erase_int(String::from("ysamples"));
+                self.sampler_params
+                    .add_int(String::from("xsamples"), std::cmp::max(1, xsamples));
+                self.sampler_params
+                    .add_int(String::from("ysamples"), std::cmp::max(1, ysamples));
+            } else {
+                self.sampler_params.erase_int(String::from("pixelsamples"));
+                self.sampler_params
+                    .add_int(String::from("pixelsamples"), pixel_samples);
+            }
+        }
+        if let Some(seed) = options.seed {
+            self.sampler_params.erase_int(String::from("seed"));
+            self.sampler_params.add_int(String::from("seed"), seed);
+        }
+        if let Some(noise_threshold) = options.noise_threshold {
+            if self.sampler_name != "halton" && self.sampler_name != "sobol" {
+                println!(
+                    "WARNING: Adaptive sampling needs the \"halton\" or \"sobol\" sampler, not \"{}\".",
+                    self.sampler_name
+                );
+            }
+            self.sampler_params
+                .erase_float(String::from("noisethreshold"));
+            self.sampler_params
+                .add_float(String::from("noisethreshold"), noise_threshold);
+        }
+        // Integrator
+        if options.quick_render {
+            if self.integrator_name == "sppm" {
+                let mut n_iterations: i32 =
+                    self.integrator_params.find_one_int("numiterations", 64);
+                n_iterations = self
+                    .integrator_params
+                    .find_one_int("iterations", n_iterations);
+                self.integrator_params
+                    .erase_int(String::from("numiterations"));
+                self.integrator_params.erase_int(String::from("iterations"));
+                self.integrator_params.add_int(
+                    String::from("iterations"),
+                    std::cmp::max(1, n_iterations / 16),
+                );
+            } else if self.integrator_name == "mlt" {
+                let mutations_per_pixel: i32 = self
+                    .integrator_params
+                    .find_one_int("mutationsperpixel", 100);
+                self.integrator_params
+                    .erase_int(String::from("mutationsperpixel"));
+                self.integrator_params.add_int(
+                    String::from("mutationsperpixel"),
+                    std::cmp::max(1, mutations_per_pixel / 16),
+                );
+            }
+        }
+    }
     pub fn make_camera(&self) -> Option<Arc<Camera>> {
         let mut some_camera: Option<Arc<Camera>> = None;
         let some_filter = make_filter(&self.filter_name, &self.filter_params);
@@ -595,14 +790,6 @@ impl GraphicsState {
 
 fn create_material(api_state: &ApiState, bsdf_state: &mut BsdfState) -> Option<Arc<Material>> {
     // CreateMaterial
-    let mut material_params = ParamSet::default();
-    material_params.copy_from(&api_state.graphics_state.material_params);
-    let mut mp: TextureParams = TextureParams {
-        float_textures: api_state.graphics_state.float_textures.clone(),
-        spectrum_textures: api_state.graphics_state.spectrum_textures.clone(),
-        geom_params: ParamSet::default(),
-        material_params,
-    };
     if api_state.graphics_state.current_material != String::new() {
         match api_state
             .graphics_state
@@ -620,70 +807,94 @@ fn create_material(api_state: &ApiState, bsdf_state: &mut BsdfState) -> Option<A
             }
         }
     } else {
-        // MakeMaterial
-        if api_state.graphics_state.material == "" || api_state.graphics_state.material == "none" {
-            return None;
-        } else if api_state.graphics_state.material == "matte" {
-            return Some(MatteMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "plastic" {
-            return Some(PlasticMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "translucent" {
-            return Some(TranslucentMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "glass" {
-            return Some(GlassMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "mirror" {
-            return Some(MirrorMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "hair" {
-            return Some(HairMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "mix" {
-            let m1: String = mp.find_string("namedmaterial1", String::from(""));
-            let m2: String = mp.find_string("namedmaterial2", String::from(""));
-            let mat1 = match api_state.graphics_state.named_materials.get(&m1) {
-                Some(named_material) => named_material,
-                None => {
-                    panic!("Material \"{}\" unknown.", m1);
-                }
-            };
-            let mat2 = match api_state.graphics_state.named_materials.get(&m2) {
-                Some(named_material) => named_material,
-                None => {
-                    panic!("Material \"{}\" unknown.", m2);
-                }
-            };
-            let scale: Arc<dyn Texture<Spectrum> + Send + Sync> =
-                mp.get_spectrum_texture("amount", Spectrum::new(0.5));
-            if let Some(m1) = mat1 {
-                if let Some(m2) = mat2 {
-                    let mix = Arc::new(Material::Mix(Box::new(MixMaterial::new(
-                        m1.clone(),
-                        m2.clone(),
-                        scale,
-                    ))));
-                    return Some(mix);
-                }
+        let mut material_params = ParamSet::default();
+        material_params.copy_from(&api_state.graphics_state.material_params);
+        let mut mp: TextureParams = TextureParams {
+            float_textures: api_state.graphics_state.float_textures.clone(),
+            spectrum_textures: api_state.graphics_state.spectrum_textures.clone(),
+            geom_params: ParamSet::default(),
+            material_params,
+        };
+        // the copied parameters share their lookups with the material
+        // statement, see report_unused_shape_params()
+        return make_material(api_state, bsdf_state, &mut mp);
+    }
+    default_material()
+}
+
+fn make_material(
+    api_state: &ApiState,
+    bsdf_state: &mut BsdfState,
+    mp: &mut TextureParams,
+) -> Option<Arc<Material>> {
+    // MakeMaterial
+    if api_state.graphics_state.material == "" || api_state.graphics_state.material == "none" {
+        None
+    } else if api_state.graphics_state.material == "matte" {
+        Some(MatteMaterial::create(mp))
+    } else if api_state.graphics_state.material == "plastic" {
+        Some(PlasticMaterial::create(mp))
+    } else if api_state.graphics_state.material == "translucent" {
+        Some(TranslucentMaterial::create(mp))
+    } else if api_state.graphics_state.material == "glass" {
+        Some(GlassMaterial::create(mp))
+    } else if api_state.graphics_state.material == "mirror" {
+        Some(MirrorMaterial::create(mp))
+    } else if api_state.graphics_state.material == "hair" {
+        Some(HairMaterial::create(mp))
+    } else if api_state.graphics_state.material == "mix" {
+        let m1: String = mp.find_string("namedmaterial1", String::from(""));
+        let m2: String = mp.find_string("namedmaterial2", String::from(""));
+        let mat1 = match api_state.graphics_state.named_materials.get(&m1) {
+            Some(named_material) => named_material,
+            None => {
+                panic!("Material \"{}\" unknown.", m1);
+            }
+        };
+        let mat2 = match api_state.graphics_state.named_materials.get(&m2) {
+            Some(named_material) => named_material,
+            None => {
+                panic!("Material \"{}\" unknown.", m2);
+            }
+        };
+        let scale: Arc<dyn Texture<Spectrum> + Send + Sync> =
+            mp.get_spectrum_texture("amount", Spectrum::new(0.5));
+        if let Some(m1) = mat1 {
+            if let Some(m2) = mat2 {
+                let mix = Arc::new(Material::Mix(Box::new(MixMaterial::new(
+                    m1.clone(),
+                    m2.clone(),
+                    scale,
+                ))));
+                return Some(mix);
             }
-            return None;
-        } else if api_state.graphics_state.material == "metal" {
-            return Some(MetalMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "substrate" {
-            return Some(SubstrateMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "uber" {
-            return Some(UberMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "subsurface" {
-            return Some(SubsurfaceMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "kdsubsurface" {
-            println!("TODO: CreateKdsubsurfaceMaterial");
-        } else if api_state.graphics_state.material == "fourier" {
-            return Some(FourierMaterial::create(&mut mp, bsdf_state));
-        } else if api_state.graphics_state.material == "disney" {
-            return Some(DisneyMaterial::create(&mut mp));
-        } else {
-            panic!(
-                "Material \"{}\" unknown.",
-                api_state.graphics_state.material
-            );
         }
+        None
+    } else if api_state.graphics_state.material == "metal" {
+        Some(MetalMaterial::create(mp))
+    } else if api_state.graphics_state.material == "substrate" {
+        Some(SubstrateMaterial::create(mp))
+    } else if api_state.graphics_state.material == "uber" {
+        Some(UberMaterial::create(mp))
+    } else if api_state.graphics_state.material == "subsurface" {
+        Some(SubsurfaceMaterial::create(mp))
+    } else if api_state.graphics_state.material == "kdsubsurface" {
+        println!("TODO: CreateKdsubsurfaceMaterial");
+        default_material()
+    } else if api_state.graphics_state.material == "fourier" {
+        Some(FourierMaterial::create(mp, bsdf_state))
+    } else if api_state.graphics_state.material == "disney" {
+        Some(DisneyMaterial::create(mp))
+    } else {
+        panic!(
+            "Material \"{}\" unknown.",
+            api_state.graphics_state.material
+        );
     }
+}
+
+/// The material of shapes without a Material statement (gray matte).
+pub fn default_material() -> Option<Arc<Material>> {
     let kd = Arc::new(ConstantTexture::new(Spectrum::new(0.5)));
     let sigma = Arc::new(ConstantTexture::new(0.0 as Float));
     Some(Arc::new(Material::Matte(Box::new(MatteMaterial::new(
@@ -701,7 +912,8 @@ fn create_medium_interface(api_state: &ApiState) -> MediumInterface {
         {
             Some(inside_medium_arc) => m.inside = Some(inside_medium_arc.clone()),
             None => {
-                panic!(
+                // continue with vacuum (like pbrt)
+                println!(
                     "ERROR: Named medium \"{}\" undefined.",
                     api_state.graphics_state.current_inside_medium
                 );
@@ -716,7 +928,8 @@ fn create_medium_interface(api_state: &ApiState) -> MediumInterface {
         {
             Some(outside_medium_arc) => m.outside = Some(outside_medium_arc.clone()),
             None => {
-                panic!(
+                // continue with vacuum (like pbrt)
+                println!(
                     "ERROR: Named medium \"{}\" undefined.",
                     api_state.graphics_state.current_outside_medium
                 );
@@ -894,8 +1107,10 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
                 texmap = String::from(path_buf.to_str().unwrap());
             }
         }
-        let n_samples: i32 = api_state.param_set.find_one_int("nsamples", 1 as i32);
-        // TODO: if (PbrtOptions.quickRender) nSamples = std::max(1, nSamples / 4);
+        let mut n_samples: i32 = api_state.param_set.find_one_int("nsamples", 1 as i32);
+        if api_state.options.quick_render {
+            n_samples = std::cmp::max(1, n_samples / 4);
+        }
 
         // return std::make_shared<InfiniteAreaLight>(light2world, L * sc, nSamples, texmap);
         let infinte_light = Arc::new(Light::InfiniteArea(Box::new(InfiniteAreaLight::new(
@@ -913,7 +1128,23 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
 fn make_medium(api_state: &mut ApiState) {
     let medium_type: String = api_state.param_set.find_one_string("type", String::new());
     if medium_type == "" {
-        panic!("ERROR: No parameter string \"type\" found in MakeNamedMedium");
+        println!("ERROR: No parameter string \"type\" found in MakeNamedMedium");
+        return;
+    }
+    if api_state.cur_transform.is_animated() {
+        println!(
+            "WARNING: Animated transformation set; ignoring for \"MakeNamedMedium\" and using the start transform only"
+        );
+    }
+    if api_state
+        .render_options
+        .named_media
+        .contains_key(&api_state.param_set.name)
+    {
+        println!(
+            "WARNING: Named medium \"{}\" redefined.",
+            api_state.param_set.name
+        );
     }
     // MakeMedium (api.cpp:685)
     let sig_a_rgb: [Float; 3] = [0.0011, 0.0024, 0.014];
@@ -986,7 +1217,8 @@ fn make_medium(api_state: &mut ApiState) {
             }
         }
     } else {
-        panic!("MakeMedium: unknown name {}", medium_type);
+        println!("WARNING: Medium \"{}\" unknown.", medium_type);
+        some_medium = None;
     }
     if let Some(medium) = some_medium {
         api_state
@@ -996,6 +1228,80 @@ fn make_medium(api_state: &mut ApiState) {
     }
 }
 
+/// Creates the 2D texture mapping selected by the "mapping" parameter
+/// ("uv", "spherical", "cylindrical" or "planar") of a texture.
+fn make_texture_mapping_2d(
+    tp: &mut TextureParams,
+    tex_2_world: &Transform,
+) -> Box<TextureMapping2D> {
+    let mapping: String = tp.find_string("mapping", String::from("uv"));
+    if mapping == "uv" {
+        let su: Float = tp.find_float("uscale", 1.0);
+        let sv: Float = tp.find_float("vscale", 1.0);
+        let du: Float = tp.find_float("udelta", 0.0);
+        let dv: Float = tp.find_float("vdelta", 0.0);
+        Box::new(TextureMapping2D::UV(UVMapping2D { su, sv, du, dv }))
+    } else if mapping == "spherical" {
+        Box::new(TextureMapping2D::Spherical(SphericalMapping2D::new(
+            *tex_2_world,
+        )))
+    } else if mapping == "cylindrical" {
+        Box::new(TextureMapping2D::Cylindrical(CylindricalMapping2D::new(
+            *tex_2_world,
+        )))
+    } else if mapping == "planar" {
+        Box::new(TextureMapping2D::Planar(PlanarMapping2D {
+            vs: tp.find_vector3f(
+                "v1",
+                Vector3f {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+            ),
+            vt: tp.find_vector3f(
+                "v2",
+                Vector3f {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0,
+                },
+            ),
+            ds: tp.find_float("udelta", 0.0),
+            dt: tp.find_float("vdelta", 0.0),
+        }))
+    } else {
+        panic!("2D texture mapping \"{}\" unknown", mapping);
+    }
+}
+
+fn get_checkerboard_aa_method(tp: &mut TextureParams) -> AAMethod {
+    let aa: String = tp.find_string("aamode", String::from("closedform"));
+    if aa == "none" {
+        AAMethod::None
+    } else if aa == "closedform" {
+        AAMethod::ClosedForm
+    } else {
+        println!(
+            "WARNING: Antialiasing mode \"{}\" not understood by Checkerboard2DTexture; using \"closedform\"",
+            aa
+        );
+        AAMethod::ClosedForm
+    }
+}
+
+fn resolve_texture_filename(api_state: &ApiState, tp: &mut TextureParams) -> String {
+    let mut filename: String = tp.find_filename("filename", String::new());
+    if let Some(ref search_directory) = api_state.search_directory {
+        // filename = AbsolutePath(ResolveFilename(filename));
+        let mut path_buf: PathBuf = PathBuf::from("/");
+        path_buf.push(search_directory.as_ref());
+        path_buf.push(filename);
+        filename = String::from(path_buf.to_str().unwrap());
+    }
+    filename
+}
+
 fn make_texture(api_state: &mut ApiState) {
     // pbrtTexture (api.cpp:1049)
     let mut geom_params: ParamSet = ParamSet::default();
@@ -1040,56 +1346,22 @@ fn make_texture(api_state: &mut ApiState) {
             Arc::make_mut(&mut api_state.graphics_state.float_textures)
                 .insert(api_state.param_set.name.clone(), mt);
         } else if api_state.param_set.tex_name == "bilerp" {
-            println!("TODO: CreateBilerpFloatTexture");
+            // CreateBilerpFloatTexture
+            let mapping: Box<TextureMapping2D> =
+                make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
+            let ft = Arc::new(BilerpTexture::<Float>::new(
+                mapping,
+                tp.find_float("v00", 0.0 as Float),
+                tp.find_float("v01", 1.0 as Float),
+                tp.find_float("v10", 0.0 as Float),
+                tp.find_float("v11", 1.0 as Float),
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.float_textures)
+                .insert(api_state.param_set.name.clone(), ft);
         } else if api_state.param_set.tex_name == "imagemap" {
             // CreateImageFloatTexture
-            let map: Option<Box<TextureMapping2D>>;
-            let mapping: String = tp.find_string("mapping", String::from("uv"));
-            if mapping == "uv" {
-                let su: Float = tp.find_float("uscale", 1.0);
-                let sv: Float = tp.find_float("vscale", 1.0);
-                let du: Float = tp.find_float("udelta", 0.0);
-                let dv: Float = tp.find_float("vdelta", 0.0);
-                map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                    su,
-                    sv,
-                    du,
-                    dv,
-                })));
-            } else if mapping == "spherical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Spherical(
-                    SphericalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "cylindrical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Cylindrical(
-                    CylindricalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "planar" {
-                map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                    vs: tp.find_vector3f(
-                        "v1",
-                        Vector3f {
-                            x: 1.0,
-                            y: 0.0,
-                            z: 0.0,
-                        },
-                    ),
-                    vt: tp.find_vector3f(
-                        "v2",
-                        Vector3f {
-                            x: 0.0,
-                            y: 1.0,
-                            z: 0.0,
-                        },
-                    ),
-                    ds: tp.find_float("udelta", 0.0),
-                    dt: tp.find_float("vdelta", 0.0),
-                })));
-            } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
-            }
+            let mapping: Box<TextureMapping2D> =
+                make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
             // initialize _ImageTexture_ parameters
             let max_aniso: Float = tp.find_float("maxanisotropy", 8.0);
             let do_trilinear: bool = tp.find_bool("trilinear", false);
@@ -1116,82 +1388,62 @@ fn make_texture(api_state: &mut ApiState) {
             // ".png"));
             let gamma: bool = tp.find_bool("gamma", true);
 
-            if let Some(mapping) = map {
-                let ft = Arc::new(ImageTexture::new(
-                    mapping,
-                    filename,
-                    do_trilinear,
-                    max_aniso,
-                    wrap_mode,
-                    scale,
-                    gamma,
-                    convert_to_float,
-                ));
-                Arc::make_mut(&mut api_state.graphics_state.float_textures)
-                    .insert(api_state.param_set.name.clone(), ft);
-            }
+            let ft = Arc::new(ImageTexture::new(
+                mapping,
+                filename,
+                do_trilinear,
+                max_aniso,
+                wrap_mode,
+                scale,
+                gamma,
+                convert_to_float,
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.float_textures)
+                .insert(api_state.param_set.name.clone(), ft);
         } else if api_state.param_set.tex_name == "uv" {
-            println!("TODO: CreateUVFloatTexture");
+            // CreateUVFloatTexture
+            let mapping: Box<TextureMapping2D> =
+                make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
+            let ft: Arc<dyn Texture<Float> + Send + Sync> = Arc::new(UVTexture::new(mapping));
+            Arc::make_mut(&mut api_state.graphics_state.float_textures)
+                .insert(api_state.param_set.name.clone(), ft);
         } else if api_state.param_set.tex_name == "checkerboard" {
-            println!("TODO: CreateCheckerboardFloatTexture");
-        } else if api_state.param_set.tex_name == "dots" {
-            // CreateDotsFloatTexture
-            let map: Option<Box<TextureMapping2D>>;
-            let mapping: String = tp.find_string("mapping", String::from("uv"));
-            if mapping == "uv" {
-                let su: Float = tp.find_float("uscale", 1.0);
-                let sv: Float = tp.find_float("vscale", 1.0);
-                let du: Float = tp.find_float("udelta", 0.0);
-                let dv: Float = tp.find_float("vdelta", 0.0);
-                map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                    su,
-                    sv,
-                    du,
-                    dv,
-                })));
-            } else if mapping == "spherical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Spherical(
-                    SphericalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "cylindrical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Cylindrical(
-                    CylindricalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "planar" {
-                map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                    vs: tp.find_vector3f(
-                        "v1",
-                        Vector3f {
-                            x: 1.0,
-                            y: 0.0,
-                            z: 0.0,
-                        },
-                    ),
-                    vt: tp.find_vector3f(
-                        "v2",
-                        Vector3f {
-                            x: 0.0,
-                            y: 1.0,
-                            z: 0.0,
-                        },
-                    ),
-                    ds: tp.find_float("udelta", 0.0),
-                    dt: tp.find_float("vdelta", 0.0),
-                })));
-            } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
+            // CreateCheckerboardFloatTexture
+            let dim: i32 = tp.find_int("dimension", 2);
+            if dim != 2 && dim != 3 {
+                panic!("{} dimensional checkerboard texture not supported", dim);
             }
-            if let Some(mapping) = map {
-                let dt = Arc::new(DotsTexture::new(
-                    mapping,
-                    tp.get_float_texture("inside", 1.0 as Float),
-                    tp.get_float_texture("outside", 0.0 as Float),
+            let tex1: Arc<dyn Texture<Float> + Send + Sync> =
+                tp.get_float_texture("tex1", 1.0 as Float);
+            let tex2: Arc<dyn Texture<Float> + Send + Sync> =
+                tp.get_float_texture("tex2", 0.0 as Float);
+            if dim == 2 {
+                let mapping: Box<TextureMapping2D> =
+                    make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
+                let aa_method: AAMethod = get_checkerboard_aa_method(&mut tp);
+                let ft = Arc::new(Checkerboard2DTexture::new(mapping, tex1, tex2, aa_method));
+                Arc::make_mut(&mut api_state.graphics_state.float_textures)
+                    .insert(api_state.param_set.name.clone(), ft);
+            } else {
+                // dim == 3
+                let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
+                    IdentityMapping3D::new(api_state.cur_transform.t[0]),
                 ));
+                let ft = Arc::new(Checkerboard3DTexture::new(map, tex1, tex2));
                 Arc::make_mut(&mut api_state.graphics_state.float_textures)
-                    .insert(api_state.param_set.name.clone(), dt);
+                    .insert(api_state.param_set.name.clone(), ft);
             }
+        } else if api_state.param_set.tex_name == "dots" {
+            // CreateDotsFloatTexture
+            let mapping: Box<TextureMapping2D> =
+                make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
+            let dt = Arc::new(DotsTexture::new(
+                mapping,
+                tp.get_float_texture("inside", 1.0 as Float),
+                tp.get_float_texture("outside", 0.0 as Float),
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.float_textures)
+                .insert(api_state.param_set.name.clone(), dt);
         } else if api_state.param_set.tex_name == "fbm" {
             // CreateFBmFloatTexture
             let tex_2_world: Transform = Transform {
@@ -1235,7 +1487,20 @@ fn make_texture(api_state: &mut ApiState) {
             Arc::make_mut(&mut api_state.graphics_state.float_textures)
                 .insert(api_state.param_set.name.clone(), ft);
         } else if api_state.param_set.tex_name == "ptex" {
-            println!("TODO: CreatePtexFloatTexture");
+            // CreatePtexFloatTexture
+            let filename: String = resolve_texture_filename(api_state, &mut tp);
+            let gamma: Float = tp.find_float("gamma", 2.2 as Float);
+            let do_trilinear: bool = tp.find_bool("trilinear", false);
+            let max_aniso: Float = tp.find_float("maxanisotropy", 8.0);
+            let ft = Arc::new(PtexTexture::new(
+                filename,
+                gamma,
+                do_trilinear,
+                max_aniso,
+                convert_to_float,
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.float_textures)
+                .insert(api_state.param_set.name.clone(), ft);
         } else {
             println!(
                 "Float texture \"{}\" unknown.",
@@ -1276,56 +1541,22 @@ fn make_texture(api_state: &mut ApiState) {
             Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
                 .insert(api_state.param_set.name.clone(), mt);
         } else if api_state.param_set.tex_name == "bilerp" {
-            println!("TODO: CreateBilerpSpectrumTexture");
+            // CreateBilerpSpectrumTexture
+            let mapping: Box<TextureMapping2D> =
+                make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
+            let st = Arc::new(BilerpTexture::<Spectrum>::new(
+                mapping,
+                tp.find_spectrum("v00", Spectrum::new(0.0)),
+                tp.find_spectrum("v01", Spectrum::new(1.0)),
+                tp.find_spectrum("v10", Spectrum::new(0.0)),
+                tp.find_spectrum("v11", Spectrum::new(1.0)),
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                .insert(api_state.param_set.name.clone(), st);
         } else if api_state.param_set.tex_name == "imagemap" {
             // CreateImageSpectrumTexture
-            let map: Option<Box<TextureMapping2D>>;
-            let mapping: String = tp.find_string("mapping", String::from("uv"));
-            if mapping == "uv" {
-                let su: Float = tp.find_float("uscale", 1.0);
-                let sv: Float = tp.find_float("vscale", 1.0);
-                let du: Float = tp.find_float("udelta", 0.0);
-                let dv: Float = tp.find_float("vdelta", 0.0);
-                map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                    su,
-                    sv,
-                    du,
-                    dv,
-                })));
-            } else if mapping == "spherical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Spherical(
-                    SphericalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "cylindrical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Cylindrical(
-                    CylindricalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "planar" {
-                map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                    vs: tp.find_vector3f(
-                        "v1",
-                        Vector3f {
-                            x: 1.0,
-                            y: 0.0,
-                            z: 0.0,
-                        },
-                    ),
-                    vt: tp.find_vector3f(
-                        "v2",
-                        Vector3f {
-                            x: 0.0,
-                            y: 1.0,
-                            z: 0.0,
-                        },
-                    ),
-                    ds: tp.find_float("udelta", 0.0),
-                    dt: tp.find_float("vdelta", 0.0),
-                })));
-            } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
-            }
+            let mapping: Box<TextureMapping2D> =
+                make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
             // initialize _ImageTexture_ parameters
             let max_aniso: Float = tp.find_float("maxanisotropy", 8.0);
             let do_trilinear: bool = tp.find_bool("trilinear", false);
@@ -1352,22 +1583,25 @@ fn make_texture(api_state: &mut ApiState) {
             // ".png"));
             let gamma: bool = tp.find_bool("gamma", true);
 
-            if let Some(mapping) = map {
-                let st = Arc::new(ImageTexture::new(
-                    mapping,
-                    filename,
-                    do_trilinear,
-                    max_aniso,
-                    wrap_mode,
-                    scale,
-                    gamma,
-                    convert_to_spectrum,
-                ));
-                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
-                    .insert(api_state.param_set.name.clone(), st);
-            }
+            let st = Arc::new(ImageTexture::new(
+                mapping,
+                filename,
+                do_trilinear,
+                max_aniso,
+                wrap_mode,
+                scale,
+                gamma,
+                convert_to_spectrum,
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                .insert(api_state.param_set.name.clone(), st);
         } else if api_state.param_set.tex_name == "uv" {
-            println!("TODO: CreateUVSpectrumTexture");
+            // CreateUVSpectrumTexture
+            let mapping: Box<TextureMapping2D> =
+                make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
+            let st: Arc<dyn Texture<Spectrum> + Send + Sync> = Arc::new(UVTexture::new(mapping));
+            Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                .insert(api_state.param_set.name.clone(), st);
         } else if api_state.param_set.tex_name == "checkerboard" {
             // CreateCheckerboardSpectrumTexture
             let dim: i32 = tp.find_int("dimension", 2);
@@ -1379,121 +1613,32 @@ fn make_texture(api_state: &mut ApiState) {
             let tex2: Arc<dyn Texture<Spectrum> + Send + Sync> =
                 tp.get_spectrum_texture("tex2", Spectrum::new(0.0));
             if dim == 2 {
-                let map: Option<Box<TextureMapping2D>>;
-                let mapping: String = tp.find_string("mapping", String::from("uv"));
-                if mapping == "uv" {
-                    let su: Float = tp.find_float("uscale", 1.0);
-                    let sv: Float = tp.find_float("vscale", 1.0);
-                    let du: Float = tp.find_float("udelta", 0.0);
-                    let dv: Float = tp.find_float("vdelta", 0.0);
-                    map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                        su,
-                        sv,
-                        du,
-                        dv,
-                    })));
-                } else if mapping == "spherical" {
-                    let tex_2_world = api_state.cur_transform.t[0];
-                    map = Some(Box::new(TextureMapping2D::Spherical(
-                        SphericalMapping2D::new(tex_2_world),
-                    )));
-                } else if mapping == "cylindrical" {
-                    let tex_2_world = api_state.cur_transform.t[0];
-                    map = Some(Box::new(TextureMapping2D::Cylindrical(
-                        CylindricalMapping2D::new(tex_2_world),
-                    )));
-                } else if mapping == "planar" {
-                    map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                        vs: tp.find_vector3f(
-                            "v1",
-                            Vector3f {
-                                x: 1.0,
-                                y: 0.0,
-                                z: 0.0,
-                            },
-                        ),
-                        vt: tp.find_vector3f(
-                            "v2",
-                            Vector3f {
-                                x: 0.0,
-                                y: 1.0,
-                                z: 0.0,
-                            },
-                        ),
-                        ds: tp.find_float("udelta", 0.0),
-                        dt: tp.find_float("vdelta", 0.0),
-                    })));
-                } else {
-                    panic!("2D texture mapping \"{}\" unknown", mapping);
-                }
-                // TODO: aamode
-                if let Some(mapping) = map {
-                    let st = Arc::new(Checkerboard2DTexture::new(mapping, tex1, tex2));
-                    Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
-                        .insert(api_state.param_set.name.clone(), st);
-                }
+                let mapping: Box<TextureMapping2D> =
+                    make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
+                let aa_method: AAMethod = get_checkerboard_aa_method(&mut tp);
+                let st = Arc::new(Checkerboard2DTexture::new(mapping, tex1, tex2, aa_method));
+                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                    .insert(api_state.param_set.name.clone(), st);
             } else {
                 // dim == 3
-                println!("TODO: TextureMapping3D");
+                let map: Box<TextureMapping3D> = Box::new(TextureMapping3D::Identity(
+                    IdentityMapping3D::new(api_state.cur_transform.t[0]),
+                ));
+                let st = Arc::new(Checkerboard3DTexture::new(map, tex1, tex2));
+                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                    .insert(api_state.param_set.name.clone(), st);
             }
         } else if api_state.param_set.tex_name == "dots" {
             // CreateDotsSpectrumTexture
-            let map: Option<Box<TextureMapping2D>>;
-            let mapping: String = tp.find_string("mapping", String::from("uv"));
-            if mapping == "uv" {
-                let su: Float = tp.find_float("uscale", 1.0);
-                let sv: Float = tp.find_float("vscale", 1.0);
-                let du: Float = tp.find_float("udelta", 0.0);
-                let dv: Float = tp.find_float("vdelta", 0.0);
-                map = Some(Box::new(TextureMapping2D::UV(UVMapping2D {
-                    su,
-                    sv,
-                    du,
-                    dv,
-                })));
-            } else if mapping == "spherical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Spherical(
-                    SphericalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "cylindrical" {
-                let tex_2_world = api_state.cur_transform.t[0];
-                map = Some(Box::new(TextureMapping2D::Cylindrical(
-                    CylindricalMapping2D::new(tex_2_world),
-                )));
-            } else if mapping == "planar" {
-                map = Some(Box::new(TextureMapping2D::Planar(PlanarMapping2D {
-                    vs: tp.find_vector3f(
-                        "v1",
-                        Vector3f {
-                            x: 1.0,
-                            y: 0.0,
-                            z: 0.0,
-                        },
-                    ),
-                    vt: tp.find_vector3f(
-                        "v2",
-                        Vector3f {
-                            x: 0.0,
-                            y: 1.0,
-                            z: 0.0,
-                        },
-                    ),
-                    ds: tp.find_float("udelta", 0.0),
-                    dt: tp.find_float("vdelta", 0.0),
-                })));
-            } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
-            }
+            let mapping: Box<TextureMapping2D> =
+                make_texture_mapping_2d(&mut tp, &api_state.cur_transform.t[0]);
             let inside: Arc<dyn Texture<Spectrum> + Send + Sync> =
                 tp.get_spectrum_texture("inside", Spectrum::new(1.0));
             let outside: Arc<dyn Texture<Spectrum> + Send + Sync> =
                 tp.get_spectrum_texture("outside", Spectrum::new(0.0));
-            if let Some(mapping) = map {
-                let dt = Arc::new(DotsTexture::new(mapping, inside, outside));
-                Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
-                    .insert(api_state.param_set.name.clone(), dt);
-            }
+            let dt = Arc::new(DotsTexture::new(mapping, inside, outside));
+            Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                .insert(api_state.param_set.name.clone(), dt);
         } else if api_state.param_set.tex_name == "fbm" {
             // CreateFBmSpectrumTexture
             let tex_2_world: Transform = Transform {
@@ -1551,6 +1696,21 @@ fn make_texture(api_state: &mut ApiState) {
             let ft = Arc::new(WindyTexture::new(map));
             Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
                 .insert(api_state.param_set.name.clone(), ft);
+        } else if api_state.param_set.tex_name == "ptex" {
+            // CreatePtexSpectrumTexture
+            let filename: String = resolve_texture_filename(api_state, &mut tp);
+            let gamma: Float = tp.find_float("gamma", 2.2 as Float);
+            let do_trilinear: bool = tp.find_bool("trilinear", false);
+            let max_aniso: Float = tp.find_float("maxanisotropy", 8.0);
+            let st = Arc::new(PtexTexture::new(
+                filename,
+                gamma,
+                do_trilinear,
+                max_aniso,
+                convert_to_spectrum,
+            ));
+            Arc::make_mut(&mut api_state.graphics_state.spectrum_textures)
+                .insert(api_state.param_set.name.clone(), st);
         } else {
             println!(
                 "Spectrum texture \"{}\" unknown.",
@@ -1558,7 +1718,7 @@ fn make_texture(api_state: &mut ApiState) {
             );
         }
     } else {
-        panic!("Texture type \"{}\" unknown.", api_state.param_set.tex_type);
+        println!("Texture type \"{}\" unknown.", api_state.param_set.tex_type);
     }
     // MakeFloatTexture(texname, curTransform[0], tp);
     // or
@@ -1708,8 +1868,22 @@ pub fn make_film(name: &str, param_set: &ParamSet, filter: Box<Filter>) -> Optio
     }
 }
 
+/// Materials are numbered (starting with 1) in the order they are
+/// first used by a shape, 0 means no material.
+fn get_material_id(api_state: &mut ApiState, material: &Option<Arc<Material>>) -> u32 {
+    if let Some(material) = material {
+        let next_id: u32 = api_state.material_ids.len() as u32 + 1;
+        *api_state
+            .material_ids
+            .entry(Arc::as_ptr(material) as usize)
+            .or_insert(next_id)
+    } else {
+        0_u32
+    }
+}
+
 fn get_shapes_and_materials(
-    api_state: &ApiState,
+    api_state: &mut ApiState,
     bsdf_state: &mut BsdfState,
 ) -> (Vec<Arc<Shape>>, Vec<Option<Arc<Material>>>) {
     if shape_may_set_material_parameters(&api_state.param_set) {
@@ -1794,11 +1968,72 @@ fn get_shapes_and_materials(
         shapes.push(disk);
         materials.push(mtl);
     } else if api_state.param_set.name == "cone" {
-        println!("TODO: CreateConeShape");
+        let radius: Float = api_state.param_set.find_one_float("radius", 1.0);
+        let height: Float = api_state.param_set.find_one_float("height", 1.0);
+        let phi_max: Float = api_state.param_set.find_one_float("phimax", 360.0);
+        let cone = Arc::new(Shape::Cn(Cone::new(
+            obj_to_world,
+            world_to_obj,
+            api_state.graphics_state.reverse_orientation,
+            height,
+            radius,
+            phi_max,
+        )));
+        let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
+        shapes.push(cone);
+        materials.push(mtl);
     } else if api_state.param_set.name == "paraboloid" {
-        println!("TODO: CreateParaboloidShape");
+        let radius: Float = api_state.param_set.find_one_float("radius", 1.0);
+        let z_min: Float = api_state.param_set.find_one_float("zmin", 0.0);
+        let z_max: Float = api_state.param_set.find_one_float("zmax", 1.0);
+        let phi_max: Float = api_state.param_set.find_one_float("phimax", 360.0);
+        let paraboloid = Arc::new(Shape::Prbld(Paraboloid::new(
+            obj_to_world,
+            world_to_obj,
+            api_state.graphics_state.reverse_orientation,
+            radius,
+            z_min,
+            z_max,
+            phi_max,
+        )));
+        let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
+        shapes.push(paraboloid);
+        materials.push(mtl);
     } else if api_state.param_set.name == "hyperboloid" {
-        println!("TODO: CreateHyperboloidShape");
+        let p1: Point3f = api_state.param_set.find_one_point3f(
+            "p1",
+            Point3f {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        );
+        let p2: Point3f = api_state.param_set.find_one_point3f(
+            "p2",
+            Point3f {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+        );
+        let phi_max: Float = api_state.param_set.find_one_float("phimax", 360.0);
+        if p1.z == p2.z {
+            api_state.warning(String::from(
+                "Hyperboloid needs \"p1\" and \"p2\" at different heights (ignored)",
+            ));
+        } else {
+            let hyperboloid = Arc::new(Shape::Hyprbld(Hyperboloid::new(
+                obj_to_world,
+                world_to_obj,
+                api_state.graphics_state.reverse_orientation,
+                p1,
+                p2,
+                phi_max,
+            )));
+            let mtl: Option<Arc<Material>> = create_material(api_state, bsdf_state);
+            shapes.push(hyperboloid);
+            materials.push(mtl);
+        }
     } else if api_state.param_set.name == "curve" {
         let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
         let curve_shapes: Vec<Arc<Shape>> = create_curve_shape(
@@ -1872,7 +2107,31 @@ fn get_shapes_and_materials(
                 );
             }
         }
-        // TODO: alpha
+        let mut face_indices: Vec<i32> = api_state.param_set.find_int("faceIndices");
+        if !face_indices.is_empty() && face_indices.len() != vi.len() / 3 {
+            println!(
+                "ERROR: Number of face indices, {}, doesn't match number of faces, {}",
+                face_indices.len(),
+                vi.len() / 3
+            );
+            face_indices.clear();
+        }
+        // each triangle is a face on its own
+        let mut face_uvs: Vec<Point2f> = Vec::new();
+        for _ in 0..face_indices.len() {
+            face_uvs.extend_from_slice(&FACE_UVS_TRIANGLE);
+        }
+        // look up an alpha texture, if applicable
+        let alpha_tex: Option<Arc<dyn Texture<Float> + Send + Sync>> = get_alpha_texture(
+            &api_state.param_set,
+            &api_state.graphics_state.float_textures,
+            "alpha",
+        );
+        let shadow_alpha_tex: Option<Arc<dyn Texture<Float> + Send + Sync>> = get_alpha_texture(
+            &api_state.param_set,
+            &api_state.graphics_state.float_textures,
+            "shadowalpha",
+        );
         // CreateTriangleMesh
         // transform mesh vertices to world space
         let mut p_ws: Vec<Point3f> = Vec::new();
@@ -1896,8 +2155,10 @@ fn get_shapes_and_materials(
             s_ws, // in world space
             n_ws, // in world space
             uvs,
-            None,
-            None,
+            alpha_tex,
+            shadow_alpha_tex,
+            face_indices,
+            face_uvs,
         ));
         let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
         for id in 0..mesh.n_triangles {
@@ -1928,7 +2189,21 @@ fn get_shapes_and_materials(
             panic!("No search directory for plymesh.");
         }
     } else if api_state.param_set.name == "heightfield" {
-        println!("TODO: CreateHeightfield");
+        match create_heightfield(
+            &obj_to_world,
+            &world_to_obj,
+            api_state.graphics_state.reverse_orientation,
+            &api_state.param_set,
+        ) {
+            Ok(heightfield_shapes) => {
+                let mtl: Option<Arc<Material>> = create_material(api_state, bsdf_state);
+                for shape in heightfield_shapes {
+                    shapes.push(shape.clone());
+                    materials.push(mtl.clone());
+                }
+            }
+            Err(message) => api_state.warning(message),
+        }
     } else if api_state.param_set.name == "loopsubdiv" {
         // CreateLoopSubdiv
         let n_levels: i32 = api_state
@@ -2138,6 +2413,8 @@ fn get_shapes_and_materials(
             uvs,
             None,
             None,
+            Vec::new(),
+            Vec::new(),
         ));
         let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
         for id in 0..mesh.n_triangles {
@@ -2149,7 +2426,7 @@ fn get_shapes_and_materials(
             materials.push(mtl.clone());
         }
     } else {
-        panic!("Shape \"{}\" unknown.", api_state.param_set.name);
+        println!("Shape \"{}\" unknown.", api_state.param_set.name);
     }
     (shapes, materials)
 }
@@ -2158,6 +2435,12 @@ fn print_params(params: &ParamSet) {
     for p in &params.strings {
         if p.n_values == 1_usize {
             println!("  \"string {}\" [\"{}\"]", p.name, p.values[0]);
+        } else {
+            print!("  \"string {}\" [ ", p.name);
+            for i in 0..p.n_values {
+                print!("\"{}\" ", p.values[i]);
+            }
+            println!("]");
         }
     }
     for p in &params.bools {
@@ -2231,10 +2514,9 @@ fn print_params(params: &ParamSet) {
     }
     for p in &params.spectra {
         if p.n_values == 1_usize {
-            println!(
-                "  \"rgb {}\" [{} {} {}]",
-                p.name, p.values[0].c[0], p.values[0].c[1], p.values[0].c[2]
-            );
+            let mut rgb: [Float; 3] = [0.0 as Float; 3];
+            p.values[0].to_rgb(&mut rgb);
+            println!("  \"rgb {}\" [{} {} {}]", p.name, rgb[0], rgb[1], rgb[2]);
         }
     }
     for p in &params.textures {
@@ -2244,14 +2526,20 @@ fn print_params(params: &ParamSet) {
     }
 }
 
-pub fn pbrt_init(number_of_threads: u8) -> (ApiState, BsdfState) {
+pub fn pbrt_init(options: Options) -> (ApiState, BsdfState) {
     let mut api_state: ApiState = ApiState::default();
     let bsdf_state: BsdfState = BsdfState::default();
-    api_state.number_of_threads = number_of_threads;
+    set_quiet(options.quiet);
+    api_state.scene_writer = SceneWriter::new(&options);
+    api_state.options = options;
     (api_state, bsdf_state)
 }
 
-pub fn pbrt_cleanup(api_state: &ApiState) {
+pub fn pbrt_cleanup(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.world_end();
+        return;
+    }
     // println!("WorldEnd");
     assert!(
         api_state.pushed_graphics_states.is_empty(),
@@ -2261,18 +2549,30 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
         api_state.pushed_transforms.is_empty(),
         "Missing end to pbrtTransformBegin()"
     );
+    // command line options override the scene description
+    api_state.render_options.apply_options(&api_state.options);
     // MakeIntegrator
     let some_integrator: Option<Box<Integrator>> = api_state.render_options.make_integrator();
     if let Some(mut integrator) = some_integrator {
         let scene = api_state.render_options.make_scene();
-        let num_threads: u8 = api_state.number_of_threads;
-        integrator.render(&scene, num_threads);
+        let warnings: Vec<ParseWarning> = api_state.render_options.report_unused();
+        api_state.add_warnings(warnings);
+        let num_threads: u8 = api_state.options.n_threads;
+        if let Some(ref role) = api_state.options.distributed {
+            integrator.render_distributed(&scene, num_threads, role);
+        } else {
+            integrator.render(&scene, num_threads);
+        }
     } else {
         panic!("Unable to create integrator.");
     }
 }
 
 pub fn pbrt_translate(api_state: &mut ApiState, dx: Float, dy: Float, dz: Float) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!("Translate {} {} {}", dx, dy, dz));
+        return;
+    }
     // println!("Translate {} {} {}", dx, dy, dz);
     let translate: Transform = Transform::translate(&Vector3f {
         x: dx,
@@ -2289,7 +2589,27 @@ pub fn pbrt_translate(api_state: &mut ApiState, dx: Float, dy: Float, dz: Float)
     }
 }
 
+pub fn pbrt_identity(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement("Identity");
+        return;
+    }
+    // println!("Identity");
+    if api_state.active_transform_bits & 1_u8 > 0_u8 {
+        // 0x?1
+        api_state.cur_transform.t[0] = Transform::default();
+    }
+    if api_state.active_transform_bits & 2_u8 > 0_u8 {
+        // 0x1?
+        api_state.cur_transform.t[1] = Transform::default();
+    }
+}
+
 pub fn pbrt_transform(api_state: &mut ApiState, tr: &Transform) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.transform("Transform", tr);
+        return;
+    }
     // println!("{:?}", tr);
     if api_state.active_transform_bits & 1_u8 > 0_u8 {
         // 0x?1
@@ -2302,6 +2622,10 @@ pub fn pbrt_transform(api_state: &mut ApiState, tr: &Transform) {
 }
 
 pub fn pbrt_concat_transform(api_state: &mut ApiState, tr: &Transform) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.transform("ConcatTransform", tr);
+        return;
+    }
     // println!("Concat{:?}", tr);
     if api_state.active_transform_bits & 1_u8 > 0_u8 {
         // 0x?1
@@ -2314,6 +2638,10 @@ pub fn pbrt_concat_transform(api_state: &mut ApiState, tr: &Transform) {
 }
 
 pub fn pbrt_rotate(api_state: &mut ApiState, angle: Float, dx: Float, dy: Float, dz: Float) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!("Rotate {} {} {} {}", angle, dx, dy, dz));
+        return;
+    }
     // println!("Rotate {} {} {} {}", angle, dx, dy, dz);
     let rotate: Transform = Transform::rotate(
         angle,
@@ -2334,6 +2662,10 @@ pub fn pbrt_rotate(api_state: &mut ApiState, angle: Float, dx: Float, dy: Float,
 }
 
 pub fn pbrt_scale(api_state: &mut ApiState, sx: Float, sy: Float, sz: Float) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!("Scale {} {} {}", sx, sy, sz));
+        return;
+    }
     // println!("Scale {} {} {}", sx, sy, sz);
     let scale: Transform = Transform::scale(sx, sy, sz);
     if api_state.active_transform_bits & 1_u8 > 0_u8 {
@@ -2358,6 +2690,13 @@ pub fn pbrt_look_at(
     uy: Float,
     uz: Float,
 ) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!(
+            "LookAt {} {} {}  {} {} {}  {} {} {}",
+            ex, ey, ez, lx, ly, lz, ux, uy, uz
+        ));
+        return;
+    }
     // println!(
     //     "LookAt {} {} {} {} {} {} {} {} {}",
     //     ex, ey, ez, lx, ly, lz, ux, uy, uz
@@ -2388,12 +2727,32 @@ pub fn pbrt_look_at(
     }
 }
 
+/// Store the current transformation under a name, which can be
+/// restored later with
+/// [pbrt_coord_sys_transform()](fn.pbrt_coord_sys_transform.html).
+/// The names "camera" and "world" are predefined.
+pub fn pbrt_coordinate_system(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!("CoordinateSystem \"{}\"", params.name));
+        return;
+    }
+    // println!("CoordinateSystem \"{}\"", params.name);
+    api_state.param_set = params;
+    api_state
+        .named_coordinate_systems
+        .insert(api_state.param_set.name.clone(), api_state.cur_transform);
+}
+
 pub fn pbrt_coord_sys_transform(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!("CoordSysTransform \"{}\"", params.name));
+        return;
+    }
     // println!("CoordSysTransform \"{}\"", params.name);
     api_state.param_set = params;
     match api_state
         .named_coordinate_systems
-        .get(api_state.param_set.name.as_str())
+        .get(&api_state.param_set.name)
     {
         Some(transform_set) => {
             api_state.cur_transform.t[0] = transform_set.t[0];
@@ -2401,7 +2760,7 @@ pub fn pbrt_coord_sys_transform(api_state: &mut ApiState, params: ParamSet) {
         }
         None => {
             println!(
-                "Couldn't find named coordinate system \"{}\"",
+                "WARNING: Couldn't find named coordinate system \"{}\"",
                 api_state.param_set.name
             );
         }
@@ -2409,27 +2768,47 @@ pub fn pbrt_coord_sys_transform(api_state: &mut ApiState, params: ParamSet) {
 }
 
 pub fn pbrt_active_transform_all(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement("ActiveTransform All");
+        return;
+    }
     // println!("ActiveTransform All");
     api_state.active_transform_bits = 3_u8 // 0x11
 }
 
 pub fn pbrt_active_transform_end_time(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement("ActiveTransform EndTime");
+        return;
+    }
     // println!("ActiveTransform EndTime");
     api_state.active_transform_bits = 2_u8 // 0x10
 }
 
 pub fn pbrt_active_transform_start_time(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement("ActiveTransform StartTime");
+        return;
+    }
     // println!("ActiveTransform StartTime");
     api_state.active_transform_bits = 1_u8 // 0x01
 }
 
 pub fn pbrt_transform_times(api_state: &mut ApiState, start: Float, end: Float) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!("TransformTimes {} {}", start, end));
+        return;
+    }
     println!("TransformTimes {} {}", start, end);
     api_state.render_options.transform_start_time = start;
     api_state.render_options.transform_end_time = end;
 }
 
 pub fn pbrt_pixel_filter(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("PixelFilter", &params);
+        return;
+    }
     // println!("PixelFilter \"{}\"", params.name);
     // print_params(&params);
     api_state.render_options.filter_name = params.name.clone();
@@ -2441,6 +2820,10 @@ pub fn pbrt_pixel_filter(api_state: &mut ApiState, params: ParamSet) {
 }
 
 pub fn pbrt_film(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("Film", &params);
+        return;
+    }
     println!("Film \"{}\"", params.name);
     print_params(&params);
     api_state.render_options.film_name = params.name.clone();
@@ -2452,6 +2835,10 @@ pub fn pbrt_film(api_state: &mut ApiState, params: ParamSet) {
 }
 
 pub fn pbrt_sampler(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("Sampler", &params);
+        return;
+    }
     println!("Sampler \"{}\"", params.name);
     print_params(&params);
     api_state.render_options.sampler_name = params.name.clone();
@@ -2463,6 +2850,10 @@ pub fn pbrt_sampler(api_state: &mut ApiState, params: ParamSet) {
 }
 
 pub fn pbrt_accelerator(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("Accelerator", &params);
+        return;
+    }
     println!("Accelerator \"{}\"", params.name);
     print_params(&params);
     api_state.render_options.accelerator_name = params.name.clone();
@@ -2474,6 +2865,10 @@ pub fn pbrt_accelerator(api_state: &mut ApiState, params: ParamSet) {
 }
 
 pub fn pbrt_integrator(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("Integrator", &params);
+        return;
+    }
     println!("Integrator \"{}\"", params.name);
     print_params(&params);
     api_state.render_options.integrator_name = params.name.clone();
@@ -2485,6 +2880,10 @@ pub fn pbrt_integrator(api_state: &mut ApiState, params: ParamSet) {
 }
 
 pub fn pbrt_camera(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("Camera", &params);
+        return;
+    }
     // println!("Camera \"{}\"", params.name);
     // print_params(&params);
     api_state.render_options.camera_name = params.name.clone();
@@ -2494,7 +2893,7 @@ pub fn pbrt_camera(api_state: &mut ApiState, params: ParamSet) {
     api_state.render_options.camera_to_world.t[1] =
         Transform::inverse(&api_state.cur_transform.t[1]);
     api_state.named_coordinate_systems.insert(
-        "camera",
+        String::from("camera"),
         TransformSet {
             t: [
                 api_state.render_options.camera_to_world.t[0],
@@ -2509,13 +2908,26 @@ pub fn pbrt_camera(api_state: &mut ApiState, params: ParamSet) {
 }
 
 pub fn pbrt_make_named_medium(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("MakeNamedMedium", &params);
+        return;
+    }
     // println!("MakeNamedMedium \"{}\"", params.name);
     // print_params(&api_state.param_set);
     api_state.param_set = params;
     make_medium(api_state);
+    let warnings: Vec<ParseWarning> = api_state.param_set.report_unused();
+    api_state.add_warnings(warnings);
 }
 
 pub fn pbrt_medium_interface(api_state: &mut ApiState, inside_name: &str, outside_name: &str) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!(
+            "MediumInterface \"{}\" \"{}\"",
+            inside_name, outside_name
+        ));
+        return;
+    }
     // println!("MediumInterface \"{}\" \"{}\"", inside_name, outside_name);
     api_state.graphics_state.current_inside_medium = inside_name.to_string();
     api_state.graphics_state.current_outside_medium = outside_name.to_string();
@@ -2523,12 +2935,16 @@ pub fn pbrt_medium_interface(api_state: &mut ApiState, inside_name: &str, outsid
 }
 
 pub fn pbrt_world_begin(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement("WorldBegin");
+        return;
+    }
     // println!("WorldBegin");
     api_state.cur_transform.t[0] = Transform::default();
     api_state.cur_transform.t[1] = Transform::default();
     api_state.active_transform_bits = 3_u8; // 0x11
     api_state.named_coordinate_systems.insert(
-        "world",
+        String::from("world"),
         TransformSet {
             t: [Transform::default(); 2],
         },
@@ -2536,6 +2952,10 @@ pub fn pbrt_world_begin(api_state: &mut ApiState) {
 }
 
 pub fn pbrt_attribute_begin(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.begin("AttributeBegin");
+        return;
+    }
     // println!("AttributeBegin");
     let mut material_param_set: ParamSet = ParamSet::default();
     material_param_set.copy_from(&api_state.graphics_state.material_params);
@@ -2572,6 +2992,10 @@ pub fn pbrt_attribute_begin(api_state: &mut ApiState) {
 }
 
 pub fn pbrt_attribute_end(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.end("AttributeEnd");
+        return;
+    }
     // println!("AttributeEnd");
     if api_state.pushed_graphics_states.is_empty() {
         panic!("Unmatched pbrtAttributeEnd() encountered.")
@@ -2585,6 +3009,10 @@ pub fn pbrt_attribute_end(api_state: &mut ApiState) {
 }
 
 pub fn pbrt_transform_begin(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.begin("TransformBegin");
+        return;
+    }
     // println!("TransformBegin");
     api_state.pushed_transforms.push(TransformSet {
         t: [
@@ -2604,6 +3032,10 @@ pub fn pbrt_transform_begin(api_state: &mut ApiState) {
 }
 
 pub fn pbrt_transform_end(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.end("TransformEnd");
+        return;
+    }
     // println!("TransformEnd");
     let popped_transform_set: TransformSet = api_state.pushed_transforms.pop().unwrap();
     api_state.cur_transform.t[0] = popped_transform_set.t[0];
@@ -2613,6 +3045,10 @@ pub fn pbrt_transform_end(api_state: &mut ApiState) {
 }
 
 pub fn pbrt_texture(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.texture(&params);
+        return;
+    }
     // println!(
     //     "Texture \"{}\" \"{}\" \"{}\"",
     //     params.name, params.tex_type, params.tex_name
@@ -2620,9 +3056,15 @@ pub fn pbrt_texture(api_state: &mut ApiState, params: ParamSet) {
     // print_params(&params);
     api_state.param_set = params;
     make_texture(api_state);
+    let warnings: Vec<ParseWarning> = api_state.param_set.report_unused();
+    api_state.add_warnings(warnings);
 }
 
 pub fn pbrt_material(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("Material", &params);
+        return;
+    }
     // println!("MakeMaterial \"{}\"", params.name);
     // print_params(&params);
     api_state.param_set = params;
@@ -2639,6 +3081,10 @@ pub fn pbrt_make_named_material(
     bsdf_state: &mut BsdfState,
     params: ParamSet,
 ) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("MakeNamedMaterial", &params);
+        return;
+    }
     // println!("MakeNamedMaterial \"{}\"", params.name);
     // print_params(&params);
     api_state.param_set = params;
@@ -2653,6 +3099,8 @@ pub fn pbrt_make_named_material(
         .copy_from(&api_state.param_set);
     api_state.graphics_state.current_material = String::new();
     let mtl: Option<Arc<Material>> = create_material(&api_state, bsdf_state);
+    let warnings: Vec<ParseWarning> = api_state.graphics_state.material_params.report_unused();
+    api_state.add_warnings(warnings);
     if let Some(_named_material) = api_state
         .graphics_state
         .named_materials
@@ -2665,20 +3113,34 @@ pub fn pbrt_make_named_material(
 }
 
 pub fn pbrt_named_material(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!("NamedMaterial \"{}\"", params.name));
+        return;
+    }
     // println!("NamedMaterial \"{}\"", params.name);
     api_state.param_set = params;
     api_state.graphics_state.current_material = api_state.param_set.name.clone();
 }
 
 pub fn pbrt_light_source(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("LightSource", &params);
+        return;
+    }
     // println!("LightSource \"{}\"", params.name);
     // print_params(&params);
     api_state.param_set = params;
     let mi: MediumInterface = create_medium_interface(&api_state);
     make_light(api_state, &mi);
+    let warnings: Vec<ParseWarning> = api_state.param_set.report_unused();
+    api_state.add_warnings(warnings);
 }
 
 pub fn pbrt_area_light_source(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.named("AreaLightSource", &params);
+        return;
+    }
     // println!("AreaLightSource \"{}\"", params.name);
     // print_params(&params);
     api_state.param_set = params;
@@ -2690,9 +3152,16 @@ pub fn pbrt_area_light_source(api_state: &mut ApiState, params: ParamSet) {
 }
 
 pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.shape(&params);
+        return;
+    }
     // println!("Shape \"{}\"", params.name);
     // print_params(&params);
     api_state.param_set = params;
+    // IDs start with 1, 0 is used for "nothing hit"
+    api_state.n_shapes += 1;
+    let object_id: u32 = api_state.n_shapes;
     // collect area lights
     let mut prims: Vec<Arc<Primitive>> = Vec::new();
     let mut area_lights: Vec<Arc<Light>> = Vec::new();
@@ -2703,8 +3172,9 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
             || api_state.graphics_state.area_light == "diffuse"
         {
             // first create the shape
-            let (shapes, materials) = get_shapes_and_materials(&api_state, bsdf_state);
+            let (shapes, materials) = get_shapes_and_materials(api_state, bsdf_state);
             assert_eq!(shapes.len(), materials.len());
+            report_unused_shape_params(api_state, !shapes.is_empty());
             // MediumInterface
             let mi: MediumInterface = create_medium_interface(&api_state);
             for i in 0..shapes.len() {
@@ -2723,14 +3193,16 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
                 let n_samples: i32 = // try "nsamples" first
                     api_state.graphics_state.area_light_params.find_one_int("nsamples",
                                                                   1);
-                let n_samples: i32 = // try "samples"next
+                let mut n_samples: i32 = // try "samples"next
                     api_state.graphics_state.area_light_params.find_one_int("samples",
                                                                   n_samples);
                 let two_sided: bool = api_state
                     .graphics_state
                     .area_light_params
                     .find_one_bool("twosided", false);
-                // TODO: if (PbrtOptions.quickRender) nSamples = std::max(1, nSamples / 4);
+                if api_state.options.quick_render {
+                    n_samples = std::cmp::max(1, n_samples / 4);
+                }
                 let l_emit: Spectrum = l * sc;
                 let area_light: Arc<Light> =
                     Arc::new(Light::DiffuseArea(Box::new(DiffuseAreaLight::new(
@@ -2742,30 +3214,42 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
                         two_sided,
                     ))));
                 area_lights.push(area_light.clone());
-                let geo_prim = Arc::new(Primitive::Geometric(Box::new(GeometricPrimitive::new(
+                let mut geo_prim = GeometricPrimitive::new(
                     shape.clone(),
                     material.clone(),
                     Some(area_light.clone()),
                     Some(Arc::new(mi.clone())),
-                ))));
+                );
+                geo_prim.object_id = object_id;
+                geo_prim.material_id = get_material_id(api_state, material);
+                let geo_prim = Arc::new(Primitive::Geometric(Box::new(geo_prim)));
                 prims.push(geo_prim.clone());
             }
+            if !shapes.is_empty() {
+                let warnings: Vec<ParseWarning> =
+                    api_state.graphics_state.area_light_params.report_unused();
+                api_state.add_warnings(warnings);
+            }
         }
     } else {
         // continue with shape itself
-        let (shapes, materials) = get_shapes_and_materials(&api_state, bsdf_state);
+        let (shapes, materials) = get_shapes_and_materials(api_state, bsdf_state);
         assert_eq!(shapes.len(), materials.len());
+        report_unused_shape_params(api_state, !shapes.is_empty());
         // MediumInterface
         let mi: MediumInterface = create_medium_interface(&api_state);
         for i in 0..shapes.len() {
             let shape = &shapes[i];
             let material = &materials[i];
-            let geo_prim = Arc::new(Primitive::Geometric(Box::new(GeometricPrimitive::new(
+            let mut geo_prim = GeometricPrimitive::new(
                 shape.clone(),
                 material.clone(),
                 None,
                 Some(Arc::new(mi.clone())),
-            ))));
+            );
+            geo_prim.object_id = object_id;
+            geo_prim.material_id = get_material_id(api_state, material);
+            let geo_prim = Arc::new(Primitive::Geometric(Box::new(geo_prim)));
             prims.push(geo_prim.clone());
         }
         // animated?
@@ -2819,6 +3303,18 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
     }
 }
 
+/// Reports the unused parameters of a Shape statement and (if a
+/// material got created for the shape) of the current Material
+/// statement. Each parameter gets reported only once, so the
+/// material parameters show up with the first shape using them.
+fn report_unused_shape_params(api_state: &mut ApiState, created: bool) {
+    let mut warnings: Vec<ParseWarning> = api_state.param_set.report_unused();
+    if created && api_state.graphics_state.current_material.is_empty() {
+        warnings.append(&mut api_state.graphics_state.material_params.report_unused());
+    }
+    api_state.add_warnings(warnings);
+}
+
 // Attempt to determine if the ParamSet for a shape may provide a value for
 // its material's parameters. Unfortunately, materials don't provide an
 // explicit representation of their parameters that we can query and
@@ -2894,11 +3390,19 @@ fn shape_may_set_material_parameters(ps: &ParamSet) -> bool {
 }
 
 pub fn pbrt_reverse_orientation(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement("ReverseOrientation");
+        return;
+    }
     // println!("ReverseOrientation");
     api_state.graphics_state.reverse_orientation = !api_state.graphics_state.reverse_orientation;
 }
 
 pub fn pbrt_object_begin(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.begin(&format!("ObjectBegin \"{}\"", params.name));
+        return;
+    }
     // println!("ObjectBegin \"{}\"", params.name);
     api_state.param_set = params;
     pbrt_attribute_begin(api_state);
@@ -2913,6 +3417,10 @@ pub fn pbrt_object_begin(api_state: &mut ApiState, params: ParamSet) {
 }
 
 pub fn pbrt_object_end(api_state: &mut ApiState) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.end("ObjectEnd");
+        return;
+    }
     // println!("ObjectEnd");
     if api_state.render_options.current_instance == "" {
         println!("ERROR: ObjectEnd called outside of instance definition");
@@ -2922,6 +3430,10 @@ pub fn pbrt_object_end(api_state: &mut ApiState) {
 }
 
 pub fn pbrt_object_instance(api_state: &mut ApiState, params: ParamSet) {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement(&format!("ObjectInstance \"{}\"", params.name));
+        return;
+    }
     // println!("ObjectInstance \"{}\"", params.name);
     api_state.param_set = params;
     // perform object instance error checking
@@ -2937,7 +3449,7 @@ pub fn pbrt_object_instance(api_state: &mut ApiState, params: ParamSet) {
         if instance_vec.is_empty() {
             return;
         }
-        // TODO: ++nObjectInstancesUsed;
+        N_OBJECT_INSTANCES_USED.inc();
         if instance_vec.len() > 1_usize {
             // create aggregate for instance _Primitive_s
             if api_state.render_options.accelerator_name == "bvh" {
@@ -11,7 +11,8 @@ use std::sync::Arc;
 use strum::IntoEnumIterator;
 // pbrt
 use crate::core::geometry::{
-    nrm_cross_vec3, nrm_dot_nrmf, nrm_dot_vec3f, pnt3_distancef, vec3_dot_nrmf, vec3_dot_vec3f,
+    nrm_abs_dot_vec3f, nrm_cross_vec3, nrm_dot_nrmf, nrm_dot_vec3f, pnt3_distancef, vec3_dot_nrmf,
+    vec3_dot_vec3f,
 };
 use crate::core::geometry::{Normal3f, Point2f, Point3f, Ray, Vector3f, XYZEnum};
 use crate::core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
@@ -25,10 +26,15 @@ use crate::core::pbrt::INV_4_PI;
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::reflection::{cos_theta, fr_dielectric};
 use crate::core::reflection::{Bsdf, Bxdf, BxdfType};
+use crate::core::rng::FLOAT_ONE_MINUS_EPSILON;
 use crate::core::scene::Scene;
-use crate::core::spectrum::RGBEnum;
+use crate::core::stats::{Prof, ProfilePhase};
 
-pub struct TabulatedBssrdf {
+/// Data shared by all separable BSSRDFs (see SeparableBSSRDF in
+/// bssrdf.h): the exit point, the local shading frame around it, and
+/// the material which restricts the probe ray intersections.
+#[derive(Clone)]
+pub struct SeparableBssrdf {
     // BSSRDF Protected Data
     pub po_p: Point3f,   // pub po: &SurfaceInteraction,
     pub po_time: Float,  // TMP
@@ -40,43 +46,19 @@ pub struct TabulatedBssrdf {
     pub ts: Vector3f,
     pub material: Arc<Material>,
     pub mode: TransportMode,
-    // TabulatedBSSRDF Private Data
-    pub table: Arc<BssrdfTable>,
-    pub sigma_t: Spectrum,
-    pub rho: Spectrum,
 }
 
-impl TabulatedBssrdf {
+impl SeparableBssrdf {
     pub fn new(
         po: &SurfaceInteraction,
         material_opt: Option<Arc<Material>>,
         mode: TransportMode,
         eta: Float,
-        sigma_a: &Spectrum,
-        sigma_s: &Spectrum,
-        table: Arc<BssrdfTable>,
     ) -> Self {
-        let sigma_t: Spectrum = *sigma_a + *sigma_s;
-        let mut rho: Spectrum = Spectrum::new(0.0 as Float);
-        if sigma_t[RGBEnum::Red] != 0.0 as Float {
-            rho[RGBEnum::Red] = sigma_s[RGBEnum::Red] / sigma_t[RGBEnum::Red];
-        } else {
-            rho[RGBEnum::Red] = 0.0 as Float;
-        }
-        if sigma_t[RGBEnum::Green] != 0.0 as Float {
-            rho[RGBEnum::Green] = sigma_s[RGBEnum::Green] / sigma_t[RGBEnum::Green];
-        } else {
-            rho[RGBEnum::Green] = 0.0 as Float;
-        }
-        if sigma_t[RGBEnum::Blue] != 0.0 as Float {
-            rho[RGBEnum::Blue] = sigma_s[RGBEnum::Blue] / sigma_t[RGBEnum::Blue];
-        } else {
-            rho[RGBEnum::Blue] = 0.0 as Float;
-        }
         let ns: Normal3f = po.shading.n;
         let ss: Vector3f = po.shading.dpdu.normalize();
         if let Some(material) = material_opt {
-            TabulatedBssrdf {
+            SeparableBssrdf {
                 po_p: *po.get_p(),
                 po_time: po.get_time(),
                 po_wo: *po.get_wo(),
@@ -86,12 +68,9 @@ impl TabulatedBssrdf {
                 ts: nrm_cross_vec3(&ns, &ss),
                 material,
                 mode,
-                table,
-                sigma_t,
-                rho,
             }
         } else {
-            panic!("TabulatedBssrdf needs Material pointer")
+            panic!("SeparableBssrdf needs Material pointer")
         }
     }
     pub fn sw(&self, w: &Vector3f) -> Spectrum {
@@ -100,22 +79,46 @@ impl TabulatedBssrdf {
             (1.0 as Float - fr_dielectric(cos_theta(w), 1.0 as Float, self.eta)) / (c * PI),
         )
     }
+}
+
+pub enum Bssrdf {
+    Tabulated(TabulatedBssrdf),
+    Disney(DisneyBssrdf),
+}
+
+impl Bssrdf {
+    fn get_separable(&self) -> &SeparableBssrdf {
+        match self {
+            Bssrdf::Tabulated(bssrdf) => &bssrdf.separable,
+            Bssrdf::Disney(bssrdf) => &bssrdf.separable,
+        }
+    }
+    pub fn get_mode(&self) -> TransportMode {
+        self.get_separable().mode
+    }
+    pub fn get_eta(&self) -> Float {
+        self.get_separable().eta
+    }
+    pub fn sw(&self, w: &Vector3f) -> Spectrum {
+        self.get_separable().sw(w)
+    }
     pub fn sp(&self, pi: &SurfaceInteraction) -> Spectrum {
-        self.sr(pnt3_distancef(&self.po_p, &pi.get_p()))
+        self.sr(pnt3_distancef(&self.get_separable().po_p, &pi.get_p()))
     }
     pub fn pdf_sp(&self, pi: &SurfaceInteraction) -> Float {
+        let separable: &SeparableBssrdf = self.get_separable();
         // express $\pti-\pto$ and $\bold{n}_i$ with respect to local coordinates at $\pto$
-        let d: Vector3f = self.po_p - *pi.get_p();
+        let d: Vector3f = separable.po_p - *pi.get_p();
         let d_local: Vector3f = Vector3f {
-            x: vec3_dot_vec3f(&self.ss, &d),
-            y: vec3_dot_vec3f(&self.ts, &d),
-            z: nrm_dot_vec3f(&self.ns, &d),
+            x: vec3_dot_vec3f(&separable.ss, &d),
+            y: vec3_dot_vec3f(&separable.ts, &d),
+            z: nrm_dot_vec3f(&separable.ns, &d),
         };
         let pi_n = pi.get_n();
         let n_local: Normal3f = Normal3f {
-            x: vec3_dot_nrmf(&self.ss, &pi_n),
-            y: vec3_dot_nrmf(&self.ts, &pi_n),
-            z: nrm_dot_nrmf(&self.ns, &pi_n),
+            x: vec3_dot_nrmf(&separable.ss, &pi_n),
+            y: vec3_dot_nrmf(&separable.ts, &pi_n),
+            z: nrm_dot_nrmf(&separable.ns, &pi_n),
         };
         // compute BSSRDF profile radius under projection along each axis
         let r_proj: [Float; 3] = [
@@ -126,9 +129,9 @@ impl TabulatedBssrdf {
         // return combined probability from all BSSRDF sampling strategies
         let mut pdf: Float = 0.0;
         let axis_prob: [Float; 3] = [0.25 as Float, 0.25 as Float, 0.5 as Float];
-        let ch_prob: Float = 1.0 as Float / 3.0 as Float;
+        let ch_prob: Float = 1.0 as Float / Spectrum::N_SAMPLES as Float;
         for axis in XYZEnum::iter() {
-            for ch in RGBEnum::iter() {
+            for ch in 0..Spectrum::N_SAMPLES {
                 pdf += self.pdf_sr(ch, r_proj[axis as usize])
                     * n_local[axis].abs()
                     * ch_prob
@@ -145,7 +148,8 @@ impl TabulatedBssrdf {
         pi: &mut SurfaceInteraction,
         pdf: &mut Float,
     ) -> Spectrum {
-        // ProfilePhase pp(Prof::BSSRDFEvaluation);
+        let _prof = ProfilePhase::new(Prof::BssrdfEvaluation);
+        let separable: &SeparableBssrdf = self.get_separable();
         let mut u1: Float = u1; // shadowing input parameter
 
         // choose projection axis for BSSRDF sampling
@@ -153,47 +157,46 @@ impl TabulatedBssrdf {
         let vy: Vector3f;
         let vz: Vector3f;
         if u1 < 0.5 as Float {
-            vx = self.ss;
-            vy = self.ts;
-            vz = Vector3f::from(self.ns);
+            vx = separable.ss;
+            vy = separable.ts;
+            vz = Vector3f::from(separable.ns);
             u1 *= 2.0 as Float;
         } else if u1 < 0.75 as Float {
             // prepare for sampling rays with respect to _self.ss_
-            vx = self.ts;
-            vy = Vector3f::from(self.ns);
-            vz = self.ss;
+            vx = separable.ts;
+            vy = Vector3f::from(separable.ns);
+            vz = separable.ss;
             u1 = (u1 - 0.5 as Float) * 4.0 as Float;
         } else {
             // prepare for sampling rays with respect to _self.ts_
-            vx = Vector3f::from(self.ns);
-            vy = self.ss;
-            vz = self.ts;
+            vx = Vector3f::from(separable.ns);
+            vy = separable.ss;
+            vz = separable.ts;
             u1 = (u1 - 0.75 as Float) * 4.0 as Float;
         }
         // choose spectral channel for BSSRDF sampling
-        let ch: u8 = clamp_t((u1 * 3.0 as Float) as u8, 0_u8, 2_u8);
-        let ch_enum: RGBEnum = match ch {
-            0 => RGBEnum::Red,
-            1 => RGBEnum::Green,
-            _ => RGBEnum::Blue,
-        };
-        u1 = u1 * 3.0 as Float - ch as Float;
+        let ch: usize = clamp_t(
+            (u1 * Spectrum::N_SAMPLES as Float) as usize,
+            0_usize,
+            Spectrum::N_SAMPLES - 1,
+        );
+        u1 = u1 * Spectrum::N_SAMPLES as Float - ch as Float;
         // sample BSSRDF profile in polar coordinates
-        let r: Float = self.sample_sr(ch_enum, u2.x);
+        let r: Float = self.sample_sr(ch, u2.x);
         if r < 0.0 as Float {
             return Spectrum::default();
         }
         let phi: Float = 2.0 as Float * PI * u2.y;
         // compute BSSRDF profile bounds and intersection height
-        let r_max: Float = self.sample_sr(ch_enum, 0.999 as Float);
+        let r_max: Float = self.sample_sr(ch, 0.999 as Float);
         if r >= r_max {
             return Spectrum::default();
         }
         let l: Float = 2.0 as Float * (r_max * r_max - r * r).sqrt();
         // compute BSSRDF sampling ray segment
         let mut base: InteractionCommon = InteractionCommon::default();
-        base.p = self.po_p + (vx * phi.cos() + vy * phi.sin()) * r - vz * (l * 0.5 as Float);
-        base.time = self.po_time;
+        base.p = separable.po_p + (vx * phi.cos() + vy * phi.sin()) * r - vz * (l * 0.5 as Float);
+        base.time = separable.po_time;
         let p_target: Point3f = base.p + vz * l;
 
         // intersect BSSRDF sampling ray against the scene geometry
@@ -229,7 +232,7 @@ impl TabulatedBssrdf {
                     let geo_prim = unsafe { &*geo_prim_raw };
                     if let Some(material) = geo_prim.get_material() {
                         //     if (ptr->si.primitive->GetMaterial() == this->material) {
-                        if Arc::ptr_eq(&material, &self.material) {
+                        if Arc::ptr_eq(&material, &separable.material) {
                             //         IntersectionChain *next = ARENA_ALLOC(arena, IntersectionChain)();
                             //         ptr->next = next;
                             //         ptr = next;
@@ -267,6 +270,7 @@ impl TabulatedBssrdf {
             pi.common.medium_interface = None;
         }
         pi.uv = selected_si.uv;
+        pi.face_index = selected_si.face_index;
         pi.dpdu = selected_si.dpdu;
         pi.dpdv = selected_si.dpdv;
         pi.dndu = selected_si.dndu;
@@ -295,11 +299,115 @@ impl TabulatedBssrdf {
         *pdf = self.pdf_sp(chain[selected].borrow()) / n_found as Float;
         self.sp(chain[selected].borrow())
     }
+    pub fn sr(&self, r: Float) -> Spectrum {
+        match self {
+            Bssrdf::Tabulated(bssrdf) => bssrdf.sr(r),
+            Bssrdf::Disney(bssrdf) => bssrdf.sr(r),
+        }
+    }
+    pub fn pdf_sr(&self, ch: usize, r: Float) -> Float {
+        match self {
+            Bssrdf::Tabulated(bssrdf) => bssrdf.pdf_sr(ch, r),
+            Bssrdf::Disney(bssrdf) => bssrdf.pdf_sr(ch, r),
+        }
+    }
+    pub fn sample_sr(&self, ch: usize, u: Float) -> Float {
+        match self {
+            Bssrdf::Tabulated(bssrdf) => bssrdf.sample_sr(ch, u),
+            Bssrdf::Disney(bssrdf) => bssrdf.sample_sr(ch, u),
+        }
+    }
+    // Bssrdf
+    pub fn s(&self, pi: &SurfaceInteraction, wi: &Vector3f) -> Spectrum {
+        let _prof = ProfilePhase::new(Prof::BssrdfEvaluation);
+        match self {
+            Bssrdf::Tabulated(bssrdf) => {
+                let ft: Float = fr_dielectric(
+                    cos_theta(&bssrdf.separable.po_wo),
+                    1.0 as Float,
+                    bssrdf.separable.eta,
+                );
+                self.sp(pi) * self.sw(wi) * (1.0 as Float - ft)
+            }
+            Bssrdf::Disney(bssrdf) => self.sp(pi) * bssrdf.s(pi, wi),
+        }
+    }
+    pub fn sample_s(
+        &self,
+        scene: &Scene,
+        u1: Float,
+        u2: Point2f,
+        pdf: &mut Float,
+    ) -> (Spectrum, Option<SurfaceInteraction>) {
+        let _prof = ProfilePhase::new(Prof::BssrdfSampling);
+        let mut si: SurfaceInteraction = SurfaceInteraction::default();
+        let sp: Spectrum = self.sample_sp(scene, u1, u2, &mut si, pdf);
+        if !sp.is_black() {
+            // initialize material model at sampled surface interaction
+            si.bsdf = Some(Bsdf::new(&si, 1.0));
+            if let Some(bsdf) = &mut si.bsdf {
+                bsdf.add(Bxdf::Bssrdf(SeparableBssrdfAdapter::new(
+                    self.clone(),
+                    self.get_mode(),
+                    self.get_eta(),
+                )));
+            }
+            si.common.wo = Vector3f::from(si.shading.n);
+            (sp, Some(si))
+        } else {
+            (sp, None)
+        }
+    }
+}
+
+impl Clone for Bssrdf {
+    fn clone(&self) -> Bssrdf {
+        match self {
+            Bssrdf::Tabulated(bssrdf) => Bssrdf::Tabulated(bssrdf.clone()),
+            Bssrdf::Disney(bssrdf) => Bssrdf::Disney(bssrdf.clone()),
+        }
+    }
+}
+
+pub struct TabulatedBssrdf {
+    pub separable: SeparableBssrdf,
+    // TabulatedBSSRDF Private Data
+    pub table: Arc<BssrdfTable>,
+    pub sigma_t: Spectrum,
+    pub rho: Spectrum,
+}
+
+impl TabulatedBssrdf {
+    pub fn new(
+        po: &SurfaceInteraction,
+        material_opt: Option<Arc<Material>>,
+        mode: TransportMode,
+        eta: Float,
+        sigma_a: &Spectrum,
+        sigma_s: &Spectrum,
+        table: Arc<BssrdfTable>,
+    ) -> Self {
+        let sigma_t: Spectrum = *sigma_a + *sigma_s;
+        let mut rho: Spectrum = Spectrum::new(0.0 as Float);
+        for c in 0..Spectrum::N_SAMPLES {
+            rho[c] = if sigma_t[c] != 0.0 as Float {
+                sigma_s[c] / sigma_t[c]
+            } else {
+                0.0 as Float
+            };
+        }
+        TabulatedBssrdf {
+            separable: SeparableBssrdf::new(po, material_opt, mode, eta),
+            table,
+            sigma_t,
+            rho,
+        }
+    }
     pub fn sr(&self, r: Float) -> Spectrum {
         let mut sr: Spectrum = Spectrum::default();
-        for ch in 0..3_usize {
+        for ch in 0..Spectrum::N_SAMPLES {
             // convert $r$ into unitless optical radius $r_{\roman{optical}}$
-            let r_optical: Float = r * self.sigma_t.c[ch];
+            let r_optical: Float = r * self.sigma_t[ch];
             // compute spline weights to interpolate BSSRDF on channel _ch_
             let mut rho_offset: i32 = 0;
             let mut radius_offset: i32 = 0;
@@ -307,7 +415,7 @@ impl TabulatedBssrdf {
             let mut radius_weights: [Float; 4] = [0.0 as Float; 4];
             if !catmull_rom_weights(
                 &self.table.rho_samples,
-                self.rho.c[ch],
+                self.rho[ch],
                 &mut rho_offset,
                 &mut rho_weights,
             ) || !catmull_rom_weights(
@@ -335,13 +443,13 @@ impl TabulatedBssrdf {
             if r_optical != 0.0 as Float {
                 srf /= 2.0 as Float * PI * r_optical;
             }
-            sr.c[ch] = srf;
+            sr[ch] = srf;
         }
         // transform BSSRDF value into world space units
         sr *= self.sigma_t * self.sigma_t;
         sr.clamp(0.0 as Float, std::f32::INFINITY as Float)
     }
-    pub fn pdf_sr(&self, ch: RGBEnum, r: Float) -> Float {
+    pub fn pdf_sr(&self, ch: usize, r: Float) -> Float {
         // convert $r$ into unitless optical radius $r_{\roman{optical}}$
         let r_optical: Float = r * self.sigma_t[ch];
         // compute spline weights to interpolate BSSRDF density on channel _ch_
@@ -387,7 +495,7 @@ impl TabulatedBssrdf {
         }
         (0.0 as Float).max(sr * self.sigma_t[ch] * self.sigma_t[ch] / rho_eff)
     }
-    pub fn sample_sr(&self, ch: RGBEnum, u: Float) -> Float {
+    pub fn sample_sr(&self, ch: usize, u: Float) -> Float {
         if self.sigma_t[ch] == 0.0 as Float {
             return -1.0 as Float;
         }
@@ -402,59 +510,115 @@ impl TabulatedBssrdf {
             None,
         ) / self.sigma_t[ch]
     }
-    // Bssrdf
-    pub fn s(&self, pi: &SurfaceInteraction, wi: &Vector3f) -> Spectrum {
-        // ProfilePhase pp(Prof::BSSRDFEvaluation);
-        let ft: Float = fr_dielectric(cos_theta(&self.po_wo), 1.0 as Float, self.eta);
-        self.sp(pi) * self.sw(wi) * (1.0 as Float - ft)
-    }
-    pub fn sample_s(
-        &self,
-        // the next three (extra) parameters are used for SeparableBssrdfAdapter
-        sc: TabulatedBssrdf,
-        mode: TransportMode,
-        eta: Float,
-        // done
-        scene: &Scene,
-        u1: Float,
-        u2: Point2f,
-        pdf: &mut Float,
-    ) -> (Spectrum, Option<SurfaceInteraction>) {
-        // ProfilePhase pp(Prof::BSSRDFSampling);
-        let mut si: SurfaceInteraction = SurfaceInteraction::default();
-        let sp: Spectrum = self.sample_sp(scene, u1, u2, &mut si, pdf);
-        if !sp.is_black() {
-            // initialize material model at sampled surface interaction
-            si.bsdf = Some(Bsdf::new(&si, 1.0));
-            if let Some(bsdf) = &mut si.bsdf {
-                bsdf.add(Bxdf::Bssrdf(SeparableBssrdfAdapter::new(sc, mode, eta)));
-            }
-            si.common.wo = Vector3f::from(si.shading.n);
-            (sp, Some(si))
-        } else {
-            (sp, None)
-        }
-    }
 }
 
 impl Clone for TabulatedBssrdf {
     fn clone(&self) -> TabulatedBssrdf {
         TabulatedBssrdf {
-            po_p: self.po_p,
-            po_time: self.po_time,
-            po_wo: self.po_wo,
-            eta: self.eta,
-            ns: self.ns,
-            ss: self.ss,
-            ts: self.ts,
-            material: self.material.clone(),
-            mode: self.mode,
+            separable: self.separable.clone(),
             table: self.table.clone(),
             sigma_t: self.sigma_t,
             rho: self.rho,
         }
     }
 }
+
+/// Normalized diffusion profile of Burley, "Extending the
+/// Disney BRDF to a BSDF with Integrated Subsurface Scattering"
+/// (2015): a sum of two exponentials, parameterized by the diffuse
+/// reflectance _r_ and the scatter distance _d_.
+#[derive(Clone)]
+pub struct DisneyBssrdf {
+    pub separable: SeparableBssrdf,
+    pub r: Spectrum,
+    pub d: Spectrum,
+}
+
+impl DisneyBssrdf {
+    pub fn new(
+        r: &Spectrum,
+        d: &Spectrum,
+        po: &SurfaceInteraction,
+        eta: Float,
+        material_opt: Option<Arc<Material>>,
+        mode: TransportMode,
+    ) -> Self {
+        DisneyBssrdf {
+            separable: SeparableBssrdf::new(po, material_opt, mode, eta),
+            r: *r,
+            // 0.2 factor comes from personal communication from Brent
+            // Burley and Matt Chiang.
+            d: *d * 0.2 as Float,
+        }
+    }
+    /// Directional part of Burley's BSSRDF (the spatial profile is
+    /// applied by the caller): fade out light entering above the
+    /// tangent plane at _po_ and apply the Schlick weights.
+    pub fn s(&self, pi: &SurfaceInteraction, wi: &Vector3f) -> Float {
+        let a: Vector3f = (*pi.get_p() - self.separable.po_p).normalize();
+        let mut fade: Float = 1.0;
+        let n: Vector3f = Vector3f::from(self.separable.ns);
+        let cos_theta: Float = vec3_dot_vec3f(&a, &n);
+        if cos_theta > 0.0 as Float {
+            // point on or above surface plane
+            let sin_theta: Float = (0.0 as Float)
+                .max(1.0 as Float - cos_theta * cos_theta)
+                .sqrt();
+            let a2: Vector3f = n * sin_theta - (a - n * cos_theta) * cos_theta / sin_theta;
+            fade = (0.0 as Float).max(vec3_dot_nrmf(&a2, &pi.shading.n));
+        }
+        let fo: Float =
+            schlick_weight(nrm_abs_dot_vec3f(&self.separable.ns, &self.separable.po_wo));
+        let fi: Float = schlick_weight(nrm_abs_dot_vec3f(&self.separable.ns, wi));
+        fade * (1.0 as Float - fo / 2.0 as Float) * (1.0 as Float - fi / 2.0 as Float) / PI
+    }
+    pub fn sr(&self, r: Float) -> Spectrum {
+        // avoid singularity at r == 0
+        let r: Float = r.max(1e-6 as Float);
+        let mut sr: Spectrum = Spectrum::default();
+        for ch in 0..Spectrum::N_SAMPLES {
+            let d: Float = self.d[ch];
+            if d > 0.0 as Float {
+                sr[ch] = self.r[ch] * ((-r / d).exp() + (-r / (3.0 as Float * d)).exp())
+                    / (8.0 as Float * PI * d * r);
+            }
+        }
+        sr
+    }
+    pub fn pdf_sr(&self, ch: usize, r: Float) -> Float {
+        // avoid singularity at r == 0
+        let r: Float = r.max(1e-6 as Float);
+        let d: Float = self.d[ch];
+        if d <= 0.0 as Float {
+            return 0.0 as Float;
+        }
+        // weight the two individual PDFs as per the sampling
+        // frequency in sample_sr()
+        0.25 as Float * (-r / d).exp() / (2.0 as Float * PI * d * r)
+            + 0.75 as Float * (-r / (3.0 as Float * d)).exp() / (6.0 as Float * PI * d * r)
+    }
+    pub fn sample_sr(&self, ch: usize, u: Float) -> Float {
+        if self.d[ch] <= 0.0 as Float {
+            return -1.0 as Float;
+        }
+        // the diffusion profile is a mix of two exponentials, sample
+        // the first one with probability 1/4 (its share of the
+        // profile's energy)
+        if u < 0.25 as Float {
+            let u: Float = (u * 4.0 as Float).min(FLOAT_ONE_MINUS_EPSILON);
+            self.d[ch] * (1.0 as Float / (1.0 as Float - u)).ln()
+        } else {
+            let u: Float = ((u - 0.25 as Float) / 0.75 as Float).min(FLOAT_ONE_MINUS_EPSILON);
+            3.0 as Float * self.d[ch] * (1.0 as Float / (1.0 as Float - u)).ln()
+        }
+    }
+}
+
+fn schlick_weight(cos_theta: Float) -> Float {
+    let m: Float = clamp_t(1.0 as Float - cos_theta, 0.0 as Float, 1.0 as Float);
+    (m * m) * (m * m) * m
+}
+
 pub struct BssrdfTable {
     pub n_rho_samples: i32,
     pub n_radius_samples: i32,
@@ -490,13 +654,13 @@ impl BssrdfTable {
 }
 
 pub struct SeparableBssrdfAdapter {
-    pub bssrdf: TabulatedBssrdf,
+    pub bssrdf: Bssrdf,
     pub mode: TransportMode,
     pub eta2: Float,
 }
 
 impl SeparableBssrdfAdapter {
-    pub fn new(bssrdf: TabulatedBssrdf, mode: TransportMode, eta: Float) -> Self {
+    pub fn new(bssrdf: Bssrdf, mode: TransportMode, eta: Float) -> Self {
         SeparableBssrdfAdapter {
             bssrdf,
             mode,
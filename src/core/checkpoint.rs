@@ -0,0 +1,226 @@
+//! Long renders can store their state periodically in a checkpoint
+//! file and continue from there after an interruption (e.g. the
+//! preemption of a render farm job). Checkpoints get written if the
+//! film's "checkpoint" parameter names a file (or the `--checkpoint`
+//! command line option was used), "resume" continues from an
+//! existing checkpoint:
+//!
+//! ```text
+//! Film "image" "string filename" "out.exr"
+//!   "string checkpoint" "out.ckpt" "float checkpointinterval" [ 600 ]
+//!   "bool resume" "true"
+//! ```
+//!
+//! The [SamplerIntegrators](../integrator/enum.SamplerIntegrator.html)
+//! store the film's weighted sums, splats and the number of samples
+//! taken in each image tile, the
+//! [SPPMIntegrator](../../integrators/sppm/struct.SPPMIntegrator.html)
+//! stores the per-pixel photon statistics after each iteration. A
+//! resumed render continues towards the same samples per pixel (or
+//! iterations), tiles use the same sampler seeds as without the
+//! interruption.
+//!
+//! A checkpoint file starts with a header (all values little
+//! endian), followed by the state of the integrator:
+//!
+//! ```text
+//! magic      8 bytes  "PBRTCKPT"
+//! version    u32      CHECKPOINT_VERSION
+//! kind       u32      see CheckpointKind
+//! bounds     4 x i32  cropped pixel bounds (x0 y0 x1 y1)
+//! target     i64      samples per pixel (or SPPM iterations)
+//! ```
+
+// std
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+// others
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+// pbrt
+use crate::core::geometry::Bounds2i;
+use crate::core::pbrt::Float;
+
+/// Incremented whenever the layout of a checkpoint file changes.
+pub const CHECKPOINT_VERSION: u32 = 1;
+const CHECKPOINT_MAGIC: &[u8; 8] = b"PBRTCKPT";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckpointKind {
+    /// film state plus the number of samples taken per image tile
+    Tiles = 1,
+    /// per-pixel SPPM statistics
+    Sppm = 2,
+}
+
+#[derive(Debug, Clone)]
+pub struct CheckpointParams {
+    /// the checkpoint file
+    pub filename: String,
+    /// minimum time between two checkpoints
+    pub interval: Duration,
+    /// continue from an existing checkpoint file
+    pub resume: bool,
+}
+
+/// Describes the render a checkpoint belongs to.
+#[derive(Debug, Clone, Copy)]
+pub struct CheckpointHeader {
+    pub kind: CheckpointKind,
+    pub pixel_bounds: Bounds2i,
+    pub target: i64,
+}
+
+/// Decides when the next periodic checkpoint is due.
+pub struct CheckpointTimer {
+    interval: Duration,
+    last: Instant,
+}
+
+impl CheckpointTimer {
+    pub fn new(interval: Duration) -> Self {
+        CheckpointTimer {
+            interval,
+            last: Instant::now(),
+        }
+    }
+    /// Returns true (and restarts the timer) if at least *interval*
+    /// passed since the last checkpoint.
+    pub fn due(&mut self) -> bool {
+        if self.last.elapsed() >= self.interval {
+            self.last = Instant::now();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Write a checkpoint: *write_state* appends the integrator's state
+/// after the header. Everything goes to a temporary file first, which
+/// replaces *filename* once it is complete, so an interruption while
+/// writing never destroys the previous checkpoint.
+pub fn write_checkpoint<F>(filename: &str, header: &CheckpointHeader, write_state: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let tmp_filename: String = format!("{}.tmp", filename);
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_filename)?);
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_u32::<LittleEndian>(CHECKPOINT_VERSION)?;
+        writer.write_u32::<LittleEndian>(header.kind as u32)?;
+        for v in &[
+            header.pixel_bounds.p_min.x,
+            header.pixel_bounds.p_min.y,
+            header.pixel_bounds.p_max.x,
+            header.pixel_bounds.p_max.y,
+        ] {
+            writer.write_i32::<LittleEndian>(*v)?;
+        }
+        writer.write_i64::<LittleEndian>(header.target)?;
+        write_state(&mut writer)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+    }
+    std::fs::rename(&tmp_filename, filename)
+}
+
+/// Read a checkpoint written by
+/// [write_checkpoint()](fn.write_checkpoint.html): the header has to
+/// match *header*, *read_state* reads the integrator's state.
+pub fn read_checkpoint<F, T>(filename: &str, header: &CheckpointHeader, read_state: F) -> Result<T>
+where
+    F: FnOnce(&mut BufReader<File>) -> Result<T>,
+{
+    let mut reader = BufReader::new(File::open(filename)?);
+    let mut magic: [u8; 8] = [0_u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != CHECKPOINT_MAGIC {
+        return Err(invalid_data("not a checkpoint file"));
+    }
+    let version: u32 = reader.read_u32::<LittleEndian>()?;
+    if version != CHECKPOINT_VERSION {
+        return Err(invalid_data(&format!(
+            "checkpoint version {} (expected {})",
+            version, CHECKPOINT_VERSION
+        )));
+    }
+    let kind: u32 = reader.read_u32::<LittleEndian>()?;
+    if kind != header.kind as u32 {
+        return Err(invalid_data("checkpoint was written by another integrator"));
+    }
+    let mut bounds: [i32; 4] = [0_i32; 4];
+    for v in bounds.iter_mut() {
+        *v = reader.read_i32::<LittleEndian>()?;
+    }
+    let expected: [i32; 4] = [
+        header.pixel_bounds.p_min.x,
+        header.pixel_bounds.p_min.y,
+        header.pixel_bounds.p_max.x,
+        header.pixel_bounds.p_max.y,
+    ];
+    if bounds != expected {
+        return Err(invalid_data(&format!(
+            "checkpoint covers pixels {:?} (expected {:?})",
+            bounds, expected
+        )));
+    }
+    let target: i64 = reader.read_i64::<LittleEndian>()?;
+    if target != header.target {
+        return Err(invalid_data(&format!(
+            "checkpoint was written for {} samples (expected {})",
+            target, header.target
+        )));
+    }
+    read_state(&mut reader)
+}
+
+/// Returns true if a previous render left a checkpoint to continue
+/// from. Prints a warning if resuming was requested, but there is no
+/// checkpoint (yet).
+pub fn can_resume(params: &CheckpointParams) -> bool {
+    if !params.resume {
+        return false;
+    }
+    if !Path::new(&params.filename).exists() {
+        println!(
+            "WARNING: No checkpoint {:?} to resume from, starting from scratch",
+            params.filename
+        );
+        return false;
+    }
+    true
+}
+
+pub fn write_floats<W: Write>(writer: &mut W, values: &[Float]) -> Result<()> {
+    for v in values {
+        writer.write_f32::<LittleEndian>(*v)?;
+    }
+    Ok(())
+}
+
+pub fn read_floats<R: Read>(reader: &mut R, values: &mut [Float]) -> Result<()> {
+    reader.read_f32_into::<LittleEndian>(values)
+}
+
+/// Checks the number of stored elements against the expected one.
+pub fn read_len<R: Read>(reader: &mut R, expected: usize) -> Result<()> {
+    let len: u64 = reader.read_u64::<LittleEndian>()?;
+    if len != expected as u64 {
+        return Err(invalid_data(&format!(
+            "checkpoint stores {} elements (expected {})",
+            len, expected
+        )));
+    }
+    Ok(())
+}
+
+pub fn write_len<W: Write>(writer: &mut W, len: usize) -> Result<()> {
+    writer.write_u64::<LittleEndian>(len as u64)
+}
+
+pub fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.to_string())
+}
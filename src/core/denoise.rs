@@ -0,0 +1,207 @@
+//! Denoising of the final image, before it gets written to file.
+//!
+//! The filter is a joint non-local means filter (see Rousselle et
+//! al., "Adaptive Rendering with Non-Local Means Filtering" and
+//! "Robust Denoising using Feature and Color Information"): each
+//! pixel becomes a weighted average of the pixels in a search window
+//! around it. The weight of a neighbor combines
+//!
+//! * the difference of small patches of color around both pixels,
+//!   normalized by the per-pixel variance of the Monte Carlo
+//!   estimates (so noisy pixels get smoothed more than converged
+//!   ones), and
+//! * the difference of the albedo and shading normal at the first
+//!   intersection, which keeps texture and geometric edges sharp.
+//!
+//! The [Film](../film/struct.Film.html) collects all required buffers
+//! and denoises the image if its "denoise" parameter is set (or the
+//! `--denoise` command line option was used):
+//!
+//! ```text
+//! Film "image" "string filename" "out.png" "bool denoise" "true"
+//!   "integer denoiseradius" [ 7 ] "float denoisestrength" [ 0.45 ]
+//! ```
+//!
+//! The filter can also be used on its own:
+//!
+//! ```rust
+//! use rs_pbrt::core::denoise::{denoise, DenoiseParams};
+//! use rs_pbrt::core::pbrt::Float;
+//!
+//! let (width, height): (usize, usize) = (8, 8);
+//! // a noisy gray image on a single, flat, white surface
+//! let rgb: Vec<Float> = (0..width * height * 3)
+//!     .map(|i| if (i / 3) % 2 == 0 { 0.4 } else { 0.6 })
+//!     .collect();
+//! let variance: Vec<Float> = vec![0.02; width * height * 3];
+//! let albedo: Vec<Float> = vec![1.0; width * height * 3];
+//! let normal: Vec<Float> = vec![0.0; width * height * 3];
+//! let denoised: Vec<Float> = denoise(
+//!     width,
+//!     height,
+//!     &rgb,
+//!     &variance,
+//!     &albedo,
+//!     &normal,
+//!     &DenoiseParams::default(),
+//! );
+//! assert!(denoised.iter().all(|v| (v - 0.5).abs() < 0.05));
+//! ```
+
+// others
+use rayon::prelude::*;
+// pbrt
+use crate::core::pbrt::Float;
+
+/// Avoids a division by zero for pixels without any variance.
+const VARIANCE_EPSILON: Float = 1e-10;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DenoiseParams {
+    /// half size (in pixels) of the search window
+    pub radius: i32,
+    /// half size (in pixels) of the patches which get compared
+    pub patch_radius: i32,
+    /// strength of the filter, larger values smooth more
+    pub k: Float,
+    /// how much variance gets cancelled out of the color distance
+    pub alpha: Float,
+    /// albedo differences well below this value are ignored
+    pub sigma_albedo: Float,
+    /// normal differences well below this value are ignored
+    pub sigma_normal: Float,
+}
+
+impl Default for DenoiseParams {
+    fn default() -> Self {
+        DenoiseParams {
+            radius: 7,
+            patch_radius: 1,
+            k: 0.45 as Float,
+            alpha: 1.0 as Float,
+            sigma_albedo: 0.1 as Float,
+            sigma_normal: 0.2 as Float,
+        }
+    }
+}
+
+/// Denoise an image of *width* times *height* pixels. *rgb*,
+/// *variance* (of each pixel's RGB estimate), *albedo* and *normal*
+/// store three values per pixel (scanline order). Empty feature
+/// buffers are ignored.
+pub fn denoise(
+    width: usize,
+    height: usize,
+    rgb: &[Float],
+    variance: &[Float],
+    albedo: &[Float],
+    normal: &[Float],
+    params: &DenoiseParams,
+) -> Vec<Float> {
+    let mut denoised: Vec<Float> = vec![0.0 as Float; rgb.len()];
+    if width == 0 || height == 0 {
+        return denoised;
+    }
+    let filter = NlMeansFilter {
+        width: width as i32,
+        height: height as i32,
+        rgb,
+        variance,
+        albedo,
+        normal,
+        params,
+    };
+    denoised
+        .par_chunks_mut(3 * width)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for (x, pixel) in row.chunks_mut(3).enumerate() {
+                filter.filter_pixel(x as i32, y as i32, pixel);
+            }
+        });
+    denoised
+}
+
+struct NlMeansFilter<'a> {
+    width: i32,
+    height: i32,
+    rgb: &'a [Float],
+    variance: &'a [Float],
+    albedo: &'a [Float],
+    normal: &'a [Float],
+    params: &'a DenoiseParams,
+}
+
+impl<'a> NlMeansFilter<'a> {
+    fn index(&self, x: i32, y: i32) -> usize {
+        // clamp to the image borders
+        let x: i32 = x.max(0).min(self.width - 1);
+        let y: i32 = y.max(0).min(self.height - 1);
+        (y * self.width + x) as usize
+    }
+    fn filter_pixel(&self, x: i32, y: i32, result: &mut [Float]) {
+        let mut sum: [Float; 3] = [0.0 as Float; 3];
+        let mut weight_sum: Float = 0.0 as Float;
+        let r: i32 = self.params.radius;
+        for qy in (y - r).max(0)..=(y + r).min(self.height - 1) {
+            for qx in (x - r).max(0)..=(x + r).min(self.width - 1) {
+                let weight: Float = self
+                    .color_weight(x, y, qx, qy)
+                    .min(self.feature_weight(x, y, qx, qy));
+                let q: usize = 3 * self.index(qx, qy);
+                for (c, item) in sum.iter_mut().enumerate() {
+                    *item += weight * self.rgb[q + c];
+                }
+                weight_sum += weight;
+            }
+        }
+        // the pixel itself always has weight one
+        for (c, item) in result.iter_mut().enumerate() {
+            *item = sum[c] / weight_sum;
+        }
+    }
+    /// Variance normalized distance of the color patches around the
+    /// pixels *p* and *q*.
+    fn color_weight(&self, px: i32, py: i32, qx: i32, qy: i32) -> Float {
+        let f: i32 = self.params.patch_radius;
+        let k2: Float = self.params.k * self.params.k;
+        let mut distance: Float = 0.0 as Float;
+        for oy in -f..=f {
+            for ox in -f..=f {
+                let p: usize = 3 * self.index(px + ox, py + oy);
+                let q: usize = 3 * self.index(qx + ox, qy + oy);
+                for c in 0..3 {
+                    let var_p: Float = self.variance[p + c];
+                    let var_q: Float = self.variance[q + c];
+                    let cancel: Float = self.params.alpha * (var_p + var_p.min(var_q));
+                    let normalization: Float = VARIANCE_EPSILON + k2 * (var_p + var_q);
+                    let diff: Float = self.rgb[p + c] - self.rgb[q + c];
+                    distance += (diff * diff - cancel) / normalization;
+                }
+            }
+        }
+        let n: Float = (3 * (2 * f + 1) * (2 * f + 1)) as Float;
+        (-(distance / n).max(0.0 as Float)).exp()
+    }
+    /// Cross-bilateral weight based on albedo and shading normal.
+    fn feature_weight(&self, px: i32, py: i32, qx: i32, qy: i32) -> Float {
+        let p: usize = 3 * self.index(px, py);
+        let q: usize = 3 * self.index(qx, qy);
+        let mut distance: Float = 0.0 as Float;
+        for (feature, sigma) in [
+            (self.albedo, self.params.sigma_albedo),
+            (self.normal, self.params.sigma_normal),
+        ] {
+            if feature.is_empty() {
+                continue;
+            }
+            let mut d2: Float = 0.0 as Float;
+            for c in 0..3 {
+                let diff: Float = feature[p + c] - feature[q + c];
+                d2 += diff * diff;
+            }
+            distance += d2 / (sigma * sigma);
+        }
+        (-distance).exp()
+    }
+}
@@ -0,0 +1,464 @@
+//! Distributed rendering spreads the tiles of an image over several
+//! processes, possibly on different machines. One process acts as the
+//! coordinator: it parses the scene, hands out work units (an image
+//! tile and a range of sample numbers) over TCP and merges the
+//! returned film tiles into its image. Worker processes parse the
+//! same scene, render the work units they get and send back the
+//! accumulated samples:
+//!
+//! ```text
+//! rs_pbrt --coordinator 0.0.0.0:7878 --unit-spp 64 -o out.exr scene.pbrt
+//! rs_pbrt --worker render01:7878 -t 16 scene.pbrt
+//! ```
+//!
+//! Each worker thread opens its own connection. If a connection is
+//! lost (e.g. because the worker process died), the work unit it was
+//! rendering goes back into the queue and gets rendered by another
+//! worker (workers can join at any time). Connections which don't
+//! send the handshake within a few seconds get closed. While a
+//! worker is connected it sends a heartbeat every few seconds, so a
+//! machine which vanishes without closing its connections (e.g.
+//! because it lost power or network) gets noticed once its heartbeats
+//! stop, and its work unit gets requeued as well.
+//!
+//! The result is deterministic: the sampler of a work unit is seeded
+//! from its tile and first sample (like the passes of progressive
+//! rendering), and the coordinator merges the tiles in the order of
+//! the work units, no matter which worker finished first.
+//!
+//! All values are little endian. A worker starts with a handshake,
+//! which the coordinator answers with a single byte (1 if the render
+//! matches, 0 otherwise):
+//!
+//! ```text
+//! magic      8 bytes  "PBRTDIST"
+//! version    u32      PROTOCOL_VERSION
+//! bounds     4 x i32  cropped pixel bounds (x0 y0 x1 y1)
+//! spp        i64      samples per pixel
+//! ```
+//!
+//! Then the coordinator sends work units (or a 0 byte once the image
+//! is done):
+//!
+//! ```text
+//! message    u8       1
+//! index      u64      index of the work unit
+//! tile       u64      index of the tile (seeds the sampler)
+//! bounds     4 x i32  sample bounds of the tile
+//! samples    2 x i64  first sample and end sample (exclusive)
+//! ```
+//!
+//! The worker answers each with the unit's index followed by the
+//! state of the film tile. Heartbeats can come at any time in between:
+//!
+//! ```text
+//! message    u8       1 (or 2 for a heartbeat, without any data)
+//! index      u64      index of the work unit
+//! tile                see FilmTile::write_state()
+//! ```
+
+// std
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+// others
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+// pbrt
+use crate::core::checkpoint::invalid_data;
+use crate::core::film::{Film, FilmTile};
+use crate::core::geometry::{Bounds2i, Point2i};
+use crate::core::progress::ProgressReporter;
+
+/// Incremented whenever the messages between coordinator and
+/// workers change.
+pub const PROTOCOL_VERSION: u32 = 2;
+const PROTOCOL_MAGIC: &[u8; 8] = b"PBRTDIST";
+const MESSAGE_DONE: u8 = 0;
+const MESSAGE_WORK: u8 = 1;
+const MESSAGE_HEARTBEAT: u8 = 2;
+/// Workers started before the coordinator keep trying to connect.
+const CONNECT_ATTEMPTS: u32 = 120;
+const CONNECT_DELAY: Duration = Duration::from_millis(500);
+const ACCEPT_DELAY: Duration = Duration::from_millis(50);
+/// Connections which don't send a handshake in time get dropped.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Workers send a heartbeat this often ...
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// ... and get dropped if nothing arrives for this long.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+pub enum DistributedRole {
+    /// serve work units to workers connecting to *address*, each unit
+    /// covers *samples_per_unit* samples of a tile (all by default)
+    Coordinator {
+        address: String,
+        samples_per_unit: Option<i64>,
+    },
+    /// render work units for the coordinator at *address*
+    Worker { address: String },
+}
+
+/// Describes the render, workers have to agree with the coordinator.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderHeader {
+    pub pixel_bounds: Bounds2i,
+    pub samples_per_pixel: i64,
+}
+
+/// Take the samples *first_sample* up to (but not including)
+/// *end_sample* of all pixels of a tile.
+#[derive(Debug, Clone, Copy)]
+pub struct WorkUnit {
+    pub tile_index: usize,
+    pub tile_bounds: Bounds2i,
+    pub first_sample: i64,
+    pub end_sample: i64,
+}
+
+/// The work units which still have to be rendered, shared by the
+/// threads serving the workers.
+struct WorkQueue {
+    state: Mutex<WorkQueueState>,
+    changed: Condvar,
+}
+
+struct WorkQueueState {
+    pending: VecDeque<usize>,
+    n_finished: usize,
+    n_units: usize,
+}
+
+impl WorkQueue {
+    fn new(n_units: usize) -> Self {
+        WorkQueue {
+            state: Mutex::new(WorkQueueState {
+                pending: (0..n_units).collect(),
+                n_finished: 0,
+                n_units,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+    /// Returns the index of the next work unit, blocks while other
+    /// workers might still give up on theirs. Returns None once all
+    /// work units are finished.
+    fn take(&self) -> Option<usize> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(i) = state.pending.pop_front() {
+                return Some(i);
+            }
+            if state.n_finished == state.n_units {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.n_finished += 1;
+        self.changed.notify_all();
+    }
+    /// Hand the work unit of a lost worker to the next one.
+    fn requeue(&self, i: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.pending.push_front(i);
+        self.changed.notify_one();
+    }
+    fn is_finished(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.n_finished == state.n_units
+    }
+}
+
+/// Serve *units* to workers connecting to *address* and merge the
+/// returned tiles into *film*. Returns once all work units are
+/// merged.
+pub fn serve_work_units(
+    address: &str,
+    header: &RenderHeader,
+    film: &Film,
+    units: &[WorkUnit],
+) -> Result<()> {
+    let listener: TcpListener = TcpListener::bind(address)?;
+    // poll, so we can stop accepting workers once the image is done
+    listener.set_nonblocking(true)?;
+    println!("Waiting for workers on {} ...", listener.local_addr()?);
+    let queue: WorkQueue = WorkQueue::new(units.len());
+    let reporter: ProgressReporter = ProgressReporter::new(units.len() as u64, "Rendering");
+    let queue = &queue;
+    let reporter = &reporter;
+    crossbeam::scope(|scope| {
+        let (tile_tx, tile_rx) = crossbeam_channel::unbounded();
+        // spawn thread to accept workers, one thread per connection
+        scope.spawn(move |scope| {
+            while !queue.is_finished() {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        let tile_tx = tile_tx.clone();
+                        scope.spawn(move |_| {
+                            if let Err(e) =
+                                serve_worker(stream, header, film, units, queue, &tile_tx)
+                            {
+                                println!("WARNING: Dropped worker {}: {}", peer, e);
+                            }
+                        });
+                    }
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_DELAY);
+                    }
+                    Err(e) => println!("WARNING: Unable to accept worker: {}", e),
+                }
+            }
+        });
+        // merge the tiles in the order of the work units, so the
+        // image doesn't depend on which worker finished first
+        let mut received: BTreeMap<usize, FilmTile> = BTreeMap::new();
+        let mut next_unit: usize = 0;
+        for (i, film_tile) in tile_rx.iter() {
+            received.insert(i, film_tile);
+            reporter.update(1);
+            while let Some(film_tile) = received.remove(&next_unit) {
+                film.merge_film_tile(&film_tile);
+                next_unit += 1;
+            }
+        }
+    })
+    .unwrap();
+    reporter.done();
+    Ok(())
+}
+
+fn serve_worker<'a>(
+    stream: TcpStream,
+    header: &RenderHeader,
+    film: &'a Film,
+    units: &[WorkUnit],
+    queue: &WorkQueue,
+    tile_tx: &crossbeam_channel::Sender<(usize, FilmTile<'a>)>,
+) -> Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    let matches: bool = read_header(&mut reader, header)?;
+    // rendering a work unit may take arbitrarily long, but the worker
+    // keeps sending heartbeats
+    reader.get_ref().set_read_timeout(Some(HEARTBEAT_TIMEOUT))?;
+    writer.write_u8(matches as u8)?;
+    writer.flush()?;
+    if !matches {
+        return Err(invalid_data("worker renders another scene"));
+    }
+    while let Some(i) = queue.take() {
+        match exchange_work_unit(&mut reader, &mut writer, film, i, &units[i]) {
+            Ok(film_tile) => {
+                queue.finish();
+                tile_tx
+                    .send((i, film_tile))
+                    .unwrap_or_else(|_| panic!("Failed to send tile"));
+            }
+            Err(e) => {
+                queue.requeue(i);
+                if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut {
+                    return Err(Error::new(
+                        ErrorKind::TimedOut,
+                        format!("no heartbeat for {} seconds", HEARTBEAT_TIMEOUT.as_secs()),
+                    ));
+                }
+                return Err(e);
+            }
+        }
+    }
+    writer.write_u8(MESSAGE_DONE)?;
+    writer.flush()
+}
+
+fn exchange_work_unit<'a, R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    film: &'a Film,
+    i: usize,
+    unit: &WorkUnit,
+) -> Result<FilmTile<'a>> {
+    writer.write_u8(MESSAGE_WORK)?;
+    writer.write_u64::<LittleEndian>(i as u64)?;
+    writer.write_u64::<LittleEndian>(unit.tile_index as u64)?;
+    write_bounds(writer, &unit.tile_bounds)?;
+    writer.write_i64::<LittleEndian>(unit.first_sample)?;
+    writer.write_i64::<LittleEndian>(unit.end_sample)?;
+    writer.flush()?;
+    loop {
+        match reader.read_u8()? {
+            MESSAGE_HEARTBEAT => {}
+            MESSAGE_WORK => break,
+            message => return Err(invalid_data(&format!("unknown message {}", message))),
+        }
+    }
+    let index: u64 = reader.read_u64::<LittleEndian>()?;
+    if index != i as u64 {
+        return Err(invalid_data(&format!(
+            "worker returned work unit {} (expected {})",
+            index, i
+        )));
+    }
+    let mut film_tile: FilmTile = film.get_film_tile(&unit.tile_bounds);
+    film_tile.read_state(reader)?;
+    Ok(film_tile)
+}
+
+/// The connection of a worker thread to the coordinator.
+pub struct WorkerConnection {
+    reader: BufReader<TcpStream>,
+    /// shared with the thread sending the heartbeats
+    writer: Arc<Mutex<BufWriter<TcpStream>>>,
+    unit_index: u64,
+    connected: Arc<AtomicBool>,
+}
+
+impl WorkerConnection {
+    /// Connect to the coordinator at *address* (retrying for a while,
+    /// the coordinator might still be parsing the scene).
+    pub fn connect(address: &str, header: &RenderHeader) -> Result<Self> {
+        let mut attempt: u32 = 1;
+        let stream: TcpStream = loop {
+            match TcpStream::connect(address) {
+                Ok(stream) => break stream,
+                Err(e) => {
+                    if attempt == CONNECT_ATTEMPTS {
+                        return Err(e);
+                    }
+                    attempt += 1;
+                    thread::sleep(CONNECT_DELAY);
+                }
+            }
+        };
+        stream.set_nodelay(true)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+        write_header(&mut writer, header)?;
+        writer.flush()?;
+        if reader.read_u8()? == 0_u8 {
+            return Err(invalid_data("the coordinator renders another scene"));
+        }
+        let writer = Arc::new(Mutex::new(writer));
+        let connected = Arc::new(AtomicBool::new(true));
+        // keep telling the coordinator we are alive (while rendering)
+        let heartbeat_writer = writer.clone();
+        let heartbeat_connected = connected.clone();
+        thread::spawn(move || loop {
+            thread::sleep(HEARTBEAT_INTERVAL);
+            if !heartbeat_connected.load(Ordering::Relaxed) {
+                break;
+            }
+            let mut writer = heartbeat_writer.lock().unwrap();
+            if writer
+                .write_u8(MESSAGE_HEARTBEAT)
+                .and_then(|_| writer.flush())
+                .is_err()
+            {
+                break;
+            }
+        });
+        Ok(WorkerConnection {
+            reader,
+            writer,
+            unit_index: 0,
+            connected,
+        })
+    }
+    /// Wait for the next work unit, None once the image is done.
+    pub fn next_work_unit(&mut self) -> Result<Option<WorkUnit>> {
+        match self.reader.read_u8()? {
+            MESSAGE_DONE => Ok(None),
+            MESSAGE_WORK => {
+                self.unit_index = self.reader.read_u64::<LittleEndian>()?;
+                let tile_index: u64 = self.reader.read_u64::<LittleEndian>()?;
+                let tile_bounds: Bounds2i = read_bounds(&mut self.reader)?;
+                let first_sample: i64 = self.reader.read_i64::<LittleEndian>()?;
+                let end_sample: i64 = self.reader.read_i64::<LittleEndian>()?;
+                Ok(Some(WorkUnit {
+                    tile_index: tile_index as usize,
+                    tile_bounds,
+                    first_sample,
+                    end_sample,
+                }))
+            }
+            message => Err(invalid_data(&format!("unknown message {}", message))),
+        }
+    }
+    /// Send the film tile rendered for the last work unit.
+    pub fn send_film_tile(&mut self, film_tile: &FilmTile) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_u8(MESSAGE_WORK)?;
+        writer.write_u64::<LittleEndian>(self.unit_index)?;
+        film_tile.write_state(&mut *writer)?;
+        writer.flush()
+    }
+}
+
+impl Drop for WorkerConnection {
+    fn drop(&mut self) {
+        // stops the heartbeats
+        self.connected.store(false, Ordering::Relaxed);
+    }
+}
+
+fn write_header<W: Write>(writer: &mut W, header: &RenderHeader) -> Result<()> {
+    writer.write_all(PROTOCOL_MAGIC)?;
+    writer.write_u32::<LittleEndian>(PROTOCOL_VERSION)?;
+    write_bounds(writer, &header.pixel_bounds)?;
+    writer.write_i64::<LittleEndian>(header.samples_per_pixel)
+}
+
+/// Returns false if the worker renders another scene (or image).
+fn read_header<R: Read>(reader: &mut R, header: &RenderHeader) -> Result<bool> {
+    let mut magic: [u8; 8] = [0_u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != PROTOCOL_MAGIC {
+        return Err(invalid_data("not a worker"));
+    }
+    let version: u32 = reader.read_u32::<LittleEndian>()?;
+    if version != PROTOCOL_VERSION {
+        return Err(invalid_data(&format!(
+            "protocol version {} (expected {})",
+            version, PROTOCOL_VERSION
+        )));
+    }
+    let pixel_bounds: Bounds2i = read_bounds(reader)?;
+    let samples_per_pixel: i64 = reader.read_i64::<LittleEndian>()?;
+    Ok(pixel_bounds.p_min.x == header.pixel_bounds.p_min.x
+        && pixel_bounds.p_min.y == header.pixel_bounds.p_min.y
+        && pixel_bounds.p_max.x == header.pixel_bounds.p_max.x
+        && pixel_bounds.p_max.y == header.pixel_bounds.p_max.y
+        && samples_per_pixel == header.samples_per_pixel)
+}
+
+fn write_bounds<W: Write>(writer: &mut W, bounds: &Bounds2i) -> Result<()> {
+    for v in &[
+        bounds.p_min.x,
+        bounds.p_min.y,
+        bounds.p_max.x,
+        bounds.p_max.y,
+    ] {
+        writer.write_i32::<LittleEndian>(*v)?;
+    }
+    Ok(())
+}
+
+fn read_bounds<R: Read>(reader: &mut R) -> Result<Bounds2i> {
+    let mut v: [i32; 4] = [0_i32; 4];
+    for item in v.iter_mut() {
+        *item = reader.read_i32::<LittleEndian>()?;
+    }
+    Ok(Bounds2i {
+        p_min: Point2i { x: v[0], y: v[1] },
+        p_max: Point2i { x: v[2], y: v[3] },
+    })
+}
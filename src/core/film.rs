@@ -8,31 +8,248 @@
 //! image. When the main rendering loop exits, the **Film** writes the
 //! final image to file.
 //!
+//! Besides the image itself, the film can store arbitrary output
+//! variables (AOVs) for compositing, requested with the film's
+//! "aovs" parameter:
+//!
+//! ```text
+//! Film "image" "string filename" "out.exr"
+//!   "string aovs" [ "depth" "normal" "albedo" "diffusedirect" ]
+//! ```
+//!
+//! AOVs are averaged over the samples taken within each pixel (no
+//! reconstruction filter is applied, IDs are taken from the first
+//! sample). For OpenEXR output they are stored as additional layers
+//! of the same file (e.g. `N.X`, `N.Y`, `N.Z`), for all other
+//! formats each AOV is written to a sibling file (e.g.
+//! `out_normal.pfm`). Integrators fill them from the first
+//! intersection of the camera ray; the lighting components (direct
+//! and indirect diffuse and specular light, emission) are only
+//! separated by the path tracer.
+//!
+//! With "bool denoise" set the final image is denoised before it
+//! gets written (see the [denoise](../denoise/index.html) module).
+//! The film also knows where to store checkpoints of long renders
+//! (see the [checkpoint](../checkpoint/index.html) module).
+//!
+//! Progressive rendering renders the whole image in successive
+//! passes (doubling the samples per pixel) and stops after the
+//! sampler's samples per pixel or a time limit, whichever comes
+//! first. Every "imagewritefrequency" passes an intermediate image
+//! gets written:
+//!
+//! ```text
+//! Film "image" "string filename" "out.exr"
+//!   "bool progressive" "true" "float timelimit" [ 600 ]
+//!   "integer imagewritefrequency" [ 2 ]
+//! ```
 
 // std
+use std::io::{Read, Result, Write};
 use std::ops::{DerefMut, Index};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use std::time::Duration;
 
 // others
-#[cfg(feature = "openexr")]
-use openexr::{FrameBuffer, Header, PixelType, ScanlineOutputFile};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use smallvec::SmallVec;
 // pbrt
+use crate::core::checkpoint::{read_floats, read_len, write_floats, write_len, CheckpointParams};
+use crate::core::denoise::{denoise, DenoiseParams};
 use crate::core::filter::Filter;
 use crate::core::geometry::{
     bnd2_intersect_bnd2i, pnt2_ceil, pnt2_floor, pnt2_inside_exclusivei, pnt2_max_pnt2i,
     pnt2_min_pnt2i,
 };
-use crate::core::geometry::{Bounds2f, Bounds2i, Point2f, Point2i, Vector2f};
+use crate::core::geometry::{Bounds2f, Bounds2i, Normal3f, Point2f, Point2i, Point3f, Vector2f};
+use crate::core::imageio::{write_image, write_image_exr_channels, ImageChannel};
 use crate::core::paramset::ParamSet;
-use crate::core::pbrt::{clamp_t, gamma_correct};
-use crate::core::pbrt::{Float, Spectrum};
+use crate::core::pbrt::clamp_t;
+use crate::core::pbrt::{Float, Spectrum, VarianceEstimator};
 use crate::core::spectrum::xyz_to_rgb;
+use crate::core::stats::{Prof, ProfilePhase};
 
 // see film.h
 
 const FILTER_TABLE_WIDTH: usize = 16;
+/// Keeps the relative error of (almost) black pixels finite.
+const RELATIVE_ERROR_EPSILON: Float = 0.01;
+
+/// Settings for progressive rendering (see the module
+/// documentation).
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressiveParams {
+    /// stop rendering after this time (wall clock)
+    pub time_limit: Option<Duration>,
+    /// write an intermediate image after every n-th pass
+    pub write_frequency: i32,
+}
+
+/// Arbitrary output variables, see the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aov {
+    /// distance from the camera to the first intersection
+    Depth,
+    /// world space position of the first intersection
+    Position,
+    /// world space shading normal of the first intersection
+    Normal,
+    /// directional albedo of the BSDF at the first intersection
+    Albedo,
+    /// surface (u, v) coordinates of the first intersection
+    Uv,
+    /// ID of the material at the first intersection (0 for none)
+    MaterialId,
+    /// ID of the shape at the first intersection (0 for none)
+    ObjectId,
+    DiffuseDirect,
+    DiffuseIndirect,
+    SpecularDirect,
+    SpecularIndirect,
+    /// light emitted towards the camera by the first intersection
+    /// (or the environment)
+    Emission,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 12] = [
+        Aov::Depth,
+        Aov::Position,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::Uv,
+        Aov::MaterialId,
+        Aov::ObjectId,
+        Aov::DiffuseDirect,
+        Aov::DiffuseIndirect,
+        Aov::SpecularDirect,
+        Aov::SpecularIndirect,
+        Aov::Emission,
+    ];
+    /// Name used in the film's "aovs" parameter and for sibling files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Position => "position",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::Uv => "uv",
+            Aov::MaterialId => "materialid",
+            Aov::ObjectId => "objectid",
+            Aov::DiffuseDirect => "diffusedirect",
+            Aov::DiffuseIndirect => "diffuseindirect",
+            Aov::SpecularDirect => "speculardirect",
+            Aov::SpecularIndirect => "specularindirect",
+            Aov::Emission => "emission",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Aov> {
+        Aov::ALL.iter().find(|aov| aov.name() == name).copied()
+    }
+    /// Channel names within an OpenEXR file.
+    pub fn channel_names(&self) -> Vec<String> {
+        let (layer, channels): (&str, &[&str]) = match self {
+            Aov::Depth => ("Z", &[]),
+            Aov::Position => ("P", &["X", "Y", "Z"]),
+            Aov::Normal => ("N", &["X", "Y", "Z"]),
+            Aov::Albedo => ("albedo", &["R", "G", "B"]),
+            Aov::Uv => ("uv", &["U", "V"]),
+            Aov::MaterialId => ("materialId", &[]),
+            Aov::ObjectId => ("objectId", &[]),
+            Aov::DiffuseDirect => ("diffuseDirect", &["R", "G", "B"]),
+            Aov::DiffuseIndirect => ("diffuseIndirect", &["R", "G", "B"]),
+            Aov::SpecularDirect => ("specularDirect", &["R", "G", "B"]),
+            Aov::SpecularIndirect => ("specularIndirect", &["R", "G", "B"]),
+            Aov::Emission => ("emission", &["R", "G", "B"]),
+        };
+        if channels.is_empty() {
+            vec![String::from(layer)]
+        } else {
+            channels
+                .iter()
+                .map(|channel| format!("{}.{}", layer, channel))
+                .collect()
+        }
+    }
+    pub fn n_channels(&self) -> usize {
+        match self {
+            Aov::Depth | Aov::MaterialId | Aov::ObjectId => 1,
+            Aov::Uv => 2,
+            _ => 3,
+        }
+    }
+    /// IDs can't be averaged, the first sample within a pixel wins.
+    pub fn is_id(&self) -> bool {
+        matches!(self, Aov::MaterialId | Aov::ObjectId)
+    }
+}
+
+/// The values of all AOVs for a single camera sample. Samples
+/// which don't hit anything leave everything at zero.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AovSample {
+    pub depth: Float,
+    pub position: Point3f,
+    pub normal: Normal3f,
+    pub albedo: Spectrum,
+    pub uv: Point2f,
+    pub material_id: u32,
+    pub object_id: u32,
+    pub diffuse_direct: Spectrum,
+    pub diffuse_indirect: Spectrum,
+    pub specular_direct: Spectrum,
+    pub specular_indirect: Spectrum,
+    pub emission: Spectrum,
+}
+
+impl AovSample {
+    /// Store the channels of *aov* in the first elements of *values*.
+    pub fn get(&self, aov: Aov, values: &mut [Float; 3]) {
+        match aov {
+            Aov::Depth => values[0] = self.depth,
+            Aov::Position => {
+                values[0] = self.position.x;
+                values[1] = self.position.y;
+                values[2] = self.position.z;
+            }
+            Aov::Normal => {
+                values[0] = self.normal.x;
+                values[1] = self.normal.y;
+                values[2] = self.normal.z;
+            }
+            Aov::Albedo => self.albedo.to_rgb(values),
+            Aov::Uv => {
+                values[0] = self.uv.x;
+                values[1] = self.uv.y;
+            }
+            Aov::MaterialId => values[0] = self.material_id as Float,
+            Aov::ObjectId => values[0] = self.object_id as Float,
+            Aov::DiffuseDirect => self.diffuse_direct.to_rgb(values),
+            Aov::DiffuseIndirect => self.diffuse_indirect.to_rgb(values),
+            Aov::SpecularDirect => self.specular_direct.to_rgb(values),
+            Aov::SpecularIndirect => self.specular_indirect.to_rgb(values),
+            Aov::Emission => self.emission.to_rgb(values),
+        }
+    }
+}
+
+/// Per pixel AOV storage: all requested channels of a pixel next to
+/// each other, and the sum of the sample weights per pixel.
+#[derive(Debug, Default, Clone)]
+pub struct AovPixels {
+    values: Vec<Float>,
+    weight_sums: Vec<Float>,
+}
+
+impl AovPixels {
+    fn new(n_pixels: usize, n_channels: usize) -> Self {
+        AovPixels {
+            values: vec![0.0 as Float; n_pixels * n_channels],
+            weight_sums: vec![0.0 as Float; n_pixels],
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Pixel {
@@ -67,6 +284,10 @@ pub struct FilmTile<'a> {
     filter_table_size: usize,
     pixels: Vec<FilmTilePixel>,
     max_sample_luminance: Float,
+    aovs: &'a [Aov],
+    n_aov_channels: usize,
+    aov_pixels: AovPixels,
+    variances: Vec<VarianceEstimator>,
 }
 
 impl<'a> FilmTile<'a> {
@@ -76,7 +297,10 @@ impl<'a> FilmTile<'a> {
         filter_table: &'a [Float; FILTER_TABLE_WIDTH * FILTER_TABLE_WIDTH],
         filter_table_size: usize,
         max_sample_luminance: Float,
+        aovs: &'a [Aov],
+        track_variance: bool,
     ) -> Self {
+        let n_aov_channels: usize = aovs.iter().map(|aov| aov.n_channels()).sum();
         FilmTile {
             pixel_bounds,
             filter_radius,
@@ -89,13 +313,81 @@ impl<'a> FilmTile<'a> {
             // TODO: pixels = std::vector<FilmTilePixel>(std::max(0, pixelBounds.Area()));
             pixels: vec![FilmTilePixel::default(); pixel_bounds.area() as usize],
             max_sample_luminance,
+            aovs,
+            n_aov_channels,
+            aov_pixels: AovPixels::new(
+                if aovs.is_empty() {
+                    0
+                } else {
+                    pixel_bounds.area() as usize
+                },
+                n_aov_channels,
+            ),
+            variances: if track_variance {
+                vec![VarianceEstimator::default(); 3 * pixel_bounds.area() as usize]
+            } else {
+                Vec::new()
+            },
         }
     }
+    /// Does the film store any AOVs (see [Aov](enum.Aov.html))?
+    pub fn has_aovs(&self) -> bool {
+        !self.aovs.is_empty()
+    }
+    /// Add the AOVs of a camera sample to the pixel containing it.
+    pub fn add_aov_sample(
+        &mut self,
+        p_film: Point2f,
+        aov_sample: &AovSample,
+        sample_weight: Float,
+    ) {
+        if self.aovs.is_empty() {
+            return;
+        }
+        let pixel: Point2i = Point2i {
+            x: p_film.x.floor() as i32,
+            y: p_film.y.floor() as i32,
+        };
+        if !pnt2_inside_exclusivei(pixel, &self.pixel_bounds) {
+            return;
+        }
+        let idx: usize = self.get_pixel_index(pixel.x, pixel.y);
+        let first_sample: bool = self.aov_pixels.weight_sums[idx] == 0.0 as Float;
+        let mut offset: usize = idx * self.n_aov_channels;
+        let mut values: [Float; 3] = [0.0 as Float; 3];
+        for aov in self.aovs {
+            aov_sample.get(*aov, &mut values);
+            for value in values.iter().take(aov.n_channels()) {
+                if !aov.is_id() {
+                    self.aov_pixels.values[offset] += *value * sample_weight;
+                } else if first_sample {
+                    self.aov_pixels.values[offset] = *value;
+                }
+                offset += 1;
+            }
+        }
+        self.aov_pixels.weight_sums[idx] += sample_weight;
+    }
     pub fn add_sample(&mut self, p_film: Point2f, l: &mut Spectrum, sample_weight: Float) {
-        // TODO: ProfilePhase _(Prof::AddFilmSample);
+        let _prof = ProfilePhase::new(Prof::AddFilmSample);
         if l.y() > self.max_sample_luminance {
             *l *= Spectrum::new(self.max_sample_luminance / l.y());
         }
+        if !self.variances.is_empty() {
+            // per pixel variance (for denoising)
+            let pixel: Point2i = Point2i {
+                x: p_film.x.floor() as i32,
+                y: p_film.y.floor() as i32,
+            };
+            if pnt2_inside_exclusivei(pixel, &self.pixel_bounds) {
+                let idx: usize = self.get_pixel_index(pixel.x, pixel.y);
+                let mut rgb: [Float; 3] = [0.0 as Float; 3];
+                l.to_rgb(&mut rgb);
+                for (c, value) in rgb.iter().enumerate() {
+                    self.variances[3 * idx + c].add(*value * sample_weight);
+                }
+            }
+        }
         // compute sample's raster bounds
         let p_film_discrete: Point2f = p_film - Vector2f { x: 0.5, y: 0.5 };
         let p0f: Point2f = pnt2_ceil(p_film_discrete - self.filter_radius);
@@ -150,6 +442,49 @@ impl<'a> FilmTile<'a> {
         let pidx = (y - self.pixel_bounds.p_min.y) * width + (x - self.pixel_bounds.p_min.x);
         pidx as usize
     }
+    /// Store the accumulated samples of the tile, e.g. to send them
+    /// to another process (see
+    /// [distributed](../distributed/index.html) rendering).
+    pub fn write_state<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_len(writer, self.pixels.len())?;
+        for pixel in &self.pixels {
+            write_floats(writer, &pixel.contrib_sum.c)?;
+            write_floats(writer, &[pixel.filter_weight_sum])?;
+        }
+        write_len(writer, self.variances.len())?;
+        for ve in &self.variances {
+            let (mean, s, n) = ve.to_raw();
+            write_floats(writer, &[mean, s])?;
+            writer.write_u64::<LittleEndian>(n)?;
+        }
+        write_len(writer, self.aov_pixels.values.len())?;
+        write_floats(writer, &self.aov_pixels.values)?;
+        write_len(writer, self.aov_pixels.weight_sums.len())?;
+        write_floats(writer, &self.aov_pixels.weight_sums)
+    }
+    /// Restore the samples stored by
+    /// [write_state()](#method.write_state) into a tile with the same
+    /// pixel bounds.
+    pub fn read_state<R: Read>(&mut self, reader: &mut R) -> Result<()> {
+        read_len(reader, self.pixels.len())?;
+        for pixel in self.pixels.iter_mut() {
+            read_floats(reader, &mut pixel.contrib_sum.c)?;
+            let mut filter_weight_sum: [Float; 1] = [0.0 as Float];
+            read_floats(reader, &mut filter_weight_sum)?;
+            pixel.filter_weight_sum = filter_weight_sum[0];
+        }
+        read_len(reader, self.variances.len())?;
+        for ve in self.variances.iter_mut() {
+            let mut values: [Float; 2] = [0.0 as Float; 2];
+            read_floats(reader, &mut values)?;
+            let n: u64 = reader.read_u64::<LittleEndian>()?;
+            *ve = VarianceEstimator::from_raw(values[0], values[1], n);
+        }
+        read_len(reader, self.aov_pixels.values.len())?;
+        read_floats(reader, &mut self.aov_pixels.values)?;
+        read_len(reader, self.aov_pixels.weight_sums.len())?;
+        read_floats(reader, &mut self.aov_pixels.weight_sums)
+    }
 }
 
 pub struct Film {
@@ -170,6 +505,18 @@ pub struct Film {
     filter_table: [Float; FILTER_TABLE_WIDTH * FILTER_TABLE_WIDTH],
     scale: Float,
     max_sample_luminance: Float,
+    /// Arbitrary output variables stored next to the image
+    pub aovs: Vec<Aov>,
+    n_aov_channels: usize,
+    aov_pixels: RwLock<AovPixels>,
+    /// The AOVs which get written (denoising might require more)
+    output_aovs: Vec<Aov>,
+    denoise: Option<DenoiseParams>,
+    variances: RwLock<Vec<VarianceEstimator>>,
+    /// Where (and how often) to store checkpoints of the render
+    pub checkpoint: Option<CheckpointParams>,
+    /// Render in passes over the whole image (within a time limit)
+    pub progressive: Option<ProgressiveParams>,
 }
 
 impl Film {
@@ -181,6 +528,10 @@ impl Film {
         filename: String,
         scale: Float,
         max_sample_luminance: Float,
+        aovs: Vec<Aov>,
+        denoise: Option<DenoiseParams>,
+        checkpoint: Option<CheckpointParams>,
+        progressive: Option<ProgressiveParams>,
     ) -> Self {
         let cropped_pixel_bounds: Bounds2i = Bounds2i {
             p_min: Point2i {
@@ -199,6 +550,17 @@ impl Film {
             [0.0; FILTER_TABLE_WIDTH * FILTER_TABLE_WIDTH];
         let mut offset: usize = 0;
         let filter_radius: Vector2f = filter.get_radius();
+        // the denoiser is guided by albedo and normals
+        let output_aovs: Vec<Aov> = aovs.clone();
+        let mut aovs: Vec<Aov> = aovs;
+        if denoise.is_some() {
+            for aov in &[Aov::Albedo, Aov::Normal] {
+                if !aovs.contains(aov) {
+                    aovs.push(*aov);
+                }
+            }
+        }
+        let n_aov_channels: usize = aovs.iter().map(|aov| aov.n_channels()).sum();
         for y in 0..FILTER_TABLE_WIDTH {
             for x in 0..FILTER_TABLE_WIDTH {
                 let p: Point2f = Point2f {
@@ -219,15 +581,35 @@ impl Film {
             filter_table,
             scale,
             max_sample_luminance,
+            n_aov_channels,
+            aov_pixels: RwLock::new(AovPixels::new(
+                if aovs.is_empty() {
+                    0
+                } else {
+                    cropped_pixel_bounds.area() as usize
+                },
+                n_aov_channels,
+            )),
+            aovs,
+            output_aovs,
+            denoise,
+            variances: RwLock::new(if denoise.is_some() {
+                vec![VarianceEstimator::default(); 3 * cropped_pixel_bounds.area() as usize]
+            } else {
+                Vec::new()
+            }),
+            checkpoint,
+            progressive,
         }
     }
     pub fn create(params: &ParamSet, filter: Box<Filter>) -> Arc<Film> {
-        let filename: String = params.find_one_string("filename", String::new());
+        let mut filename: String = params.find_one_string("filename", String::new());
+        if filename.is_empty() {
+            filename = String::from("pbrt.png");
+        }
         let xres: i32 = params.find_one_int("xresolution", 1280);
         let yres: i32 = params.find_one_int("yresolution", 720);
         let resolution: Point2i = Point2i { x: xres, y: yres };
-        // TODO: if (PbrtOptions.quickRender) xres = std::max(1, xres / 4);
-        // TODO: if (PbrtOptions.quickRender) yres = std::max(1, yres / 4);
         let mut crop: Bounds2f = Bounds2f {
             p_min: Point2f { x: 0.0, y: 0.0 },
             p_max: Point2f { x: 1.0, y: 1.0 },
@@ -248,6 +630,61 @@ impl Film {
         let diagonal: Float = params.find_one_float("diagonal", 35.0);
         let max_sample_luminance: Float =
             params.find_one_float("maxsampleluminance", std::f32::INFINITY);
+        let mut aovs: Vec<Aov> = Vec::new();
+        for name in params.find_string("aovs") {
+            match Aov::from_name(&name.to_lowercase()) {
+                Some(aov) => {
+                    if !aovs.contains(&aov) {
+                        aovs.push(aov);
+                    }
+                }
+                None => println!("WARNING: Unknown AOV {:?} ignored", name),
+            }
+        }
+        let denoise: Option<DenoiseParams> = if params.find_one_bool("denoise", false) {
+            let default: DenoiseParams = DenoiseParams::default();
+            Some(DenoiseParams {
+                radius: params.find_one_int("denoiseradius", default.radius),
+                k: params.find_one_float("denoisestrength", default.k),
+                ..default
+            })
+        } else {
+            None
+        };
+        let resume: bool = params.find_one_bool("resume", false);
+        let mut checkpoint_file: String = params.find_one_string("checkpoint", String::new());
+        if checkpoint_file.is_empty() && resume {
+            // default: image filename with .ckpt extension
+            checkpoint_file = Path::new(&filename)
+                .with_extension("ckpt")
+                .to_string_lossy()
+                .to_string();
+        }
+        let checkpoint: Option<CheckpointParams> = if checkpoint_file.is_empty() {
+            None
+        } else {
+            let interval: Float = params.find_one_float("checkpointinterval", 300.0);
+            Some(CheckpointParams {
+                filename: checkpoint_file,
+                interval: Duration::from_secs_f32(interval.max(0.0 as Float)),
+                resume,
+            })
+        };
+        let time_limit: Float = params.find_one_float("timelimit", 0.0);
+        let progressive: Option<ProgressiveParams> = if params.find_one_bool("progressive", false)
+            || time_limit > 0.0 as Float
+        {
+            Some(ProgressiveParams {
+                time_limit: if time_limit > 0.0 as Float {
+                    Some(Duration::from_secs_f32(time_limit))
+                } else {
+                    None
+                },
+                write_frequency: std::cmp::max(1, params.find_one_int("imagewritefrequency", 1)),
+            })
+        } else {
+            None
+        };
         Arc::new(Film::new(
             resolution,
             crop,
@@ -256,6 +693,10 @@ impl Film {
             filename,
             scale,
             max_sample_luminance,
+            aovs,
+            denoise,
+            checkpoint,
+            progressive,
         ))
     }
     pub fn get_cropped_pixel_bounds(&self) -> Bounds2i {
@@ -339,10 +780,46 @@ impl Film {
             &self.filter_table,
             FILTER_TABLE_WIDTH,
             self.max_sample_luminance,
+            &self.aovs,
+            !self.variances.read().unwrap().is_empty(),
         )
     }
+    /// Start to collect per pixel variances (needed for adaptive
+    /// sampling and denoising). Only affects film tiles created
+    /// afterwards.
+    pub fn track_variance(&self) {
+        let mut variances = self.variances.write().unwrap();
+        if variances.is_empty() {
+            *variances =
+                vec![VarianceEstimator::default(); 3 * self.cropped_pixel_bounds.area() as usize];
+        }
+    }
+    /// The largest relative error of the pixels within *bounds*: the
+    /// standard error of a pixel's RGB estimate divided by its
+    /// magnitude. Pixels with less than two samples have an infinite
+    /// error (see [track_variance()](#method.track_variance)).
+    pub fn get_max_relative_error(&self, bounds: &Bounds2i) -> Float {
+        let variances = self.variances.read().unwrap();
+        let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
+        let mut max_error: Float = 0.0 as Float;
+        for pixel in &bnd2_intersect_bnd2i(bounds, &self.cropped_pixel_bounds) {
+            let idx: usize = ((pixel.x - self.cropped_pixel_bounds.p_min.x)
+                + (pixel.y - self.cropped_pixel_bounds.p_min.y) * width)
+                as usize;
+            let rgb: &[VarianceEstimator] = &variances[3 * idx..3 * idx + 3];
+            if rgb[0].count() < 2 {
+                return Float::INFINITY;
+            }
+            let variance: Float = rgb.iter().map(|ve| ve.variance()).sum::<Float>();
+            let mean: Float = rgb.iter().map(|ve| ve.mean()).sum::<Float>();
+            let error: Float =
+                (variance / rgb[0].count() as Float).sqrt() / (mean.abs() + RELATIVE_ERROR_EPSILON);
+            max_error = max_error.max(error);
+        }
+        max_error
+    }
     pub fn merge_film_tile(&self, tile: &FilmTile) {
-        // TODO: ProfilePhase p(Prof::MergeFilmTile);
+        let _prof = ProfilePhase::new(Prof::MergeFilmTile);
         // println!("Merging film tile {:?}", tile.pixel_bounds);
         // TODO: std::lock_guard<std::mutex> lock(mutex);
         for pixel in &tile.pixel_bounds {
@@ -366,6 +843,111 @@ impl Film {
             // write pixel back
             // pixels_write[offset as usize] = *merge_pixel;
         }
+        if !self.aovs.is_empty() {
+            self.merge_aov_pixels(tile);
+        }
+        if !tile.variances.is_empty() {
+            let mut variances = self.variances.write().unwrap();
+            let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
+            for pixel in &tile.pixel_bounds {
+                let idx: usize = ((pixel.x - self.cropped_pixel_bounds.p_min.x)
+                    + (pixel.y - self.cropped_pixel_bounds.p_min.y) * width)
+                    as usize;
+                let tile_idx: usize = tile.get_pixel_index(pixel.x, pixel.y);
+                for c in 0..3 {
+                    variances[3 * idx + c].merge(&tile.variances[3 * tile_idx + c]);
+                }
+            }
+        }
+    }
+    fn merge_aov_pixels(&self, tile: &FilmTile) {
+        let mut aov_pixels = self.aov_pixels.write().unwrap();
+        let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
+        for pixel in &tile.pixel_bounds {
+            let tile_idx: usize = tile.get_pixel_index(pixel.x, pixel.y);
+            let tile_weight_sum: Float = tile.aov_pixels.weight_sums[tile_idx];
+            if tile_weight_sum == 0.0 as Float {
+                continue;
+            }
+            let idx: usize = ((pixel.x - self.cropped_pixel_bounds.p_min.x)
+                + (pixel.y - self.cropped_pixel_bounds.p_min.y) * width)
+                as usize;
+            let first_sample: bool = aov_pixels.weight_sums[idx] == 0.0 as Float;
+            let mut offset: usize = 0;
+            for aov in &self.aovs {
+                for _ in 0..aov.n_channels() {
+                    let value: Float =
+                        tile.aov_pixels.values[tile_idx * self.n_aov_channels + offset];
+                    let merge_value: &mut Float =
+                        &mut aov_pixels.values[idx * self.n_aov_channels + offset];
+                    if !aov.is_id() {
+                        *merge_value += value;
+                    } else if first_sample {
+                        *merge_value = value;
+                    }
+                    offset += 1;
+                }
+            }
+            aov_pixels.weight_sums[idx] += tile_weight_sum;
+        }
+    }
+    /// Store the accumulated pixels (weighted sums, splats, variance
+    /// estimates and AOVs) in a checkpoint.
+    pub fn write_checkpoint_state<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let pixels = self.pixels.read().unwrap();
+        write_len(writer, pixels.len())?;
+        for pixel in pixels.iter() {
+            write_floats(writer, &pixel.xyz)?;
+            write_floats(writer, &[pixel.filter_weight_sum])?;
+            write_floats(writer, &pixel.splat_xyz)?;
+        }
+        let variances = self.variances.read().unwrap();
+        write_len(writer, variances.len())?;
+        for ve in variances.iter() {
+            let (mean, s, n) = ve.to_raw();
+            write_floats(writer, &[mean, s])?;
+            writer.write_u64::<LittleEndian>(n)?;
+        }
+        let aov_pixels = self.aov_pixels.read().unwrap();
+        write_len(writer, aov_pixels.values.len())?;
+        write_floats(writer, &aov_pixels.values)?;
+        write_len(writer, aov_pixels.weight_sums.len())?;
+        write_floats(writer, &aov_pixels.weight_sums)
+    }
+    /// Restore the pixels stored by
+    /// [write_checkpoint_state()](#method.write_checkpoint_state). The
+    /// film only changes if the whole state could be read.
+    pub fn read_checkpoint_state<R: Read>(&self, reader: &mut R) -> Result<()> {
+        let mut pixels = self.pixels.write().unwrap();
+        read_len(reader, pixels.len())?;
+        let mut pixel_values: Vec<Float> = vec![0.0 as Float; 7 * pixels.len()];
+        read_floats(reader, &mut pixel_values)?;
+        let mut variances = self.variances.write().unwrap();
+        read_len(reader, variances.len())?;
+        let mut variance_values: Vec<VarianceEstimator> = Vec::with_capacity(variances.len());
+        for _i in 0..variances.len() {
+            let mut values: [Float; 2] = [0.0 as Float; 2];
+            read_floats(reader, &mut values)?;
+            let n: u64 = reader.read_u64::<LittleEndian>()?;
+            variance_values.push(VarianceEstimator::from_raw(values[0], values[1], n));
+        }
+        let mut aov_pixels = self.aov_pixels.write().unwrap();
+        read_len(reader, aov_pixels.values.len())?;
+        let mut aov_values: Vec<Float> = vec![0.0 as Float; aov_pixels.values.len()];
+        read_floats(reader, &mut aov_values)?;
+        read_len(reader, aov_pixels.weight_sums.len())?;
+        let mut aov_weight_sums: Vec<Float> = vec![0.0 as Float; aov_pixels.weight_sums.len()];
+        read_floats(reader, &mut aov_weight_sums)?;
+        // everything was read, update the film
+        for (pixel, values) in pixels.iter_mut().zip(pixel_values.chunks_exact(7)) {
+            pixel.xyz.copy_from_slice(&values[0..3]);
+            pixel.filter_weight_sum = values[3];
+            pixel.splat_xyz.copy_from_slice(&values[4..7]);
+        }
+        *variances = variance_values;
+        aov_pixels.values = aov_values;
+        aov_pixels.weight_sums = aov_weight_sums;
+        Ok(())
     }
     pub fn set_image(&self, img: &[Spectrum]) {
         let n_pixels: i32 = self.cropped_pixel_bounds.area();
@@ -385,7 +967,7 @@ impl Film {
     }
     pub fn add_splat(&self, p: Point2f, v: &Spectrum) {
         let mut v: Spectrum = *v;
-        // TODO: ProfilePhase pp(Prof::SplatFilm);
+        let _prof = ProfilePhase::new(Prof::SplatFilm);
         if v.has_nans() {
             println!(
                 "ERROR: Ignoring splatted spectrum with NaN values at ({:?}, {:?})",
@@ -432,7 +1014,6 @@ impl Film {
         splat_xyz[1] += xyz[1];
         splat_xyz[2] += xyz[2];
     }
-    #[cfg(not(feature = "openexr"))]
     pub fn write_image(&self, splat_scale: Float) {
         let mut rgb: Vec<Float> =
             vec![0.0 as Float; (3 * self.cropped_pixel_bounds.area()) as usize];
@@ -476,172 +1057,149 @@ impl Film {
             rgb[start + 1] *= self.scale;
             rgb[start + 2] *= self.scale;
         }
-        let filename = "pbrt.png";
+        if let Some(ref params) = self.denoise {
+            rgb = self.denoise_image(&rgb, params);
+        }
         println!(
             "Writing image {:?} with bounds {:?}",
-            filename, // TODO: self.filename,
-            self.cropped_pixel_bounds
+            self.filename, self.cropped_pixel_bounds
         );
-        // TODO: pbrt::WriteImage(filename, &rgb[0], croppedPixelBounds, fullResolution);
-        let mut buffer: Vec<u8> = vec![0.0 as u8; (3 * self.cropped_pixel_bounds.area()) as usize];
-        // 8-bit format; apply gamma (see WriteImage(...) in imageio.cpp)
-        let width: u32 =
-            (self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x) as u32;
-        let height: u32 =
-            (self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y) as u32;
-        for y in 0..height {
-            for x in 0..width {
-                // red
-                let index: usize = (3 * (y * width + x)) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // green
-                let index: usize = (3 * (y * width + x) + 1) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // blue
-                let index: usize = (3 * (y * width + x) + 2) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-            }
+        if self.output_aovs.is_empty() {
+            write_image(
+                &self.filename,
+                &rgb,
+                &self.cropped_pixel_bounds,
+                &self.full_resolution,
+            );
+        } else {
+            self.write_image_with_aovs(&rgb);
+        }
+    }
+    /// AOVs requested by the scene, see
+    /// [Aov](enum.Aov.html).
+    pub fn get_output_aovs(&self) -> &[Aov] {
+        &self.output_aovs
+    }
+    fn denoise_image(&self, rgb: &[Float], params: &DenoiseParams) -> Vec<Float> {
+        let variances = self.variances.read().unwrap();
+        if variances.iter().all(|ve| ve.count() < 2) {
+            println!("WARNING: Denoising needs at least two camera samples per pixel, skipped");
+            return rgb.to_vec();
         }
-        // write "pbrt.png" to disk
-        image::save_buffer(
-            &Path::new("pbrt.png"),
-            &buffer,
-            width,
-            height,
-            image::ColorType::Rgb8,
+        // variance of each pixel's (RGB) estimate, scaled like the image
+        let variance: Vec<Float> = variances
+            .iter()
+            .map(|ve| {
+                if ve.count() > 0 {
+                    ve.variance() * self.scale * self.scale / ve.count() as Float
+                } else {
+                    0.0 as Float
+                }
+            })
+            .collect();
+        let albedo: Vec<Float> = self.get_aov(Aov::Albedo).unwrap_or_default();
+        let normal: Vec<Float> = self.get_aov(Aov::Normal).unwrap_or_default();
+        println!("Denoising image ...");
+        denoise(
+            (self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x) as usize,
+            (self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y) as usize,
+            rgb,
+            &variance,
+            &albedo,
+            &normal,
+            params,
         )
-        .unwrap();
     }
-    #[cfg(feature = "openexr")]
-    pub fn write_image(&self, splat_scale: Float) {
-        let mut rgb: Vec<Float> =
-            vec![0.0 as Float; (3 * self.cropped_pixel_bounds.area()) as usize];
-        let mut exr: Vec<(Float, Float, Float)> = // copy data for OpenEXR image
-            vec![(0.0_f32, 0.0_f32, 0.0_f32); self.cropped_pixel_bounds.area() as usize];
-        let mut offset;
-        for p in &self.cropped_pixel_bounds {
-            // convert pixel XYZ color to RGB
-            assert!(pnt2_inside_exclusivei(p, &self.cropped_pixel_bounds));
-            let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
-            offset = ((p.x - self.cropped_pixel_bounds.p_min.x)
-                + (p.y - self.cropped_pixel_bounds.p_min.y) * width) as usize;
-            let pixel: &Pixel = &self.pixels.read().unwrap()[offset];
-            let start = 3 * offset;
-            let mut rgb_array: [Float; 3] = [0.0 as Float; 3];
-            xyz_to_rgb(&pixel.xyz, &mut rgb_array); // TODO: Use 'rgb' directly.
-            rgb[start] = rgb_array[0];
-            rgb[start + 1] = rgb_array[1];
-            rgb[start + 2] = rgb_array[2];
-            // normalize pixel with weight sum
-            let filter_weight_sum: Float = pixel.filter_weight_sum;
-            if filter_weight_sum != 0.0 as Float {
-                let inv_wt: Float = 1.0 as Float / filter_weight_sum;
-                rgb[start] = (rgb[start] * inv_wt).max(0.0 as Float);
-                rgb[start + 1] = (rgb[start + 1] * inv_wt).max(0.0 as Float);
-                rgb[start + 2] = (rgb[start + 2] * inv_wt).max(0.0 as Float);
+    /// Returns the averaged values of *aov* for all pixels (one
+    /// element per channel and pixel), or *None* if the film doesn't
+    /// store that AOV.
+    pub fn get_aov(&self, aov: Aov) -> Option<Vec<Float>> {
+        let index: usize = self.aovs.iter().position(|a| *a == aov)?;
+        let first_channel: usize = self.aovs[..index].iter().map(|a| a.n_channels()).sum();
+        let n_channels: usize = aov.n_channels();
+        let aov_pixels = self.aov_pixels.read().unwrap();
+        let mut values: Vec<Float> = Vec::with_capacity(aov_pixels.weight_sums.len() * n_channels);
+        for (idx, weight_sum) in aov_pixels.weight_sums.iter().enumerate() {
+            let start: usize = idx * self.n_aov_channels + first_channel;
+            for value in &aov_pixels.values[start..start + n_channels] {
+                if aov.is_id() || *weight_sum == 0.0 as Float {
+                    values.push(*value);
+                } else {
+                    values.push(*value / *weight_sum);
+                }
             }
-            // add splat value at pixel
-            let mut splat_rgb: [Float; 3] = [0.0 as Float; 3];
-            let pixel_splat_xyz: &[Float; 3] = &pixel.splat_xyz;
-            let splat_xyz: [Float; 3] = [
-                *pixel_splat_xyz.index(0),
-                *pixel_splat_xyz.index(1),
-                *pixel_splat_xyz.index(2),
-            ];
-            xyz_to_rgb(&splat_xyz, &mut splat_rgb);
-            rgb[start] += splat_scale * splat_rgb[0];
-            rgb[start + 1] += splat_scale * splat_rgb[1];
-            rgb[start + 2] += splat_scale * splat_rgb[2];
-            // scale pixel value by _scale_
-            rgb[start] *= self.scale;
-            rgb[start + 1] *= self.scale;
-            rgb[start + 2] *= self.scale;
-            // copy data for OpenEXR image
-            exr[offset].0 = rgb[start];
-            exr[offset].1 = rgb[start + 1];
-            exr[offset].2 = rgb[start + 2];
         }
-        let filename = "pbrt.png";
-        println!(
-            "Writing image {:?} with bounds {:?}",
-            filename, // TODO: self.filename,
-            self.cropped_pixel_bounds
-        );
-        // TODO: pbrt::WriteImage(filename, &rgb[0], croppedPixelBounds, fullResolution);
-        let mut buffer: Vec<u8> = vec![0.0 as u8; (3 * self.cropped_pixel_bounds.area()) as usize];
-        // 8-bit format; apply gamma (see WriteImage(...) in imageio.cpp)
-        let width: u32 =
-            (self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x) as u32;
-        let height: u32 =
-            (self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y) as u32;
-        // OpenEXR
-        let filename = "pbrt_rust.exr";
-        println!(
-            "Writing image {:?} with bounds {:?}",
-            filename, // TODO: self.filename,
-            self.cropped_pixel_bounds
-        );
-        let mut file = std::fs::File::create("pbrt_rust.exr").unwrap();
-        let mut output_file = ScanlineOutputFile::new(
-            &mut file,
-            Header::new()
-                .set_resolution(width, height)
-                .add_channel("R", PixelType::FLOAT)
-                .add_channel("G", PixelType::FLOAT)
-                .add_channel("B", PixelType::FLOAT),
-        )
-        .unwrap();
-        let mut fb = FrameBuffer::new(width as u32, height as u32);
-        fb.insert_channels(&["R", "G", "B"], &exr);
-        output_file.write_pixels(&fb).unwrap();
-
-        // OpenEXR
-        for y in 0..height {
-            for x in 0..width {
-                // red
-                let index: usize = (3 * (y * width + x)) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // green
-                let index: usize = (3 * (y * width + x) + 1) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // blue
-                let index: usize = (3 * (y * width + x) + 2) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
+        Some(values)
+    }
+    fn write_image_with_aovs(&self, rgb: &[Float]) {
+        let path: &Path = Path::new(&self.filename);
+        let extension: String = match path.extension() {
+            Some(ext) => ext.to_string_lossy().to_lowercase(),
+            None => String::new(),
+        };
+        if extension == "exr" {
+            // one multi-channel file with AOVs as layers
+            let mut channels: Vec<ImageChannel> = Vec::new();
+            for (c, name) in ["R", "G", "B"].iter().enumerate() {
+                channels.push(ImageChannel {
+                    name: String::from(*name),
+                    values: rgb.iter().skip(c).step_by(3).copied().collect(),
+                });
+            }
+            for aov in &self.output_aovs {
+                let values: Vec<Float> = self.get_aov(*aov).unwrap();
+                let n_channels: usize = aov.n_channels();
+                for (c, name) in aov.channel_names().into_iter().enumerate() {
+                    channels.push(ImageChannel {
+                        name,
+                        values: values.iter().skip(c).step_by(n_channels).copied().collect(),
+                    });
+                }
+            }
+            write_image_exr_channels(
+                &self.filename,
+                &channels,
+                &self.cropped_pixel_bounds,
+                &self.full_resolution,
+            );
+        } else {
+            write_image(
+                &self.filename,
+                rgb,
+                &self.cropped_pixel_bounds,
+                &self.full_resolution,
+            );
+            // one sibling file per AOV, e.g. "out_normal.png"
+            let stem: String = match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().to_string(),
+                None => String::new(),
+            };
+            for aov in &self.output_aovs {
+                let values: Vec<Float> = self.get_aov(*aov).unwrap();
+                let n_channels: usize = aov.n_channels();
+                let mut aov_rgb: Vec<Float> = Vec::with_capacity(rgb.len());
+                for pixel in values.chunks(n_channels) {
+                    match n_channels {
+                        // gray
+                        1 => aov_rgb.extend_from_slice(&[pixel[0], pixel[0], pixel[0]]),
+                        // (u, v, 0)
+                        2 => aov_rgb.extend_from_slice(&[pixel[0], pixel[1], 0.0 as Float]),
+                        _ => aov_rgb.extend_from_slice(pixel),
+                    }
+                }
+                let mut file_name: String = format!("{}_{}", stem, aov.name());
+                if !extension.is_empty() {
+                    file_name = format!("{}.{}", file_name, extension);
+                }
+                let aov_path: PathBuf = path.with_file_name(file_name);
+                write_image(
+                    &aov_path.to_string_lossy(),
+                    &aov_rgb,
+                    &self.cropped_pixel_bounds,
+                    &self.full_resolution,
+                );
             }
         }
-        // write "pbrt.png" to disk
-        image::save_buffer(
-            &Path::new("pbrt.png"),
-            &buffer,
-            width,
-            height,
-            image::ColorType::Rgb8,
-        )
-        .unwrap();
     }
     // pub fn get_pixel<'a>(&self, p: &Point2i) -> &'a Pixel {
     //     assert!(pnt2_inside_exclusivei(p, &self.cropped_pixel_bounds));
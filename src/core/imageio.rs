@@ -0,0 +1,235 @@
+//! Write rendered images to disk. The file format is chosen by the
+//! extension of the filename: 8-bit sRGB images (e.g. PNG or TGA),
+//! linear floating-point PFM, Radiance HDR, and OpenEXR.
+
+// std
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+// others
+use byteorder::{LittleEndian, WriteBytesExt};
+// pbrt
+use crate::core::geometry::{Bounds2i, Point2i};
+use crate::core::pbrt::{clamp_t, gamma_correct, Float};
+
+// see imageio.h
+
+/// Write the RGB triples in *rgb* (linear, not gamma corrected) to a
+/// file called *name*. *output_bounds* is the (cropped) region of the
+/// image the data covers and *total_resolution* the full image
+/// resolution, which is only used by formats that can store a data
+/// window inside a display window (OpenEXR).
+pub fn write_image(
+    name: &str,
+    rgb: &[Float],
+    output_bounds: &Bounds2i,
+    total_resolution: &Point2i,
+) {
+    let extension: String = match Path::new(name).extension() {
+        Some(ext) => ext.to_string_lossy().to_lowercase(),
+        None => String::new(),
+    };
+    let result: std::io::Result<()> = match extension.as_str() {
+        "exr" => write_image_exr(name, rgb, output_bounds, total_resolution),
+        "pfm" => write_image_pfm(name, rgb, output_bounds),
+        "hdr" => write_image_hdr(name, rgb, output_bounds),
+        _ => write_image_8bit(name, rgb, output_bounds),
+    };
+    if let Err(e) = result {
+        println!("ERROR: Unable to write image {:?}: {}", name, e);
+    }
+}
+
+fn resolution(output_bounds: &Bounds2i) -> (usize, usize) {
+    let width: usize = (output_bounds.p_max.x - output_bounds.p_min.x) as usize;
+    let height: usize = (output_bounds.p_max.y - output_bounds.p_min.y) as usize;
+    (width, height)
+}
+
+/// 8-bit format; apply gamma (see WriteImage(...) in imageio.cpp).
+/// The actual encoder (PNG, TGA, ...) is picked by the **image**
+/// crate based on the extension.
+fn write_image_8bit(name: &str, rgb: &[Float], output_bounds: &Bounds2i) -> std::io::Result<()> {
+    let (width, height) = resolution(output_bounds);
+    let mut buffer: Vec<u8> = vec![0_u8; 3 * width * height];
+    for (index, value) in buffer.iter_mut().enumerate() {
+        *value = clamp_t(
+            255.0 as Float * gamma_correct(rgb[index]) + 0.5,
+            0.0 as Float,
+            255.0 as Float,
+        ) as u8;
+    }
+    image::save_buffer(
+        Path::new(name),
+        &buffer,
+        width as u32,
+        height as u32,
+        image::ColorType::Rgb8,
+    )
+    .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+/// Portable float map: linear floating-point values, written bottom
+/// to top in little-endian byte order (negative scale).
+fn write_image_pfm(name: &str, rgb: &[Float], output_bounds: &Bounds2i) -> std::io::Result<()> {
+    let (width, height) = resolution(output_bounds);
+    let mut writer = BufWriter::new(File::create(name)?);
+    // only write 3 channel PFMs here...
+    write!(writer, "PF\n{} {}\n-1\n", width, height)?;
+    // write the data from bottom left to upper right as specified by
+    // http://netpbm.sourceforge.net/doc/pfm.html
+    for y in (0..height).rev() {
+        for value in rgb.iter().skip(3 * y * width).take(3 * width) {
+            writer.write_f32::<LittleEndian>(*value)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Radiance RGBE image, encoded by the **image** crate.
+fn write_image_hdr(name: &str, rgb: &[Float], output_bounds: &Bounds2i) -> std::io::Result<()> {
+    let (width, height) = resolution(output_bounds);
+    let pixels: Vec<image::Rgb<f32>> = rgb
+        .chunks(3)
+        .take(width * height)
+        .map(|c| image::Rgb([c[0].max(0.0), c[1].max(0.0), c[2].max(0.0)]))
+        .collect();
+    let writer = BufWriter::new(File::create(name)?);
+    image::hdr::HdrEncoder::new(writer)
+        .encode(&pixels[..], width, height)
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+/// A named channel of an image, one value per pixel (scanline
+/// order), e.g. "R" or "N.X".
+#[derive(Debug, Clone)]
+pub struct ImageChannel {
+    pub name: String,
+    pub values: Vec<Float>,
+}
+
+/// Write an arbitrary set of channels (e.g. RGB plus AOV layers) to
+/// the OpenEXR file *name*. See [write_image()](fn.write_image.html)
+/// for the meaning of *output_bounds* and *total_resolution*.
+pub fn write_image_exr_channels(
+    name: &str,
+    channels: &[ImageChannel],
+    output_bounds: &Bounds2i,
+    total_resolution: &Point2i,
+) {
+    if let Err(e) = write_exr(name, channels, output_bounds, total_resolution) {
+        println!("ERROR: Unable to write image {:?}: {}", name, e);
+    }
+}
+
+/// OpenEXR file with the three channels B, G, R.
+fn write_image_exr(
+    name: &str,
+    rgb: &[Float],
+    output_bounds: &Bounds2i,
+    total_resolution: &Point2i,
+) -> std::io::Result<()> {
+    let channels: Vec<ImageChannel> = ["R", "G", "B"]
+        .iter()
+        .enumerate()
+        .map(|(c, channel_name)| ImageChannel {
+            name: String::from(*channel_name),
+            values: rgb.iter().skip(c).step_by(3).copied().collect(),
+        })
+        .collect();
+    write_exr(name, &channels, output_bounds, total_resolution)
+}
+
+/// Uncompressed single-part scanline OpenEXR file with the data
+/// window placed at the crop window's offset inside a display window
+/// covering the full resolution. Channels are stored as 32-bit floats
+/// in alphabetical order (as required by the format), one scanline
+/// per chunk.
+fn write_exr(
+    name: &str,
+    image_channels: &[ImageChannel],
+    output_bounds: &Bounds2i,
+    total_resolution: &Point2i,
+) -> std::io::Result<()> {
+    let (width, height) = resolution(output_bounds);
+    let mut sorted: Vec<&ImageChannel> = image_channels.iter().collect();
+    sorted.sort_by(|a, b| a.name.as_bytes().cmp(b.name.as_bytes()));
+    let mut header: Vec<u8> = Vec::new();
+    // magic number and version (2, single-part scanline)
+    header.write_u32::<LittleEndian>(20_000_630)?;
+    header.write_u32::<LittleEndian>(2)?;
+    // channels
+    let mut channels: Vec<u8> = Vec::new();
+    for channel in &sorted {
+        channels.extend_from_slice(channel.name.as_bytes());
+        channels.push(0);
+        channels.write_i32::<LittleEndian>(2)?; // FLOAT
+        channels.extend_from_slice(&[0_u8; 4]); // pLinear + reserved
+        channels.write_i32::<LittleEndian>(1)?; // xSampling
+        channels.write_i32::<LittleEndian>(1)?; // ySampling
+    }
+    channels.push(0);
+    write_exr_attribute(&mut header, "channels", "chlist", &channels)?;
+    write_exr_attribute(&mut header, "compression", "compression", &[0_u8])?;
+    let mut data_window: Vec<u8> = Vec::new();
+    data_window.write_i32::<LittleEndian>(output_bounds.p_min.x)?;
+    data_window.write_i32::<LittleEndian>(output_bounds.p_min.y)?;
+    data_window.write_i32::<LittleEndian>(output_bounds.p_max.x - 1)?;
+    data_window.write_i32::<LittleEndian>(output_bounds.p_max.y - 1)?;
+    write_exr_attribute(&mut header, "dataWindow", "box2i", &data_window)?;
+    let mut display_window: Vec<u8> = Vec::new();
+    display_window.write_i32::<LittleEndian>(0)?;
+    display_window.write_i32::<LittleEndian>(0)?;
+    display_window.write_i32::<LittleEndian>(total_resolution.x - 1)?;
+    display_window.write_i32::<LittleEndian>(total_resolution.y - 1)?;
+    write_exr_attribute(&mut header, "displayWindow", "box2i", &display_window)?;
+    write_exr_attribute(&mut header, "lineOrder", "lineOrder", &[0_u8])?;
+    write_exr_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    )?;
+    write_exr_attribute(&mut header, "screenWindowCenter", "v2f", &[0_u8; 8])?;
+    write_exr_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0_f32.to_le_bytes(),
+    )?;
+    header.push(0);
+    // offset table (one entry per scanline)
+    let chunk_size: u64 = 8 + sorted.len() as u64 * 4 * width as u64;
+    let first_chunk: u64 = header.len() as u64 + 8 * height as u64;
+    let mut writer = BufWriter::new(File::create(name)?);
+    writer.write_all(&header)?;
+    for y in 0..height as u64 {
+        writer.write_u64::<LittleEndian>(first_chunk + y * chunk_size)?;
+    }
+    // scanlines
+    for y in 0..height {
+        writer.write_i32::<LittleEndian>(output_bounds.p_min.y + y as i32)?;
+        writer.write_i32::<LittleEndian>((sorted.len() * 4 * width) as i32)?;
+        for channel in &sorted {
+            for x in 0..width {
+                writer.write_f32::<LittleEndian>(channel.values[y * width + x])?;
+            }
+        }
+    }
+    writer.flush()
+}
+
+fn write_exr_attribute(
+    header: &mut Vec<u8>,
+    name: &str,
+    type_name: &str,
+    value: &[u8],
+) -> std::io::Result<()> {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(type_name.as_bytes());
+    header.push(0);
+    header.write_i32::<LittleEndian>(value.len() as i32)?;
+    header.extend_from_slice(value);
+    Ok(())
+}
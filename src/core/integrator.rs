@@ -2,21 +2,34 @@
 //! class that implements the **Integrator** interface.
 
 // std
+use std::io::Result;
 use std::sync::Arc;
+use std::time::Instant;
+// others
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 // pbrt
 use crate::blockqueue::BlockQueue;
 use crate::core::camera::{Camera, CameraSample};
-use crate::core::geometry::{pnt2_inside_exclusivei, vec3_abs_dot_nrmf};
+use crate::core::checkpoint::{can_resume, read_checkpoint, read_len, write_checkpoint, write_len};
+use crate::core::checkpoint::{CheckpointHeader, CheckpointKind, CheckpointTimer};
+use crate::core::distributed::{serve_work_units, DistributedRole, RenderHeader};
+use crate::core::distributed::{WorkUnit, WorkerConnection};
+use crate::core::film::{AovSample, Film, FilmTile, ProgressiveParams};
+use crate::core::geometry::{pnt2_inside_exclusivei, pnt3_distancef, vec3_abs_dot_nrmf};
 use crate::core::geometry::{Bounds2i, Point2f, Point2i, Ray, Vector2i, Vector3f};
 use crate::core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
 use crate::core::light::is_delta_light;
 use crate::core::light::{Light, VisibilityTester};
+use crate::core::material::TransportMode;
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progress::ProgressReporter;
 use crate::core::reflection::BxdfType;
 use crate::core::sampler::Sampler;
 use crate::core::sampling::power_heuristic;
 use crate::core::sampling::Distribution1D;
 use crate::core::scene::Scene;
+use crate::core::stats::{clear_stats, init_profiler, print_stats, report_profiler_results};
+use crate::core::stats::{Prof, ProfilePhase, StatCounter};
 use crate::integrators::ao::AOIntegrator;
 use crate::integrators::bdpt::BDPTIntegrator;
 use crate::integrators::directlighting::DirectLightingIntegrator;
@@ -28,6 +41,8 @@ use crate::integrators::whitted::WhittedIntegrator;
 
 // see integrator.h
 
+static N_CAMERA_RAYS: StatCounter = StatCounter::new("Integrator/Camera rays traced");
+
 pub enum Integrator {
     BDPT(BDPTIntegrator),
     MLT(MLTIntegrator),
@@ -37,12 +52,39 @@ pub enum Integrator {
 
 impl Integrator {
     pub fn render(&mut self, scene: &Scene, num_threads: u8) {
+        init_profiler();
         match self {
             Integrator::BDPT(integrator) => integrator.render(scene, num_threads),
             Integrator::MLT(integrator) => integrator.render(scene, num_threads),
             Integrator::SPPM(integrator) => integrator.render(scene, num_threads),
             Integrator::Sampler(integrator) => integrator.render(scene, num_threads),
         }
+        // only does something with the "stats" feature
+        print_stats();
+        report_profiler_results();
+        clear_stats();
+    }
+    /// Render together with other processes, see
+    /// [distributed](../distributed/index.html).
+    pub fn render_distributed(&mut self, scene: &Scene, num_threads: u8, role: &DistributedRole) {
+        match (self, role) {
+            (Integrator::Sampler(integrator), _) => {
+                init_profiler();
+                integrator.render_distributed(scene, num_threads, role);
+                print_stats();
+                report_profiler_results();
+                clear_stats();
+            }
+            (integrator, DistributedRole::Coordinator { .. }) => {
+                println!(
+                    "WARNING: Only sampler integrators support distributed rendering, rendering locally"
+                );
+                integrator.render(scene, num_threads);
+            }
+            (_, DistributedRole::Worker { .. }) => {
+                println!("ERROR: Only sampler integrators support distributed rendering");
+            }
+        }
     }
 }
 
@@ -76,147 +118,462 @@ impl SamplerIntegrator {
         let x: i32 = (sample_extent.x + tile_size - 1) / tile_size;
         let y: i32 = (sample_extent.y + tile_size - 1) / tile_size;
         let n_tiles: Point2i = Point2i { x, y };
-        // TODO: ProgressReporter reporter(nTiles.x * nTiles.y, "Rendering");
         let num_cores = if num_threads == 0_u8 {
             num_cpus::get()
         } else {
             num_threads as usize
         };
         println!("Rendering with {:?} thread(s) ...", num_cores);
-        {
-            let block_queue = BlockQueue::new(
-                (
-                    (n_tiles.x * tile_size) as u32,
-                    (n_tiles.y * tile_size) as u32,
-                ),
-                (tile_size as u32, tile_size as u32),
-                (0, 0),
+        let integrator: &SamplerIntegrator = self;
+        let spp: i64 = integrator.get_sampler().get_samples_per_pixel();
+        let some_adaptive = integrator.get_sampler().get_adaptive_sampling();
+        if some_adaptive.is_some() {
+            film.track_variance();
+        }
+        // number of samples taken so far in each tile
+        let n_total: usize = (n_tiles.x * n_tiles.y) as usize;
+        let mut tile_samples: Vec<i64> = vec![0_i64; n_total];
+        let checkpoint_header: CheckpointHeader = CheckpointHeader {
+            kind: CheckpointKind::Tiles,
+            pixel_bounds: film.cropped_pixel_bounds,
+            target: spp,
+        };
+        let mut some_timer: Option<CheckpointTimer> = None;
+        if let Some(ref checkpoint) = film.checkpoint {
+            if can_resume(checkpoint) {
+                println!("Resuming from checkpoint {:?} ...", checkpoint.filename);
+                match read_checkpoint(&checkpoint.filename, &checkpoint_header, |reader| {
+                    read_tiles_checkpoint(reader, &film, n_total)
+                }) {
+                    Ok(samples) => tile_samples = samples,
+                    Err(e) => println!(
+                        "ERROR: Unable to resume from checkpoint {:?}: {} (starting from scratch)",
+                        checkpoint.filename, e
+                    ),
+                }
+            }
+            some_timer = Some(CheckpointTimer::new(checkpoint.interval));
+        }
+        let deadline: Option<Instant> = film
+            .progressive
+            .and_then(|progressive| progressive.time_limit)
+            .map(|time_limit| Instant::now() + time_limit);
+        let mut some_progressive: Option<ProgressiveParams> = film.progressive;
+        if some_progressive.is_some() && !integrator.get_sampler().can_continue_pixels() {
+            println!(
+                "WARNING: Progressive rendering needs the \"halton\", \"random\" or \"sobol\" sampler, taking all samples in a single pass"
             );
-            let integrator = &self;
-            let bq = &block_queue;
-            let sampler = &self.get_sampler();
-            let camera = &self.get_camera();
-            let film = &film;
-            let pixel_bounds = &self.get_pixel_bounds();
-            crossbeam::scope(|scope| {
-                let (pixel_tx, pixel_rx) = crossbeam_channel::bounded(num_cores);
-                // spawn worker threads
-                for _ in 0..num_cores {
-                    let pixel_tx = pixel_tx.clone();
-                    let mut tile_sampler: Box<Sampler> = sampler.clone_with_seed(0_u64);
+            some_progressive = None;
+        }
+        // render the active tiles until their pixels have *end_sample*
+        // samples (or the time is up)
+        let mut render_pass =
+            |end_sample: i64, active: &[bool], tile_samples: &mut Vec<i64>, title: &str| {
+                let first_samples: Vec<i64> = tile_samples.clone();
+                let mut block_queue = BlockQueue::new(
+                    (
+                        (n_tiles.x * tile_size) as u32,
+                        (n_tiles.y * tile_size) as u32,
+                    ),
+                    (tile_size as u32, tile_size as u32),
+                    (0, 0),
+                );
+                block_queue.retain(|&(x, y)| {
+                    let i: usize = (y as i32 * n_tiles.x + x as i32) as usize;
+                    active[i] && first_samples[i] < end_sample
+                });
+                if block_queue.is_empty() {
+                    return;
+                }
+                let reporter = &ProgressReporter::new(block_queue.len() as u64, title);
+                let bq = &block_queue;
+                let first_samples = &first_samples;
+                // reborrowed for the thread collecting the tiles
+                let merged_samples: &mut Vec<i64> = &mut *tile_samples;
+                let checkpoint_timer: &mut Option<CheckpointTimer> = &mut some_timer;
+                let checkpoint_header = &checkpoint_header;
+                let sampler = &integrator.get_sampler();
+                let film = &film;
+                crossbeam::scope(|scope| {
+                    let (pixel_tx, pixel_rx) = crossbeam_channel::bounded(num_cores);
+                    // spawn worker threads
+                    for _ in 0..num_cores {
+                        let pixel_tx = pixel_tx.clone();
+                        let mut tile_sampler: Box<Sampler> = sampler.clone_with_seed(0_u64);
+                        scope.spawn(move |_| {
+                            while let Some((x, y)) = bq.next() {
+                                if time_is_up(deadline) {
+                                    break;
+                                }
+                                let tile: Point2i = Point2i {
+                                    x: x as i32,
+                                    y: y as i32,
+                                };
+                                let seed: i32 = tile.y * n_tiles.x + tile.x;
+                                let first_sample: i64 = first_samples[seed as usize];
+                                tile_sampler.reseed(get_tile_seed(
+                                    seed as usize,
+                                    n_total,
+                                    first_sample,
+                                ));
+                                let tile_bounds: Bounds2i =
+                                    get_tile_bounds(&sample_bounds, tile, tile_size);
+                                let film_tile = integrator.render_tile(
+                                    scene,
+                                    film,
+                                    &mut tile_sampler,
+                                    &tile_bounds,
+                                    first_sample,
+                                    end_sample,
+                                );
+                                // send the tile through the channel to main thread
+                                pixel_tx
+                                    .send((seed as usize, film_tile))
+                                    .unwrap_or_else(|_| panic!("Failed to send tile"));
+                            }
+                        });
+                    }
+                    // workers stop early if the time is up
+                    drop(pixel_tx);
+                    // spawn thread to collect pixels and render image to file
                     scope.spawn(move |_| {
-                        while let Some((x, y)) = bq.next() {
-                            let tile: Point2i = Point2i {
-                                x: x as i32,
-                                y: y as i32,
-                            };
-                            let seed: i32 = tile.y * n_tiles.x + tile.x;
-                            tile_sampler.reseed(seed as u64);
-                            let x0: i32 = sample_bounds.p_min.x + tile.x * tile_size;
-                            let x1: i32 = std::cmp::min(x0 + tile_size, sample_bounds.p_max.x);
-                            let y0: i32 = sample_bounds.p_min.y + tile.y * tile_size;
-                            let y1: i32 = std::cmp::min(y0 + tile_size, sample_bounds.p_max.y);
-                            let tile_bounds: Bounds2i =
-                                Bounds2i::new(Point2i { x: x0, y: y0 }, Point2i { x: x1, y: y1 });
-                            // println!("Starting image tile {:?}", tile_bounds);
-                            let mut film_tile = film.get_film_tile(&tile_bounds);
-                            for pixel in &tile_bounds {
-                                tile_sampler.start_pixel(pixel);
-                                if !pnt2_inside_exclusivei(pixel, &pixel_bounds) {
-                                    continue;
+                        for (tile_index, film_tile) in pixel_rx.iter() {
+                            // merge image tile into _Film_
+                            film.merge_film_tile(&film_tile);
+                            merged_samples[tile_index] = end_sample;
+                            reporter.update(1);
+                            if let Some(timer) = checkpoint_timer {
+                                if timer.due() {
+                                    write_tiles_checkpoint(film, checkpoint_header, merged_samples);
                                 }
-                                let mut done: bool = false;
-                                while !done {
-                                    // let's use the copy_arena crate instead of pbrt's MemoryArena
-                                    // let mut arena: Arena = Arena::with_capacity(262144); // 256kB
-
-                                    // initialize _CameraSample_ for current sample
-                                    let camera_sample: CameraSample =
-                                        tile_sampler.get_camera_sample(pixel);
-                                    // generate camera ray for current sample
-                                    let mut ray: Ray = Ray::default();
-                                    let ray_weight: Float =
-                                        camera.generate_ray_differential(&camera_sample, &mut ray);
-                                    ray.scale_differentials(
-                                        1.0 as Float
-                                            / (tile_sampler.get_samples_per_pixel() as Float)
-                                                .sqrt(),
-                                    );
-                                    // TODO: ++nCameraRays;
-                                    // evaluate radiance along camera ray
-                                    let mut l: Spectrum = Spectrum::new(0.0 as Float);
-                                    let y: Float = l.y();
-                                    if ray_weight > 0.0 {
-                                        // ADDED
-                                        let clipping_start: Float = camera.get_clipping_start();
-                                        if clipping_start > 0.0 as Float {
-                                            // adjust ray origin for near clipping
-                                            ray.o = ray.position(clipping_start);
-                                        }
-                                        // ADDED
-                                        l = integrator.li(
-                                            &mut ray,
-                                            scene,
-                                            &mut tile_sampler, // &mut arena,
-                                            0_i32,
-                                        );
-                                    }
-                                    if l.has_nans() {
-                                        println!(
-                                            "Not-a-number radiance value returned for pixel \
-                                                     ({:?}, {:?}), sample {:?}. Setting to black.",
-                                            pixel.x,
-                                            pixel.y,
-                                            tile_sampler.get_current_sample_number()
-                                        );
-                                        l = Spectrum::new(0.0);
-                                    } else if y < -10.0e-5 as Float {
-                                        println!(
-                                            "Negative luminance value, {:?}, returned for pixel \
-                                                 ({:?}, {:?}), sample {:?}. Setting to black.",
-                                            y,
-                                            pixel.x,
-                                            pixel.y,
-                                            tile_sampler.get_current_sample_number()
-                                        );
-                                        l = Spectrum::new(0.0);
-                                    } else if y.is_infinite() {
-                                        println!(
-                                            "Infinite luminance value returned for pixel ({:?}, \
-                                                 {:?}), sample {:?}. Setting to black.",
-                                            pixel.x,
-                                            pixel.y,
-                                            tile_sampler.get_current_sample_number()
-                                        );
-                                        l = Spectrum::new(0.0);
-                                    }
-                                    // println!("Camera sample: {:?} -> ray: {:?} -> L = {:?}",
-                                    //          camera_sample, ray, l);
-                                    // add camera ray's contribution to image
-                                    film_tile.add_sample(camera_sample.p_film, &mut l, ray_weight);
-                                    done = !tile_sampler.start_next_sample();
-                                } // arena is dropped here !
                             }
-                            // send the tile through the channel to main thread
-                            pixel_tx
-                                .send(film_tile)
-                                .unwrap_or_else(|_| panic!("Failed to send tile"));
                         }
                     });
+                })
+                .unwrap();
+                reporter.done();
+                // a checkpoint at the end of each pass
+                if some_timer.is_some() {
+                    write_tiles_checkpoint(film, checkpoint_header, tile_samples);
                 }
-                // spawn thread to collect pixels and render image to file
-                scope.spawn(move |_| {
-                    for _ in pbr::PbIter::new(0..bq.len()) {
-                        let film_tile = pixel_rx.recv().unwrap();
-                        // merge image tile into _Film_
-                        film.merge_film_tile(&film_tile);
+            };
+        // Adaptive sampling and progressive rendering start with a few
+        // samples everywhere, then keep doubling the samples per pixel
+        // (of tiles which are still too noisy). Otherwise all samples
+        // are taken in a single pass.
+        let mut end_sample: i64 = match (some_adaptive, some_progressive) {
+            (Some(adaptive), _) => std::cmp::min(adaptive.min_samples_per_pixel, spp),
+            (None, Some(_)) => 1_i64,
+            (None, None) => spp,
+        };
+        let mut active: Vec<bool> = vec![true; n_total];
+        let mut n_active: usize = n_total;
+        let mut pass: i32 = 1;
+        loop {
+            let title: String = if end_sample == spp && pass == 1 {
+                String::from("Rendering")
+            } else {
+                format!("Rendering pass {} ({} spp)", pass, end_sample)
+            };
+            render_pass(end_sample, &active, &mut tile_samples, &title);
+            if let Some(adaptive) = some_adaptive {
+                for (i, is_active) in active.iter_mut().enumerate() {
+                    // tiles of a resumed render might be ahead
+                    if !*is_active || tile_samples[i] < end_sample {
+                        continue;
                     }
-                });
-            })
-            .unwrap();
+                    let tile: Point2i = Point2i {
+                        x: i as i32 % n_tiles.x,
+                        y: i as i32 / n_tiles.x,
+                    };
+                    let tile_bounds: Bounds2i = get_tile_bounds(&sample_bounds, tile, tile_size);
+                    if film.get_max_relative_error(&tile_bounds) <= adaptive.noise_threshold {
+                        *is_active = false;
+                        n_active -= 1;
+                    }
+                }
+            }
+            // tiles are only skipped if the time is up
+            let interrupted: bool = active
+                .iter()
+                .zip(tile_samples.iter())
+                .any(|(is_active, samples)| *is_active && *samples < end_sample);
+            let finished: bool = n_active == 0 || end_sample >= spp;
+            if interrupted || (!finished && time_is_up(deadline)) {
+                println!(
+                    "Time limit reached after {:.1} spp on average",
+                    tile_samples.iter().sum::<i64>() as Float / n_total as Float
+                );
+                break;
+            }
+            if finished {
+                break;
+            }
+            if let Some(progressive) = some_progressive {
+                if pass % progressive.write_frequency == 0 {
+                    film.write_image(1.0 as Float);
+                }
+            }
+            end_sample = std::cmp::min(2_i64 * end_sample, spp);
+            pass += 1;
+        }
+        if some_adaptive.is_some() {
+            println!(
+                "Adaptive sampling: {:.1} spp on average, {} of {} tiles reached the noise threshold",
+                tile_samples.iter().sum::<i64>() as Float / n_total as Float,
+                n_total - n_active,
+                n_total
+            );
         }
         film.write_image(1.0 as Float);
     }
+    /// Like [render()](#method.render), but the tiles get rendered by
+    /// worker processes (see [distributed](../distributed/index.html)).
+    pub fn render_distributed(&mut self, scene: &Scene, num_threads: u8, role: &DistributedRole) {
+        let film = self.get_camera().get_film();
+        let sample_bounds: Bounds2i = film.get_sample_bounds();
+        self.preprocess(scene);
+        let sample_extent: Vector2i = sample_bounds.diagonal();
+        let tile_size: i32 = 16;
+        let x: i32 = (sample_extent.x + tile_size - 1) / tile_size;
+        let y: i32 = (sample_extent.y + tile_size - 1) / tile_size;
+        let n_tiles: Point2i = Point2i { x, y };
+        let n_total: usize = (n_tiles.x * n_tiles.y) as usize;
+        let spp: i64 = self.get_sampler().get_samples_per_pixel();
+        if self.get_sampler().get_adaptive_sampling().is_some()
+            || film.checkpoint.is_some()
+            || film.progressive.is_some()
+        {
+            println!(
+                "WARNING: Adaptive sampling, checkpoints and progressive rendering are ignored by distributed rendering"
+            );
+        }
+        let header: RenderHeader = RenderHeader {
+            pixel_bounds: film.cropped_pixel_bounds,
+            samples_per_pixel: spp,
+        };
+        match role {
+            DistributedRole::Coordinator {
+                address,
+                samples_per_unit,
+            } => {
+                // split the samples of each tile (in Morton order) into work units
+                let mut samples_per_unit: i64 =
+                    std::cmp::max(samples_per_unit.unwrap_or(spp), 1_i64);
+                if samples_per_unit < spp && !self.get_sampler().can_continue_pixels() {
+                    println!(
+                        "WARNING: Splitting the samples of a tile needs the \"halton\", \"random\" or \"sobol\" sampler, ignoring --unit-spp"
+                    );
+                    samples_per_unit = spp;
+                }
+                let block_queue = BlockQueue::new(
+                    (
+                        (n_tiles.x * tile_size) as u32,
+                        (n_tiles.y * tile_size) as u32,
+                    ),
+                    (tile_size as u32, tile_size as u32),
+                    (0, 0),
+                );
+                let mut units: Vec<WorkUnit> = Vec::new();
+                for (x, y) in block_queue.iter() {
+                    let tile: Point2i = Point2i {
+                        x: x as i32,
+                        y: y as i32,
+                    };
+                    let mut first_sample: i64 = 0;
+                    while first_sample < spp {
+                        let end_sample: i64 = std::cmp::min(first_sample + samples_per_unit, spp);
+                        units.push(WorkUnit {
+                            tile_index: (tile.y * n_tiles.x + tile.x) as usize,
+                            tile_bounds: get_tile_bounds(&sample_bounds, tile, tile_size),
+                            first_sample,
+                            end_sample,
+                        });
+                        first_sample = end_sample;
+                    }
+                }
+                match serve_work_units(address, &header, &film, &units) {
+                    Ok(()) => film.write_image(1.0 as Float),
+                    Err(e) => println!("ERROR: Unable to serve workers on {}: {}", address, e),
+                }
+            }
+            DistributedRole::Worker { address } => {
+                let num_cores = if num_threads == 0_u8 {
+                    num_cpus::get()
+                } else {
+                    num_threads as usize
+                };
+                println!(
+                    "Rendering for {} with {:?} thread(s) ...",
+                    address, num_cores
+                );
+                let integrator: &SamplerIntegrator = self;
+                let sampler = &integrator.get_sampler();
+                let film = &film;
+                let header = &header;
+                let n_rendered: usize = crossbeam::scope(|scope| {
+                    // each thread has its own connection to the coordinator
+                    let threads: Vec<_> = (0..num_cores)
+                        .map(|_| {
+                            let mut tile_sampler: Box<Sampler> = sampler.clone_with_seed(0_u64);
+                            scope.spawn(move |_| {
+                                integrator
+                                    .work_for_coordinator(
+                                        scene,
+                                        film,
+                                        &mut tile_sampler,
+                                        address,
+                                        header,
+                                        n_total,
+                                    )
+                                    .unwrap_or_else(|e| {
+                                        println!("ERROR: Unable to render for {}: {}", address, e);
+                                        0
+                                    })
+                            })
+                        })
+                        .collect();
+                    threads
+                        .into_iter()
+                        .map(|thread| thread.join().unwrap())
+                        .sum()
+                })
+                .unwrap();
+                println!("Rendered {} work unit(s) for {}", n_rendered, address);
+            }
+        }
+    }
+    /// Render the work units of a coordinator until the image is
+    /// done, returns the number of work units rendered.
+    fn work_for_coordinator(
+        &self,
+        scene: &Scene,
+        film: &Film,
+        tile_sampler: &mut Sampler,
+        address: &str,
+        header: &RenderHeader,
+        n_tiles: usize,
+    ) -> Result<usize> {
+        let mut connection: WorkerConnection = WorkerConnection::connect(address, header)?;
+        let mut n_rendered: usize = 0;
+        while let Some(unit) = connection.next_work_unit()? {
+            tile_sampler.reseed(get_tile_seed(unit.tile_index, n_tiles, unit.first_sample));
+            let film_tile: FilmTile = self.render_tile(
+                scene,
+                film,
+                tile_sampler,
+                &unit.tile_bounds,
+                unit.first_sample,
+                unit.end_sample,
+            );
+            connection.send_film_tile(&film_tile)?;
+            n_rendered += 1;
+        }
+        Ok(n_rendered)
+    }
+    /// Take the samples *first_sample* up to (but not including)
+    /// *end_sample* of all pixels within *tile_bounds*. The sampler has
+    /// to be seeded for the tile (see
+    /// [get_tile_seed()](fn.get_tile_seed.html)).
+    pub fn render_tile<'a>(
+        &self,
+        scene: &Scene,
+        film: &'a Film,
+        tile_sampler: &mut Sampler,
+        tile_bounds: &Bounds2i,
+        first_sample: i64,
+        end_sample: i64,
+    ) -> FilmTile<'a> {
+        let camera: Arc<Camera> = self.get_camera();
+        let pixel_bounds: Bounds2i = self.get_pixel_bounds();
+        // println!("Starting image tile {:?}", tile_bounds);
+        let mut film_tile: FilmTile = film.get_film_tile(tile_bounds);
+        for pixel in tile_bounds {
+            tile_sampler.start_pixel(pixel);
+            if !pnt2_inside_exclusivei(pixel, &pixel_bounds) {
+                continue;
+            }
+            if first_sample > 0_i64 {
+                // continue the sample sequence of previous passes
+                tile_sampler.set_sample_number(first_sample);
+            }
+            let mut done: bool = false;
+            while !done {
+                // let's use the copy_arena crate instead of pbrt's MemoryArena
+                // let mut arena: Arena = Arena::with_capacity(262144); // 256kB
+
+                // initialize _CameraSample_ for current sample
+                let camera_sample: CameraSample = tile_sampler.get_camera_sample(pixel);
+                // generate camera ray for current sample
+                let mut ray: Ray = Ray::default();
+                let ray_weight: Float = camera.generate_ray_differential(&camera_sample, &mut ray);
+                ray.scale_differentials(
+                    1.0 as Float / (tile_sampler.get_samples_per_pixel() as Float).sqrt(),
+                );
+                N_CAMERA_RAYS.inc();
+                // evaluate radiance along camera ray
+                let mut l: Spectrum = Spectrum::new(0.0 as Float);
+                let y: Float = l.y();
+                let mut aov_sample: AovSample = AovSample::default();
+                if ray_weight > 0.0 {
+                    // ADDED
+                    let clipping_start: Float = camera.get_clipping_start();
+                    if clipping_start > 0.0 as Float {
+                        // adjust ray origin for near clipping
+                        ray.o = ray.position(clipping_start);
+                    }
+                    // ADDED
+                    if film_tile.has_aovs() {
+                        l = self.li_aovs(&mut ray, scene, tile_sampler, 0_i32, &mut aov_sample);
+                    } else {
+                        l = self.li(
+                            &mut ray,
+                            scene,
+                            tile_sampler, // &mut arena,
+                            0_i32,
+                        );
+                    }
+                }
+                if l.has_nans() {
+                    println!(
+                        "Not-a-number radiance value returned for pixel \
+                                         ({:?}, {:?}), sample {:?}. Setting to black.",
+                        pixel.x,
+                        pixel.y,
+                        tile_sampler.get_current_sample_number()
+                    );
+                    l = Spectrum::new(0.0);
+                } else if y < -10.0e-5 as Float {
+                    println!(
+                        "Negative luminance value, {:?}, returned for pixel \
+                                     ({:?}, {:?}), sample {:?}. Setting to black.",
+                        y,
+                        pixel.x,
+                        pixel.y,
+                        tile_sampler.get_current_sample_number()
+                    );
+                    l = Spectrum::new(0.0);
+                } else if y.is_infinite() {
+                    println!(
+                        "Infinite luminance value returned for pixel ({:?}, \
+                                     {:?}), sample {:?}. Setting to black.",
+                        pixel.x,
+                        pixel.y,
+                        tile_sampler.get_current_sample_number()
+                    );
+                    l = Spectrum::new(0.0);
+                }
+                // println!("Camera sample: {:?} -> ray: {:?} -> L = {:?}",
+                //          camera_sample, ray, l);
+                // add camera ray's contribution to image
+                film_tile.add_sample(camera_sample.p_film, &mut l, ray_weight);
+                film_tile.add_aov_sample(camera_sample.p_film, &aov_sample, ray_weight);
+                done = !tile_sampler.start_next_sample()
+                    || tile_sampler.get_current_sample_number() >= end_sample;
+            } // arena is dropped here !
+        }
+        film_tile
+    }
     pub fn li(&self, ray: &mut Ray, scene: &Scene, sampler: &mut Sampler, depth: i32) -> Spectrum {
         match self {
             SamplerIntegrator::AO(integrator) => integrator.li(ray, scene, sampler, depth),
@@ -228,6 +585,28 @@ impl SamplerIntegrator {
             SamplerIntegrator::Whitted(integrator) => integrator.li(ray, scene, sampler, depth),
         }
     }
+    /// Like li(), but also fills the AOVs of *aov_sample*. The path
+    /// tracer does that while tracing the camera path, the other
+    /// integrators only get the geometric AOVs (by re-tracing the
+    /// camera ray).
+    pub fn li_aovs(
+        &self,
+        ray: &mut Ray,
+        scene: &Scene,
+        sampler: &mut Sampler,
+        depth: i32,
+        aov_sample: &mut AovSample,
+    ) -> Spectrum {
+        match self {
+            SamplerIntegrator::Path(integrator) => {
+                integrator.li_aovs(ray, scene, sampler, depth, Some(aov_sample))
+            }
+            _ => {
+                compute_geometric_aovs(ray, scene, aov_sample);
+                self.li(ray, scene, sampler, depth)
+            }
+        }
+    }
     pub fn get_camera(&self) -> Arc<Camera> {
         match self {
             SamplerIntegrator::AO(integrator) => integrator.get_camera(),
@@ -303,7 +682,7 @@ pub fn uniform_sample_all_lights(
     n_light_samples: &[i32],
     handle_media: bool,
 ) -> Spectrum {
-    // TODO: ProfilePhase p(Prof::DirectLighting);
+    let _prof = ProfilePhase::new(Prof::DirectLighting);
     let mut l: Spectrum = Spectrum::new(0.0);
     for (j, n_samples) in n_light_samples.iter().enumerate().take(scene.lights.len()) {
         // accumulate contribution of _j_th light to _L_
@@ -362,7 +741,21 @@ pub fn uniform_sample_one_light(
     handle_media: bool,
     light_distrib: Option<&Distribution1D>,
 ) -> Spectrum {
-    // TODO: ProfilePhase p(Prof::DirectLighting);
+    uniform_sample_one_light_split(it, scene, sampler, handle_media, light_distrib, None)
+}
+
+/// Same as uniform_sample_one_light(), but additionally stores the
+/// part of the estimate due to diffuse BSDF lobes in *diffuse* (if
+/// given). Uses the same sample values.
+pub fn uniform_sample_one_light_split(
+    it: &dyn Interaction,
+    scene: &Scene,
+    sampler: &mut Sampler,
+    handle_media: bool,
+    light_distrib: Option<&Distribution1D>,
+    mut diffuse: Option<&mut Spectrum>,
+) -> Spectrum {
+    let _prof = ProfilePhase::new(Prof::DirectLighting);
 
     // randomly choose a single light to sample, _light_
     let n_lights: usize = scene.lights.len();
@@ -389,7 +782,7 @@ pub fn uniform_sample_one_light(
     let light = &scene.lights[light_num];
     let u_light: Point2f = sampler.get_2d();
     let u_scattering: Point2f = sampler.get_2d();
-    estimate_direct(
+    let ld: Spectrum = estimate_direct_split(
         it,
         u_scattering,
         light,
@@ -398,7 +791,12 @@ pub fn uniform_sample_one_light(
         sampler,
         handle_media,
         false,
-    ) / pdf
+        diffuse.as_deref_mut(),
+    );
+    if let Some(diffuse) = diffuse {
+        *diffuse /= pdf;
+    }
+    ld / pdf
 }
 
 /// Computes a direct lighting estimate for a single light source sample.
@@ -413,6 +811,35 @@ pub fn estimate_direct(
     handle_media: bool,
     specular: bool,
 ) -> Spectrum {
+    estimate_direct_split(
+        it,
+        u_scattering,
+        light,
+        u_light,
+        scene,
+        sampler,
+        handle_media,
+        specular,
+        None,
+    )
+}
+
+/// See estimate_direct(), the part of the estimate due to diffuse
+/// BSDF lobes gets added to *diffuse* (if given).
+fn estimate_direct_split(
+    it: &dyn Interaction,
+    u_scattering: Point2f,
+    light: &Light,
+    u_light: Point2f,
+    scene: &Scene,
+    sampler: &mut Sampler,
+    handle_media: bool,
+    specular: bool,
+    mut diffuse: Option<&mut Spectrum>,
+) -> Spectrum {
+    let diffuse_flags: u8 = BxdfType::BsdfReflection as u8
+        | BxdfType::BsdfTransmission as u8
+        | BxdfType::BsdfDiffuse as u8;
     let bsdf_flags = if !specular {
         // bitwise not in Rust is ! (not the ~ operator like in C)
         BxdfType::BsdfAll as u8 & !(BxdfType::BsdfSpecular as u8)
@@ -439,12 +866,17 @@ pub fn estimate_direct(
     if light_pdf > 0.0 as Float && !li.is_black() {
         // compute BSDF or phase function's value for light sample
         let mut f: Spectrum = Spectrum::new(0.0);
+        let mut f_diffuse: Spectrum = Spectrum::new(0.0);
         if it.is_surface_interaction() {
             // evaluate BSDF for light sampling strategy
             if let Some(ref bsdf) = it.get_bsdf() {
                 if let Some(shading_n) = it.get_shading_n() {
                     f = bsdf.f(&it.get_wo(), &wi, bsdf_flags)
                         * Spectrum::new(vec3_abs_dot_nrmf(&wi, &shading_n));
+                    if diffuse.is_some() {
+                        f_diffuse = bsdf.f(it.get_wo(), &wi, diffuse_flags)
+                            * Spectrum::new(vec3_abs_dot_nrmf(&wi, shading_n));
+                    }
                     scattering_pdf = bsdf.pdf(&it.get_wo(), &wi, bsdf_flags);
                     // TODO: println!("  surf f*dot :{:?}, scatteringPdf: {:?}", f, scattering_pdf);
                 }
@@ -466,11 +898,14 @@ pub fn estimate_direct(
             }
             // add light's contribution to reflected radiance
             if !li.is_black() {
-                if is_delta_light(light.get_flags()) {
-                    ld += f * li / light_pdf;
+                let weight: Float = if is_delta_light(light.get_flags()) {
+                    1.0 as Float
                 } else {
-                    let weight: Float = power_heuristic(1_u8, light_pdf, 1_u8, scattering_pdf);
-                    ld += f * li * Spectrum::new(weight) / light_pdf;
+                    power_heuristic(1_u8, light_pdf, 1_u8, scattering_pdf)
+                };
+                ld += f * li * Spectrum::new(weight) / light_pdf;
+                if let Some(diffuse) = diffuse.as_deref_mut() {
+                    *diffuse += f_diffuse * li * Spectrum::new(weight) / light_pdf;
                 }
             }
         }
@@ -478,6 +913,7 @@ pub fn estimate_direct(
     // sample BSDF with multiple importance sampling
     if !is_delta_light(light.get_flags()) {
         let mut f: Spectrum = Spectrum::new(0.0);
+        let mut f_diffuse: Spectrum = Spectrum::new(0.0);
         let mut sampled_specular: bool = false;
         if it.is_surface_interaction() {
             // sample scattered direction for surface interactions
@@ -494,6 +930,10 @@ pub fn estimate_direct(
                     );
                     f *= Spectrum::new(vec3_abs_dot_nrmf(&wi, &shading_n));
                     sampled_specular = (sampled_type & BxdfType::BsdfSpecular as u8) != 0_u8;
+                    if diffuse.is_some() && !sampled_specular {
+                        f_diffuse = bsdf.f(it.get_wo(), &wi, diffuse_flags)
+                            * Spectrum::new(vec3_abs_dot_nrmf(&wi, shading_n));
+                    }
                 }
             } else {
                 println!("TODO: if let Some(ref bsdf) = it.get_bsdf() failed");
@@ -562,12 +1002,130 @@ pub fn estimate_direct(
             }
             if !li.is_black() {
                 ld += f * li * tr * weight / scattering_pdf;
+                if let Some(diffuse) = diffuse {
+                    *diffuse += f_diffuse * li * tr * weight / scattering_pdf;
+                }
             }
         }
     }
     ld
 }
 
+/// Store the film and the number of samples taken in each tile in a
+/// checkpoint (if the film asks for checkpoints).
+fn write_tiles_checkpoint(film: &Film, header: &CheckpointHeader, tile_samples: &[i64]) {
+    if let Some(ref checkpoint) = film.checkpoint {
+        let result: Result<()> = write_checkpoint(&checkpoint.filename, header, |writer| {
+            write_len(writer, tile_samples.len())?;
+            for samples in tile_samples {
+                writer.write_i64::<LittleEndian>(*samples)?;
+            }
+            film.write_checkpoint_state(writer)
+        });
+        if let Err(e) = result {
+            println!(
+                "ERROR: Unable to write checkpoint {:?}: {}",
+                checkpoint.filename, e
+            );
+        }
+    }
+}
+
+/// Restore the state stored by
+/// [write_tiles_checkpoint()](fn.write_tiles_checkpoint.html) and
+/// return the number of samples taken in each tile.
+fn read_tiles_checkpoint<R: std::io::Read>(
+    reader: &mut R,
+    film: &Film,
+    n_tiles: usize,
+) -> Result<Vec<i64>> {
+    read_len(reader, n_tiles)?;
+    let mut tile_samples: Vec<i64> = vec![0_i64; n_tiles];
+    reader.read_i64_into::<LittleEndian>(&mut tile_samples)?;
+    film.read_checkpoint_state(reader)?;
+    Ok(tile_samples)
+}
+
+/// Seed for the sampler of a tile, which depends on the first sample
+/// to take, so that later passes of the random sampler don't repeat
+/// the random numbers of earlier ones (global samplers ignore the
+/// seed, pixel samplers never [continue
+/// pixels](../sampler/enum.Sampler.html#method.can_continue_pixels)).
+pub fn get_tile_seed(tile_index: usize, n_tiles: usize, first_sample: i64) -> u64 {
+    (first_sample * n_tiles as i64 + tile_index as i64) as u64
+}
+
+/// Returns true once the *deadline* (if any) has passed.
+pub fn time_is_up(deadline: Option<Instant>) -> bool {
+    match deadline {
+        Some(deadline) => Instant::now() >= deadline,
+        None => false,
+    }
+}
+
+/// Pixel bounds of the image *tile* (clipped to *sample_bounds*).
+pub fn get_tile_bounds(sample_bounds: &Bounds2i, tile: Point2i, tile_size: i32) -> Bounds2i {
+    let x0: i32 = sample_bounds.p_min.x + tile.x * tile_size;
+    let x1: i32 = std::cmp::min(x0 + tile_size, sample_bounds.p_max.x);
+    let y0: i32 = sample_bounds.p_min.y + tile.y * tile_size;
+    let y1: i32 = std::cmp::min(y0 + tile_size, sample_bounds.p_max.y);
+    Bounds2i::new(Point2i { x: x0, y: y0 }, Point2i { x: x1, y: y1 })
+}
+
+/// Fill the geometric AOVs (depth, position, normal, albedo, uv and
+/// IDs) of *aov_sample* from the first surface hit by the camera ray
+/// *r*. Doesn't use any sample values, the albedo is estimated with
+/// a few fixed, stratified BSDF samples. Only used by integrators
+/// which don't fill the AOVs while tracing their camera paths.
+pub fn compute_geometric_aovs(r: &Ray, scene: &Scene, aov_sample: &mut AovSample) {
+    let mut ray: Ray = r.clone();
+    loop {
+        let mut isect: SurfaceInteraction = SurfaceInteraction::default();
+        if !scene.intersect(&ray, &mut isect) {
+            return;
+        }
+        isect.compute_scattering_functions(&ray, true, TransportMode::Radiance);
+        if let Some(ref bsdf) = isect.bsdf {
+            aov_sample.depth = pnt3_distancef(&r.o, &isect.common.p);
+            aov_sample.position = isect.common.p;
+            aov_sample.normal = isect.shading.n;
+            aov_sample.uv = isect.uv;
+            if let Some(primitive_raw) = isect.primitive {
+                let primitive = unsafe { &*primitive_raw };
+                aov_sample.object_id = primitive.get_object_id();
+                aov_sample.material_id = primitive.get_material_id();
+            }
+            // directional albedo (2x2 stratified samples)
+            let n_strata: usize = 2;
+            let mut albedo: Spectrum = Spectrum::default();
+            for i in 0..n_strata * n_strata {
+                let u: Point2f = Point2f {
+                    x: ((i % n_strata) as Float + 0.5 as Float) / n_strata as Float,
+                    y: ((i / n_strata) as Float + 0.5 as Float) / n_strata as Float,
+                };
+                let mut wi: Vector3f = Vector3f::default();
+                let mut pdf: Float = 0.0 as Float;
+                let mut sampled_type: u8 = 0_u8;
+                let f: Spectrum = bsdf.sample_f(
+                    &isect.common.wo,
+                    &mut wi,
+                    &u,
+                    &mut pdf,
+                    BxdfType::BsdfAll as u8,
+                    &mut sampled_type,
+                );
+                if pdf > 0.0 as Float {
+                    albedo += f * vec3_abs_dot_nrmf(&wi, &isect.shading.n) / pdf;
+                }
+            }
+            aov_sample.albedo = albedo / (n_strata * n_strata) as Float;
+            return;
+        }
+        // skip over medium boundaries
+        ray = isect.spawn_ray(&ray.d);
+    }
+}
+
 /// The light to start each photon path from is chosen according to a
 /// PDF defined by the lights' respective powers.
 pub fn compute_light_power_distribution(scene: &Scene) -> Option<Arc<Distribution1D>> {
@@ -11,7 +11,7 @@
 use std::cell::Cell;
 use std::sync::Arc;
 // pbrt
-use crate::core::bssrdf::TabulatedBssrdf;
+use crate::core::bssrdf::Bssrdf;
 use crate::core::geometry::{
     nrm_dot_vec3f, nrm_faceforward_nrm, pnt3_offset_ray_origin, vec3_cross_vec3, vec3_dot_nrmf,
 };
@@ -263,8 +263,11 @@ pub struct SurfaceInteraction<'a> {
     pub primitive: Option<*const Primitive>,
     pub shading: Shading,
     pub bsdf: Option<Bsdf>,
-    pub bssrdf: Option<TabulatedBssrdf>,
+    pub bssrdf: Option<Bssrdf>,
     pub shape: Option<&'a Shape>,
+    pub face_index: i32,
+    /// spectral channel a path got restricted to (e.g. by dispersion)
+    pub spectral_channel: Option<usize>,
 }
 
 impl<'a> SurfaceInteraction<'a> {
@@ -327,6 +330,8 @@ impl<'a> SurfaceInteraction<'a> {
                 bsdf: None,
                 bssrdf: None,
                 shape: Some(shape),
+                face_index: 0_i32,
+                spectral_channel: None,
             }
         } else {
             SurfaceInteraction {
@@ -347,6 +352,8 @@ impl<'a> SurfaceInteraction<'a> {
                 bsdf: None,
                 bssrdf: None,
                 shape: None,
+                face_index: 0_i32,
+                spectral_channel: None,
             }
         }
     }
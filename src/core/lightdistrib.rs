@@ -17,9 +17,15 @@ use crate::core::pbrt::clamp_t;
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::sampling::Distribution1D;
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase, StatCounter, StatIntDistribution, StatRatio};
 
 // see lightdistrib.h
 
+static N_CREATED: StatCounter = StatCounter::new("SpatialLightDistribution/Distributions created");
+static N_LOOKUPS: StatRatio = StatRatio::new("SpatialLightDistribution/Lookups per distribution");
+static N_PROBES_PER_LOOKUP: StatIntDistribution =
+    StatIntDistribution::new("SpatialLightDistribution/Hash probes per lookup");
+
 /// LightDistribution defines a general interface for classes that
 /// provide probability distributions for sampling light sources at a
 /// given point in space.
@@ -169,6 +175,8 @@ impl SpatialLightDistribution {
     /// Compute the sampling distribution for the voxel with integer
     /// coordiantes given by "pi".
     pub fn compute_distribution(&self, pi: &Point3i) -> Distribution1D {
+        N_CREATED.inc();
+        N_LOOKUPS.add_denom(1);
         // Compute the world-space bounding box of the voxel
         // corresponding to |pi|.
         let p0: Point3f = Point3f {
@@ -276,8 +284,8 @@ impl SpatialLightDistribution {
     /// effective) sampling distribution for light sources at that
     /// point.
     pub fn lookup(&self, p: &Point3f) -> Arc<Distribution1D> {
-        // TODO: ProfilePhase _(Prof::LightDistribLookup);
-        // TODO: ++nLookups;
+        let _prof = ProfilePhase::new(Prof::LightDistribLookup);
+        N_LOOKUPS.add_num(1);
 
         // first, compute integer voxel coordinates for the given
         // point |p| with respect to the overall voxel grid.
@@ -318,13 +326,14 @@ impl SpatialLightDistribution {
         // entry is already used for another value; step stores the
         // square root of the probe step.
         let mut step: u64 = 1;
-        // TODO: int nProbes = 0;
+        let mut n_probes: i64 = 0;
         loop {
-            // TODO: ++nProbes;
+            n_probes += 1;
             let entry: &HashEntry = &self.hash_table[hash as usize];
             // does the hash table entry at offset |hash| match the current point?
             let entry_packed_pos: u64 = entry.packed_pos.load(Ordering::Acquire);
             if entry_packed_pos == packed_pos {
+                N_PROBES_PER_LOOKUP.report_value(n_probes);
                 // Yes! Most of the time, there should already by a light
                 // sampling distribution available.
                 let option: Option<Arc<Distribution1D>> = entry.distribution.dup();
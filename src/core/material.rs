@@ -129,6 +129,7 @@ impl Material {
             si_eval.common.medium_interface = None;
         }
         si_eval.uv = si.uv;
+        si_eval.face_index = si.face_index;
         si_eval.dpdu = si.dpdu;
         si_eval.dpdv = si.dpdv;
         si_eval.dndu = si.dndu;
@@ -11,6 +11,7 @@ use crate::core::interaction::MediumInteraction;
 use crate::core::pbrt::INV_4_PI;
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::sampler::Sampler;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::media::grid::GridDensityMedium;
 use crate::media::homogeneous::HomogeneousMedium;
 
@@ -300,11 +301,11 @@ pub struct HenyeyGreenstein {
 
 impl HenyeyGreenstein {
     pub fn p(&self, wo: &Vector3f, wi: &Vector3f) -> Float {
-        // TODO: ProfilePhase _(Prof::PhaseFuncEvaluation);
+        let _prof = ProfilePhase::new(Prof::PhaseFuncEvaluation);
         phase_hg(vec3_dot_vec3f(wo, wi), self.g)
     }
     pub fn sample_p(&self, wo: &Vector3f, wi: &mut Vector3f, u: Point2f) -> Float {
-        // TODO: ProfilePhase _(Prof::PhaseFuncSampling);
+        let _prof = ProfilePhase::new(Prof::PhaseFuncSampling);
         // compute $\cos \theta$ for Henyey--Greenstein sample
         let cos_theta = if self.g.abs() < 1e-3 as Float {
             1.0 as Float - 2.0 as Float * u[XYEnum::X]
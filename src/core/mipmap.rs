@@ -8,12 +8,17 @@ use std::ops::{Add, AddAssign, Div, Mul};
 // pbrt
 use crate::core::geometry::{Point2f, Point2i, Vector2f};
 use crate::core::memory::BlockedArray;
+use crate::core::pbrt::Float;
 use crate::core::pbrt::{clamp_t, is_power_of_2, lerp, mod_t, round_up_pow2_32};
-use crate::core::pbrt::{Float, Spectrum};
+use crate::core::spectrum::{RGBSpectrum, SampledSpectrum};
+use crate::core::stats::{Prof, ProfilePhase, StatCounter};
 use crate::core::texture::lanczos;
 
 // see mipmap.h
 
+static N_TRILERP_LOOKUPS: StatCounter = StatCounter::new("Texture/Trilinear lookups");
+static N_EWA_LOOKUPS: StatCounter = StatCounter::new("Texture/EWA lookups");
+
 const WEIGHT_LUT_SIZE: usize = 128;
 
 #[derive(Debug, Clone)]
@@ -231,8 +236,8 @@ where
         &l[(ss, tt)]
     }
     pub fn lookup_pnt_flt(&self, st: Point2f, width: Float) -> T {
-        // TODO: ++nTrilerpLookups;
-        // TODO: ProfilePhase p(Prof::TexFiltTrilerp);
+        N_TRILERP_LOOKUPS.inc();
+        let _prof = ProfilePhase::new(Prof::TexFiltTrilerp);
         // compute MIPMap level for trilinear filtering
         let level: Float = self.levels() as Float - 1.0 as Float + width.max(1e-8 as Float).log2();
         // perform trilinear interpolation at appropriate MIPMap level
@@ -259,8 +264,8 @@ where
                 .max(dst1.x.abs().max(dst1.y.abs()));
             return self.lookup_pnt_flt(st, width);
         }
-        // TODO: ++nEWALookups;
-        // TODO: ProfilePhase p(Prof::TexFiltEWA);
+        N_EWA_LOOKUPS.inc();
+        let _prof = ProfilePhase::new(Prof::TexFiltEwa);
         // compute ellipse minor and major axes
         if dst0.length_squared() < dst1.length_squared() {
             // std::swap(dst0, dst1);
@@ -403,12 +408,22 @@ impl Clampable for Float {
     }
 }
 
-impl Clampable for Spectrum {
-    fn clamp(self, min: Float, max: Float) -> Spectrum {
-        Spectrum::rgb(
-            clamp_t(self.c[0], min, max),
-            clamp_t(self.c[1], min, max),
-            clamp_t(self.c[2], min, max),
-        )
+impl Clampable for RGBSpectrum {
+    fn clamp(self, min: Float, max: Float) -> RGBSpectrum {
+        let mut ret: RGBSpectrum = self;
+        for i in 0..RGBSpectrum::N_SAMPLES {
+            ret[i] = clamp_t(self[i], min, max);
+        }
+        ret
+    }
+}
+
+impl Clampable for SampledSpectrum {
+    fn clamp(self, min: Float, max: Float) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = self;
+        for i in 0..SampledSpectrum::N_SAMPLES {
+            ret[i] = clamp_t(self[i], min, max);
+        }
+        ret
     }
 }
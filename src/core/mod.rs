@@ -3,11 +3,15 @@
 pub mod api;
 pub mod bssrdf;
 pub mod camera;
+pub mod checkpoint;
+pub mod denoise;
+pub mod distributed;
 pub mod efloat;
 pub mod film;
 pub mod filter;
 pub mod floatfile;
 pub mod geometry;
+pub mod imageio;
 pub mod integrator;
 pub mod interaction;
 pub mod interpolation;
@@ -23,14 +27,18 @@ pub mod parallel;
 pub mod paramset;
 pub mod pbrt;
 pub mod primitive;
+pub mod progress;
 pub mod quaternion;
 pub mod reflection;
 pub mod rng;
 pub mod sampler;
 pub mod sampling;
 pub mod scene;
+pub mod scenebuilder;
+pub mod scenewriter;
 pub mod shape;
 pub mod sobolmatrices;
 pub mod spectrum;
+pub mod stats;
 pub mod texture;
 pub mod transform;
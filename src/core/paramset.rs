@@ -1,10 +1,35 @@
 //! Bundle up parameters and their values in a generic way.
+//!
+//! All lookups get recorded, so after creating an object from a
+//! [ParamSet](struct.ParamSet.html) its
+//! [report_unused()](struct.ParamSet.html#method.report_unused) can
+//! warn about misspelled parameters (which would be silently ignored
+//! otherwise) and about parameters declared with the wrong type:
+//!
+//! ```rust
+//! use rs_pbrt::core::paramset::ParamSet;
+//!
+//! let mut params: ParamSet = ParamSet::default();
+//! params.add_float(String::from("fov"), 40.0);
+//! params.add_int(String::from("maxdepht"), 5);
+//! params.add_int(String::from("xresolution"), 320);
+//! params.add_float(String::from("yresolution"), 200.0);
+//! assert_eq!(params.find_one_float("fov", 90.0), 40.0);
+//! assert_eq!(params.find_one_int("maxdepth", 5), 5);
+//! assert_eq!(params.find_one_int("xresolution", 1280), 320);
+//! assert_eq!(params.find_one_int("yresolution", 720), 720);
+//! // "maxdepht" was never looked up, "yresolution" should be an integer
+//! assert_eq!(params.report_unused().len(), 2);
+//! // every parameter gets reported only once
+//! assert!(params.report_unused().is_empty());
+//! ```
 
 // std
 use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 // pbrt
 use crate::core::floatfile::read_float_file;
@@ -13,15 +38,106 @@ use crate::core::pbrt::{Float, Spectrum};
 use crate::core::spectrum::blackbody_normalized;
 use crate::core::spectrum::{CIE_LAMBDA, N_CIE_SAMPLES};
 use crate::core::texture::Texture;
+use crate::parser::ParseWarning;
 use crate::textures::constant::ConstantTexture;
 
 // see paramset.h
 
+/// The types a parameter can be declared as.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamType {
+    Bool,
+    Int,
+    Float,
+    Point2f,
+    Vector2f,
+    Point3f,
+    Vector3f,
+    Normal3f,
+    Spectrum,
+    String,
+    Texture,
+}
+
+impl ParamType {
+    /// The name used in parameter declarations.
+    pub fn name(self) -> &'static str {
+        match self {
+            ParamType::Bool => "bool",
+            ParamType::Int => "integer",
+            ParamType::Float => "float",
+            ParamType::Point2f => "point2",
+            ParamType::Vector2f => "vector2",
+            ParamType::Point3f => "point3",
+            ParamType::Vector3f => "vector3",
+            ParamType::Normal3f => "normal",
+            ParamType::Spectrum => "spectrum",
+            ParamType::String => "string",
+            ParamType::Texture => "texture",
+        }
+    }
+}
+
+const PARAM_TYPES: [ParamType; 11] = [
+    ParamType::Bool,
+    ParamType::Int,
+    ParamType::Float,
+    ParamType::Point2f,
+    ParamType::Vector2f,
+    ParamType::Point3f,
+    ParamType::Vector3f,
+    ParamType::Normal3f,
+    ParamType::Spectrum,
+    ParamType::String,
+    ParamType::Texture,
+];
+
+/// The types a parameter was looked up as. Copies of a parameter
+/// share the record (like the reference counted items of pbrt-v3), so
+/// lookups in a copy count for the original as well.
+#[derive(Debug, Clone, Default)]
+pub struct LookupRecord {
+    types: Arc<AtomicU32>,
+}
+
+// set by lookups which don't know the declared type
+const LOOKED_UP: u32 = 1_u32 << 31;
+
+impl LookupRecord {
+    pub fn add(&self, param_type: ParamType) {
+        self.types
+            .fetch_or(1_u32 << param_type as u32, Ordering::Relaxed);
+    }
+    pub fn contains(&self, param_type: ParamType) -> bool {
+        self.types.load(Ordering::Relaxed) & (1_u32 << param_type as u32) != 0_u32
+    }
+    /// Records a lookup with whatever type the parameter was declared.
+    pub fn add_any(&self) {
+        self.types.fetch_or(LOOKED_UP, Ordering::Relaxed);
+    }
+    /// Returns true if the parameter was looked up with its declared
+    /// type *param_type*.
+    pub fn is_used(&self, param_type: ParamType) -> bool {
+        self.contains(param_type) || self.types.load(Ordering::Relaxed) & LOOKED_UP != 0_u32
+    }
+}
+
+/// A spectrum parameter the way it was declared in the scene file,
+/// e.g. `"blackbody I" [ 5500 1 ]`, before it got converted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrumDeclaration {
+    pub name: String,
+    /// "rgb", "blackbody" or "spectrum"
+    pub param_type: String,
+    pub floats: Vec<Float>,
+    pub strings: Vec<String>,
+}
+
 pub struct ParamSetItem<T> {
     pub name: String,
     pub values: Vec<T>,
     pub n_values: usize,
-    pub looked_up: bool, // false
+    pub lookups: LookupRecord,
 }
 
 #[derive(Default)]
@@ -41,6 +157,12 @@ pub struct ParamSet {
     pub spectra: Vec<ParamSetItem<Spectrum>>,
     pub strings: Vec<ParamSetItem<String>>,
     pub textures: Vec<ParamSetItem<String>>,
+    /// original declarations of (some of) the **spectra**, used to
+    /// write them back unchanged
+    pub spectrum_declarations: Vec<SpectrumDeclaration>,
+    /// file, line and column of the statement the parameters were
+    /// read from (used for warnings)
+    pub location: Option<(String, usize, usize)>,
 }
 
 impl ParamSet {
@@ -49,6 +171,7 @@ impl ParamSet {
         self.name = name;
         self.tex_type = tex_type;
         self.tex_name = tex_name;
+        self.location = None;
         self.bools.clear();
         self.ints.clear();
         self.floats.clear();
@@ -60,13 +183,14 @@ impl ParamSet {
         self.spectra.clear();
         self.strings.clear();
         self.textures.clear();
+        self.spectrum_declarations.clear();
     }
     pub fn add_float(&mut self, name: String, value: Float) {
         self.floats.push(ParamSetItem::<Float> {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_floats(&mut self, name: String, values: Vec<Float>) {
@@ -75,7 +199,7 @@ impl ParamSet {
             name,
             values,
             n_values,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_int(&mut self, name: String, value: i32) {
@@ -83,7 +207,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_ints(&mut self, name: String, values: Vec<i32>) {
@@ -92,7 +216,7 @@ impl ParamSet {
             name,
             values,
             n_values,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_bool(&mut self, name: String, value: bool) {
@@ -100,7 +224,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_point2f(&mut self, name: String, value: Point2f) {
@@ -108,7 +232,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_point2fs(&mut self, name: String, values: Vec<Float>) {
@@ -130,7 +254,7 @@ impl ParamSet {
             name,
             values: p_values,
             n_values: n_points,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_point3f(&mut self, name: String, value: Point3f) {
@@ -138,7 +262,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_point3fs(&mut self, name: String, values: Vec<Float>) {
@@ -161,7 +285,7 @@ impl ParamSet {
             name,
             values: p_values,
             n_values: n_points,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_sampled_spectrum_files(&mut self, name: String, names: Vec<String>) {
@@ -206,11 +330,17 @@ impl ParamSet {
             }
         }
         let n_values: usize = s.len();
+        self.spectrum_declarations.push(SpectrumDeclaration {
+            name: name.clone(),
+            param_type: String::from("spectrum"),
+            floats: Vec::new(),
+            strings: names,
+        });
         self.spectra.push(ParamSetItem::<Spectrum> {
             name,
             values: s,
             n_values,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_string(&mut self, name: String, value: String) {
@@ -218,7 +348,16 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
+        });
+    }
+    pub fn add_strings(&mut self, name: String, values: Vec<String>) {
+        let n_values: usize = values.len();
+        self.strings.push(ParamSetItem::<String> {
+            name,
+            values,
+            n_values,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_texture(&mut self, name: String, value: String) {
@@ -226,7 +365,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_vector3f(&mut self, name: String, value: Vector3f) {
@@ -234,7 +373,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_vector3fs(&mut self, name: String, values: Vec<Float>) {
@@ -252,7 +391,7 @@ impl ParamSet {
             name,
             values: p_values,
             n_values: n_vectors,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_normal3f(&mut self, name: String, value: Normal3f) {
@@ -260,7 +399,7 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_normal3fs(&mut self, name: String, values: Vec<Float>) {
@@ -278,7 +417,7 @@ impl ParamSet {
             name,
             values: p_values,
             n_values: n_normals,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn add_rgb_spectrum(&mut self, name: String, value: Spectrum) {
@@ -286,7 +425,18 @@ impl ParamSet {
             name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            lookups: LookupRecord::default(),
+        });
+    }
+    /// Adds an RGB spectrum and remembers the values it was declared
+    /// with.
+    pub fn add_rgb(&mut self, name: String, rgb: [Float; 3]) {
+        self.add_rgb_spectrum(name.clone(), Spectrum::rgb(rgb[0], rgb[1], rgb[2]));
+        self.spectrum_declarations.push(SpectrumDeclaration {
+            name,
+            param_type: String::from("rgb"),
+            floats: rgb.to_vec(),
+            strings: Vec::new(),
         });
     }
     pub fn add_blackbody_spectrum(&mut self, name: String, values: Vec<Float>) {
@@ -301,16 +451,23 @@ impl ParamSet {
                 Spectrum::from_sampled(&CIE_LAMBDA, &v, N_CIE_SAMPLES as i32) * values[2 * i + 1],
             );
         }
+        self.spectrum_declarations.push(SpectrumDeclaration {
+            name: name.clone(),
+            param_type: String::from("blackbody"),
+            floats: values,
+            strings: Vec::new(),
+        });
         self.spectra.push(ParamSetItem::<Spectrum> {
             name,
             values: s,
             n_values,
-            looked_up: false,
+            lookups: LookupRecord::default(),
         });
     }
     pub fn copy_from(&mut self, param_set: &ParamSet) {
         self.key_word = param_set.key_word.clone();
-        // self.name = param_set.name.clone();
+        self.name = param_set.name.clone();
+        self.location = param_set.location.clone();
         self.bools.clear();
         for b in &param_set.bools {
             let mut values: Vec<bool> = Vec::new();
@@ -321,7 +478,7 @@ impl ParamSet {
                 name: b.name.clone(),
                 values,
                 n_values: b.n_values,
-                looked_up: false,
+                lookups: b.lookups.clone(),
             });
         }
         self.ints.clear();
@@ -334,7 +491,7 @@ impl ParamSet {
                 name: i.name.clone(),
                 values,
                 n_values: i.n_values,
-                looked_up: false,
+                lookups: i.lookups.clone(),
             });
         }
         self.floats.clear();
@@ -347,7 +504,7 @@ impl ParamSet {
                 name: f.name.clone(),
                 values,
                 n_values: f.n_values,
-                looked_up: false,
+                lookups: f.lookups.clone(),
             });
         }
         self.point2fs.clear();
@@ -362,7 +519,7 @@ impl ParamSet {
                 name: p.name.clone(),
                 values,
                 n_values: p.n_values,
-                looked_up: false,
+                lookups: p.lookups.clone(),
             });
         }
         self.vector3fs.clear();
@@ -377,9 +534,10 @@ impl ParamSet {
                 name: s.name.clone(),
                 values,
                 n_values: s.n_values,
-                looked_up: false,
+                lookups: s.lookups.clone(),
             });
         }
+        self.spectrum_declarations = param_set.spectrum_declarations.clone();
         self.strings.clear();
         for s in &param_set.strings {
             let mut values: Vec<String> = Vec::new();
@@ -390,7 +548,7 @@ impl ParamSet {
                 name: s.name.clone(),
                 values,
                 n_values: s.n_values,
-                looked_up: false,
+                lookups: s.lookups.clone(),
             });
         }
         self.textures.clear();
@@ -403,11 +561,48 @@ impl ParamSet {
                 name: s.name.clone(),
                 values,
                 n_values: s.n_values,
-                looked_up: false,
+                lookups: s.lookups.clone(),
             });
         }
     }
+    pub fn erase_bool(&mut self, name: String) -> bool {
+        for i in 0..self.bools.len() {
+            if self.bools[i].name == name {
+                self.bools.remove(i);
+                return true;
+            }
+        }
+        false
+    }
+    pub fn erase_float(&mut self, name: String) -> bool {
+        for i in 0..self.floats.len() {
+            if self.floats[i].name == name {
+                self.floats.remove(i);
+                return true;
+            }
+        }
+        false
+    }
+    pub fn erase_int(&mut self, name: String) -> bool {
+        for i in 0..self.ints.len() {
+            if self.ints[i].name == name {
+                self.ints.remove(i);
+                return true;
+            }
+        }
+        false
+    }
+    pub fn erase_string(&mut self, name: String) -> bool {
+        for i in 0..self.strings.len() {
+            if self.strings[i].name == name {
+                self.strings.remove(i);
+                return true;
+            }
+        }
+        false
+    }
     pub fn erase_spectrum(&mut self, name: String) -> bool {
+        self.spectrum_declarations.retain(|d| d.name != name);
         for i in 0..self.spectra.len() {
             if self.spectra[i].name == name {
                 self.spectra.remove(i);
@@ -419,64 +614,71 @@ impl ParamSet {
     pub fn find_one_float(&self, name: &str, d: Float) -> Float {
         for v in &self.floats {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Float);
                 return v.values[0];
             }
         }
+        self.mistyped(name, ParamType::Float);
         d
     }
     pub fn find_one_int(&self, name: &str, d: i32) -> i32 {
         for v in &self.ints {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Int);
                 return v.values[0];
             }
         }
+        self.mistyped(name, ParamType::Int);
         d
     }
     pub fn find_one_bool(&self, name: &str, d: bool) -> bool {
         for v in &self.bools {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Bool);
                 return v.values[0];
             }
         }
+        self.mistyped(name, ParamType::Bool);
         d
     }
     pub fn find_one_point3f(&self, name: &str, d: Point3f) -> Point3f {
         for v in &self.point3fs {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Point3f);
                 return v.values[0];
             }
         }
+        self.mistyped(name, ParamType::Point3f);
         d
     }
     pub fn find_one_vector3f(&self, name: &str, d: Vector3f) -> Vector3f {
         for v in &self.vector3fs {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Vector3f);
                 return v.values[0];
             }
         }
+        self.mistyped(name, ParamType::Vector3f);
         d
     }
     pub fn find_one_spectrum(&self, name: &str, d: Spectrum) -> Spectrum {
         for v in &self.spectra {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Spectrum);
                 return v.values[0];
             }
         }
+        self.mistyped(name, ParamType::Spectrum);
         d
     }
     pub fn find_one_string(&self, name: &str, d: String) -> String {
         for v in &self.strings {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.lookups.add(ParamType::String);
                 return v.values[0].clone();
             }
         }
+        self.mistyped(name, ParamType::String);
         d
     }
     pub fn find_one_filename(&self, name: &str, d: String) -> String {
@@ -489,19 +691,26 @@ impl ParamSet {
     }
     pub fn find_texture(&self, name: &str) -> String {
         let d: String = String::new();
-        lookup_one(&self.textures, name, d)
+        let texture: String = lookup_one(&self.textures, name, d);
+        if texture.is_empty() {
+            self.mistyped(name, ParamType::Texture);
+        }
+        texture
     }
     pub fn find_int(&self, name: &str) -> Vec<i32> {
         let mut values: Vec<i32> = Vec::new();
         for v in &self.ints {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Int);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
             }
         }
+        if values.is_empty() {
+            self.mistyped(name, ParamType::Int);
+        }
         values
     }
     pub fn find_float(&self, name: &str) -> Vec<Float> {
@@ -509,12 +718,15 @@ impl ParamSet {
         for v in &self.floats {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Float);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
             }
         }
+        if values.is_empty() {
+            self.mistyped(name, ParamType::Float);
+        }
         values
     }
     pub fn find_point2f(&self, name: &str) -> Vec<Point2f> {
@@ -522,12 +734,15 @@ impl ParamSet {
         for v in &self.point2fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Point2f);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
             }
         }
+        if values.is_empty() {
+            self.mistyped(name, ParamType::Point2f);
+        }
         values
     }
     pub fn find_vector2f(&self, name: &str) -> Vec<Vector2f> {
@@ -535,12 +750,15 @@ impl ParamSet {
         for v in &self.vector2fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Vector2f);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
             }
         }
+        if values.is_empty() {
+            self.mistyped(name, ParamType::Vector2f);
+        }
         values
     }
     pub fn find_point3f(&self, name: &str) -> Vec<Point3f> {
@@ -548,12 +766,15 @@ impl ParamSet {
         for v in &self.point3fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Point3f);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
             }
         }
+        if values.is_empty() {
+            self.mistyped(name, ParamType::Point3f);
+        }
         values
     }
     pub fn find_vector3f(&self, name: &str) -> Vec<Vector3f> {
@@ -561,12 +782,15 @@ impl ParamSet {
         for v in &self.vector3fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Vector3f);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
             }
         }
+        if values.is_empty() {
+            self.mistyped(name, ParamType::Vector3f);
+        }
         values
     }
     pub fn find_normal3f(&self, name: &str) -> Vec<Normal3f> {
@@ -574,12 +798,15 @@ impl ParamSet {
         for v in &self.normals {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Normal3f);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
             }
         }
+        if values.is_empty() {
+            self.mistyped(name, ParamType::Normal3f);
+        }
         values
     }
     pub fn find_spectrum(&self, name: &str) -> Vec<Spectrum> {
@@ -587,14 +814,145 @@ impl ParamSet {
         for v in &self.spectra {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.lookups.add(ParamType::Spectrum);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
             }
         }
+        if values.is_empty() {
+            self.mistyped(name, ParamType::Spectrum);
+        }
         values
     }
+    pub fn find_string(&self, name: &str) -> Vec<String> {
+        let mut values: Vec<String> = Vec::new();
+        for v in &self.strings {
+            if v.name == name {
+                let n_values = v.n_values;
+                v.lookups.add(ParamType::String);
+                for i in 0..n_values {
+                    values.push(v.values[i].clone());
+                }
+            }
+        }
+        if values.is_empty() {
+            self.mistyped(name, ParamType::String);
+        }
+        values
+    }
+    /// Returns a warning (located at the statement, if known) for each
+    /// parameter which was never looked up (or only as another type).
+    /// Each parameter gets reported only once.
+    pub fn report_unused(&self) -> Vec<ParseWarning> {
+        let mut warnings: Vec<ParseWarning> = Vec::new();
+        self.report_unused_items(&self.bools, ParamType::Bool, &mut warnings);
+        self.report_unused_items(&self.ints, ParamType::Int, &mut warnings);
+        self.report_unused_items(&self.floats, ParamType::Float, &mut warnings);
+        self.report_unused_items(&self.point2fs, ParamType::Point2f, &mut warnings);
+        self.report_unused_items(&self.vector2fs, ParamType::Vector2f, &mut warnings);
+        self.report_unused_items(&self.point3fs, ParamType::Point3f, &mut warnings);
+        self.report_unused_items(&self.vector3fs, ParamType::Vector3f, &mut warnings);
+        self.report_unused_items(&self.normals, ParamType::Normal3f, &mut warnings);
+        self.report_unused_items(&self.spectra, ParamType::Spectrum, &mut warnings);
+        self.report_unused_items(&self.strings, ParamType::String, &mut warnings);
+        self.report_unused_items(&self.textures, ParamType::Texture, &mut warnings);
+        warnings
+    }
+    fn report_unused_items<T>(
+        &self,
+        items: &[ParamSetItem<T>],
+        item_type: ParamType,
+        warnings: &mut Vec<ParseWarning>,
+    ) {
+        let context: String = if self.key_word.is_empty() {
+            String::new()
+        } else if self.name.is_empty() {
+            format!(" ({})", self.key_word)
+        } else {
+            format!(" ({} \"{}\")", self.key_word, self.name)
+        };
+        let (file, line, column) = self.location.clone().unwrap_or_default();
+        for item in items {
+            if item.lookups.is_used(item_type) {
+                continue;
+            }
+            let expected: Vec<String> = PARAM_TYPES
+                .iter()
+                .filter(|param_type| item.lookups.contains(**param_type))
+                .map(|param_type| format!("\"{}\"", param_type.name()))
+                .collect();
+            let message: String = if expected.is_empty() {
+                format!(
+                    "Parameter \"{} {}\" not used{}",
+                    item_type.name(),
+                    item.name,
+                    context
+                )
+            } else {
+                format!(
+                    "Parameter \"{} {}\" has the wrong type, expected {}{}",
+                    item_type.name(),
+                    item.name,
+                    expected.join(" or "),
+                    context
+                )
+            };
+            warnings.push(ParseWarning {
+                file: file.clone(),
+                line,
+                column,
+                message,
+            });
+            // report it only once
+            item.lookups.add(item_type);
+        }
+    }
+    /// Marks the parameters named *name* as looked up, so they never
+    /// get reported as unused.
+    pub fn mark_used(&self, name: &str) {
+        self.record_lookups(name, Some);
+    }
+    /// Records a lookup as *param_type* for the parameters named
+    /// *name*, which were declared with another type.
+    fn mistyped(&self, name: &str, param_type: ParamType) {
+        self.record_lookups(name, |item_type| {
+            if item_type != param_type {
+                Some(param_type)
+            } else {
+                None
+            }
+        });
+    }
+    /// *lookup* returns the type to record for the declared type of
+    /// each parameter named *name*.
+    fn record_lookups<F>(&self, name: &str, lookup: F)
+    where
+        F: Fn(ParamType) -> Option<ParamType>,
+    {
+        add_lookup(&self.bools, ParamType::Bool, name, &lookup);
+        add_lookup(&self.ints, ParamType::Int, name, &lookup);
+        add_lookup(&self.floats, ParamType::Float, name, &lookup);
+        add_lookup(&self.point2fs, ParamType::Point2f, name, &lookup);
+        add_lookup(&self.vector2fs, ParamType::Vector2f, name, &lookup);
+        add_lookup(&self.point3fs, ParamType::Point3f, name, &lookup);
+        add_lookup(&self.vector3fs, ParamType::Vector3f, name, &lookup);
+        add_lookup(&self.normals, ParamType::Normal3f, name, &lookup);
+        add_lookup(&self.spectra, ParamType::Spectrum, name, &lookup);
+        add_lookup(&self.strings, ParamType::String, name, &lookup);
+        add_lookup(&self.textures, ParamType::Texture, name, &lookup);
+    }
+}
+
+fn add_lookup<T, F>(items: &[ParamSetItem<T>], item_type: ParamType, name: &str, lookup: &F)
+where
+    F: Fn(ParamType) -> Option<ParamType>,
+{
+    if let Some(param_type) = lookup(item_type) {
+        for item in items.iter().filter(|item| item.name == name) {
+            item.lookups.add(param_type);
+        }
+    }
 }
 
 #[derive(Default)]
@@ -755,6 +1113,21 @@ impl TextureParams {
         self.geom_params
             .find_one_spectrum(name, self.material_params.find_one_spectrum(name, d))
     }
+    /// Reports the unused parameters of both parameter sets (see
+    /// [ParamSet::report_unused()](struct.ParamSet.html#method.report_unused)).
+    pub fn report_unused(&self) -> Vec<ParseWarning> {
+        let mut warnings: Vec<ParseWarning> = self.geom_params.report_unused();
+        warnings.append(&mut self.material_params.report_unused());
+        warnings
+    }
+    pub fn find_floats(&mut self, name: &str) -> Vec<Float> {
+        let values: Vec<Float> = self.geom_params.find_float(name);
+        if values.is_empty() {
+            self.material_params.find_float(name)
+        } else {
+            values
+        }
+    }
 }
 
 /// Replaces a macro on the C++ side.
@@ -764,7 +1137,7 @@ where
 {
     for v in vec {
         if v.name == name && v.n_values == 1_usize {
-            // v.looked_up = true;
+            v.lookups.add_any();
             return v.values[0].clone();
         }
     }
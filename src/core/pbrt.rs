@@ -5,11 +5,18 @@
 use std::f32::consts::PI;
 use std::ops::{Add, BitAnd, Div, Mul, Sub};
 // pbrt
+use crate::core::distributed::DistributedRole;
+#[cfg(not(feature = "sampled_spectrum"))]
 use crate::core::spectrum::RGBSpectrum;
+#[cfg(feature = "sampled_spectrum")]
+use crate::core::spectrum::SampledSpectrum;
 
 // see pbrt.h
 
+#[cfg(not(feature = "sampled_spectrum"))]
 pub type Spectrum = RGBSpectrum;
+#[cfg(feature = "sampled_spectrum")]
+pub type Spectrum = SampledSpectrum;
 
 pub type Float = f32;
 
@@ -22,6 +29,47 @@ pub const PI_OVER_2: Float = 1.570_796_326_794_896_619_23;
 pub const PI_OVER_4: Float = 0.785_398_163_397_448_309_61;
 pub const SQRT_2: Float = 1.414_213_562_373_095_048_80;
 
+/// Global rendering options, usually set from the command line and
+/// applied on top of the scene description (see
+/// [RenderOptions::apply_options()](../api/struct.RenderOptions.html#method.apply_options)).
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// number of threads (0 means use all cores)
+    pub n_threads: u8,
+    /// lower resolution and fewer samples for a quick preview
+    pub quick_render: bool,
+    /// overrides the film's "filename"
+    pub image_file: Option<String>,
+    /// overrides the film's "cropwindow" [x0 x1 y0 y1]
+    pub crop_window: Option<[Float; 4]>,
+    /// overrides the sampler's "pixelsamples"
+    pub pixel_samples: Option<i32>,
+    /// seed for the random number generators of the sampler
+    pub seed: Option<i32>,
+    /// don't draw progress bars
+    pub quiet: bool,
+    /// denoise the final image (overrides the film's "denoise")
+    pub denoise: bool,
+    /// overrides the sampler's "noisethreshold" (adaptive sampling)
+    pub noise_threshold: Option<Float>,
+    /// store checkpoints of the render in this file
+    pub checkpoint: Option<String>,
+    /// seconds between two checkpoints
+    pub checkpoint_interval: Option<Float>,
+    /// continue from the checkpoint of an interrupted render
+    pub resume: bool,
+    /// render in passes over the whole image
+    pub progressive: bool,
+    /// stop (progressive) rendering after this many seconds
+    pub time_limit: Option<Float>,
+    /// render together with other processes (coordinator or worker)
+    pub distributed: Option<DistributedRole>,
+    /// print the scene (in pbrt-v3 syntax) instead of rendering it
+    pub cat: bool,
+    /// like *cat*, but write triangle meshes to PLY files
+    pub to_ply: bool,
+}
+
 /// Use **unsafe**
 /// [std::mem::transmute_copy][transmute_copy]
 /// to convert *f32* to *u32*.
@@ -314,3 +362,86 @@ pub fn erf(x: Float) -> Float {
         1.0 as Float - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
     sign * y
 }
+
+/// Scramble the bits of a 64-bit value (finalizer of MurmurHash3),
+/// e.g. to derive well distributed random sequences from a seed.
+pub fn mix_bits(v: u64) -> u64 {
+    let mut v: u64 = v;
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5_d329_728e_a185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81da_def4_bc2d_d44d);
+    v ^= v >> 33;
+    v
+}
+
+/// Hash the bit patterns of some floats (e.g. a ray's origin and
+/// direction) to a deterministic pseudo-random value in [0, 1).
+pub fn hash_float(values: &[Float]) -> Float {
+    let mut hash: u64 = 0x9e37_79b9_7f4a_7c15;
+    for value in values {
+        hash = mix_bits(hash ^ u64::from(float_to_bits(*value)));
+    }
+    // keep 24 bits, which a Float represents exactly
+    (hash >> 40) as Float / (1_u64 << 24) as Float
+}
+
+/// Mean and variance of a stream of values, computed with Welford's
+/// numerically stable online algorithm (see pbrt-v4's
+/// VarianceEstimator).
+#[derive(Debug, Default, Copy, Clone)]
+pub struct VarianceEstimator {
+    mean: Float,
+    s: Float,
+    n: u64,
+}
+
+impl VarianceEstimator {
+    pub fn add(&mut self, x: Float) {
+        self.n += 1;
+        let delta: Float = x - self.mean;
+        self.mean += delta / self.n as Float;
+        let delta2: Float = x - self.mean;
+        self.s += delta * delta2;
+    }
+    pub fn count(&self) -> u64 {
+        self.n
+    }
+    pub fn mean(&self) -> Float {
+        self.mean
+    }
+    /// Sample variance (zero for less than two values).
+    pub fn variance(&self) -> Float {
+        if self.n > 1 {
+            self.s / (self.n - 1) as Float
+        } else {
+            0.0 as Float
+        }
+    }
+    /// The raw state (mean, sum of squared differences, count), e.g.
+    /// to store it in a checkpoint.
+    pub fn to_raw(&self) -> (Float, Float, u64) {
+        (self.mean, self.s, self.n)
+    }
+    pub fn from_raw(mean: Float, s: Float, n: u64) -> Self {
+        VarianceEstimator { mean, s, n }
+    }
+    pub fn relative_variance(&self) -> Float {
+        if self.n < 1 || self.mean == 0.0 as Float {
+            0.0 as Float
+        } else {
+            self.variance() / self.mean
+        }
+    }
+    /// Combine with the estimate for another set of values.
+    pub fn merge(&mut self, ve: &VarianceEstimator) {
+        if ve.n == 0 {
+            return;
+        }
+        let n: u64 = self.n + ve.n;
+        let delta: Float = ve.mean - self.mean;
+        self.s += ve.s + delta * delta * self.n as Float * ve.n as Float / n as Float;
+        self.mean += delta * ve.n as Float / n as Float;
+        self.n = n;
+    }
+}
@@ -72,6 +72,18 @@ impl Primitive {
             Primitive::KdTree(primitive) => primitive.get_material(),
         }
     }
+    pub fn get_object_id(&self) -> u32 {
+        match self {
+            Primitive::Geometric(primitive) => primitive.object_id,
+            _ => 0_u32,
+        }
+    }
+    pub fn get_material_id(&self) -> u32 {
+        match self {
+            Primitive::Geometric(primitive) => primitive.material_id,
+            _ => 0_u32,
+        }
+    }
     pub fn compute_scattering_functions(
         &self,
         isect: &mut SurfaceInteraction,
@@ -102,6 +114,12 @@ pub struct GeometricPrimitive {
     pub material: Option<Arc<Material>>,
     pub area_light: Option<Arc<Light>>,
     pub medium_interface: Option<Arc<MediumInterface>>,
+    /// ID of the shape this primitive belongs to (0 for none), see
+    /// [Aov::ObjectId](../film/enum.Aov.html)
+    pub object_id: u32,
+    /// ID of the material (0 for none), see
+    /// [Aov::MaterialId](../film/enum.Aov.html)
+    pub material_id: u32,
 }
 
 impl GeometricPrimitive {
@@ -118,6 +136,8 @@ impl GeometricPrimitive {
                     material,
                     area_light: Some(area_light),
                     medium_interface: Some(medium_interface),
+                    object_id: 0_u32,
+                    material_id: 0_u32,
                 }
             } else {
                 GeometricPrimitive {
@@ -125,6 +145,8 @@ impl GeometricPrimitive {
                     material,
                     area_light: Some(area_light),
                     medium_interface: None,
+                    object_id: 0_u32,
+                    material_id: 0_u32,
                 }
             }
         } else if let Some(medium_interface) = medium_interface {
@@ -133,6 +155,8 @@ impl GeometricPrimitive {
                 material,
                 area_light: None,
                 medium_interface: Some(medium_interface),
+                object_id: 0_u32,
+                material_id: 0_u32,
             }
         } else {
             GeometricPrimitive {
@@ -140,6 +164,8 @@ impl GeometricPrimitive {
                 material,
                 area_light: None,
                 medium_interface: None,
+                object_id: 0_u32,
+                material_id: 0_u32,
             }
         }
     }
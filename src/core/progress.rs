@@ -0,0 +1,187 @@
+//! Progress reporting with an estimate of the remaining time, similar
+//! to **pbrt-v3**'s `ProgressReporter`.
+//!
+//! A [ProgressReporter](struct.ProgressReporter.html) counts units of
+//! work (tiles, iterations, mutations, ...) and can be updated from
+//! several threads at once. Unless progress output was switched off
+//! with [set_quiet()](fn.set_quiet.html) it draws a progress bar on
+//! the terminal.
+//!
+//! Applications using the library (e.g. a GUI or a job scheduler) can
+//! register a callback to follow the progress of a render:
+//!
+//! ```rust
+//! use rs_pbrt::core::progress::{set_progress_callback, set_quiet, ProgressReporter};
+//!
+//! set_quiet(true);
+//! set_progress_callback(|progress| {
+//!     if progress.done {
+//!         println!("{}: {} units of work done", progress.title, progress.work_done);
+//!     }
+//! });
+//! let reporter = ProgressReporter::new(4, "Rendering");
+//! for _ in 0..4 {
+//!     reporter.update(1);
+//! }
+//! reporter.done();
+//! ```
+
+// std
+use std::io::Stdout;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+// others
+use pbr::ProgressBar;
+// pbrt
+use crate::core::pbrt::Float;
+
+// see progressreporter.h
+
+/// Minimum time between two reports (redrawing the bar, calling the
+/// callback) while work is being done.
+const REPORT_INTERVAL_MS: u64 = 100;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static PROGRESS_CALLBACK: RwLock<Option<Arc<ProgressCallback>>> = RwLock::new(None);
+
+/// A snapshot of the progress, passed to the progress callback.
+#[derive(Debug, Clone)]
+pub struct Progress<'a> {
+    /// what is being done, e.g. "Rendering"
+    pub title: &'a str,
+    pub work_done: u64,
+    pub total_work: u64,
+    pub elapsed: Duration,
+    /// estimated time to completion (unknown before any work was done)
+    pub remaining: Option<Duration>,
+    /// true for the last report of a [ProgressReporter](struct.ProgressReporter.html)
+    pub done: bool,
+}
+
+pub type ProgressCallback = dyn Fn(&Progress) + Send + Sync;
+
+/// Switch the progress bar on the terminal off (or on again). The
+/// progress callback is called either way.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Register a callback which gets called (from whatever thread did
+/// the work) whenever progress is reported. Replaces a previously
+/// registered callback.
+pub fn set_progress_callback<F>(callback: F)
+where
+    F: Fn(&Progress) + Send + Sync + 'static,
+{
+    *PROGRESS_CALLBACK.write().unwrap() = Some(Arc::new(callback));
+}
+
+/// Remove the progress callback again.
+pub fn clear_progress_callback() {
+    *PROGRESS_CALLBACK.write().unwrap() = None;
+}
+
+pub struct ProgressReporter {
+    title: String,
+    total_work: u64,
+    work_done: AtomicU64,
+    start_time: Instant,
+    last_report_ms: AtomicU64,
+    finished: AtomicBool,
+    bar: Mutex<Option<ProgressBar<Stdout>>>,
+}
+
+impl ProgressReporter {
+    pub fn new(total_work: u64, title: &str) -> Self {
+        let bar: Option<ProgressBar<Stdout>> =
+            if QUIET.load(Ordering::Relaxed) || total_work == 0_u64 {
+                None
+            } else {
+                let mut bar = ProgressBar::new(total_work);
+                bar.message(&format!("{}: ", title));
+                bar.set_max_refresh_rate(Some(Duration::from_millis(REPORT_INTERVAL_MS)));
+                Some(bar)
+            };
+        ProgressReporter {
+            title: String::from(title),
+            total_work,
+            work_done: AtomicU64::new(0_u64),
+            start_time: Instant::now(),
+            last_report_ms: AtomicU64::new(0_u64),
+            finished: AtomicBool::new(false),
+            bar: Mutex::new(bar),
+        }
+    }
+    /// Add *num* units of work. Can be called from several threads.
+    pub fn update(&self, num: u64) {
+        let work_done: u64 = self.work_done.fetch_add(num, Ordering::Relaxed) + num;
+        // only one thread reports at a time, and not too often
+        let now_ms: u64 = self.start_time.elapsed().as_millis() as u64;
+        let last_ms: u64 = self.last_report_ms.load(Ordering::Relaxed);
+        if now_ms >= last_ms + REPORT_INTERVAL_MS
+            && self
+                .last_report_ms
+                .compare_exchange(last_ms, now_ms, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.report(work_done, false);
+        }
+    }
+    pub fn elapsed_ms(&self) -> Float {
+        self.start_time.elapsed().as_secs_f64() as Float * 1000.0 as Float
+    }
+    /// Estimated time to completion, assuming the remaining work
+    /// proceeds at the same rate as the work done so far.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.estimate_remaining(
+            self.work_done.load(Ordering::Relaxed),
+            self.start_time.elapsed(),
+        )
+    }
+    /// Finish the progress bar (also called when the reporter is
+    /// dropped).
+    pub fn done(&self) {
+        if !self.finished.swap(true, Ordering::Relaxed) {
+            self.work_done.store(self.total_work, Ordering::Relaxed);
+            self.report(self.total_work, true);
+        }
+    }
+    fn estimate_remaining(&self, work_done: u64, elapsed: Duration) -> Option<Duration> {
+        if work_done == 0_u64 {
+            None
+        } else {
+            let work_left: u64 = self.total_work.saturating_sub(work_done);
+            Some(elapsed.mul_f64(work_left as f64 / work_done as f64))
+        }
+    }
+    fn report(&self, work_done: u64, done: bool) {
+        let work_done: u64 = work_done.min(self.total_work);
+        if let Some(bar) = self.bar.lock().unwrap().as_mut() {
+            if done {
+                bar.finish_println("");
+            } else {
+                bar.set(work_done);
+            }
+        }
+        // don't hold the lock while calling back
+        let callback: Option<Arc<ProgressCallback>> = PROGRESS_CALLBACK.read().unwrap().clone();
+        if let Some(callback) = callback {
+            let elapsed: Duration = self.start_time.elapsed();
+            callback(&Progress {
+                title: &self.title,
+                work_done,
+                total_work: self.total_work,
+                elapsed,
+                remaining: self.estimate_remaining(work_done, elapsed),
+                done,
+            });
+        }
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        self.done();
+    }
+}
@@ -32,6 +32,7 @@ use crate::core::pbrt::{clamp_t, lerp, radians};
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::rng::FLOAT_ONE_MINUS_EPSILON;
 use crate::core::sampling::cosine_sample_hemisphere;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::materials::disney::{
     DisneyClearCoat, DisneyDiffuse, DisneyFakeSS, DisneyRetro, DisneySheen,
 };
@@ -272,7 +273,7 @@ impl Bsdf {
         }
     }
     pub fn f(&self, wo_w: &Vector3f, wi_w: &Vector3f, flags: u8) -> Spectrum {
-        // TODO: ProfilePhase pp(Prof::BSDFEvaluation);
+        let _prof = ProfilePhase::new(Prof::BsdfEvaluation);
         let wi: Vector3f = self.world_to_local(wi_w);
         let wo: Vector3f = self.world_to_local(wo_w);
         if wo.z == 0.0 as Float {
@@ -304,7 +305,32 @@ impl Bsdf {
         bsdf_flags: u8,
         sampled_type: &mut u8,
     ) -> Spectrum {
-        // TODO: ProfilePhase pp(Prof::BSDFSampling);
+        let mut channel: Option<usize> = None;
+        self.sample_f_channel(
+            wo_world,
+            wi_world,
+            u,
+            pdf,
+            bsdf_flags,
+            sampled_type,
+            &mut channel,
+        )
+    }
+    /// Like sample_f(), but a dispersive BxDF which refracts a single
+    /// spectral channel stores it in *channel* (the spectral channel
+    /// the path is restricted to, if any, see
+    /// _SurfaceInteraction::spectral_channel_).
+    pub fn sample_f_channel(
+        &self,
+        wo_world: &Vector3f,
+        wi_world: &mut Vector3f,
+        u: &Point2f,
+        pdf: &mut Float,
+        bsdf_flags: u8,
+        sampled_type: &mut u8,
+        channel: &mut Option<usize>,
+    ) -> Spectrum {
+        let _prof = ProfilePhase::new(Prof::BsdfSampling);
         // choose which _BxDF_ to sample
         let matching_comps: u8 = self.num_components(bsdf_flags);
         if matching_comps == 0 {
@@ -360,7 +386,11 @@ impl Bsdf {
             if *sampled_type != 0_u8 {
                 *sampled_type = bxdf.get_type();
             }
-            let mut f: Spectrum = bxdf.sample_f(&wo, &mut wi, &u_remapped, pdf, sampled_type);
+            let mut f: Spectrum = if let Bxdf::DispersiveSpec(dispersive) = bxdf {
+                dispersive.sample_f_channel(&wo, &mut wi, &u_remapped, pdf, sampled_type, channel)
+            } else {
+                bxdf.sample_f(&wo, &mut wi, &u_remapped, pdf, sampled_type)
+            };
             // let mut ratio: Spectrum = Spectrum::default();
             // if *pdf > 0.0 as Float {
             //     ratio = f / *pdf;
@@ -420,7 +450,7 @@ impl Bsdf {
         }
     }
     pub fn pdf(&self, wo_world: &Vector3f, wi_world: &Vector3f, bsdf_flags: u8) -> Float {
-        // TODO: ProfilePhase pp(Prof::BSDFPdf);
+        let _prof = ProfilePhase::new(Prof::BsdfPdf);
         let n_bxdfs: usize = self.bxdfs.len();
         if n_bxdfs == 0 {
             return 0.0 as Float;
@@ -465,6 +495,7 @@ pub enum Bxdf {
     SpecRefl(SpecularReflection),
     SpecTrans(SpecularTransmission),
     FresnelSpec(FresnelSpecular),
+    DispersiveSpec(DispersiveSpecular),
     LambertianRefl(LambertianReflection),
     LambertianTrans(LambertianTransmission),
     OrenNayarRefl(OrenNayar),
@@ -491,6 +522,7 @@ impl Bxdf {
             Bxdf::SpecRefl(bxdf) => bxdf.get_type() & t == bxdf.get_type(),
             Bxdf::SpecTrans(bxdf) => bxdf.get_type() & t == bxdf.get_type(),
             Bxdf::FresnelSpec(bxdf) => bxdf.get_type() & t == bxdf.get_type(),
+            Bxdf::DispersiveSpec(bxdf) => bxdf.get_type() & t == bxdf.get_type(),
             Bxdf::LambertianRefl(bxdf) => bxdf.get_type() & t == bxdf.get_type(),
             Bxdf::LambertianTrans(bxdf) => bxdf.get_type() & t == bxdf.get_type(),
             Bxdf::OrenNayarRefl(bxdf) => bxdf.get_type() & t == bxdf.get_type(),
@@ -513,6 +545,7 @@ impl Bxdf {
             Bxdf::SpecRefl(bxdf) => bxdf.f(wo, wi),
             Bxdf::SpecTrans(bxdf) => bxdf.f(wo, wi),
             Bxdf::FresnelSpec(bxdf) => bxdf.f(wo, wi),
+            Bxdf::DispersiveSpec(bxdf) => bxdf.f(wo, wi),
             Bxdf::LambertianRefl(bxdf) => bxdf.f(wo, wi),
             Bxdf::LambertianTrans(bxdf) => bxdf.f(wo, wi),
             Bxdf::OrenNayarRefl(bxdf) => bxdf.f(wo, wi),
@@ -546,6 +579,7 @@ impl Bxdf {
             Bxdf::SpecRefl(bxdf) => bxdf.sample_f(wo, wi, u, pdf, sampled_type),
             Bxdf::SpecTrans(bxdf) => bxdf.sample_f(wo, wi, u, pdf, sampled_type),
             Bxdf::FresnelSpec(bxdf) => bxdf.sample_f(wo, wi, u, pdf, sampled_type),
+            Bxdf::DispersiveSpec(bxdf) => bxdf.sample_f(wo, wi, u, pdf, sampled_type),
             Bxdf::LambertianRefl(bxdf) => bxdf.sample_f(wo, wi, u, pdf, sampled_type),
             Bxdf::LambertianTrans(bxdf) => bxdf.sample_f(wo, wi, u, pdf, sampled_type),
             Bxdf::OrenNayarRefl(bxdf) => bxdf.sample_f(wo, wi, u, pdf, sampled_type),
@@ -586,6 +620,7 @@ impl Bxdf {
             Bxdf::SpecRefl(bxdf) => bxdf.pdf(wo, wi),
             Bxdf::SpecTrans(bxdf) => bxdf.pdf(wo, wi),
             Bxdf::FresnelSpec(bxdf) => bxdf.pdf(wo, wi),
+            Bxdf::DispersiveSpec(bxdf) => bxdf.pdf(wo, wi),
             Bxdf::LambertianRefl(bxdf) => bxdf.pdf(wo, wi),
             Bxdf::LambertianTrans(bxdf) => bxdf.pdf(wo, wi),
             Bxdf::OrenNayarRefl(bxdf) => bxdf.pdf(wo, wi),
@@ -615,6 +650,7 @@ impl Bxdf {
             Bxdf::SpecRefl(bxdf) => bxdf.get_type(),
             Bxdf::SpecTrans(bxdf) => bxdf.get_type(),
             Bxdf::FresnelSpec(bxdf) => bxdf.get_type(),
+            Bxdf::DispersiveSpec(bxdf) => bxdf.get_type(),
             Bxdf::LambertianRefl(bxdf) => bxdf.get_type(),
             Bxdf::LambertianTrans(bxdf) => bxdf.get_type(),
             Bxdf::OrenNayarRefl(bxdf) => bxdf.get_type(),
@@ -950,6 +986,152 @@ impl FresnelSpecular {
     }
 }
 
+/// Like _FresnelSpecular_, but with an index of refraction per
+/// spectral channel. Reflection keeps all channels, transmission
+/// refracts a single channel (picked uniformly unless the path is
+/// already restricted to one, see _SurfaceInteraction::spectral_channel_).
+#[derive(Copy, Clone)]
+pub struct DispersiveSpecular {
+    pub r: Spectrum,
+    pub t: Spectrum,
+    pub eta_a: Float,
+    pub eta_b: Spectrum,
+    pub mode: TransportMode,
+    pub channel: Option<usize>,
+    pub sc_opt: Option<Spectrum>,
+}
+
+impl DispersiveSpecular {
+    pub fn new(
+        r: Spectrum,
+        t: Spectrum,
+        eta_a: Float,
+        eta_b: Spectrum,
+        mode: TransportMode,
+        channel: Option<usize>,
+        sc_opt: Option<Spectrum>,
+    ) -> Self {
+        DispersiveSpecular {
+            r,
+            t,
+            eta_a,
+            eta_b,
+            mode,
+            channel,
+            sc_opt,
+        }
+    }
+    pub fn f(&self, _wo: &Vector3f, _wi: &Vector3f) -> Spectrum {
+        Spectrum::new(0.0 as Float)
+    }
+    pub fn sample_f(
+        &self,
+        wo: &Vector3f,
+        wi: &mut Vector3f,
+        sample: &Point2f,
+        pdf: &mut Float,
+        sampled_type: &mut u8,
+    ) -> Spectrum {
+        let mut channel: Option<usize> = None;
+        self.sample_f_channel(wo, wi, sample, pdf, sampled_type, &mut channel)
+    }
+    /// Like sample_f(), but stores the refracted spectral channel in
+    /// *channel*.
+    pub fn sample_f_channel(
+        &self,
+        wo: &Vector3f,
+        wi: &mut Vector3f,
+        sample: &Point2f,
+        pdf: &mut Float,
+        sampled_type: &mut u8,
+        channel: &mut Option<usize>,
+    ) -> Spectrum {
+        let ct: Float = cos_theta(wo);
+        let mut f: Spectrum = Spectrum::default();
+        for c in 0..Spectrum::N_SAMPLES {
+            f[c] = fr_dielectric(ct, self.eta_a, self.eta_b[c]);
+        }
+        // probability of choosing reflection
+        let p_reflect: Float = if let Some(c) = self.channel {
+            f[c]
+        } else {
+            let mut sum: Float = 0.0 as Float;
+            for c in 0..Spectrum::N_SAMPLES {
+                sum += f[c];
+            }
+            sum / Spectrum::N_SAMPLES as Float
+        };
+        let ret: Spectrum = if sample[XYEnum::X] < p_reflect {
+            // compute specular reflection for all channels
+            *wi = Vector3f {
+                x: -wo.x,
+                y: -wo.y,
+                z: wo.z,
+            };
+            if *sampled_type != 0_u8 {
+                *sampled_type = BxdfType::BsdfReflection as u8 | BxdfType::BsdfSpecular as u8
+            }
+            *pdf = p_reflect;
+            self.r * f / abs_cos_theta(&*wi)
+        } else {
+            // choose the spectral channel to refract
+            let mut channel_pdf: Float = 1.0 as Float;
+            let c: usize = if let Some(c) = self.channel {
+                c
+            } else {
+                channel_pdf = 1.0 as Float / Spectrum::N_SAMPLES as Float;
+                ((sample[XYEnum::Y] * Spectrum::N_SAMPLES as Float) as usize)
+                    .min(Spectrum::N_SAMPLES - 1)
+            };
+            // figure out which $\eta$ is incident and which is transmitted
+            let entering: bool = ct > 0.0 as Float;
+            let eta_i = if entering { self.eta_a } else { self.eta_b[c] };
+            let eta_t = if entering { self.eta_b[c] } else { self.eta_a };
+            // compute ray direction for specular transmission
+            if !refract(
+                wo,
+                &nrm_faceforward_vec3(
+                    &Normal3f {
+                        x: 0.0,
+                        y: 0.0,
+                        z: 1.0,
+                    },
+                    wo,
+                ),
+                eta_i / eta_t,
+                wi,
+            ) {
+                return Spectrum::default();
+            }
+            let mut ft: Spectrum = Spectrum::default();
+            ft[c] = self.t[c] * (1.0 as Float - f[c]);
+            // account for non-symmetry with transmission to different medium
+            if self.mode == TransportMode::Radiance {
+                ft[c] *= (eta_i * eta_i) / (eta_t * eta_t);
+            }
+            if *sampled_type != 0_u8 {
+                *sampled_type = BxdfType::BsdfTransmission as u8 | BxdfType::BsdfSpecular as u8
+            }
+            *channel = Some(c);
+            *pdf = (1.0 as Float - p_reflect) * channel_pdf;
+            ft / abs_cos_theta(&*wi)
+        };
+        if let Some(sc) = self.sc_opt {
+            sc * ret
+        } else {
+            ret
+        }
+    }
+    pub fn pdf(&self, _wo: &Vector3f, _wi: &Vector3f) -> Float {
+        0.0 as Float
+    }
+    pub fn get_type(&self) -> u8 {
+        BxdfType::BsdfReflection as u8
+            | BxdfType::BsdfTransmission as u8
+            | BxdfType::BsdfSpecular as u8
+    }
+}
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct LambertianReflection {
     pub r: Spectrum,
@@ -1409,7 +1591,7 @@ impl FresnelBlend {
         wh = wh.normalize();
         if let Some(ref distribution) = self.distribution {
             let schlick_fresnel: Spectrum = self.schlick_fresnel(vec3_dot_vec3f(wi, &wh));
-            assert!(schlick_fresnel.c[0] >= 0.0, "wi = {:?}; wh = {:?}", wi, wh);
+            assert!(schlick_fresnel[0] >= 0.0, "wi = {:?}; wh = {:?}", wi, wh);
             let specular: Spectrum = schlick_fresnel
                 * (distribution.d(&wh)
                     / (4.0
@@ -5,6 +5,7 @@
 // pbrt
 use crate::core::camera::CameraSample;
 use crate::core::geometry::{Point2f, Point2i};
+use crate::core::paramset::ParamSet;
 use crate::core::pbrt::Float;
 use crate::integrators::mlt::MLTSampler;
 use crate::samplers::halton::HaltonSampler;
@@ -16,6 +17,46 @@ use crate::samplers::zerotwosequence::ZeroTwoSequenceSampler;
 
 // see sampler.h
 
+/// Settings for adaptive sampling, which is supported by the global
+/// samplers (**halton** and **sobol**):
+///
+/// ```text
+/// Sampler "sobol" "integer pixelsamples" [ 1024 ]
+///   "float noisethreshold" [ 0.01 ] "integer minpixelsamples" [ 16 ]
+/// ```
+///
+/// The render loop of the
+/// [SamplerIntegrators](../integrator/enum.SamplerIntegrator.html)
+/// first takes *min_samples_per_pixel* samples in each pixel and then
+/// keeps doubling the sample count of all image tiles which still
+/// contain a pixel with a larger relative error than
+/// *noise_threshold*, up to "pixelsamples". As the samples of each
+/// pass continue the pixel's sample sequence, the stratification of
+/// the sampler is preserved.
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveSampling {
+    /// the relative (standard) error a pixel should reach
+    pub noise_threshold: Float,
+    /// samples per pixel taken in the first pass
+    pub min_samples_per_pixel: i64,
+}
+
+impl AdaptiveSampling {
+    /// Returns *None* if no (positive) "noisethreshold" was given.
+    pub fn create(params: &ParamSet) -> Option<AdaptiveSampling> {
+        let noise_threshold: Float = params.find_one_float("noisethreshold", 0.0 as Float);
+        if noise_threshold > 0.0 as Float {
+            let min_samples_per_pixel: i32 = params.find_one_int("minpixelsamples", 16);
+            Some(AdaptiveSampling {
+                noise_threshold,
+                min_samples_per_pixel: std::cmp::max(2, min_samples_per_pixel) as i64,
+            })
+        } else {
+            None
+        }
+    }
+}
+
 pub enum Sampler {
     Halton(HaltonSampler),
     MaxMinDist(MaxMinDistSampler),
@@ -191,10 +232,33 @@ impl Sampler {
             Sampler::ZeroTwoSequence(sampler) => sampler.get_samples_per_pixel(),
         }
     }
-    // GlobalSampler
+    pub fn get_adaptive_sampling(&self) -> Option<AdaptiveSampling> {
+        match self {
+            Sampler::Halton(sampler) => sampler.adaptive,
+            Sampler::Sobol(sampler) => sampler.adaptive,
+            _ => None,
+        }
+    }
+    /// Pixel samplers (maxmindist, stratified, 02sequence) generate
+    /// all samples of a pixel at once in
+    /// [start_pixel()](#method.start_pixel), so a pixel can't be
+    /// continued in a later pass without breaking their
+    /// stratification.
+    pub fn can_continue_pixels(&self) -> bool {
+        matches!(
+            self,
+            Sampler::Halton(_) | Sampler::Random(_) | Sampler::Sobol(_)
+        )
+    }
+    /// Continue with sample *sample_num* of the current pixel (only
+    /// for samplers which [can continue
+    /// pixels](#method.can_continue_pixels)). Global samplers keep
+    /// their stratification, the random sampler has to be reseeded
+    /// for each pass to not repeat earlier samples.
     pub fn set_sample_number(&mut self, sample_num: i64) -> bool {
         match self {
             Sampler::Halton(sampler) => sampler.set_sample_number(sample_num),
+            Sampler::Random(sampler) => sampler.set_sample_number(sample_num),
             Sampler::Sobol(sampler) => sampler.set_sample_number(sample_num),
             _ => false,
         }
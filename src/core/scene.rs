@@ -12,9 +12,15 @@ use crate::core::light::{Light, LightFlags};
 use crate::core::pbrt::Spectrum;
 use crate::core::primitive::Primitive;
 use crate::core::sampler::Sampler;
+use crate::core::stats::StatCounter;
 
 // see scene.h
 
+static N_INTERSECTION_TESTS: StatCounter =
+    StatCounter::new("Intersections/Regular ray intersection tests");
+static N_SHADOW_TESTS: StatCounter =
+    StatCounter::new("Intersections/Shadow ray intersection tests");
+
 #[derive(Clone)]
 pub struct Scene {
     pub lights: Vec<Arc<Light>>,
@@ -53,7 +59,7 @@ impl Scene {
         &self.world_bound
     }
     pub fn intersect(&self, ray: &Ray, isect: &mut SurfaceInteraction) -> bool {
-        // TODO: ++nIntersectionTests;
+        N_INTERSECTION_TESTS.inc();
         assert_ne!(
             ray.d,
             Vector3f {
@@ -65,7 +71,7 @@ impl Scene {
         self.aggregate.intersect(ray, isect)
     }
     pub fn intersect_p(&self, ray: &mut Ray) -> bool {
-        // TODO: ++nShadowTests;
+        N_SHADOW_TESTS.inc();
         assert_ne!(
             ray.d,
             Vector3f {
@@ -0,0 +1,1111 @@
+//! Scenes can be built directly from Rust code, without filling
+//! [ParamSets](../paramset/struct.ParamSet.html) with parameter names
+//! (like a scene file would): the [SceneBuilder](struct.SceneBuilder.html)
+//! takes typed descriptions of the camera, film, filter, sampler,
+//! integrator and lights, materials are passed as
+//! [Material](../material/enum.Material.html) values. The compiler
+//! checks every field, misspelled or mistyped parameters can't be
+//! silently ignored.
+//!
+//! The builder follows the semantics of the scene file format: shapes
+//! get the current transformation, material and area light,
+//! [attribute_begin()](struct.SceneBuilder.html#method.attribute_begin)
+//! and [attribute_end()](struct.SceneBuilder.html#method.attribute_end)
+//! save and restore them. The camera is placed by the transformation
+//! which is current when [camera()](struct.SceneBuilder.html#method.camera)
+//! gets called, afterwards the transformation starts from the identity
+//! again (like after `WorldBegin`). Invalid shape descriptions (e.g.
+//! a mesh with out-of-bounds vertex indices) are collected and
+//! returned as a [SceneBuilderError](struct.SceneBuilderError.html) by
+//! [build()](struct.SceneBuilder.html#method.build).
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use rs_pbrt::core::geometry::{Point2i, Point3f, Vector3f};
+//! use rs_pbrt::core::material::Material;
+//! use rs_pbrt::core::pbrt::{Float, Spectrum};
+//! use rs_pbrt::core::scenebuilder::{
+//!     FilmDesc, IntegratorDesc, LightDesc, LightSampling, MeshDesc, PerspectiveCameraDesc,
+//!     SamplerDesc, SceneBuilder,
+//! };
+//! use rs_pbrt::materials::matte::MatteMaterial;
+//! use rs_pbrt::textures::constant::ConstantTexture;
+//!
+//! fn main() {
+//!     let filename = std::env::temp_dir().join("scenebuilder_doctest.png");
+//!     let red: Material = Material::Matte(Box::new(MatteMaterial::new(
+//!         Arc::new(ConstantTexture::new(Spectrum::rgb(0.8, 0.1, 0.1))),
+//!         Arc::new(ConstantTexture::new(0.0 as Float)),
+//!         None,
+//!     )));
+//!     let floor: MeshDesc = MeshDesc {
+//!         indices: vec![0, 1, 2, 0, 2, 3],
+//!         p: vec![
+//!             Point3f { x: -5.0, y: -1.0, z: -5.0 },
+//!             Point3f { x: 5.0, y: -1.0, z: -5.0 },
+//!             Point3f { x: 5.0, y: -1.0, z: 5.0 },
+//!             Point3f { x: -5.0, y: -1.0, z: 5.0 },
+//!         ],
+//!         ..Default::default()
+//!     };
+//!     let mut built = SceneBuilder::new()
+//!         .look_at(
+//!             Point3f { x: 0.0, y: 1.0, z: 6.0 },
+//!             Point3f { x: 0.0, y: 0.0, z: 0.0 },
+//!             Vector3f { x: 0.0, y: 1.0, z: 0.0 },
+//!         )
+//!         .camera(PerspectiveCameraDesc {
+//!             fov: 40.0,
+//!             ..Default::default()
+//!         })
+//!         .film(FilmDesc {
+//!             resolution: Point2i { x: 32, y: 24 },
+//!             filename: String::from(filename.to_str().unwrap()),
+//!             ..Default::default()
+//!         })
+//!         .sampler(SamplerDesc::Halton {
+//!             pixel_samples: 4,
+//!             sample_at_pixel_center: false,
+//!         })
+//!         .integrator(IntegratorDesc::Path {
+//!             max_depth: 5,
+//!             rr_threshold: 1.0,
+//!             light_sampling: LightSampling::Spatial,
+//!         })
+//!         .add_light(LightDesc::Distant {
+//!             l: Spectrum::new(3.0),
+//!             from: Point3f { x: 1.0, y: 4.0, z: 2.0 },
+//!             to: Point3f { x: 0.0, y: 0.0, z: 0.0 },
+//!         })
+//!         .add_mesh(floor)
+//!         .material(red)
+//!         .add_sphere(1.0)
+//!         .build()
+//!         .expect("invalid scene");
+//!     assert_eq!(built.scene.lights.len(), 1);
+//!     built.integrator.render(&built.scene, 1);
+//!     assert!(filename.exists());
+//!     // a triangle needs three vertex indices
+//!     let broken = SceneBuilder::new()
+//!         .add_mesh(MeshDesc {
+//!             indices: vec![0, 1],
+//!             p: vec![Point3f::default(); 2],
+//!             ..Default::default()
+//!         })
+//!         .build();
+//!     assert!(broken.is_err());
+//! }
+//! ```
+
+// std
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+// pbrt
+use crate::accelerators::bvh::{BVHAccel, SplitMethod};
+use crate::cameras::orthographic::OrthographicCamera;
+use crate::cameras::perspective::PerspectiveCamera;
+use crate::core::api::default_material;
+use crate::core::camera::Camera;
+use crate::core::checkpoint::CheckpointParams;
+use crate::core::denoise::DenoiseParams;
+use crate::core::film::{Aov, Film, ProgressiveParams};
+use crate::core::filter::Filter;
+use crate::core::geometry::vec3_coordinate_system;
+use crate::core::geometry::{
+    Bounds2f, Bounds2i, Normal3f, Point2f, Point2i, Point3f, Vector2f, Vector3f,
+};
+use crate::core::integrator::{Integrator, SamplerIntegrator};
+use crate::core::light::Light;
+use crate::core::material::Material;
+use crate::core::medium::MediumInterface;
+use crate::core::pbrt::{Float, Spectrum};
+use crate::core::primitive::{GeometricPrimitive, Primitive};
+use crate::core::sampler::Sampler;
+use crate::core::scene::Scene;
+use crate::core::shape::Shape;
+use crate::core::transform::{AnimatedTransform, Transform};
+use crate::filters::boxfilter::BoxFilter;
+use crate::filters::gaussian::GaussianFilter;
+use crate::filters::mitchell::MitchellNetravali;
+use crate::filters::sinc::LanczosSincFilter;
+use crate::filters::triangle::TriangleFilter;
+use crate::integrators::ao::AOIntegrator;
+use crate::integrators::bdpt::BDPTIntegrator;
+use crate::integrators::directlighting::{DirectLightingIntegrator, LightStrategy};
+use crate::integrators::mlt::MLTIntegrator;
+use crate::integrators::path::PathIntegrator;
+use crate::integrators::sppm::SPPMIntegrator;
+use crate::integrators::volpath::VolPathIntegrator;
+use crate::integrators::whitted::WhittedIntegrator;
+use crate::lights::diffuse::DiffuseAreaLight;
+use crate::lights::distant::DistantLight;
+use crate::lights::infinite::InfiniteAreaLight;
+use crate::lights::point::PointLight;
+use crate::lights::spot::SpotLight;
+use crate::samplers::halton::HaltonSampler;
+use crate::samplers::maxmin::MaxMinDistSampler;
+use crate::samplers::random::RandomSampler;
+use crate::samplers::sobol::SobolSampler;
+use crate::samplers::stratified::StratifiedSampler;
+use crate::samplers::zerotwosequence::ZeroTwoSequenceSampler;
+use crate::shapes::sphere::Sphere;
+use crate::shapes::triangle::{Triangle, TriangleMesh};
+
+/// Camera "perspective", see
+/// [PerspectiveCamera](../../cameras/perspective/struct.PerspectiveCamera.html).
+#[derive(Debug, Clone, Copy)]
+pub struct PerspectiveCameraDesc {
+    /// field of view (in degrees) of the shorter image axis
+    pub fov: Float,
+    pub lens_radius: Float,
+    pub focal_distance: Float,
+    /// defaults to the film's aspect ratio
+    pub screen_window: Option<Bounds2f>,
+    pub shutter_open: Float,
+    pub shutter_close: Float,
+}
+
+impl Default for PerspectiveCameraDesc {
+    fn default() -> Self {
+        PerspectiveCameraDesc {
+            fov: 90.0 as Float,
+            lens_radius: 0.0 as Float,
+            focal_distance: 1e6 as Float,
+            screen_window: None,
+            shutter_open: 0.0 as Float,
+            shutter_close: 1.0 as Float,
+        }
+    }
+}
+
+/// Camera "orthographic", see
+/// [OrthographicCamera](../../cameras/orthographic/struct.OrthographicCamera.html).
+#[derive(Debug, Clone, Copy)]
+pub struct OrthographicCameraDesc {
+    pub lens_radius: Float,
+    pub focal_distance: Float,
+    /// defaults to the film's aspect ratio
+    pub screen_window: Option<Bounds2f>,
+    pub shutter_open: Float,
+    pub shutter_close: Float,
+}
+
+impl Default for OrthographicCameraDesc {
+    fn default() -> Self {
+        OrthographicCameraDesc {
+            lens_radius: 0.0 as Float,
+            focal_distance: 1e6 as Float,
+            screen_window: None,
+            shutter_open: 0.0 as Float,
+            shutter_close: 1.0 as Float,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum CameraDesc {
+    Orthographic(OrthographicCameraDesc),
+    Perspective(PerspectiveCameraDesc),
+}
+
+impl From<PerspectiveCameraDesc> for CameraDesc {
+    fn from(desc: PerspectiveCameraDesc) -> Self {
+        CameraDesc::Perspective(desc)
+    }
+}
+
+impl From<OrthographicCameraDesc> for CameraDesc {
+    fn from(desc: OrthographicCameraDesc) -> Self {
+        CameraDesc::Orthographic(desc)
+    }
+}
+
+/// Film "image", see [Film](../film/struct.Film.html).
+#[derive(Debug, Clone)]
+pub struct FilmDesc {
+    pub resolution: Point2i,
+    pub filename: String,
+    /// in NDC space, [0, 1] in both directions renders the full image
+    pub crop_window: Bounds2f,
+    pub scale: Float,
+    /// in millimeters
+    pub diagonal: Float,
+    pub max_sample_luminance: Float,
+    pub aovs: Vec<Aov>,
+    pub denoise: Option<DenoiseParams>,
+    pub checkpoint: Option<CheckpointParams>,
+    pub progressive: Option<ProgressiveParams>,
+}
+
+impl Default for FilmDesc {
+    fn default() -> Self {
+        FilmDesc {
+            resolution: Point2i { x: 1280, y: 720 },
+            filename: String::from("pbrt.png"),
+            crop_window: Bounds2f {
+                p_min: Point2f { x: 0.0, y: 0.0 },
+                p_max: Point2f { x: 1.0, y: 1.0 },
+            },
+            scale: 1.0 as Float,
+            diagonal: 35.0 as Float,
+            max_sample_luminance: Float::INFINITY,
+            aovs: Vec::new(),
+            denoise: None,
+            checkpoint: None,
+            progressive: None,
+        }
+    }
+}
+
+/// The pixel reconstruction filter, *radius* corresponds to the
+/// "xwidth" and "ywidth" parameters.
+#[derive(Debug, Clone, Copy)]
+pub enum FilterDesc {
+    Box {
+        radius: Vector2f,
+    },
+    Gaussian {
+        radius: Vector2f,
+        alpha: Float,
+    },
+    Mitchell {
+        radius: Vector2f,
+        b: Float,
+        c: Float,
+    },
+    Sinc {
+        radius: Vector2f,
+        tau: Float,
+    },
+    Triangle {
+        radius: Vector2f,
+    },
+}
+
+impl Default for FilterDesc {
+    fn default() -> Self {
+        FilterDesc::Box {
+            radius: Vector2f { x: 0.5, y: 0.5 },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SamplerDesc {
+    Halton {
+        pixel_samples: i64,
+        sample_at_pixel_center: bool,
+    },
+    MaxMinDist {
+        pixel_samples: i64,
+        dimensions: i64,
+    },
+    Random {
+        pixel_samples: i64,
+    },
+    Sobol {
+        pixel_samples: i64,
+    },
+    Stratified {
+        x_samples: i32,
+        y_samples: i32,
+        jitter: bool,
+        dimensions: i64,
+    },
+    ZeroTwoSequence {
+        pixel_samples: i64,
+        dimensions: i64,
+    },
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        SamplerDesc::Halton {
+            pixel_samples: 16,
+            sample_at_pixel_center: false,
+        }
+    }
+}
+
+/// How the path tracers (and BDPT) choose a light to sample, see
+/// [lightdistrib](../lightdistrib/index.html).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightSampling {
+    Uniform,
+    Power,
+    Spatial,
+}
+
+impl LightSampling {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightSampling::Uniform => "uniform",
+            LightSampling::Power => "power",
+            LightSampling::Spatial => "spatial",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum IntegratorDesc {
+    AO {
+        cos_sample: bool,
+        n_samples: i32,
+    },
+    BDPT {
+        max_depth: u32,
+        light_sampling: LightSampling,
+    },
+    DirectLighting {
+        strategy: LightStrategy,
+        max_depth: u32,
+    },
+    MLT {
+        max_depth: u32,
+        bootstrap_samples: u32,
+        chains: u32,
+        mutations_per_pixel: u32,
+        sigma: Float,
+        large_step_probability: Float,
+    },
+    Path {
+        max_depth: u32,
+        rr_threshold: Float,
+        light_sampling: LightSampling,
+    },
+    SPPM {
+        iterations: i32,
+        /// -1 uses the number of pixels
+        photons_per_iteration: i32,
+        max_depth: u32,
+        radius: Float,
+        image_write_frequency: i32,
+    },
+    VolPath {
+        max_depth: u32,
+        rr_threshold: Float,
+        light_sampling: LightSampling,
+    },
+    Whitted {
+        max_depth: u32,
+    },
+}
+
+impl Default for IntegratorDesc {
+    fn default() -> Self {
+        IntegratorDesc::Path {
+            max_depth: 5,
+            rr_threshold: 1.0 as Float,
+            light_sampling: LightSampling::Spatial,
+        }
+    }
+}
+
+/// Light sources, positions and directions are given in the space of
+/// the current transformation (like for `LightSource`).
+#[derive(Debug, Clone)]
+pub enum LightDesc {
+    Distant {
+        l: Spectrum,
+        from: Point3f,
+        to: Point3f,
+    },
+    /// an environment map (if *map_name* isn't empty) or constant
+    /// illumination from all directions
+    Infinite {
+        l: Spectrum,
+        n_samples: i32,
+        map_name: String,
+    },
+    Point {
+        i: Spectrum,
+        from: Point3f,
+    },
+    Spot {
+        i: Spectrum,
+        from: Point3f,
+        to: Point3f,
+        /// in degrees
+        cone_angle: Float,
+        /// in degrees
+        cone_delta_angle: Float,
+    },
+}
+
+/// `AreaLightSource "diffuse"`, turns shapes into emitters.
+#[derive(Debug, Clone, Copy)]
+pub struct DiffuseAreaLightDesc {
+    pub l: Spectrum,
+    pub two_sided: bool,
+    pub n_samples: i32,
+}
+
+impl Default for DiffuseAreaLightDesc {
+    fn default() -> Self {
+        DiffuseAreaLightDesc {
+            l: Spectrum::new(1.0 as Float),
+            two_sided: false,
+            n_samples: 1,
+        }
+    }
+}
+
+/// A triangle mesh (like `Shape "trianglemesh"`), vertices and normals
+/// are given in object space. Normals and uv coordinates are optional
+/// (leave them empty), otherwise there has to be one per vertex.
+#[derive(Debug, Clone, Default)]
+pub struct MeshDesc {
+    /// three per triangle
+    pub indices: Vec<u32>,
+    pub p: Vec<Point3f>,
+    pub n: Vec<Normal3f>,
+    pub uv: Vec<Point2f>,
+}
+
+impl MeshDesc {
+    fn check(&self) -> Result<(), String> {
+        if !self.indices.chunks_exact(3).remainder().is_empty() {
+            return Err(format!(
+                "Number of vertex indices {} not a multiple of 3",
+                self.indices.len()
+            ));
+        }
+        if let Some(index) = self
+            .indices
+            .iter()
+            .find(|index| **index as usize >= self.p.len())
+        {
+            return Err(format!(
+                "mesh has out of-bounds vertex index {} ({} vertices were given)",
+                index,
+                self.p.len()
+            ));
+        }
+        if !self.n.is_empty() && self.n.len() != self.p.len() {
+            return Err(format!(
+                "Number of normals {} doesn't match number of vertices {}",
+                self.n.len(),
+                self.p.len()
+            ));
+        }
+        if !self.uv.is_empty() && self.uv.len() != self.p.len() {
+            return Err(format!(
+                "Number of uv coordinates {} doesn't match number of vertices {}",
+                self.uv.len(),
+                self.p.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Returned by [SceneBuilder::build()](struct.SceneBuilder.html#method.build)
+/// if shapes were rejected.
+#[derive(Debug)]
+pub struct SceneBuilderError {
+    /// one message per rejected shape, in the order they were added
+    pub messages: Vec<String>,
+}
+
+impl fmt::Display for SceneBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ERROR: {}", self.messages.join("; "))
+    }
+}
+
+/// The result of [SceneBuilder::build()](struct.SceneBuilder.html#method.build),
+/// ready to be rendered by `integrator.render(&scene, n_threads)`.
+pub struct BuiltScene {
+    pub scene: Scene,
+    pub camera: Arc<Camera>,
+    pub integrator: Box<Integrator>,
+}
+
+/// Everything *attribute_begin()* saves and *attribute_end()*
+/// restores.
+#[derive(Clone)]
+struct BuilderState {
+    transform: Transform,
+    reverse_orientation: bool,
+    material: Option<Arc<Material>>,
+    area_light: Option<DiffuseAreaLightDesc>,
+}
+
+pub struct SceneBuilder {
+    camera: CameraDesc,
+    camera_to_world: Transform,
+    film: FilmDesc,
+    filter: FilterDesc,
+    sampler: SamplerDesc,
+    integrator: IntegratorDesc,
+    state: BuilderState,
+    pushed_states: Vec<BuilderState>,
+    primitives: Vec<Arc<Primitive>>,
+    lights: Vec<Arc<Light>>,
+    n_shapes: u32,
+    material_ids: HashMap<usize, u32>,
+    errors: Vec<String>,
+}
+
+impl Default for SceneBuilder {
+    fn default() -> Self {
+        SceneBuilder::new()
+    }
+}
+
+impl SceneBuilder {
+    /// Starts with the same defaults as an empty scene file.
+    pub fn new() -> Self {
+        SceneBuilder {
+            camera: CameraDesc::Perspective(PerspectiveCameraDesc::default()),
+            camera_to_world: Transform::default(),
+            film: FilmDesc::default(),
+            filter: FilterDesc::default(),
+            sampler: SamplerDesc::default(),
+            integrator: IntegratorDesc::default(),
+            state: BuilderState {
+                transform: Transform::default(),
+                reverse_orientation: false,
+                material: default_material(),
+                area_light: None,
+            },
+            pushed_states: Vec::new(),
+            primitives: Vec::new(),
+            lights: Vec::new(),
+            n_shapes: 0_u32,
+            material_ids: HashMap::new(),
+            errors: Vec::new(),
+        }
+    }
+    // transformations
+    pub fn identity(mut self) -> Self {
+        self.state.transform = Transform::default();
+        self
+    }
+    pub fn concat_transform(mut self, tr: &Transform) -> Self {
+        self.state.transform = self.state.transform * *tr;
+        self
+    }
+    pub fn translate(self, delta: Vector3f) -> Self {
+        self.concat_transform(&Transform::translate(&delta))
+    }
+    /// *angle* in degrees
+    pub fn rotate(self, angle: Float, axis: Vector3f) -> Self {
+        self.concat_transform(&Transform::rotate(angle, &axis))
+    }
+    pub fn scale(self, sx: Float, sy: Float, sz: Float) -> Self {
+        self.concat_transform(&Transform::scale(sx, sy, sz))
+    }
+    pub fn look_at(self, eye: Point3f, look: Point3f, up: Vector3f) -> Self {
+        self.concat_transform(&Transform::look_at(&eye, &look, &up))
+    }
+    // rendering options
+    /// Places the camera by the current transformation (which gets
+    /// reset to the identity).
+    pub fn camera<C: Into<CameraDesc>>(mut self, camera: C) -> Self {
+        self.camera = camera.into();
+        self.camera_to_world = Transform::inverse(&self.state.transform);
+        self.state.transform = Transform::default();
+        self
+    }
+    pub fn film(mut self, film: FilmDesc) -> Self {
+        self.film = film;
+        self
+    }
+    pub fn filter(mut self, filter: FilterDesc) -> Self {
+        self.filter = filter;
+        self
+    }
+    pub fn sampler(mut self, sampler: SamplerDesc) -> Self {
+        self.sampler = sampler;
+        self
+    }
+    pub fn integrator(mut self, integrator: IntegratorDesc) -> Self {
+        self.integrator = integrator;
+        self
+    }
+    // graphics state
+    pub fn attribute_begin(mut self) -> Self {
+        self.pushed_states.push(self.state.clone());
+        self
+    }
+    pub fn attribute_end(mut self) -> Self {
+        if let Some(state) = self.pushed_states.pop() {
+            self.state = state;
+        } else {
+            println!("ERROR: Unmatched attribute_end() encountered. Ignoring it.");
+        }
+        self
+    }
+    /// Accepts a [Material](../material/enum.Material.html) or a
+    /// shared `Arc<Material>`.
+    pub fn material<M: Into<Arc<Material>>>(mut self, material: M) -> Self {
+        self.state.material = Some(material.into());
+        self
+    }
+    /// Shapes added from now on emit light.
+    pub fn area_light(mut self, area_light: DiffuseAreaLightDesc) -> Self {
+        self.state.area_light = Some(area_light);
+        self
+    }
+    pub fn no_area_light(mut self) -> Self {
+        self.state.area_light = None;
+        self
+    }
+    pub fn reverse_orientation(mut self) -> Self {
+        self.state.reverse_orientation = !self.state.reverse_orientation;
+        self
+    }
+    // lights
+    pub fn add_light(mut self, light: LightDesc) -> Self {
+        let cur_transform: Transform = self.state.transform;
+        let medium_interface: MediumInterface = MediumInterface::default();
+        let light: Arc<Light> = match light {
+            LightDesc::Distant { l, from, to } => Arc::new(Light::Distant(Box::new(
+                DistantLight::new(&cur_transform, &l, &(from - to)),
+            ))),
+            LightDesc::Infinite {
+                l,
+                n_samples,
+                map_name,
+            } => Arc::new(Light::InfiniteArea(Box::new(InfiniteAreaLight::new(
+                &cur_transform,
+                &l,
+                n_samples,
+                map_name,
+            )))),
+            LightDesc::Point { i, from } => {
+                let l2w: Transform = Transform::translate(&Vector3f {
+                    x: from.x,
+                    y: from.y,
+                    z: from.z,
+                }) * cur_transform;
+                Arc::new(Light::Point(Box::new(PointLight::new(
+                    &l2w,
+                    &medium_interface,
+                    &i,
+                ))))
+            }
+            LightDesc::Spot {
+                i,
+                from,
+                to,
+                cone_angle,
+                cone_delta_angle,
+            } => {
+                // compute spotlight world to light transformation
+                let dir: Vector3f = (to - from).normalize();
+                let mut du: Vector3f = Vector3f::default();
+                let mut dv: Vector3f = Vector3f::default();
+                vec3_coordinate_system(&dir, &mut du, &mut dv);
+                let dir_to_z: Transform = Transform::new(
+                    du.x, du.y, du.z, 0.0, dv.x, dv.y, dv.z, 0.0, dir.x, dir.y, dir.z, 0.0, 0.0,
+                    0.0, 0.0, 1.0,
+                );
+                let light2world: Transform = cur_transform
+                    * Transform::translate(&Vector3f {
+                        x: from.x,
+                        y: from.y,
+                        z: from.z,
+                    })
+                    * Transform::inverse(&dir_to_z);
+                Arc::new(Light::Spot(Box::new(SpotLight::new(
+                    &light2world,
+                    &medium_interface,
+                    &i,
+                    cone_angle,
+                    cone_angle - cone_delta_angle,
+                ))))
+            }
+        };
+        self.lights.push(light);
+        self
+    }
+    // shapes
+    /// A full sphere around the origin (of the current
+    /// transformation).
+    pub fn add_sphere(self, radius: Float) -> Self {
+        let obj_to_world: Transform = self.state.transform;
+        let world_to_obj: Transform = Transform::inverse(&obj_to_world);
+        let sphere: Arc<Shape> = Arc::new(Shape::Sphr(Sphere::new(
+            obj_to_world,
+            world_to_obj,
+            self.state.reverse_orientation,
+            radius,
+            -radius,
+            radius,
+            360.0 as Float,
+        )));
+        self.add_shapes(vec![sphere])
+    }
+    /// Invalid meshes are skipped and reported by
+    /// [build()](#method.build).
+    pub fn add_mesh(mut self, mesh: MeshDesc) -> Self {
+        if let Err(message) = mesh.check() {
+            self.errors.push(message);
+            return self;
+        }
+        let obj_to_world: Transform = self.state.transform;
+        let world_to_obj: Transform = Transform::inverse(&obj_to_world);
+        // transform mesh vertices and normals to world space
+        let p_ws: Vec<Point3f> = mesh
+            .p
+            .iter()
+            .map(|p| obj_to_world.transform_point(p))
+            .collect();
+        let n_ws: Vec<Normal3f> = mesh
+            .n
+            .iter()
+            .map(|n| obj_to_world.transform_normal(n))
+            .collect();
+        let n_vertices: u32 = p_ws.len() as u32;
+        let n_triangles: u32 = (mesh.indices.len() / 3) as u32;
+        let mesh = Arc::new(TriangleMesh::new(
+            obj_to_world,
+            world_to_obj,
+            self.state.reverse_orientation,
+            n_triangles,
+            mesh.indices,
+            n_vertices,
+            p_ws,
+            Vec::new(),
+            n_ws,
+            mesh.uv,
+            None,
+            None,
+            Vec::new(),
+            Vec::new(),
+        ));
+        let triangles: Vec<Arc<Shape>> = (0..n_triangles)
+            .map(|id| Arc::new(Shape::Trngl(Triangle::new(mesh.clone(), id))))
+            .collect();
+        self.add_shapes(triangles)
+    }
+    /// Any other shape, which has to be created with its transformation
+    /// already (the current transformation only affects area lights).
+    pub fn add_shape(self, shape: Shape) -> Self {
+        self.add_shapes(vec![Arc::new(shape)])
+    }
+    /// Adds the shapes of one object, with the current material and
+    /// area light.
+    fn add_shapes(mut self, shapes: Vec<Arc<Shape>>) -> Self {
+        // IDs start with 1, 0 is used for "nothing hit"
+        self.n_shapes += 1;
+        let object_id: u32 = self.n_shapes;
+        let material: Option<Arc<Material>> = self.state.material.clone();
+        let material_id: u32 = self.get_material_id(&material);
+        let mi: MediumInterface = MediumInterface::default();
+        for shape in shapes {
+            let area_light: Option<Arc<Light>> = if let Some(desc) = self.state.area_light {
+                let area_light: Arc<Light> =
+                    Arc::new(Light::DiffuseArea(Box::new(DiffuseAreaLight::new(
+                        &self.state.transform,
+                        &mi,
+                        &desc.l,
+                        desc.n_samples,
+                        shape.clone(),
+                        desc.two_sided,
+                    ))));
+                self.lights.push(area_light.clone());
+                Some(area_light)
+            } else {
+                None
+            };
+            let mut geo_prim = GeometricPrimitive::new(
+                shape,
+                material.clone(),
+                area_light,
+                Some(Arc::new(mi.clone())),
+            );
+            geo_prim.object_id = object_id;
+            geo_prim.material_id = material_id;
+            self.primitives
+                .push(Arc::new(Primitive::Geometric(Box::new(geo_prim))));
+        }
+        self
+    }
+    /// Materials are numbered (starting with 1) in the order they are
+    /// first used by a shape, 0 means no material.
+    fn get_material_id(&mut self, material: &Option<Arc<Material>>) -> u32 {
+        if let Some(material) = material {
+            let next_id: u32 = self.material_ids.len() as u32 + 1;
+            *self
+                .material_ids
+                .entry(Arc::as_ptr(material) as usize)
+                .or_insert(next_id)
+        } else {
+            0_u32
+        }
+    }
+    // build
+    /// Creates the scene (with a BVH as accelerator), the camera and
+    /// the integrator, unless shapes were rejected.
+    pub fn build(self) -> Result<BuiltScene, SceneBuilderError> {
+        if !self.errors.is_empty() {
+            return Err(SceneBuilderError {
+                messages: self.errors,
+            });
+        }
+        if !self.pushed_states.is_empty() {
+            println!("WARNING: Missing end to attribute_begin()");
+        }
+        let film: Arc<Film> = Arc::new(Film::new(
+            self.film.resolution,
+            self.film.crop_window,
+            make_filter(&self.filter),
+            self.film.diagonal,
+            self.film.filename,
+            self.film.scale,
+            self.film.max_sample_luminance,
+            self.film.aovs,
+            self.film.denoise,
+            self.film.checkpoint,
+            self.film.progressive,
+        ));
+        let camera: Arc<Camera> = make_camera(&self.camera, &self.camera_to_world, film);
+        let integrator: Box<Integrator> = make_integrator(&self.integrator, &self.sampler, &camera);
+        let accelerator: Arc<Primitive> = Arc::new(Primitive::BVH(Box::new(BVHAccel::new(
+            self.primitives,
+            4,
+            SplitMethod::SAH,
+        ))));
+        Ok(BuiltScene {
+            scene: Scene::new(accelerator, self.lights),
+            camera,
+            integrator,
+        })
+    }
+}
+
+fn make_filter(desc: &FilterDesc) -> Box<Filter> {
+    let inverse = |radius: &Vector2f| Vector2f {
+        x: 1.0 / radius.x,
+        y: 1.0 / radius.y,
+    };
+    match *desc {
+        FilterDesc::Box { radius } => Box::new(Filter::Bx(BoxFilter {
+            radius,
+            inv_radius: inverse(&radius),
+        })),
+        FilterDesc::Gaussian { radius, alpha } => Box::new(Filter::Gaussian(GaussianFilter {
+            alpha,
+            exp_x: (-alpha * radius.x * radius.x).exp(),
+            exp_y: (-alpha * radius.y * radius.y).exp(),
+            radius,
+            inv_radius: inverse(&radius),
+        })),
+        FilterDesc::Mitchell { radius, b, c } => Box::new(Filter::MitchellNetravali(
+            MitchellNetravali::new(radius.x, radius.y, b, c),
+        )),
+        FilterDesc::Sinc { radius, tau } => {
+            Box::new(Filter::LanczosSinc(LanczosSincFilter::new(radius, tau)))
+        }
+        FilterDesc::Triangle { radius } => Box::new(Filter::Triangle(TriangleFilter {
+            radius,
+            inv_radius: inverse(&radius),
+        })),
+    }
+}
+
+/// The default screen window covers [-1, 1] along the shorter image
+/// axis.
+fn default_screen_window(film: &Film) -> Bounds2f {
+    let frame: Float = film.full_resolution.x as Float / film.full_resolution.y as Float;
+    let mut screen: Bounds2f = Bounds2f::default();
+    if frame > 1.0 {
+        screen.p_min.x = -frame;
+        screen.p_max.x = frame;
+        screen.p_min.y = -1.0;
+        screen.p_max.y = 1.0;
+    } else {
+        screen.p_min.x = -1.0;
+        screen.p_max.x = 1.0;
+        screen.p_min.y = -1.0 / frame;
+        screen.p_max.y = 1.0 / frame;
+    }
+    screen
+}
+
+fn make_camera(desc: &CameraDesc, camera_to_world: &Transform, film: Arc<Film>) -> Arc<Camera> {
+    let screen_window: Bounds2f = default_screen_window(&film);
+    match desc {
+        CameraDesc::Perspective(desc) => {
+            let animated_cam_to_world: AnimatedTransform = AnimatedTransform::new(
+                camera_to_world,
+                desc.shutter_open,
+                camera_to_world,
+                desc.shutter_close,
+            );
+            Arc::new(Camera::Perspective(Box::new(PerspectiveCamera::new(
+                animated_cam_to_world,
+                desc.screen_window.unwrap_or(screen_window),
+                desc.shutter_open,
+                desc.shutter_close,
+                desc.lens_radius,
+                desc.focal_distance,
+                desc.fov,
+                film,
+                None,
+                0.0 as Float,
+            ))))
+        }
+        CameraDesc::Orthographic(desc) => {
+            let animated_cam_to_world: AnimatedTransform = AnimatedTransform::new(
+                camera_to_world,
+                desc.shutter_open,
+                camera_to_world,
+                desc.shutter_close,
+            );
+            Arc::new(Camera::Orthographic(Box::new(OrthographicCamera::new(
+                animated_cam_to_world,
+                desc.screen_window.unwrap_or(screen_window),
+                desc.shutter_open,
+                desc.shutter_close,
+                desc.lens_radius,
+                desc.focal_distance,
+                film,
+                None,
+            ))))
+        }
+    }
+}
+
+fn make_sampler(desc: &SamplerDesc, sample_bounds: &Bounds2i) -> Box<Sampler> {
+    match *desc {
+        SamplerDesc::Halton {
+            pixel_samples,
+            sample_at_pixel_center,
+        } => Box::new(Sampler::Halton(HaltonSampler::new(
+            pixel_samples,
+            sample_bounds,
+            sample_at_pixel_center,
+        ))),
+        SamplerDesc::MaxMinDist {
+            pixel_samples,
+            dimensions,
+        } => Box::new(Sampler::MaxMinDist(MaxMinDistSampler::new(
+            pixel_samples,
+            dimensions,
+        ))),
+        SamplerDesc::Random { pixel_samples } => {
+            Box::new(Sampler::Random(RandomSampler::new(pixel_samples)))
+        }
+        SamplerDesc::Sobol { pixel_samples } => Box::new(Sampler::Sobol(SobolSampler::new(
+            pixel_samples,
+            sample_bounds,
+        ))),
+        SamplerDesc::Stratified {
+            x_samples,
+            y_samples,
+            jitter,
+            dimensions,
+        } => Box::new(Sampler::Stratified(StratifiedSampler::new(
+            x_samples, y_samples, jitter, dimensions,
+        ))),
+        SamplerDesc::ZeroTwoSequence {
+            pixel_samples,
+            dimensions,
+        } => Box::new(Sampler::ZeroTwoSequence(ZeroTwoSequenceSampler::new(
+            pixel_samples,
+            dimensions,
+        ))),
+    }
+}
+
+fn make_integrator(
+    desc: &IntegratorDesc,
+    sampler_desc: &SamplerDesc,
+    camera: &Arc<Camera>,
+) -> Box<Integrator> {
+    let camera: Arc<Camera> = camera.clone();
+    let pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
+    let sampler: Box<Sampler> = make_sampler(sampler_desc, &pixel_bounds);
+    match desc.clone() {
+        IntegratorDesc::AO {
+            cos_sample,
+            n_samples,
+        } => Box::new(Integrator::Sampler(SamplerIntegrator::AO(
+            AOIntegrator::new(cos_sample, n_samples, camera, sampler, pixel_bounds),
+        ))),
+        IntegratorDesc::BDPT {
+            max_depth,
+            light_sampling,
+        } => Box::new(Integrator::BDPT(BDPTIntegrator::new(
+            camera,
+            sampler,
+            pixel_bounds,
+            max_depth,
+            String::from(light_sampling.name()),
+        ))),
+        IntegratorDesc::DirectLighting {
+            strategy,
+            max_depth,
+        } => Box::new(Integrator::Sampler(SamplerIntegrator::DirectLighting(
+            DirectLightingIntegrator::new(strategy, max_depth, camera, sampler, pixel_bounds),
+        ))),
+        IntegratorDesc::MLT {
+            max_depth,
+            bootstrap_samples,
+            chains,
+            mutations_per_pixel,
+            sigma,
+            large_step_probability,
+        } => Box::new(Integrator::MLT(MLTIntegrator::new(
+            camera,
+            max_depth,
+            bootstrap_samples,
+            chains,
+            mutations_per_pixel,
+            sigma,
+            large_step_probability,
+        ))),
+        IntegratorDesc::Path {
+            max_depth,
+            rr_threshold,
+            light_sampling,
+        } => Box::new(Integrator::Sampler(SamplerIntegrator::Path(
+            PathIntegrator::new(
+                max_depth,
+                camera,
+                sampler,
+                pixel_bounds,
+                rr_threshold,
+                String::from(light_sampling.name()),
+            ),
+        ))),
+        IntegratorDesc::SPPM {
+            iterations,
+            photons_per_iteration,
+            max_depth,
+            radius,
+            image_write_frequency,
+        } => Box::new(Integrator::SPPM(SPPMIntegrator::new(
+            camera,
+            iterations,
+            photons_per_iteration,
+            max_depth,
+            radius,
+            image_write_frequency,
+        ))),
+        IntegratorDesc::VolPath {
+            max_depth,
+            rr_threshold,
+            light_sampling,
+        } => Box::new(Integrator::Sampler(SamplerIntegrator::VolPath(
+            VolPathIntegrator::new(
+                max_depth,
+                camera,
+                sampler,
+                pixel_bounds,
+                rr_threshold,
+                String::from(light_sampling.name()),
+            ),
+        ))),
+        IntegratorDesc::Whitted { max_depth } => {
+            Box::new(Integrator::Sampler(SamplerIntegrator::Whitted(
+                WhittedIntegrator::new(max_depth, camera, sampler, pixel_bounds),
+            )))
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! Instead of rendering, the scene can be written back to a scene
+//! file (like pbrt-v3's `--cat` and `--toply` command line options):
+//! every statement which reaches the [api](../api/index.html) gets
+//! printed in a canonical form, with included files expanded and
+//! pbrt-v4 scenes translated to pbrt-v3 syntax. The scene goes to
+//! standard output, or to the file given by `--outfile` (which keeps
+//! it apart from parser warnings):
+//!
+//! ```text
+//! rs_pbrt --cat scene.pbrt --outfile normalized.pbrt
+//! rs_pbrt --toply scene.pbrt --outfile converted.pbrt
+//! ```
+//!
+//! With `--toply` inline triangle meshes are written as binary PLY
+//! files (`mesh_00001.ply`, ...) next to the output, the scene refers
+//! to them by `Shape "plymesh"`. Meshes with tangents ("S") stay
+//! inline, PLY files can't store them.
+//!
+//! ```rust
+//! use rs_pbrt::core::api::pbrt_init;
+//! use rs_pbrt::core::pbrt::Options;
+//! use rs_pbrt::parser::parse_str;
+//!
+//! fn main() {
+//!     let filename = std::env::temp_dir().join("scenewriter_doctest.pbrt");
+//!     let options: Options = Options {
+//!         cat: true,
+//!         image_file: Some(String::from(filename.to_str().unwrap())),
+//!         ..Default::default()
+//!     };
+//!     let (mut api_state, mut bsdf_state) = pbrt_init(options);
+//!     let scene: &str = "WorldBegin\n\
+//!                        AttributeBegin\n\
+//!                        Shape \"sphere\" \"float radius\" 2\n\
+//!                        AttributeEnd\n\
+//!                        WorldEnd\n";
+//!     parse_str(scene, &mut api_state, &mut bsdf_state).unwrap();
+//!     let written: String = std::fs::read_to_string(&filename).unwrap();
+//!     assert!(written.contains("    Shape \"sphere\"\n        \"float radius\" [ 2 ]\n"));
+//!     assert!(written.ends_with("WorldEnd\n"));
+//! }
+//! ```
+
+// std
+use std::fs::File;
+use std::io::{BufWriter, Result, Write};
+use std::path::{Path, PathBuf};
+// others
+use byteorder::{LittleEndian, WriteBytesExt};
+use ply_rs::ply::{
+    Addable, DefaultElement, ElementDef, Encoding, Header, PropertyDef, PropertyType, ScalarType,
+};
+use ply_rs::writer::Writer;
+// pbrt
+use crate::core::geometry::{Normal3f, Point2f, Point3f};
+use crate::core::paramset::{ParamSet, ParamSetItem};
+use crate::core::pbrt::{Float, Options};
+use crate::core::transform::Transform;
+
+/// Indentation per AttributeBegin (and parameter lists).
+const INDENT: usize = 4;
+/// Values per line of long parameter lists.
+const VALUES_PER_LINE: usize = 12;
+/// Parameters of a triangle mesh which end up in its PLY file.
+const PLY_PARAMS: &[&str] = &["P", "N", "uv", "st", "indices", "faceIndices"];
+
+pub struct SceneWriter {
+    out: Box<dyn Write>,
+    /// convert triangle meshes to PLY files
+    to_ply: bool,
+    /// where the PLY files go
+    directory: PathBuf,
+    n_meshes: usize,
+    indent: usize,
+    /// reported the first write error
+    failed: bool,
+}
+
+impl SceneWriter {
+    /// Returns a writer if the options ask for `--cat` or `--toply`.
+    pub fn new(options: &Options) -> Option<SceneWriter> {
+        if !options.cat && !options.to_ply {
+            return None;
+        }
+        let (out, directory): (Box<dyn Write>, PathBuf) = match options.image_file {
+            Some(ref filename) => {
+                let file: File = match File::create(filename) {
+                    Ok(file) => file,
+                    Err(e) => panic!("Couldn't create {:?} ({})", filename, e),
+                };
+                let directory: PathBuf = match Path::new(filename).parent() {
+                    Some(parent) => PathBuf::from(parent),
+                    None => PathBuf::new(),
+                };
+                (Box::new(BufWriter::new(file)), directory)
+            }
+            None => (Box::new(std::io::stdout()), PathBuf::new()),
+        };
+        Some(SceneWriter {
+            out,
+            to_ply: options.to_ply,
+            directory,
+            n_meshes: 0_usize,
+            indent: 0_usize,
+            failed: false,
+        })
+    }
+    fn line(&mut self, indent: usize, text: &str) {
+        if let Err(e) = writeln!(self.out, "{:indent$}{}", "", text, indent = indent) {
+            if !self.failed {
+                println!("ERROR: Couldn't write scene ({})", e);
+                self.failed = true;
+            }
+        }
+    }
+    /// A statement without parameters (e.g. "Translate 1 2 3").
+    pub fn statement(&mut self, text: &str) {
+        self.line(self.indent, text);
+    }
+    /// A statement which opens a block (e.g. "AttributeBegin").
+    pub fn begin(&mut self, text: &str) {
+        self.statement(text);
+        self.indent += INDENT;
+    }
+    /// A statement which closes a block (e.g. "AttributeEnd").
+    pub fn end(&mut self, text: &str) {
+        self.indent = self.indent.saturating_sub(INDENT);
+        self.statement(text);
+    }
+    /// "Transform" or "ConcatTransform" with the matrix in the order
+    /// of the scene file.
+    pub fn transform(&mut self, directive: &str, tr: &Transform) {
+        let mut values: Vec<String> = Vec::with_capacity(16);
+        for j in 0..4 {
+            for i in 0..4 {
+                values.push(format!("{}", tr.m.m[i][j]));
+            }
+        }
+        self.statement(&format!("{} [ {} ]", directive, values.join(" ")));
+    }
+    /// A directive with the name and parameters of *params* (e.g.
+    /// `Camera "perspective"`).
+    pub fn named(&mut self, directive: &str, params: &ParamSet) {
+        self.statement(&format!("{} \"{}\"", directive, params.name));
+        self.params(params, &[]);
+    }
+    /// `Texture "name" "type" "class"` and its parameters.
+    pub fn texture(&mut self, params: &ParamSet) {
+        self.statement(&format!(
+            "Texture \"{}\" \"{}\" \"{}\"",
+            params.name, params.tex_type, params.tex_name
+        ));
+        self.params(params, &[]);
+    }
+    /// A shape, triangle meshes go to PLY files (if requested).
+    pub fn shape(&mut self, params: &ParamSet) {
+        if self.to_ply && params.name == "trianglemesh" {
+            match self.write_ply_mesh(params) {
+                Ok(Some(filename)) => {
+                    self.statement("Shape \"plymesh\"");
+                    let indent: usize = self.indent + INDENT;
+                    self.line(indent, &format!("\"string filename\" [ \"{}\" ]", filename));
+                    self.params(params, PLY_PARAMS);
+                    return;
+                }
+                Ok(None) => {}
+                Err(e) => println!(
+                    "ERROR: Couldn't write PLY file ({}), keeping mesh inline",
+                    e
+                ),
+            }
+        }
+        self.named("Shape", params);
+    }
+    /// Ends the scene description.
+    pub fn world_end(&mut self) {
+        self.indent = 0_usize;
+        self.statement("WorldEnd");
+        if let Err(e) = self.out.flush() {
+            println!("ERROR: Couldn't write scene ({})", e);
+        }
+    }
+    fn values<T>(
+        &mut self,
+        param_type: &str,
+        item: &ParamSetItem<T>,
+        format: impl Fn(&T) -> String,
+    ) {
+        let values: Vec<String> = item.values.iter().map(format).collect();
+        self.declaration(param_type, &item.name, values);
+    }
+    fn declaration(&mut self, param_type: &str, name: &str, values: Vec<String>) {
+        let indent: usize = self.indent + INDENT;
+        let declaration: String = format!("\"{} {}\"", param_type, name);
+        if values.len() <= VALUES_PER_LINE {
+            self.line(indent, &format!("{} [ {} ]", declaration, values.join(" ")));
+        } else {
+            self.line(indent, &format!("{} [", declaration));
+            for chunk in values.chunks(VALUES_PER_LINE) {
+                self.line(indent + INDENT, &chunk.join(" "));
+            }
+            self.line(indent, "]");
+        }
+    }
+    /// Writes all parameters, except the ones named in *skip*.
+    fn params(&mut self, params: &ParamSet, skip: &[&str]) {
+        let keep = |name: &String| -> bool { !skip.contains(&name.as_str()) };
+        for item in params.bools.iter().filter(|item| keep(&item.name)) {
+            // the parser expects quoted booleans
+            self.values("bool", item, |b| format!("\"{}\"", b));
+        }
+        for item in params.ints.iter().filter(|item| keep(&item.name)) {
+            self.values("integer", item, |i| format!("{}", i));
+        }
+        for item in params.floats.iter().filter(|item| keep(&item.name)) {
+            self.values("float", item, |f| format!("{}", f));
+        }
+        for item in params.point2fs.iter().filter(|item| keep(&item.name)) {
+            self.values("point2", item, |p| format!("{} {}", p.x, p.y));
+        }
+        for item in params.vector2fs.iter().filter(|item| keep(&item.name)) {
+            self.values("vector2", item, |v| format!("{} {}", v.x, v.y));
+        }
+        for item in params.point3fs.iter().filter(|item| keep(&item.name)) {
+            self.values("point", item, |p| format!("{} {} {}", p.x, p.y, p.z));
+        }
+        for item in params.vector3fs.iter().filter(|item| keep(&item.name)) {
+            self.values("vector", item, |v| format!("{} {} {}", v.x, v.y, v.z));
+        }
+        for item in params.normals.iter().filter(|item| keep(&item.name)) {
+            self.values("normal", item, |n| format!("{} {} {}", n.x, n.y, n.z));
+        }
+        for item in params.spectra.iter().filter(|item| keep(&item.name)) {
+            // write spectra back the way they were declared
+            if let Some(declaration) = params
+                .spectrum_declarations
+                .iter()
+                .find(|d| d.name == item.name)
+            {
+                let mut values: Vec<String> = declaration
+                    .floats
+                    .iter()
+                    .map(|f| format!("{}", f))
+                    .collect();
+                values.extend(declaration.strings.iter().map(|s| format!("\"{}\"", s)));
+                self.declaration(&declaration.param_type, &item.name, values);
+            } else if let Some(s) = item.values.first() {
+                // otherwise only a single RGB value per parameter can
+                // be read back
+                let mut rgb: [Float; 3] = [0.0 as Float; 3];
+                s.to_rgb(&mut rgb);
+                let indent: usize = self.indent + INDENT;
+                self.line(
+                    indent,
+                    &format!("\"rgb {}\" [ {} {} {} ]", item.name, rgb[0], rgb[1], rgb[2]),
+                );
+            }
+        }
+        for item in params.strings.iter().filter(|item| keep(&item.name)) {
+            self.values("string", item, |s| format!("\"{}\"", s));
+        }
+        for item in params.textures.iter().filter(|item| keep(&item.name)) {
+            self.values("texture", item, |s| format!("\"{}\"", s));
+        }
+    }
+    /// Writes the geometry of a triangle mesh to the next PLY file and
+    /// returns its name, None if the mesh has to stay inline.
+    fn write_ply_mesh(&mut self, params: &ParamSet) -> Result<Option<String>> {
+        let p: &[Point3f] = find(&params.point3fs, "P");
+        let indices: &[i32] = find(&params.ints, "indices");
+        let n: &[Normal3f] = find(&params.normals, "N");
+        let face_indices: &[i32] = find(&params.ints, "faceIndices");
+        let mut uv: Vec<Point2f> = find(&params.point2fs, "uv").to_vec();
+        if uv.is_empty() {
+            uv = find(&params.point2fs, "st").to_vec();
+        }
+        if uv.is_empty() {
+            let mut fuv: &[Float] = find(&params.floats, "uv");
+            if fuv.is_empty() {
+                fuv = find(&params.floats, "st");
+            }
+            uv = fuv
+                .chunks_exact(2)
+                .map(|c| Point2f { x: c[0], y: c[1] })
+                .collect();
+        }
+        let consistent: bool = !p.is_empty()
+            && !indices.is_empty()
+            && indices.chunks_exact(3).remainder().is_empty()
+            && indices.iter().all(|i| *i >= 0 && (*i as usize) < p.len())
+            && (n.is_empty() || n.len() == p.len())
+            && (uv.is_empty() || uv.len() == p.len())
+            && (face_indices.is_empty() || face_indices.len() == indices.len() / 3)
+            && find(&params.vector3fs, "S").is_empty();
+        if !consistent {
+            return Ok(None);
+        }
+        let mut header = Header::new();
+        header.encoding = Encoding::BinaryLittleEndian;
+        let mut vertex_names: Vec<&str> = vec!["x", "y", "z"];
+        if !n.is_empty() {
+            vertex_names.extend_from_slice(&["nx", "ny", "nz"]);
+        }
+        if !uv.is_empty() {
+            vertex_names.extend_from_slice(&["u", "v"]);
+        }
+        let mut vertex_element = ElementDef::new(String::from("vertex"));
+        vertex_element.count = p.len();
+        for name in &vertex_names {
+            vertex_element.properties.add(PropertyDef::new(
+                String::from(*name),
+                PropertyType::Scalar(ScalarType::Float),
+            ));
+        }
+        header.elements.add(vertex_element);
+        let mut face_element = ElementDef::new(String::from("face"));
+        face_element.count = indices.len() / 3;
+        face_element.properties.add(PropertyDef::new(
+            String::from("vertex_indices"),
+            PropertyType::List(ScalarType::UChar, ScalarType::Int),
+        ));
+        if !face_indices.is_empty() {
+            face_element.properties.add(PropertyDef::new(
+                String::from("face_indices"),
+                PropertyType::Scalar(ScalarType::Int),
+            ));
+        }
+        header.elements.add(face_element);
+        self.n_meshes += 1;
+        let filename: String = format!("mesh_{:05}.ply", self.n_meshes);
+        let mut file = BufWriter::new(File::create(self.directory.join(&filename))?);
+        Writer::<DefaultElement>::new().write_header(&mut file, &header)?;
+        // ply-rs (0.1.3) writes the number of faces as length of each
+        // binary list, so the payload gets written here
+        for (i, pi) in p.iter().enumerate() {
+            let mut values: Vec<Float> = vec![pi.x, pi.y, pi.z];
+            if !n.is_empty() {
+                values.extend_from_slice(&[n[i].x, n[i].y, n[i].z]);
+            }
+            if !uv.is_empty() {
+                values.extend_from_slice(&[uv[i].x, uv[i].y]);
+            }
+            for v in values {
+                file.write_f32::<LittleEndian>(v)?;
+            }
+        }
+        for (f, triangle) in indices.chunks_exact(3).enumerate() {
+            file.write_u8(3_u8)?;
+            for vi in triangle {
+                file.write_i32::<LittleEndian>(*vi)?;
+            }
+            if !face_indices.is_empty() {
+                file.write_i32::<LittleEndian>(face_indices[f])?;
+            }
+        }
+        file.flush()?;
+        Ok(Some(filename))
+    }
+}
+
+/// Values of the parameter *name* (empty if there is none).
+fn find<'a, T>(items: &'a [ParamSetItem<T>], name: &str) -> &'a [T] {
+    match items.iter().find(|item| item.name == name) {
+        Some(item) => &item.values,
+        None => &[],
+    }
+}
@@ -1,6 +1,12 @@
 //! The **Spectrum** type hides the details of the particular spectral
 //! representation used, so that changing this detail of the system
 //! only requires changing the **Spectrum** implementation.
+//!
+//! By default **Spectrum** is an **RGBSpectrum**. Compiling with the
+//! `sampled_spectrum` feature (`cargo build --features
+//! sampled_spectrum`) switches to a **SampledSpectrum** with 60
+//! samples between 400 and 700 nm, which gets converted to XYZ when
+//! samples are added to the film.
 
 // std
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
@@ -1532,6 +1538,8 @@ pub struct RGBSpectrum {
 }
 
 impl RGBSpectrum {
+    /// Number of spectral samples (Spectrum::nSamples in C++).
+    pub const N_SAMPLES: usize = 3;
     pub fn new(v: Float) -> Self {
         // let n_spectrum_samples = 3; // RGB
         RGBSpectrum { c: [v, v, v] }
@@ -1565,6 +1573,11 @@ impl RGBSpectrum {
         // TODO: DCHECK(!s.HasNaNs());
         s
     }
+    /// RGB values don't depend on the spectrum type (see
+    /// **SampledSpectrum::from_rgb_typed()**).
+    pub fn from_rgb_typed(rgb: &[Float; 3], _spectrum_type: SpectrumType) -> RGBSpectrum {
+        RGBSpectrum::from_rgb(rgb)
+    }
     pub fn to_rgb(&self, rgb: &mut [Float; 3]) {
         rgb[0] = self.c[0];
         rgb[1] = self.c[1];
@@ -1605,6 +1618,11 @@ impl RGBSpectrum {
         xyz[2] *= scale;
         RGBSpectrum::from_xyz(&xyz, SpectrumType::Reflectance)
     }
+    /// Wavelength representing the $i$th channel (dominant wavelengths
+    /// of the sRGB primaries).
+    pub fn wavelength(i: usize) -> Float {
+        [611.3 as Float, 549.1 as Float, 464.3 as Float][i]
+    }
     // from CoefficientSpectrum
     pub fn is_black(&self) -> bool {
         for i in 0..3 {
@@ -1818,6 +1836,508 @@ impl From<Float> for RGBSpectrum {
     }
 }
 
+impl Index<usize> for RGBSpectrum {
+    type Output = Float;
+    fn index(&self, index: usize) -> &Float {
+        &self.c[index]
+    }
+}
+
+impl IndexMut<usize> for RGBSpectrum {
+    fn index_mut(&mut self, index: usize) -> &mut Float {
+        &mut self.c[index]
+    }
+}
+
+pub const SAMPLED_LAMBDA_START: Float = 400.0;
+pub const SAMPLED_LAMBDA_END: Float = 700.0;
+pub const N_SPECTRAL_SAMPLES: usize = 60;
+/// Scale applied by **SampledSpectrum::from_rgb_typed()** to
+/// reflectances (same value as in pbrt-v3).
+const RGB_REFL_TO_SPECT_SCALE: Float = 0.94;
+/// Scale applied by **SampledSpectrum::from_rgb_typed()** to
+/// illuminants (same value as in pbrt-v3).
+const RGB_ILLUM_TO_SPECT_SCALE: Float = 0.86445;
+
+lazy_static::lazy_static! {
+    /// CIE matching functions averaged over the wavelength ranges of
+    /// the _SampledSpectrum_ samples.
+    static ref SAMPLED_XYZ: [SampledSpectrum; 3] = {
+        [
+            SampledSpectrum::from_sampled(&CIE_LAMBDA, &CIE_X, N_CIE_SAMPLES as i32),
+            SampledSpectrum::from_sampled(&CIE_LAMBDA, &CIE_Y, N_CIE_SAMPLES as i32),
+            SampledSpectrum::from_sampled(&CIE_LAMBDA, &CIE_Z, N_CIE_SAMPLES as i32),
+        ]
+    };
+    /// White, cyan, magenta, yellow, red, green and blue spectra used
+    /// to convert RGB illuminants into a _SampledSpectrum_.
+    static ref RGB_ILLUM_TO_SPECTRUM_BASIS: [SampledSpectrum; 7] =
+        compute_rgb_to_spectrum_basis(&SampledSpectrum::new(1.0 as Float), RGB_ILLUM_TO_SPECT_SCALE);
+    /// White, cyan, magenta, yellow, red, green and blue spectra used
+    /// to convert RGB reflectances into a _SampledSpectrum_. They are
+    /// computed under the illuminant white, so that a white
+    /// reflectance is constant over all wavelengths.
+    static ref RGB_REFL_TO_SPECTRUM_BASIS: [SampledSpectrum; 7] = compute_rgb_to_spectrum_basis(
+        &(RGB_ILLUM_TO_SPECTRUM_BASIS[RGBBasis::White as usize] * RGB_ILLUM_TO_SPECT_SCALE),
+        RGB_REFL_TO_SPECT_SCALE,
+    );
+}
+
+/// Index into **RGB_REFL_TO_SPECTRUM_BASIS** and
+/// **RGB_ILLUM_TO_SPECTRUM_BASIS**.
+#[derive(Debug, Copy, Clone)]
+#[repr(u8)]
+enum RGBBasis {
+    White = 0,
+    Cyan = 1,
+    Magenta = 2,
+    Yellow = 3,
+    Red = 4,
+    Green = 5,
+    Blue = 6,
+}
+
+/// Represents a spectral power distribution by point samples taken
+/// at regular intervals between **SAMPLED_LAMBDA_START** and
+/// **SAMPLED_LAMBDA_END**. It replaces _RGBSpectrum_ as **Spectrum**
+/// if the crate gets compiled with the `sampled_spectrum` feature.
+#[derive(Debug, Copy, Clone)]
+pub struct SampledSpectrum {
+    pub c: [Float; N_SPECTRAL_SAMPLES],
+}
+
+impl Default for SampledSpectrum {
+    fn default() -> Self {
+        SampledSpectrum::new(0.0 as Float)
+    }
+}
+
+impl SampledSpectrum {
+    /// Number of spectral samples (Spectrum::nSamples in C++).
+    pub const N_SAMPLES: usize = N_SPECTRAL_SAMPLES;
+    pub fn new(v: Float) -> Self {
+        SampledSpectrum {
+            c: [v; N_SPECTRAL_SAMPLES],
+        }
+    }
+    pub fn rgb(r: Float, g: Float, b: Float) -> SampledSpectrum {
+        SampledSpectrum::from_rgb(&[r, g, b])
+    }
+    pub fn from_srgb(rgb: [u8; 3]) -> SampledSpectrum {
+        SampledSpectrum::from(RGBSpectrum::from_srgb(rgb))
+    }
+    pub fn inverse_gamma_correct(&self) -> SampledSpectrum {
+        let mut rgb: [Float; 3] = [0.0 as Float; 3];
+        self.to_rgb(&mut rgb);
+        SampledSpectrum::from(RGBSpectrum::from_rgb(&rgb).inverse_gamma_correct())
+    }
+    /// Converts RGB reflectances into a spectrum (see
+    /// **SampledSpectrum::from_rgb_typed()**).
+    pub fn from_rgb(rgb: &[Float; 3]) -> SampledSpectrum {
+        SampledSpectrum::from_rgb_typed(rgb, SpectrumType::Reflectance)
+    }
+    /// Converts RGB values into a smooth spectrum by adding up the
+    /// white, cyan, magenta, yellow, red, green and blue basis spectra
+    /// (see Smits, "An RGB-to-Spectrum Conversion for Reflectances").
+    /// Reflectances and illuminants use different bases.
+    pub fn from_rgb_typed(rgb: &[Float; 3], spectrum_type: SpectrumType) -> SampledSpectrum {
+        let (bases, scale): (&[SampledSpectrum; 7], Float) = match spectrum_type {
+            SpectrumType::Reflectance => (&RGB_REFL_TO_SPECTRUM_BASIS, RGB_REFL_TO_SPECT_SCALE),
+            SpectrumType::Illuminant => (&RGB_ILLUM_TO_SPECTRUM_BASIS, RGB_ILLUM_TO_SPECT_SCALE),
+        };
+        let basis = |b: RGBBasis| -> SampledSpectrum { bases[b as usize] };
+        let mut r: SampledSpectrum = SampledSpectrum::new(0.0 as Float);
+        if rgb[0] <= rgb[1] && rgb[0] <= rgb[2] {
+            // compute spectrum with _rgb[0]_ as minimum
+            r += basis(RGBBasis::White) * rgb[0];
+            if rgb[1] <= rgb[2] {
+                r += basis(RGBBasis::Cyan) * (rgb[1] - rgb[0]);
+                r += basis(RGBBasis::Blue) * (rgb[2] - rgb[1]);
+            } else {
+                r += basis(RGBBasis::Cyan) * (rgb[2] - rgb[0]);
+                r += basis(RGBBasis::Green) * (rgb[1] - rgb[2]);
+            }
+        } else if rgb[1] <= rgb[0] && rgb[1] <= rgb[2] {
+            // compute spectrum with _rgb[1]_ as minimum
+            r += basis(RGBBasis::White) * rgb[1];
+            if rgb[0] <= rgb[2] {
+                r += basis(RGBBasis::Magenta) * (rgb[0] - rgb[1]);
+                r += basis(RGBBasis::Blue) * (rgb[2] - rgb[0]);
+            } else {
+                r += basis(RGBBasis::Magenta) * (rgb[2] - rgb[1]);
+                r += basis(RGBBasis::Red) * (rgb[0] - rgb[2]);
+            }
+        } else {
+            // compute spectrum with _rgb[2]_ as minimum
+            r += basis(RGBBasis::White) * rgb[2];
+            if rgb[0] <= rgb[1] {
+                r += basis(RGBBasis::Yellow) * (rgb[0] - rgb[2]);
+                r += basis(RGBBasis::Green) * (rgb[1] - rgb[0]);
+            } else {
+                r += basis(RGBBasis::Yellow) * (rgb[1] - rgb[2]);
+                r += basis(RGBBasis::Red) * (rgb[0] - rgb[1]);
+            }
+        }
+        r = r * scale;
+        r.clamp(0.0 as Float, Float::INFINITY)
+    }
+    pub fn to_rgb(&self, rgb: &mut [Float; 3]) {
+        let mut xyz: [Float; 3] = [0.0 as Float; 3];
+        self.to_xyz(&mut xyz);
+        xyz_to_rgb(&xyz, rgb);
+    }
+    pub fn to_xyz(&self, xyz: &mut [Float; 3]) {
+        let scale: Float = (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START)
+            / (CIE_Y_INTEGRAL * N_SPECTRAL_SAMPLES as Float);
+        for (k, item) in xyz.iter_mut().enumerate() {
+            let mut sum: Float = 0.0 as Float;
+            for i in 0..N_SPECTRAL_SAMPLES {
+                sum += SAMPLED_XYZ[k].c[i] * self.c[i];
+            }
+            *item = sum * scale;
+        }
+    }
+    pub fn from_xyz(xyz: &[Float; 3], spectrum_type: SpectrumType) -> SampledSpectrum {
+        let mut rgb: [Float; 3] = [0.0 as Float; 3];
+        xyz_to_rgb(xyz, &mut rgb);
+        SampledSpectrum::from_rgb_typed(&rgb, spectrum_type)
+    }
+    pub fn y(&self) -> Float {
+        let mut yy: Float = 0.0 as Float;
+        for i in 0..N_SPECTRAL_SAMPLES {
+            yy += SAMPLED_XYZ[1].c[i] * self.c[i];
+        }
+        yy * (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START)
+            / (CIE_Y_INTEGRAL * N_SPECTRAL_SAMPLES as Float)
+    }
+    pub fn from_sampled(lambda: &[Float], v: &[Float], n: i32) -> SampledSpectrum {
+        // sort samples if unordered, use sorted for returned spectrum
+        if !spectrum_samples_sorted(lambda, v, n) {
+            let mut samples: Vec<(Float, Float)> = lambda
+                .iter()
+                .zip(v.iter())
+                .take(n as usize)
+                .map(|(l, v)| (*l, *v))
+                .collect();
+            samples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            let slambda: Vec<Float> = samples.iter().map(|s| s.0).collect();
+            let sv: Vec<Float> = samples.iter().map(|s| s.1).collect();
+            return SampledSpectrum::from_sampled(&slambda, &sv, n);
+        }
+        let mut r: SampledSpectrum = SampledSpectrum::new(0.0 as Float);
+        for i in 0..N_SPECTRAL_SAMPLES {
+            // compute average value of given SPD over $i$th sample's range
+            let lambda0: Float = lerp(
+                i as Float / N_SPECTRAL_SAMPLES as Float,
+                SAMPLED_LAMBDA_START,
+                SAMPLED_LAMBDA_END,
+            );
+            let lambda1: Float = lerp(
+                (i + 1) as Float / N_SPECTRAL_SAMPLES as Float,
+                SAMPLED_LAMBDA_START,
+                SAMPLED_LAMBDA_END,
+            );
+            r.c[i] = average_spectrum_samples(lambda, v, n, lambda0, lambda1);
+        }
+        r
+    }
+    /// Wavelength in the middle of the $i$th sample's range.
+    pub fn wavelength(i: usize) -> Float {
+        lerp(
+            (i as Float + 0.5 as Float) / N_SPECTRAL_SAMPLES as Float,
+            SAMPLED_LAMBDA_START,
+            SAMPLED_LAMBDA_END,
+        )
+    }
+    // from CoefficientSpectrum
+    pub fn is_black(&self) -> bool {
+        self.c.iter().all(|v| *v == 0.0 as Float)
+    }
+    pub fn sqrt(&self) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = *self;
+        ret.c.iter_mut().for_each(|v| *v = v.sqrt());
+        ret
+    }
+    pub fn exp(&self) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = *self;
+        ret.c.iter_mut().for_each(|v| *v = v.exp());
+        ret
+    }
+    /// Clamp spectrum to lie between the values low and high. Use
+    /// (0.0 as Float, std::f32::INFINITY as Float) if there are no
+    /// specific values.
+    pub fn clamp(&self, low: Float, high: Float) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = *self;
+        ret.c.iter_mut().for_each(|v| *v = clamp_t(*v, low, high));
+        assert!(!ret.has_nans());
+        ret
+    }
+    pub fn max_component_value(&self) -> Float {
+        self.c.iter().fold(self.c[0], |m, v| m.max(*v))
+    }
+    pub fn has_nans(&self) -> bool {
+        self.c.iter().any(|v| v.is_nan())
+    }
+}
+
+impl PartialEq for SampledSpectrum {
+    fn eq(&self, rhs: &SampledSpectrum) -> bool {
+        self.c[..] == rhs.c[..]
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn add(self, rhs: SampledSpectrum) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = self;
+        ret += rhs;
+        ret
+    }
+}
+
+impl AddAssign for SampledSpectrum {
+    fn add_assign(&mut self, rhs: SampledSpectrum) {
+        for i in 0..N_SPECTRAL_SAMPLES {
+            self.c[i] += rhs.c[i];
+        }
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn mul(self, rhs: SampledSpectrum) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = self;
+        ret *= rhs;
+        ret
+    }
+}
+
+impl Mul<Float> for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn mul(self, rhs: Float) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = self;
+        ret.c.iter_mut().for_each(|v| *v *= rhs);
+        ret
+    }
+}
+
+impl Mul<SampledSpectrum> for Float {
+    type Output = SampledSpectrum;
+    fn mul(self, rhs: SampledSpectrum) -> SampledSpectrum {
+        rhs * self
+    }
+}
+
+impl MulAssign for SampledSpectrum {
+    fn mul_assign(&mut self, rhs: SampledSpectrum) {
+        for i in 0..N_SPECTRAL_SAMPLES {
+            self.c[i] *= rhs.c[i];
+        }
+    }
+}
+
+impl Sub for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn sub(self, rhs: SampledSpectrum) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = self;
+        for i in 0..N_SPECTRAL_SAMPLES {
+            ret.c[i] -= rhs.c[i];
+        }
+        ret
+    }
+}
+
+impl Div for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn div(self, rhs: SampledSpectrum) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = self;
+        for i in 0..N_SPECTRAL_SAMPLES {
+            ret.c[i] /= rhs.c[i];
+        }
+        ret
+    }
+}
+
+impl Div<Float> for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn div(self, rhs: Float) -> SampledSpectrum {
+        let mut ret: SampledSpectrum = self;
+        ret /= rhs;
+        assert!(!ret.has_nans());
+        ret
+    }
+}
+
+impl DivAssign<Float> for SampledSpectrum {
+    fn div_assign(&mut self, rhs: Float) {
+        assert_ne!(rhs, 0.0 as Float);
+        assert!(!rhs.is_nan());
+        self.c.iter_mut().for_each(|v| *v /= rhs);
+    }
+}
+
+impl Neg for SampledSpectrum {
+    type Output = SampledSpectrum;
+    fn neg(self) -> SampledSpectrum {
+        self * -1.0 as Float
+    }
+}
+
+impl Zero for SampledSpectrum {
+    fn zero() -> SampledSpectrum {
+        SampledSpectrum::new(0.0 as Float)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.is_black()
+    }
+}
+
+impl Index<usize> for SampledSpectrum {
+    type Output = Float;
+    fn index(&self, index: usize) -> &Float {
+        &self.c[index]
+    }
+}
+
+impl IndexMut<usize> for SampledSpectrum {
+    fn index_mut(&mut self, index: usize) -> &mut Float {
+        &mut self.c[index]
+    }
+}
+
+impl From<Float> for SampledSpectrum {
+    fn from(f: Float) -> Self {
+        SampledSpectrum::new(f)
+    }
+}
+
+impl From<RGBSpectrum> for SampledSpectrum {
+    fn from(rgb: RGBSpectrum) -> Self {
+        SampledSpectrum::from_rgb(&rgb.c)
+    }
+}
+
+/// Computes the basis spectra for **SampledSpectrum::from_rgb_typed()**.
+/// Each one is the smoothest non-negative spectrum (smallest sum of
+/// squared differences between neighbouring samples) which, lit by
+/// _illuminant_, maps exactly onto the requested RGB value divided by
+/// _rgb_scale_. Fixing negative samples to zero and solving again is
+/// repeated until all samples are valid.
+fn compute_rgb_to_spectrum_basis(
+    illuminant: &SampledSpectrum,
+    rgb_scale: Float,
+) -> [SampledSpectrum; 7] {
+    let n: usize = N_SPECTRAL_SAMPLES;
+    // linear map from spectral samples to RGB (see to_xyz())
+    let scale: Float = (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START)
+        / (CIE_Y_INTEGRAL * N_SPECTRAL_SAMPLES as Float);
+    let a: Vec<[f64; 3]> = (0..n)
+        .map(|i| {
+            let xyz: [Float; 3] = [
+                SAMPLED_XYZ[0].c[i] * scale,
+                SAMPLED_XYZ[1].c[i] * scale,
+                SAMPLED_XYZ[2].c[i] * scale,
+            ];
+            let mut rgb: [Float; 3] = [0.0 as Float; 3];
+            xyz_to_rgb(&xyz, &mut rgb);
+            let e: f64 = illuminant.c[i] as f64;
+            [rgb[0] as f64 * e, rgb[1] as f64 * e, rgb[2] as f64 * e]
+        })
+        .collect();
+    let targets: [[f64; 3]; 7] = [
+        [1.0, 1.0, 1.0], // white
+        [0.0, 1.0, 1.0], // cyan
+        [1.0, 0.0, 1.0], // magenta
+        [1.0, 1.0, 0.0], // yellow
+        [1.0, 0.0, 0.0], // red
+        [0.0, 1.0, 0.0], // green
+        [0.0, 0.0, 1.0], // blue
+    ];
+    let mut basis: [SampledSpectrum; 7] = [SampledSpectrum::default(); 7];
+    for (b, target) in targets.iter().enumerate() {
+        let mut fixed: Vec<bool> = vec![false; n];
+        let mut s: Vec<f64> = vec![0.0; n];
+        for _iteration in 0..n {
+            // solve the KKT system for the free samples and the three
+            // Lagrange multipliers
+            let free: Vec<usize> = (0..n).filter(|i| !fixed[*i]).collect();
+            let m: usize = free.len() + 3;
+            let mut kkt: Vec<Vec<f64>> = vec![vec![0.0; m + 1]; m];
+            for (r, i) in free.iter().enumerate() {
+                for (c, j) in free.iter().enumerate() {
+                    // $2 D^T D$ of the first differences (plus a small
+                    // ridge to keep the system well conditioned)
+                    let mut h: f64 = 0.0;
+                    if i == j {
+                        h = if *i == 0 || *i == n - 1 { 1.0 } else { 2.0 };
+                        h += 1.0e-6;
+                    } else if (*i as i64 - *j as i64).abs() == 1 {
+                        h = -1.0;
+                    }
+                    kkt[r][c] = 2.0 * h;
+                }
+                for k in 0..3 {
+                    kkt[r][free.len() + k] = a[*i][k];
+                    kkt[free.len() + k][r] = a[*i][k];
+                }
+            }
+            for k in 0..3 {
+                kkt[free.len() + k][m] = target[k] / rgb_scale as f64;
+            }
+            let x: Vec<f64> = solve_linear_system(kkt);
+            s.iter_mut().for_each(|v| *v = 0.0);
+            for (r, i) in free.iter().enumerate() {
+                s[*i] = x[r];
+            }
+            let mut done: bool = true;
+            for i in free.iter() {
+                if s[*i] < -1.0e-9 {
+                    fixed[*i] = true;
+                    done = false;
+                }
+            }
+            if done {
+                break;
+            }
+        }
+        for (v, si) in basis[b].c.iter_mut().zip(s.iter()) {
+            *v = si.max(0.0) as Float;
+        }
+    }
+    basis
+}
+
+/// Solves a linear system given as augmented matrix by Gaussian
+/// elimination with partial pivoting.
+fn solve_linear_system(mut m: Vec<Vec<f64>>) -> Vec<f64> {
+    let n: usize = m.len();
+    for col in 0..n {
+        let pivot: usize = (col..n)
+            .max_by(|a, b| m[*a][col].abs().partial_cmp(&m[*b][col].abs()).unwrap())
+            .unwrap();
+        m.swap(col, pivot);
+        let pivot_row: Vec<f64> = m[col].clone();
+        for row in m.iter_mut().skip(col + 1) {
+            let f: f64 = row[col] / pivot_row[col];
+            if f != 0.0 {
+                for (v, p) in row.iter_mut().zip(pivot_row.iter()).skip(col) {
+                    *v -= f * p;
+                }
+            }
+        }
+    }
+    let mut x: Vec<f64> = vec![0.0; n];
+    for row in (0..n).rev() {
+        let mut sum: f64 = m[row][n];
+        for k in (row + 1)..n {
+            sum -= m[row][k] * x[k];
+        }
+        x[row] = sum / m[row][row];
+    }
+    x
+}
+
 /// Calculate RGB coefficients from a XYZ representation.
 pub fn xyz_to_rgb(xyz: &[Float; 3], rgb: &mut [Float; 3]) {
     rgb[0] = 3.240_479 * xyz[0] - 1.537_150 * xyz[1] - 0.498_535 * xyz[2];
@@ -1862,6 +2382,58 @@ pub fn interpolate_spectrum_samples(lambda: &[Float], vals: &[Float], n: i32, l:
     lerp(t, vals[offset], vals[offset + 1])
 }
 
+/// Average of the piecewise linear function given by the samples
+/// over the wavelength range [lambda_start, lambda_end].
+pub fn average_spectrum_samples(
+    lambda: &[Float],
+    vals: &[Float],
+    n: i32,
+    lambda_start: Float,
+    lambda_end: Float,
+) -> Float {
+    let n: usize = n as usize;
+    // handle cases with out-of-bounds range or single sample only
+    if lambda_end <= lambda[0] {
+        return vals[0];
+    }
+    if lambda_start >= lambda[n - 1] {
+        return vals[n - 1];
+    }
+    if n == 1 {
+        return vals[0];
+    }
+    let mut sum: Float = 0.0;
+    // add contributions of constant segments before/after samples
+    if lambda_start < lambda[0] {
+        sum += vals[0] * (lambda[0] - lambda_start);
+    }
+    if lambda_end > lambda[n - 1] {
+        sum += vals[n - 1] * (lambda_end - lambda[n - 1]);
+    }
+    // advance to first relevant wavelength segment
+    let mut i: usize = 0;
+    while lambda_start > lambda[i + 1] {
+        i += 1;
+    }
+    // loop over wavelength sample segments and add contributions
+    let interp = |w: Float, i: usize| -> Float {
+        lerp(
+            (w - lambda[i]) / (lambda[i + 1] - lambda[i]),
+            vals[i],
+            vals[i + 1],
+        )
+    };
+    while i + 1 < n && lambda_end >= lambda[i] {
+        let seg_lambda_start: Float = lambda_start.max(lambda[i]);
+        let seg_lambda_end: Float = lambda_end.min(lambda[i + 1]);
+        sum += 0.5 as Float
+            * (interp(seg_lambda_start, i) + interp(seg_lambda_end, i))
+            * (seg_lambda_end - seg_lambda_start);
+        i += 1;
+    }
+    sum / (lambda_end - lambda_start)
+}
+
 pub fn inverse_gamma_convert_float(v: Float) -> Float {
     if v <= 0.04045 {
         v / 12.92
@@ -0,0 +1,782 @@
+//! Runtime statistics and profiling, similar to what **pbrt-v3**
+//! reports after rendering.
+//!
+//! Statistics are declared as statics next to the code which
+//! updates them. The title is split at the first `/` into a category
+//! and the name printed within that category:
+//!
+//! ```rust
+//! use rs_pbrt::core::stats::{StatCounter, StatPercent};
+//!
+//! static N_TESTS: StatPercent = StatPercent::new("Intersections/Ray-foo intersection tests");
+//! static N_FOOS: StatCounter = StatCounter::new("Scene/Foos");
+//!
+//! N_FOOS.inc();
+//! N_TESTS.inc_denom();
+//! N_TESTS.inc_num();
+//! ```
+//!
+//! Each thread updates its own copy of the values, which get merged
+//! when the report is printed (at the end of
+//! [Integrator::render()](../integrator/enum.Integrator.html#method.render)).
+//!
+//! A [ProfilePhase](struct.ProfilePhase.html) marks a phase of the
+//! computation for the duration of its lifetime. While rendering, a
+//! background thread samples which phases every thread is in.
+//!
+//! Nothing is gathered (and all calls compile to nothing) unless the
+//! crate is built with the `stats` feature.
+
+// see stats.h
+
+/// Categories for [ProfilePhase](struct.ProfilePhase.html).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prof {
+    SceneConstruction,
+    AccelConstruction,
+    TextureLoading,
+    MipMapCreation,
+    IntegratorRender,
+    SamplerIntegratorLi,
+    SppmCameraPass,
+    SppmGridConstruction,
+    SppmPhotonPass,
+    SppmStatsUpdate,
+    BdptGenerateSubpath,
+    BdptConnectSubpaths,
+    LightDistribLookup,
+    LightDistribSpinWait,
+    LightDistribCreation,
+    DirectLighting,
+    BsdfEvaluation,
+    BsdfSampling,
+    BsdfPdf,
+    BssrdfEvaluation,
+    BssrdfSampling,
+    PhaseFuncEvaluation,
+    PhaseFuncSampling,
+    AccelIntersect,
+    AccelIntersectP,
+    LightSample,
+    LightPdf,
+    MediumSample,
+    MediumTr,
+    TriIntersect,
+    TriIntersectP,
+    CurveIntersect,
+    CurveIntersectP,
+    ShapeIntersect,
+    ShapeIntersectP,
+    ComputeScatteringFuncs,
+    GenerateCameraRay,
+    MergeFilmTile,
+    SplatFilm,
+    AddFilmSample,
+    StartPixel,
+    GetSample,
+    TexFiltTrilerp,
+    TexFiltEwa,
+    TexFiltPtex,
+}
+
+pub const PROF_NAMES: [&str; 45] = [
+    "Scene parsing and creation",
+    "Acceleration structure creation",
+    "Texture loading",
+    "MIP map generation",
+    "Integrator::render()",
+    "SamplerIntegrator::li()",
+    "SPPM camera pass",
+    "SPPM grid construction",
+    "SPPM photon pass",
+    "SPPM statistics update",
+    "BDPT subpath generation",
+    "BDPT subpath connections",
+    "SpatialLightDistribution lookup",
+    "SpatialLightDistribution spin wait",
+    "SpatialLightDistribution creation",
+    "Direct lighting",
+    "Bsdf::f()",
+    "Bsdf::sample_f()",
+    "Bsdf::pdf()",
+    "Bssrdf::s()",
+    "Bssrdf::sample_s()",
+    "PhaseFunction::p()",
+    "PhaseFunction::sample_p()",
+    "Accelerator::intersect()",
+    "Accelerator::intersect_p()",
+    "Light::sample_*()",
+    "Light::pdf_*()",
+    "Medium::sample()",
+    "Medium::tr()",
+    "Triangle::intersect()",
+    "Triangle::intersect_p()",
+    "Curve::intersect()",
+    "Curve::intersect_p()",
+    "Other Shape::intersect()",
+    "Other Shape::intersect_p()",
+    "Material::compute_scattering_functions()",
+    "Camera::generate_ray[_differential]()",
+    "Film::merge_film_tile()",
+    "Film::add_splat()",
+    "FilmTile::add_sample()",
+    "Sampler::start_pixel()",
+    "Sampler::get_[12]d()",
+    "MipMap::lookup() (trilinear)",
+    "MipMap::lookup() (EWA)",
+    "Ptex lookup",
+];
+
+#[cfg(feature = "stats")]
+pub use self::enabled::*;
+
+#[cfg(not(feature = "stats"))]
+pub use self::disabled::*;
+
+#[cfg(feature = "stats")]
+mod enabled {
+    // std
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    // pbrt
+    use super::{Prof, PROF_NAMES};
+
+    const MAX_STAT_SLOTS: usize = 1024;
+    const PROFILE_INTERVAL: Duration = Duration::from_millis(2);
+
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    enum StatKind {
+        Counter,
+        MemoryCounter,
+        IntDistribution,
+        FloatDistribution,
+        Percent,
+        Ratio,
+    }
+
+    impl StatKind {
+        fn n_slots(self) -> usize {
+            match self {
+                StatKind::Counter | StatKind::MemoryCounter => 1,
+                StatKind::Percent | StatKind::Ratio => 2,
+                // sum, count, min, max
+                StatKind::IntDistribution | StatKind::FloatDistribution => 4,
+            }
+        }
+    }
+
+    struct StatInfo {
+        kind: StatKind,
+        title: &'static str,
+        base: usize,
+    }
+
+    /// Values of all statistics (and the current profiling phases)
+    /// of one thread. Only the owning thread writes to them, so
+    /// relaxed loads and stores are sufficient.
+    struct ThreadStats {
+        slots: Vec<AtomicU64>,
+        profiler_state: AtomicU64,
+    }
+
+    struct Profiler {
+        running: Arc<AtomicBool>,
+        start: Instant,
+        handle: thread::JoinHandle<HashMap<u64, u64>>,
+    }
+
+    lazy_static::lazy_static! {
+        static ref STAT_INFOS: Mutex<Vec<StatInfo>> = Mutex::new(Vec::new());
+        static ref THREAD_STATS: Mutex<Vec<Arc<ThreadStats>>> = Mutex::new(Vec::new());
+        static ref PROFILER: Mutex<Option<Profiler>> = Mutex::new(None);
+    }
+
+    thread_local! {
+        static LOCAL_STATS: Arc<ThreadStats> = {
+            let mut slots: Vec<AtomicU64> = Vec::with_capacity(MAX_STAT_SLOTS);
+            slots.resize_with(MAX_STAT_SLOTS, || AtomicU64::new(0));
+            let stats: Arc<ThreadStats> = Arc::new(ThreadStats {
+                slots,
+                profiler_state: AtomicU64::new(0),
+            });
+            THREAD_STATS.lock().unwrap().push(stats.clone());
+            stats
+        };
+    }
+
+    struct Stat {
+        kind: StatKind,
+        title: &'static str,
+        // first slot plus one, zero until registered
+        base: AtomicUsize,
+    }
+
+    impl Stat {
+        const fn new(kind: StatKind, title: &'static str) -> Self {
+            Stat {
+                kind,
+                title,
+                base: AtomicUsize::new(0),
+            }
+        }
+        #[inline]
+        fn with_slots<F: FnOnce(&[AtomicU64])>(&self, f: F) {
+            let mut base: usize = self.base.load(Ordering::Acquire);
+            if base == 0 {
+                base = self.register();
+            }
+            let n_slots: usize = self.kind.n_slots();
+            // the thread local might be gone already (thread exit)
+            let _ = LOCAL_STATS.try_with(|stats| f(&stats.slots[base - 1..base - 1 + n_slots]));
+        }
+        #[cold]
+        fn register(&self) -> usize {
+            let mut infos = STAT_INFOS.lock().unwrap();
+            let base: usize = self.base.load(Ordering::Acquire);
+            if base != 0 {
+                return base;
+            }
+            let first: usize = infos
+                .last()
+                .map_or(0, |info| info.base + info.kind.n_slots());
+            assert!(
+                first + self.kind.n_slots() <= MAX_STAT_SLOTS,
+                "too many statistics, increase MAX_STAT_SLOTS"
+            );
+            infos.push(StatInfo {
+                kind: self.kind,
+                title: self.title,
+                base: first,
+            });
+            self.base.store(first + 1, Ordering::Release);
+            first + 1
+        }
+    }
+
+    #[inline]
+    fn add(slot: &AtomicU64, value: u64) {
+        slot.store(
+            slot.load(Ordering::Relaxed).wrapping_add(value),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub struct StatCounter(Stat);
+
+    impl StatCounter {
+        pub const fn new(title: &'static str) -> Self {
+            StatCounter(Stat::new(StatKind::Counter, title))
+        }
+        #[inline]
+        pub fn inc(&self) {
+            self.add(1);
+        }
+        #[inline]
+        pub fn add(&self, value: u64) {
+            self.0.with_slots(|slots| add(&slots[0], value));
+        }
+    }
+
+    pub struct StatMemoryCounter(Stat);
+
+    impl StatMemoryCounter {
+        pub const fn new(title: &'static str) -> Self {
+            StatMemoryCounter(Stat::new(StatKind::MemoryCounter, title))
+        }
+        #[inline]
+        pub fn add(&self, bytes: usize) {
+            self.0.with_slots(|slots| add(&slots[0], bytes as u64));
+        }
+    }
+
+    pub struct StatIntDistribution(Stat);
+
+    impl StatIntDistribution {
+        pub const fn new(title: &'static str) -> Self {
+            StatIntDistribution(Stat::new(StatKind::IntDistribution, title))
+        }
+        #[inline]
+        pub fn report_value(&self, value: i64) {
+            self.0.with_slots(|slots| {
+                let count: u64 = slots[1].load(Ordering::Relaxed);
+                add(&slots[0], value as u64);
+                slots[1].store(count + 1, Ordering::Relaxed);
+                if count == 0 || value < slots[2].load(Ordering::Relaxed) as i64 {
+                    slots[2].store(value as u64, Ordering::Relaxed);
+                }
+                if count == 0 || value > slots[3].load(Ordering::Relaxed) as i64 {
+                    slots[3].store(value as u64, Ordering::Relaxed);
+                }
+            });
+        }
+    }
+
+    pub struct StatFloatDistribution(Stat);
+
+    impl StatFloatDistribution {
+        pub const fn new(title: &'static str) -> Self {
+            StatFloatDistribution(Stat::new(StatKind::FloatDistribution, title))
+        }
+        #[inline]
+        pub fn report_value(&self, value: f64) {
+            self.0.with_slots(|slots| {
+                let count: u64 = slots[1].load(Ordering::Relaxed);
+                let sum: f64 = f64::from_bits(slots[0].load(Ordering::Relaxed));
+                slots[0].store((sum + value).to_bits(), Ordering::Relaxed);
+                slots[1].store(count + 1, Ordering::Relaxed);
+                if count == 0 || value < f64::from_bits(slots[2].load(Ordering::Relaxed)) {
+                    slots[2].store(value.to_bits(), Ordering::Relaxed);
+                }
+                if count == 0 || value > f64::from_bits(slots[3].load(Ordering::Relaxed)) {
+                    slots[3].store(value.to_bits(), Ordering::Relaxed);
+                }
+            });
+        }
+    }
+
+    pub struct StatPercent(Stat);
+
+    impl StatPercent {
+        pub const fn new(title: &'static str) -> Self {
+            StatPercent(Stat::new(StatKind::Percent, title))
+        }
+        #[inline]
+        pub fn inc_num(&self) {
+            self.0.with_slots(|slots| add(&slots[0], 1));
+        }
+        #[inline]
+        pub fn inc_denom(&self) {
+            self.0.with_slots(|slots| add(&slots[1], 1));
+        }
+    }
+
+    pub struct StatRatio(Stat);
+
+    impl StatRatio {
+        pub const fn new(title: &'static str) -> Self {
+            StatRatio(Stat::new(StatKind::Ratio, title))
+        }
+        #[inline]
+        pub fn add_num(&self, value: u64) {
+            self.0.with_slots(|slots| add(&slots[0], value));
+        }
+        #[inline]
+        pub fn add_denom(&self, value: u64) {
+            self.0.with_slots(|slots| add(&slots[1], value));
+        }
+    }
+
+    /// Marks the current thread as being in the given phase of the
+    /// computation until it gets dropped.
+    pub struct ProfilePhase {
+        category_bit: u64,
+        reset: bool,
+    }
+
+    impl ProfilePhase {
+        #[inline]
+        pub fn new(p: Prof) -> Self {
+            let category_bit: u64 = 1_u64 << (p as u64);
+            let reset: bool = LOCAL_STATS
+                .try_with(|stats| {
+                    let state: u64 = stats.profiler_state.load(Ordering::Relaxed);
+                    if state & category_bit == 0 {
+                        stats
+                            .profiler_state
+                            .store(state | category_bit, Ordering::Relaxed);
+                        true
+                    } else {
+                        false
+                    }
+                })
+                .unwrap_or(false);
+            ProfilePhase {
+                category_bit,
+                reset,
+            }
+        }
+    }
+
+    impl Drop for ProfilePhase {
+        #[inline]
+        fn drop(&mut self) {
+            if self.reset {
+                let category_bit: u64 = self.category_bit;
+                let _ = LOCAL_STATS.try_with(|stats| {
+                    let state: u64 = stats.profiler_state.load(Ordering::Relaxed);
+                    stats
+                        .profiler_state
+                        .store(state & !category_bit, Ordering::Relaxed);
+                });
+            }
+        }
+    }
+
+    /// Merged values of one statistic over all threads.
+    fn merged_values(info: &StatInfo, threads: &[Arc<ThreadStats>]) -> [u64; 4] {
+        let mut values: [u64; 4] = [0_u64; 4];
+        let mut count: u64 = 0;
+        for stats in threads {
+            let slots: &[AtomicU64] = &stats.slots[info.base..info.base + info.kind.n_slots()];
+            match info.kind {
+                StatKind::Counter | StatKind::MemoryCounter => {
+                    values[0] += slots[0].load(Ordering::Relaxed);
+                }
+                StatKind::Percent | StatKind::Ratio => {
+                    values[0] += slots[0].load(Ordering::Relaxed);
+                    values[1] += slots[1].load(Ordering::Relaxed);
+                }
+                StatKind::IntDistribution => {
+                    let n: u64 = slots[1].load(Ordering::Relaxed);
+                    if n == 0 {
+                        continue;
+                    }
+                    let min: i64 = slots[2].load(Ordering::Relaxed) as i64;
+                    let max: i64 = slots[3].load(Ordering::Relaxed) as i64;
+                    values[0] = values[0].wrapping_add(slots[0].load(Ordering::Relaxed));
+                    if count == 0 || min < values[2] as i64 {
+                        values[2] = min as u64;
+                    }
+                    if count == 0 || max > values[3] as i64 {
+                        values[3] = max as u64;
+                    }
+                    count += n;
+                }
+                StatKind::FloatDistribution => {
+                    let n: u64 = slots[1].load(Ordering::Relaxed);
+                    if n == 0 {
+                        continue;
+                    }
+                    let sum: f64 = f64::from_bits(slots[0].load(Ordering::Relaxed));
+                    let min: f64 = f64::from_bits(slots[2].load(Ordering::Relaxed));
+                    let max: f64 = f64::from_bits(slots[3].load(Ordering::Relaxed));
+                    values[0] = (f64::from_bits(values[0]) + sum).to_bits();
+                    if count == 0 || min < f64::from_bits(values[2]) {
+                        values[2] = min.to_bits();
+                    }
+                    if count == 0 || max > f64::from_bits(values[3]) {
+                        values[3] = max.to_bits();
+                    }
+                    count += n;
+                }
+            }
+        }
+        if info.kind == StatKind::IntDistribution || info.kind == StatKind::FloatDistribution {
+            values[1] = count;
+        }
+        values
+    }
+
+    fn format_memory(bytes: u64) -> String {
+        let kb: f64 = bytes as f64 / 1024.0;
+        if kb < 1024.0 {
+            format!("{:9.2} kB", kb)
+        } else if kb < 1024.0 * 1024.0 {
+            format!("{:9.2} MiB", kb / 1024.0)
+        } else {
+            format!("{:9.2} GiB", kb / (1024.0 * 1024.0))
+        }
+    }
+
+    /// Merge the statistics of all threads and print them, grouped
+    /// by category.
+    pub fn print_stats() {
+        let infos = STAT_INFOS.lock().unwrap();
+        let threads = THREAD_STATS.lock().unwrap();
+        let mut to_print: BTreeMap<&str, Vec<(StatKind, &str, String)>> = BTreeMap::new();
+        for info in infos.iter() {
+            let (category, title): (&str, &str) = match info.title.find('/') {
+                Some(index) => (&info.title[..index], &info.title[index + 1..]),
+                None => ("", info.title),
+            };
+            let values: [u64; 4] = merged_values(info, &threads);
+            let line: String = match info.kind {
+                StatKind::Counter => {
+                    if values[0] == 0 {
+                        continue;
+                    }
+                    format!("{:<42}               {:>12}", title, values[0])
+                }
+                StatKind::MemoryCounter => {
+                    if values[0] == 0 {
+                        continue;
+                    }
+                    format!(
+                        "{:<42}                  {}",
+                        title,
+                        format_memory(values[0])
+                    )
+                }
+                StatKind::IntDistribution => {
+                    if values[1] == 0 {
+                        continue;
+                    }
+                    let avg: f64 = values[0] as i64 as f64 / values[1] as f64;
+                    format!(
+                        "{:<42}                      {:.3} avg [range {} - {}]",
+                        title, avg, values[2] as i64, values[3] as i64
+                    )
+                }
+                StatKind::FloatDistribution => {
+                    if values[1] == 0 {
+                        continue;
+                    }
+                    let avg: f64 = f64::from_bits(values[0]) / values[1] as f64;
+                    format!(
+                        "{:<42}                      {:.3} avg [range {:.3} - {:.3}]",
+                        title,
+                        avg,
+                        f64::from_bits(values[2]),
+                        f64::from_bits(values[3])
+                    )
+                }
+                StatKind::Percent => {
+                    if values[1] == 0 {
+                        continue;
+                    }
+                    format!(
+                        "{:<42}{:>12} / {:>12} ({:.2}%)",
+                        title,
+                        values[0],
+                        values[1],
+                        (100.0 * values[0] as f64) / values[1] as f64
+                    )
+                }
+                StatKind::Ratio => {
+                    if values[1] == 0 {
+                        continue;
+                    }
+                    format!(
+                        "{:<42}{:>12} / {:>12} ({:.2}x)",
+                        title,
+                        values[0],
+                        values[1],
+                        values[0] as f64 / values[1] as f64
+                    )
+                }
+            };
+            to_print
+                .entry(category)
+                .or_default()
+                .push((info.kind, title, line));
+        }
+        println!("Statistics:");
+        for (category, lines) in to_print.iter_mut() {
+            println!("  {}", category);
+            lines.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap().then_with(|| a.1.cmp(b.1)));
+            for (_kind, _title, line) in lines.iter() {
+                println!("    {}", line);
+            }
+        }
+    }
+
+    /// Reset the statistics of all threads (and forget about the
+    /// threads which are gone).
+    pub fn clear_stats() {
+        let mut threads = THREAD_STATS.lock().unwrap();
+        threads.retain(|stats| Arc::strong_count(stats) > 1);
+        for stats in threads.iter() {
+            for slot in &stats.slots {
+                slot.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Start a thread which periodically records the phases all
+    /// threads are in.
+    pub fn init_profiler() {
+        let mut profiler = PROFILER.lock().unwrap();
+        if profiler.is_some() {
+            return;
+        }
+        let running: Arc<AtomicBool> = Arc::new(AtomicBool::new(true));
+        let still_running: Arc<AtomicBool> = running.clone();
+        let handle = thread::spawn(move || {
+            let mut samples: HashMap<u64, u64> = HashMap::new();
+            while still_running.load(Ordering::Relaxed) {
+                thread::sleep(PROFILE_INTERVAL);
+                for stats in THREAD_STATS.lock().unwrap().iter() {
+                    let state: u64 = stats.profiler_state.load(Ordering::Relaxed);
+                    if state != 0 {
+                        *samples.entry(state).or_insert(0) += 1;
+                    }
+                }
+            }
+            samples
+        });
+        *profiler = Some(Profiler {
+            running,
+            start: Instant::now(),
+            handle,
+        });
+    }
+
+    fn phase_names(state: u64) -> String {
+        let mut names: Vec<&str> = Vec::new();
+        for (bit, name) in PROF_NAMES.iter().enumerate() {
+            if state & (1_u64 << bit) != 0 {
+                names.push(name);
+            }
+        }
+        names.join(" / ")
+    }
+
+    fn time_string(pct: f64, elapsed: Duration) -> String {
+        let ns: f64 = elapsed.as_nanos() as f64 * pct / 100.0;
+        let seconds: u64 = (ns / 1e9) as u64;
+        let ms: u64 = ((ns / 1e6) as u64) % 1000;
+        format!(
+            "{}:{:02}:{:02}.{:03}",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60,
+            ms
+        )
+    }
+
+    /// Stop the profiler thread and print which share of the samples
+    /// fell into each combination of phases and into each phase.
+    pub fn report_profiler_results() {
+        let profiler: Option<Profiler> = PROFILER.lock().unwrap().take();
+        if let Some(profiler) = profiler {
+            profiler.running.store(false, Ordering::Relaxed);
+            let elapsed: Duration = profiler.start.elapsed();
+            let samples: HashMap<u64, u64> = profiler.handle.join().unwrap_or_default();
+            let overall_count: u64 = samples.values().sum();
+            if overall_count == 0 {
+                return;
+            }
+            let mut states: Vec<(u64, u64)> = samples.into_iter().collect();
+            states.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            println!("  Profile");
+            let mut flat_results: Vec<u64> = vec![0_u64; PROF_NAMES.len()];
+            for (state, count) in &states {
+                let pct: f64 = (100.0 * *count as f64) / overall_count as f64;
+                println!(
+                    "    {:<85} {:5.2}% ({})",
+                    phase_names(*state),
+                    pct,
+                    time_string(pct, elapsed)
+                );
+                for (bit, flat_result) in flat_results.iter_mut().enumerate() {
+                    if state & (1_u64 << bit) != 0 {
+                        *flat_result += count;
+                    }
+                }
+            }
+            // print the totals for each category
+            println!("  Profile (per phase)");
+            let mut flat: Vec<(usize, u64)> = flat_results
+                .into_iter()
+                .enumerate()
+                .filter(|(_, count)| *count > 0)
+                .collect();
+            flat.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+            for (bit, count) in flat {
+                let pct: f64 = (100.0 * count as f64) / overall_count as f64;
+                println!(
+                    "    {:<85} {:5.2}% ({})",
+                    PROF_NAMES[bit],
+                    pct,
+                    time_string(pct, elapsed)
+                );
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "stats"))]
+mod disabled {
+    // pbrt
+    use super::Prof;
+
+    pub struct StatCounter;
+
+    impl StatCounter {
+        pub const fn new(_title: &'static str) -> Self {
+            StatCounter
+        }
+        #[inline(always)]
+        pub fn inc(&self) {}
+        #[inline(always)]
+        pub fn add(&self, _value: u64) {}
+    }
+
+    pub struct StatMemoryCounter;
+
+    impl StatMemoryCounter {
+        pub const fn new(_title: &'static str) -> Self {
+            StatMemoryCounter
+        }
+        #[inline(always)]
+        pub fn add(&self, _bytes: usize) {}
+    }
+
+    pub struct StatIntDistribution;
+
+    impl StatIntDistribution {
+        pub const fn new(_title: &'static str) -> Self {
+            StatIntDistribution
+        }
+        #[inline(always)]
+        pub fn report_value(&self, _value: i64) {}
+    }
+
+    pub struct StatFloatDistribution;
+
+    impl StatFloatDistribution {
+        pub const fn new(_title: &'static str) -> Self {
+            StatFloatDistribution
+        }
+        #[inline(always)]
+        pub fn report_value(&self, _value: f64) {}
+    }
+
+    pub struct StatPercent;
+
+    impl StatPercent {
+        pub const fn new(_title: &'static str) -> Self {
+            StatPercent
+        }
+        #[inline(always)]
+        pub fn inc_num(&self) {}
+        #[inline(always)]
+        pub fn inc_denom(&self) {}
+    }
+
+    pub struct StatRatio;
+
+    impl StatRatio {
+        pub const fn new(_title: &'static str) -> Self {
+            StatRatio
+        }
+        #[inline(always)]
+        pub fn add_num(&self, _value: u64) {}
+        #[inline(always)]
+        pub fn add_denom(&self, _value: u64) {}
+    }
+
+    pub struct ProfilePhase;
+
+    impl ProfilePhase {
+        #[inline(always)]
+        pub fn new(_p: Prof) -> Self {
+            ProfilePhase
+        }
+    }
+
+    #[inline(always)]
+    pub fn print_stats() {}
+
+    #[inline(always)]
+    pub fn clear_stats() {}
+
+    #[inline(always)]
+    pub fn init_profiler() {}
+
+    #[inline(always)]
+    pub fn report_profiler_results() {}
+}
@@ -864,7 +864,7 @@ impl Transform {
         // ret.bssrdf = si.bssrdf.clone();
         ret.primitive = None; // TODO? si.primitive;
         ret.shading.n = nrm_faceforward_nrm(&ret.shading.n, &ret.common.n);
-        // TODO: ret.faceIndex = si.faceIndex;
+        ret.face_index = si.face_index;
         *si = ret;
     }
 }
@@ -13,6 +13,7 @@ use crate::core::sampling::{
     uniform_sample_hemisphere,
 };
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase};
 
 // see ao.h
 
@@ -55,7 +56,7 @@ impl AOIntegrator {
         // arena: &mut Arena,
         _depth: i32,
     ) -> Spectrum {
-        // TODO: ProfilePhase p(Prof::SamplerIntegratorLi);
+        let _prof = ProfilePhase::new(Prof::SamplerIntegratorLi);
         let mut l: Spectrum = Spectrum::default();
         let mut isect: SurfaceInteraction = SurfaceInteraction::default();
         if scene.intersect(ray, &mut isect) {
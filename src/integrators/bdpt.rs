@@ -2,9 +2,11 @@
 use std::cell::Cell;
 use std::f32::consts::PI;
 use std::sync::Arc;
+use std::time::Instant;
 // pbrt
 use crate::blockqueue::BlockQueue;
 use crate::core::camera::{Camera, CameraSample};
+use crate::core::film::{AovSample, ProgressiveParams};
 use crate::core::geometry::{
     nrm_abs_dot_vec3f, pnt2_inside_exclusivei, pnt3_offset_ray_origin, vec3_abs_dot_nrmf,
     vec3_dot_nrmf,
@@ -12,6 +14,8 @@ use crate::core::geometry::{
 use crate::core::geometry::{
     Bounds2i, Bounds3f, Normal3f, Point2f, Point2i, Point3f, Ray, Vector2i, Vector3f,
 };
+use crate::core::integrator::{compute_geometric_aovs, time_is_up};
+use crate::core::integrator::{get_tile_bounds, get_tile_seed};
 use crate::core::interaction::{
     Interaction, InteractionCommon, MediumInteraction, SurfaceInteraction,
 };
@@ -21,14 +25,19 @@ use crate::core::lightdistrib::create_light_sample_distribution;
 use crate::core::material::TransportMode;
 use crate::core::medium::{HenyeyGreenstein, Medium, MediumInterface};
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progress::ProgressReporter;
 use crate::core::reflection::Bsdf;
 use crate::core::reflection::BxdfType;
 use crate::core::sampler::Sampler;
 use crate::core::sampling::Distribution1D;
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase, StatIntDistribution, StatPercent};
 
 // see bdpt.h
 
+static ZERO_RADIANCE_PATHS: StatPercent = StatPercent::new("Integrator/Zero-radiance paths");
+static PATH_LENGTH: StatIntDistribution = StatIntDistribution::new("Integrator/Path length");
+
 #[derive(Default)]
 pub struct EndpointInteraction<'a> {
     // Interaction Public Data
@@ -853,12 +862,26 @@ impl BDPTIntegrator {
         // }
         // partition the image into tiles
         let film = self.get_camera().get_film();
+        if film.checkpoint.is_some() {
+            println!("WARNING: Checkpoints are not supported by the BDPT integrator");
+        }
         let sample_bounds: Bounds2i = film.get_sample_bounds();
         let sample_extent: Vector2i = sample_bounds.diagonal();
         let tile_size: i32 = 16;
         let n_x_tiles: i32 = (sample_extent.x + tile_size - 1) / tile_size;
         let n_y_tiles: i32 = (sample_extent.y + tile_size - 1) / tile_size;
-        // TODO: ProgressReporter reporter(nXTiles * nYTiles, "Rendering");
+        let n_tiles: usize = (n_x_tiles * n_y_tiles) as usize;
+        let deadline: Option<Instant> = film
+            .progressive
+            .and_then(|progressive| progressive.time_limit)
+            .map(|time_limit| Instant::now() + time_limit);
+        let mut some_progressive: Option<ProgressiveParams> = film.progressive;
+        if some_progressive.is_some() && !self.sampler.can_continue_pixels() {
+            println!(
+                "WARNING: Progressive rendering needs the \"halton\", \"random\" or \"sobol\" sampler, taking all samples in a single pass"
+            );
+            some_progressive = None;
+        }
         // TODO: Allocate buffers for debug visualization
         // ...
         // render and write the output image to disk
@@ -870,7 +893,12 @@ impl BDPTIntegrator {
                 num_threads as usize
             };
             println!("Rendering with {:?} thread(s) ...", num_cores);
-            {
+            // number of samples taken so far in each tile
+            let mut tile_samples: Vec<i64> = vec![0_i64; n_tiles];
+            // render all tiles until their pixels have *end_sample*
+            // samples (or the time is up)
+            let render_pass = |end_sample: i64, tile_samples: &mut Vec<i64>, title: &str| {
+                let first_samples: Vec<i64> = tile_samples.clone();
                 let block_queue = BlockQueue::new(
                     (
                         (n_x_tiles * tile_size) as u32,
@@ -879,7 +907,11 @@ impl BDPTIntegrator {
                     (tile_size as u32, tile_size as u32),
                     (0, 0),
                 );
+                let reporter = &ProgressReporter::new(block_queue.len() as u64, title);
                 let bq = &block_queue;
+                let first_samples = &first_samples;
+                // reborrowed for the thread collecting the tiles
+                let merged_samples: &mut Vec<i64> = &mut *tile_samples;
                 let integrator = &self;
                 let sampler = &self.get_sampler();
                 let camera = &self.get_camera();
@@ -892,21 +924,20 @@ impl BDPTIntegrator {
                         let pixel_tx = pixel_tx.clone();
                         scope.spawn(move |_| {
                             while let Some((x, y)) = bq.next() {
+                                if time_is_up(deadline) {
+                                    break;
+                                }
                                 let tile: Point2i = Point2i {
                                     x: x as i32,
                                     y: y as i32,
                                 };
                                 let seed: i32 = tile.y * n_x_tiles + tile.x;
-                                let mut tile_sampler: Box<Sampler> =
-                                    sampler.clone_with_seed(seed as u64);
-                                let x0: i32 = sample_bounds.p_min.x + tile.x * tile_size;
-                                let x1: i32 = std::cmp::min(x0 + tile_size, sample_bounds.p_max.x);
-                                let y0: i32 = sample_bounds.p_min.y + tile.y * tile_size;
-                                let y1: i32 = std::cmp::min(y0 + tile_size, sample_bounds.p_max.y);
-                                let tile_bounds: Bounds2i = Bounds2i::new(
-                                    Point2i { x: x0, y: y0 },
-                                    Point2i { x: x1, y: y1 },
+                                let first_sample: i64 = first_samples[seed as usize];
+                                let mut tile_sampler: Box<Sampler> = sampler.clone_with_seed(
+                                    get_tile_seed(seed as usize, n_tiles, first_sample),
                                 );
+                                let tile_bounds: Bounds2i =
+                                    get_tile_bounds(&sample_bounds, tile, tile_size);
                                 // println!("Starting image tile {:?}", tile_bounds);
                                 let mut film_tile = film.get_film_tile(&tile_bounds);
                                 for p_pixel in &tile_bounds {
@@ -914,6 +945,10 @@ impl BDPTIntegrator {
                                     if !pnt2_inside_exclusivei(p_pixel, &integrator.pixel_bounds) {
                                         continue;
                                     }
+                                    if first_sample > 0_i64 {
+                                        // continue the sample sequence of previous passes
+                                        tile_sampler.set_sample_number(first_sample);
+                                    }
                                     let mut done: bool = false;
                                     while !done {
                                         // Get a distribution for sampling
@@ -1040,35 +1075,132 @@ impl BDPTIntegrator {
                                             //     l.y()
                                             // );
                                             film_tile.add_sample(p_film, &mut l, 1.0 as Float);
-                                            done = !tile_sampler.start_next_sample();
+                                            if film_tile.has_aovs() && n_camera > 1 {
+                                                // geometric AOVs along the first segment
+                                                // of the camera subpath
+                                                let o: Point3f = camera_vertices[0].p();
+                                                let ray: Ray = Ray {
+                                                    o,
+                                                    d: (camera_vertices[1].p() - o).normalize(),
+                                                    t_max: Cell::new(Float::INFINITY),
+                                                    time,
+                                                    differential: None,
+                                                    medium: None,
+                                                };
+                                                let mut aov_sample: AovSample =
+                                                    AovSample::default();
+                                                compute_geometric_aovs(
+                                                    &ray,
+                                                    scene,
+                                                    &mut aov_sample,
+                                                );
+                                                film_tile.add_aov_sample(
+                                                    p_film,
+                                                    &aov_sample,
+                                                    1.0 as Float,
+                                                );
+                                            }
+                                            done = !tile_sampler.start_next_sample()
+                                                || tile_sampler.get_current_sample_number()
+                                                    >= end_sample;
                                         }
                                     }
                                 }
                                 // send the tile through the channel to main thread
                                 pixel_tx
-                                    .send(film_tile)
+                                    .send((seed as usize, film_tile))
                                     .unwrap_or_else(|_| panic!("Failed to send tile"));
                             }
                         });
                     }
+                    // workers stop early if the time is up
+                    drop(pixel_tx);
                     // spawn thread to collect pixels and render image to file
                     scope.spawn(move |_| {
-                        for _ in pbr::PbIter::new(0..bq.len()) {
-                            let film_tile = pixel_rx.recv().unwrap();
+                        for (tile_index, film_tile) in pixel_rx.iter() {
                             // merge image tile into _Film_
                             film.merge_film_tile(&film_tile);
+                            merged_samples[tile_index] = end_sample;
+                            reporter.update(1);
                         }
                     });
                 })
                 .unwrap();
-            }
-            film.write_image(1.0 as Float / samples_per_pixel as Float);
+                reporter.done();
+            };
+            if let Some(progressive) = some_progressive {
+                // keep doubling the samples per pixel
+                let mut end_sample: i64 = 1;
+                let mut pass: i32 = 1;
+                loop {
+                    let title: String = format!("Rendering pass {} ({} spp)", pass, end_sample);
+                    render_pass(end_sample, &mut tile_samples, &title);
+                    if end_sample >= samples_per_pixel {
+                        break;
+                    }
+                    if time_is_up(deadline) {
+                        println!(
+                            "Time limit reached after {:.1} spp on average",
+                            tile_samples.iter().sum::<i64>() as Float / n_tiles as Float
+                        );
+                        break;
+                    }
+                    if pass % progressive.write_frequency == 0 {
+                        film.write_image(self.get_splat_scale(
+                            &sample_bounds,
+                            tile_size,
+                            n_x_tiles,
+                            &tile_samples,
+                        ));
+                    }
+                    end_sample = std::cmp::min(2_i64 * end_sample, samples_per_pixel);
+                    pass += 1;
+                }
+            } else {
+                render_pass(samples_per_pixel, &mut tile_samples, "Rendering");
+            }
+            film.write_image(self.get_splat_scale(
+                &sample_bounds,
+                tile_size,
+                n_x_tiles,
+                &tile_samples,
+            ));
             // TODO: Write buffers for debug visualization
         }
     }
     pub fn get_camera(&self) -> Arc<Camera> {
         self.camera.clone()
     }
+    /// Light subpaths splat anywhere on the film, so their
+    /// contributions get scaled by the inverse of the average samples
+    /// per pixel (tiles differ if the time limit was reached).
+    fn get_splat_scale(
+        &self,
+        sample_bounds: &Bounds2i,
+        tile_size: i32,
+        n_x_tiles: i32,
+        tile_samples: &[i64],
+    ) -> Float {
+        let mut n_pixels: i64 = 0;
+        let mut n_samples: i64 = 0;
+        for (i, samples) in tile_samples.iter().enumerate() {
+            let tile: Point2i = Point2i {
+                x: i as i32 % n_x_tiles,
+                y: i as i32 / n_x_tiles,
+            };
+            for p_pixel in &get_tile_bounds(sample_bounds, tile, tile_size) {
+                if pnt2_inside_exclusivei(p_pixel, &self.pixel_bounds) {
+                    n_pixels += 1;
+                    n_samples += samples;
+                }
+            }
+        }
+        if n_samples > 0 {
+            n_pixels as Float / n_samples as Float
+        } else {
+            0.0 as Float
+        }
+    }
     pub fn get_sampler(&self) -> &Sampler {
         &self.sampler
     }
@@ -1110,7 +1242,7 @@ pub fn generate_camera_subpath<'a>(
     if max_depth == 0 {
         return (0_usize, Point3f::default(), Float::default());
     }
-    // TODO: ProfilePhase _(Prof::BDPTGenerateSubpath);
+    let _prof = ProfilePhase::new(Prof::BdptGenerateSubpath);
     // sample initial ray for camera subpath
     let mut camera_sample: CameraSample = CameraSample::default();
     camera_sample.p_film = p_film;
@@ -1157,7 +1289,7 @@ pub fn generate_light_subpath<'a>(
     if max_depth == 0_u32 {
         return 0_usize;
     }
-    // TODO: ProfilePhase _(Prof::BDPTGenerateSubpath);
+    let _prof = ProfilePhase::new(Prof::BdptGenerateSubpath);
     // sample initial ray for light subpath
     let mut light_pdf: Option<Float> = Some(0.0 as Float);
     let light_num: usize = light_distr.sample_discrete(sampler.get_1d(), light_pdf.as_mut());
@@ -1239,6 +1371,8 @@ pub fn random_walk<'a>(
     // declare variables for forward and reverse probability densities
     let mut pdf_fwd: Float = pdf;
     let mut pdf_rev: Float = 0.0;
+    // spectral channel a dispersive event restricted the path to
+    let mut spectral_channel: Option<usize> = None;
     loop {
         // attempt to create the next subpath vertex in _path_
         // println!(
@@ -1315,6 +1449,8 @@ pub fn random_walk<'a>(
         } else {
             // compute scattering functions for _mode_ and skip over medium
             // boundaries
+            // stay on the spectral channel a dispersive event picked
+            isect.spectral_channel = spectral_channel;
             isect.compute_scattering_functions(&ray, true, mode);
             let isect_wo: Vector3f = isect.common.wo;
             let isect_shading_n: Normal3f = isect.shading.n;
@@ -1336,6 +1472,7 @@ pub fn random_walk<'a>(
                 si_eval.common.medium_interface = None;
             }
             si_eval.uv = isect.uv;
+            si_eval.face_index = isect.face_index;
             si_eval.dpdu = isect.dpdu;
             si_eval.dpdv = isect.dpdv;
             si_eval.dndu = isect.dndu;
@@ -1384,13 +1521,14 @@ pub fn random_walk<'a>(
                 let mut wi: Vector3f = Vector3f::default();
                 let bsdf_flags: u8 = BxdfType::BsdfAll as u8;
                 let mut sampled_type: u8 = u8::max_value(); // != 0
-                let f: Spectrum = bsdf.sample_f(
+                let f: Spectrum = bsdf.sample_f_channel(
                     &isect_wo,
                     &mut wi,
                     &sampler.get_2d(),
                     &mut pdf_fwd,
                     bsdf_flags,
                     &mut sampled_type,
+                    &mut spectral_channel,
                 );
                 // println!(
                 //     "Random walk sampled dir {:?} f: {:?}, pdf_fwd: {:?}",
@@ -2200,7 +2338,7 @@ pub fn connect_bdpt<'a>(
     p_raster: &mut Point2f,
     mis_weight_opt: Option<&mut Float>,
 ) -> Spectrum {
-    // TODO: ProfilePhase _(Prof::BDPTConnectSubpaths);
+    let _prof = ProfilePhase::new(Prof::BdptConnectSubpaths);
     let mut l: Spectrum = Spectrum::default();
     // ignore invalid connections related to infinite area lights
     if t > 1 && s != 0 && camera_vertices[t - 1].vertex_type == VertexType::Light {
@@ -2415,10 +2553,11 @@ pub fn connect_bdpt<'a>(
             }
         }
     }
-    // TODO:
-    // ++totalPaths;
-    // if (L.IsBlack()) ++zeroRadiancePaths;
-    // ReportValue(pathLength, s + t - 2);
+    ZERO_RADIANCE_PATHS.inc_denom();
+    if l.is_black() {
+        ZERO_RADIANCE_PATHS.inc_num();
+    }
+    PATH_LENGTH.report_value((s + t - 2) as i64);
 
     // compute MIS weight for connection strategy
     let mis_weight_flt = if !l.is_black() {
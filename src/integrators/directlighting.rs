@@ -11,6 +11,7 @@ use crate::core::pbrt::{Float, Spectrum};
 use crate::core::reflection::BxdfType;
 use crate::core::sampler::Sampler;
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase};
 
 // see directlighting.h
 
@@ -76,7 +77,7 @@ impl DirectLightingIntegrator {
         // arena: &mut Arena,
         depth: i32,
     ) -> Spectrum {
-        // TODO: ProfilePhase p(Prof::SamplerIntegratorLi);
+        let _prof = ProfilePhase::new(Prof::SamplerIntegratorLi);
         let mut l: Spectrum = Spectrum::new(0.0 as Float);
         // find closest ray intersection or return background radiance
         let mut isect: SurfaceInteraction = SurfaceInteraction::default();
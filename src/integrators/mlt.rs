@@ -1,7 +1,6 @@
 // std
 use std::ops::DerefMut;
 use std::sync::Arc;
-use std::thread;
 // pbrt
 use crate::core::camera::Camera;
 use crate::core::film::Film;
@@ -10,15 +9,19 @@ use crate::core::integrator::compute_light_power_distribution;
 use crate::core::pbrt::erf_inv;
 use crate::core::pbrt::SQRT_2;
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progress::ProgressReporter;
 use crate::core::rng::Rng;
 use crate::core::sampler::Sampler;
 use crate::core::sampling::Distribution1D;
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase, StatPercent};
 use crate::integrators::bdpt::Vertex;
 use crate::integrators::bdpt::{connect_bdpt, generate_camera_subpath, generate_light_subpath};
 // others
 use rayon::prelude::*;
 
+static ACCEPTANCE_RATE: StatPercent = StatPercent::new("Integrator/Acceptance rate");
+
 pub const CAMERA_STREAM_INDEX: u8 = 0;
 pub const LIGHT_STREAM_INDEX: u8 = 1;
 pub const CONNECTION_STREAM_INDEX: u8 = 2;
@@ -199,7 +202,7 @@ impl MLTSampler {
         self.array_2d_offset = 0_usize;
     }
     pub fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         let index: i32 = self.get_next_index();
         self.ensure_ready(index);
         self.x[index as usize].value
@@ -411,6 +414,12 @@ impl MLTIntegrator {
         } else {
             num_threads as usize
         };
+        if !self.camera.get_film().get_output_aovs().is_empty() {
+            println!("WARNING: AOVs are not supported by the MLT integrator (left black)");
+        }
+        if self.camera.get_film().checkpoint.is_some() {
+            println!("WARNING: Checkpoints are not supported by the MLT integrator");
+        }
         if let Some(light_distr) = compute_light_power_distribution(scene) {
             println!("Generating bootstrap paths ...");
             // generate bootstrap samples and compute normalization constant $b$
@@ -419,7 +428,8 @@ impl MLTIntegrator {
             let mut bootstrap_weights: Vec<Float> =
                 vec![0.0 as Float; n_bootstrap_samples as usize];
             if !scene.lights.is_empty() {
-                // TODO: ProgressReporter progress(nBootstrap / 256, "Generating bootstrap paths");
+                let progress =
+                    ProgressReporter::new(n_bootstrap_samples as u64, "Generating bootstrap paths");
                 // let chunk_size: u32 = clamp_t(integrator.n_bootstrap / 128, 1, 8192);
                 let chunk_size: usize = (n_bootstrap_samples / num_cores as u32) as usize;
                 {
@@ -427,11 +437,10 @@ impl MLTIntegrator {
                         bootstrap_weights.chunks_mut(chunk_size).collect();
                     let integrator = &self;
                     let light_distr = &light_distr;
+                    let progress = &progress;
                     crossbeam::scope(|scope| {
-                        let (band_tx, band_rx) = crossbeam_channel::bounded(num_cores);
                         // spawn worker threads
                         for (b, band) in bands.into_iter().enumerate() {
-                            let band_tx = band_tx.clone();
                             scope.spawn(move |_| {
                                 for (w, weight) in band.iter_mut().enumerate() {
                                     let rng_index: u64 = ((b * chunk_size) + w) as u64;
@@ -455,22 +464,14 @@ impl MLTIntegrator {
                                             &mut p_raster,
                                         )
                                         .y();
+                                    progress.update(1);
                                 }
                             });
-                            // send progress through the channel to main thread
-                            band_tx
-                                .send(b)
-                                .unwrap_or_else(|_| panic!("Failed to send progress"));
                         }
-                        // spawn thread to report progress
-                        scope.spawn(move |_| {
-                            for _ in pbr::PbIter::new(0..num_cores) {
-                                band_rx.recv().unwrap();
-                            }
-                        });
                     })
                     .unwrap();
                 }
+                progress.done();
             }
             let bootstrap: Distribution1D = Distribution1D::new(bootstrap_weights);
             let b: Float = bootstrap.func_int * (self.max_depth + 1) as Float;
@@ -480,22 +481,18 @@ impl MLTIntegrator {
             let n_total_mutations: u64 =
                 self.mutations_per_pixel as u64 * film.get_sample_bounds().area() as u64;
             if !scene.lights.is_empty() {
-                // TODO: let progress_frequency = 32768;
-                // TODO: ProgressReporter progress(nTotalMutations / progressFrequency,
-                //                           "Rendering");
-                // use parallel iterator (par_iter_with) from rayon crate
-                let (sender, receiver) = crossbeam_channel::bounded(num_cores);
+                let progress_frequency: u64 = 32768;
+                let progress =
+                    ProgressReporter::new(n_total_mutations / progress_frequency, "Rendering");
+                // use parallel iterator (par_iter) from rayon crate
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(num_cores)
+                    .build()
+                    .unwrap();
                 let n_chains = self.n_chains;
-                // spawn thread to report progress
-                let finish = thread::spawn(move || {
-                    for _ in pbr::PbIter::new(0..n_chains) {
-                        receiver.recv().unwrap();
-                    }
-                });
                 // for i in 0..n_chains {
                 let ivec: Vec<u32> = (0..n_chains).collect();
-                ivec.par_iter().for_each_with(sender, |s, &i| {
-                    s.send(i).unwrap_or_else(|_| panic!("Failed to send chain"));
+                let run_chain = |&i: &u32| {
                     let n_chain_mutations: u64 = ((i as u64 + 1) * n_total_mutations
                         / n_chains as u64)
                         .min(n_total_mutations)
@@ -523,7 +520,7 @@ impl MLTIntegrator {
                         &mut p_current,
                     );
                     // run the Markov chain for _n_chain_mutations_ steps
-                    for _j in 0..n_chain_mutations {
+                    for j in 0..n_chain_mutations {
                         match sampler.deref_mut() {
                             Sampler::MLT(mlt_sampler) => mlt_sampler.start_iteration(),
                             _ => panic!("MLTSampler needed."),
@@ -554,21 +551,24 @@ impl MLTIntegrator {
                                 Sampler::MLT(mlt_sampler) => mlt_sampler.accept(),
                                 _ => panic!("MLTSampler needed."),
                             }
-                        // TODO: ++acceptedMutations;
+                            ACCEPTANCE_RATE.inc_num();
                         } else {
                             match sampler.deref_mut() {
                                 Sampler::MLT(mlt_sampler) => mlt_sampler.reject(),
                                 _ => panic!("MLTSampler needed."),
                             }
                         }
-                        // TODO: ++totalMutations;
-                        // if (i * n_total_mutations / n_chains + j) % progress_frequency == 0 {
-                        //     progress.update();
-                        // }
+                        ACCEPTANCE_RATE.inc_denom();
+                        if (i as u64 * n_total_mutations / n_chains as u64 + j) % progress_frequency
+                            == 0_u64
+                        {
+                            progress.update(1);
+                        }
                         // TODO: arena.Reset();
                     }
-                });
-                finish.join().unwrap();
+                };
+                pool.install(|| ivec.par_iter().for_each(run_chain));
+                progress.done();
             }
             // Store final image computed with MLT
             film.write_image(b / self.mutations_per_pixel as Float);
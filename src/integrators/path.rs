@@ -5,9 +5,10 @@ use std::sync::Arc;
 // pbrt
 // use crate::core::bssrdf::Bssrdf;
 use crate::core::camera::Camera;
-use crate::core::geometry::{vec3_abs_dot_nrmf, vec3_dot_nrmf};
+use crate::core::film::AovSample;
+use crate::core::geometry::{pnt3_distancef, vec3_abs_dot_nrmf, vec3_dot_nrmf};
 use crate::core::geometry::{Bounds2i, Point2f, Ray, Vector3f};
-use crate::core::integrator::uniform_sample_one_light;
+use crate::core::integrator::{uniform_sample_one_light, uniform_sample_one_light_split};
 use crate::core::interaction::{Interaction, SurfaceInteraction};
 use crate::core::lightdistrib::create_light_sample_distribution;
 use crate::core::lightdistrib::LightDistribution;
@@ -17,9 +18,13 @@ use crate::core::reflection::BxdfType;
 use crate::core::sampler::Sampler;
 use crate::core::sampling::Distribution1D;
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase, StatIntDistribution, StatPercent};
 
 // see path.h
 
+static ZERO_RADIANCE_PATHS: StatPercent = StatPercent::new("Integrator/Zero-radiance paths");
+static PATH_LENGTH: StatIntDistribution = StatIntDistribution::new("Integrator/Path length");
+
 /// Path Tracing (Global Illumination) - uses the render loop of a
 /// [SamplerIntegrator](../../core/integrator/enum.SamplerIntegrator.html)
 pub struct PathIntegrator {
@@ -63,9 +68,23 @@ impl PathIntegrator {
         scene: &Scene,
         sampler: &mut Sampler,
         // arena: &mut Arena,
+        depth: i32,
+    ) -> Spectrum {
+        self.li_aovs(r, scene, sampler, depth, None)
+    }
+    /// Same as li(), but if *aov_sample* is given the radiance is
+    /// also split into emission, direct and indirect light (the
+    /// latter classified as diffuse or specular by the lobe sampled
+    /// at the first intersection).
+    pub fn li_aovs(
+        &self,
+        r: &Ray,
+        scene: &Scene,
+        sampler: &mut Sampler,
         _depth: i32,
+        mut aov_sample: Option<&mut AovSample>,
     ) -> Spectrum {
-        // TODO: ProfilePhase p(Prof::SamplerIntegratorLi);
+        let _prof = ProfilePhase::new(Prof::SamplerIntegratorLi);
         let mut l: Spectrum = Spectrum::default();
         let mut beta: Spectrum = Spectrum::new(1.0 as Float);
         let mut ray: Ray = Ray {
@@ -77,6 +96,7 @@ impl PathIntegrator {
             medium: r.medium.clone(),
         };
         let mut specular_bounce: bool = false;
+        let mut first_bounce_diffuse: bool = false;
         let mut bounces: u32 = 0_u32;
         // Added after book publication: etaScale tracks the
         // accumulated effect of radiance scaling due to rays passing
@@ -87,6 +107,8 @@ impl PathIntegrator {
         // refracted rays that are about to be refracted back out of a
         // medium and thus have their beta value increased.
         let mut eta_scale: Float = 1.0;
+        // spectral channel a dispersive event restricted the path to
+        let mut spectral_channel: Option<usize> = None;
         loop {
             // find next path vertex and accumulate contribution
             // println!("Path tracer bounce {:?}, current L = {:?}, beta = {:?}",
@@ -97,7 +119,11 @@ impl PathIntegrator {
                 // possibly add emitted light at intersection
                 if bounces == 0 || specular_bounce {
                     // add emitted light at path vertex
-                    l += beta * isect.le(&-ray.d);
+                    let le: Spectrum = beta * isect.le(&-ray.d);
+                    l += le;
+                    if let Some(aov_sample) = aov_sample.as_deref_mut() {
+                        add_emitted_aov(aov_sample, bounces, first_bounce_diffuse, le);
+                    }
                     // println!("Added Le -> L = {:?}", l);
                 }
                 // terminate path if _maxDepth_ was reached
@@ -106,9 +132,16 @@ impl PathIntegrator {
                 }
                 // compute scattering functions and skip over medium boundaries
                 let mode: TransportMode = TransportMode::Radiance;
+                // follow the spectral channel an earlier (dispersive) event picked
+                isect.spectral_channel = spectral_channel;
                 isect.compute_scattering_functions(&ray, true, mode);
                 if let Some(ref _bsdf) = isect.bsdf {
                     // we are fine (for below)
+                    if bounces == 0 {
+                        if let Some(aov_sample) = aov_sample.as_deref_mut() {
+                            add_geometric_aovs(aov_sample, r, &isect);
+                        }
+                    }
                 } else {
                     // TODO: println!("Skipping intersection due to null bsdf");
                     ray = isect.spawn_ray(&ray.d);
@@ -122,20 +155,35 @@ impl PathIntegrator {
                     let bsdf_flags: u8 = BxdfType::BsdfAll as u8 & !(BxdfType::BsdfSpecular as u8);
                     if let Some(ref bsdf) = isect.bsdf {
                         if bsdf.num_components(bsdf_flags) > 0 {
-                            // TODO: ++total_paths;
+                            ZERO_RADIANCE_PATHS.inc_denom();
                             let it: &SurfaceInteraction = isect.borrow();
+                            let mut ld_diffuse: Spectrum = Spectrum::default();
                             let ld: Spectrum = beta
-                                * uniform_sample_one_light(
+                                * uniform_sample_one_light_split(
                                     it,
                                     scene,
                                     sampler,
                                     false,
                                     Some(&distrib),
+                                    if aov_sample.is_some() {
+                                        Some(&mut ld_diffuse)
+                                    } else {
+                                        None
+                                    },
                                 );
+                            if let Some(aov_sample) = aov_sample.as_deref_mut() {
+                                if bounces == 0 {
+                                    ld_diffuse *= beta;
+                                    aov_sample.diffuse_direct += ld_diffuse;
+                                    aov_sample.specular_direct += ld - ld_diffuse;
+                                } else {
+                                    add_indirect_aov(aov_sample, first_bounce_diffuse, ld);
+                                }
+                            }
                             // TODO: println!("Sampled direct lighting Ld = {:?}", ld);
-                            // TODO: if ld.is_black() {
-                            //     ++zero_radiance_paths;
-                            // }
+                            if ld.is_black() {
+                                ZERO_RADIANCE_PATHS.inc_num();
+                            }
                             assert!(ld.y() >= 0.0 as Float, "ld = {:?}", ld);
                             l += ld;
                         }
@@ -145,16 +193,24 @@ impl PathIntegrator {
                         let mut pdf: Float = 0.0 as Float;
                         let bsdf_flags: u8 = BxdfType::BsdfAll as u8;
                         let mut sampled_type: u8 = u8::max_value(); // != 0
-                        let f: Spectrum = bsdf.sample_f(
+                        let f: Spectrum = bsdf.sample_f_channel(
                             &wo,
                             &mut wi,
                             &sampler.get_2d(),
                             &mut pdf,
                             bsdf_flags,
                             &mut sampled_type,
+                            &mut spectral_channel,
                         );
 
                         // println!("Sampled BSDF, f = {:?}, pdf = {:?}", f, pdf);
+                        if bounces == 0 && pdf > 0.0 as Float {
+                            if let Some(aov_sample) = aov_sample.as_deref_mut() {
+                                // single sample estimate of the directional albedo
+                                aov_sample.albedo =
+                                    f * vec3_abs_dot_nrmf(&wi, &isect.shading.n) / pdf;
+                            }
+                        }
                         if f.is_black() || pdf == 0.0 as Float {
                             break;
                         }
@@ -172,6 +228,10 @@ impl PathIntegrator {
                             pdf
                         );
                         specular_bounce = (sampled_type & BxdfType::BsdfSpecular as u8) != 0_u8;
+                        if bounces == 0 {
+                            first_bounce_diffuse =
+                                (sampled_type & BxdfType::BsdfDiffuse as u8) != 0_u8;
+                        }
                         if ((sampled_type & BxdfType::BsdfSpecular as u8) != 0_u8)
                             && ((sampled_type & BxdfType::BsdfTransmission as u8) != 0_u8)
                         {
@@ -194,17 +254,7 @@ impl PathIntegrator {
                                 // importance sample the BSSRDF
                                 let s2: Point2f = sampler.get_2d();
                                 let s1: Float = sampler.get_1d();
-                                let (s, pi_opt) = bssrdf.sample_s(
-                                    // the next three (extra) parameters are used for SeparableBssrdfAdapter
-                                    bssrdf.clone(),
-                                    bssrdf.mode,
-                                    bssrdf.eta,
-                                    // done
-                                    scene,
-                                    s1,
-                                    s2,
-                                    &mut pdf,
-                                );
+                                let (s, pi_opt) = bssrdf.sample_s(scene, s1, s2, &mut pdf);
                                 if s.is_black() || pdf == 0.0 as Float {
                                     break;
                                 }
@@ -214,7 +264,7 @@ impl PathIntegrator {
                                     // account for the direct subsurface scattering component
                                     let distrib: Arc<Distribution1D> =
                                         light_distribution.lookup(&pi.common.p);
-                                    l += beta
+                                    let ld: Spectrum = beta
                                         * uniform_sample_one_light(
                                             &pi,
                                             scene,
@@ -222,6 +272,10 @@ impl PathIntegrator {
                                             false,
                                             Some(&distrib),
                                         );
+                                    l += ld;
+                                    if let Some(aov_sample) = aov_sample.as_deref_mut() {
+                                        add_indirect_aov(aov_sample, first_bounce_diffuse, ld);
+                                    }
                                     // account for the indirect subsurface scattering component
                                     let mut wi: Vector3f = Vector3f::default();
                                     let mut pdf: Float = 0.0 as Float;
@@ -270,7 +324,11 @@ impl PathIntegrator {
                 if bounces == 0 || specular_bounce {
                     // for (const auto &light : scene.infiniteLights)
                     for light in &scene.infinite_lights {
-                        l += beta * light.le(&ray);
+                        let le: Spectrum = beta * light.le(&ray);
+                        l += le;
+                        if let Some(aov_sample) = aov_sample.as_deref_mut() {
+                            add_emitted_aov(aov_sample, bounces, first_bounce_diffuse, le);
+                        }
                     }
                     // println!("Added infinite area lights -> L = {:?}", l);
                 }
@@ -279,6 +337,7 @@ impl PathIntegrator {
             }
             bounces += 1_u32;
         }
+        PATH_LENGTH.report_value(bounces as i64);
         l
     }
     pub fn get_camera(&self) -> Arc<Camera> {
@@ -291,3 +350,40 @@ impl PathIntegrator {
         self.pixel_bounds
     }
 }
+
+/// Depth, position, normal, uv and IDs of the first surface hit by
+/// the camera ray *r*.
+fn add_geometric_aovs(aov_sample: &mut AovSample, r: &Ray, isect: &SurfaceInteraction) {
+    aov_sample.depth = pnt3_distancef(&r.o, &isect.common.p);
+    aov_sample.position = isect.common.p;
+    aov_sample.normal = isect.shading.n;
+    aov_sample.uv = isect.uv;
+    if let Some(primitive_raw) = isect.primitive {
+        let primitive = unsafe { &*primitive_raw };
+        aov_sample.object_id = primitive.get_object_id();
+        aov_sample.material_id = primitive.get_material_id();
+    }
+}
+
+/// Emitted light seen directly by the camera, after a single
+/// specular bounce (direct), or further down the path (indirect).
+fn add_emitted_aov(
+    aov_sample: &mut AovSample,
+    bounces: u32,
+    first_bounce_diffuse: bool,
+    le: Spectrum,
+) {
+    match bounces {
+        0 => aov_sample.emission += le,
+        1 => aov_sample.specular_direct += le,
+        _ => add_indirect_aov(aov_sample, first_bounce_diffuse, le),
+    }
+}
+
+fn add_indirect_aov(aov_sample: &mut AovSample, first_bounce_diffuse: bool, l: Spectrum) {
+    if first_bounce_diffuse {
+        aov_sample.diffuse_indirect += l;
+    } else {
+        aov_sample.specular_indirect += l;
+    }
+}
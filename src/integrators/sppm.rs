@@ -1,14 +1,19 @@
 // std
 use std::borrow::Borrow;
 use std::f32::consts::PI;
+use std::io::{Read, Result, Write};
 use std::sync::Arc;
 // others
 use atom::*;
 use atomic::Atomic;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use strum::IntoEnumIterator;
 // pbrt
 use crate::blockqueue::BlockQueue;
 use crate::core::camera::{Camera, CameraSample};
+use crate::core::checkpoint::{can_resume, read_checkpoint, read_floats, read_len};
+use crate::core::checkpoint::{write_checkpoint, write_floats, write_len};
+use crate::core::checkpoint::{CheckpointHeader, CheckpointKind, CheckpointTimer};
 use crate::core::film::Film;
 use crate::core::geometry::{
     bnd3_expand, bnd3_union_bnd3f, nrm_abs_dot_vec3f, pnt3_distance_squaredf, vec3_abs_dot_nrmf,
@@ -25,11 +30,20 @@ use crate::core::material::TransportMode;
 use crate::core::parallel::AtomicFloat;
 use crate::core::pbrt::{clamp_t, lerp};
 use crate::core::pbrt::{Float, Spectrum};
+use crate::core::progress::ProgressReporter;
 use crate::core::reflection::{Bsdf, BxdfType};
 use crate::core::scene::Scene;
-use crate::core::spectrum::RGBEnum;
+use crate::core::stats::{Prof, ProfilePhase, StatCounter, StatIntDistribution, StatRatio};
 use crate::samplers::halton::HaltonSampler;
 
+static PHOTON_PATHS: StatCounter =
+    StatCounter::new("Stochastic Progressive Photon Mapping/Photon paths followed");
+static GRID_CELLS_PER_VISIBLE_POINT: StatIntDistribution =
+    StatIntDistribution::new("Stochastic Progressive Photon Mapping/Grid cells per visible point");
+static VISIBLE_POINTS_CHECKED: StatRatio = StatRatio::new(
+    "Stochastic Progressive Photon Mapping/Visible points checked per photon intersection",
+);
+
 /// Stochastic Progressive Photon Mapping
 pub struct SPPMIntegrator {
     pub camera: Arc<Camera>,
@@ -71,11 +85,14 @@ impl SPPMIntegrator {
             num_threads as usize
         };
         println!("Rendering with {:?} thread(s) ...", num_cores);
-        // TODO: ProfilePhase p(Prof::IntegratorRender);
+        let _prof = ProfilePhase::new(Prof::IntegratorRender);
 
         // initialize _pixel_bounds_ and _pixels_ array for SPPM
         let film: Arc<Film> = self.get_camera().get_film();
         let pixel_bounds: Bounds2i = film.cropped_pixel_bounds;
+        if !film.get_output_aovs().is_empty() {
+            println!("WARNING: AOVs are not supported by the SPPM integrator (left black)");
+        }
         let n_pixels: i32 = pixel_bounds.area();
         let mut pixels: Vec<SPPMPixel> = Vec::with_capacity(n_pixels as usize);
         for _i in 0..n_pixels as usize {
@@ -84,6 +101,36 @@ impl SPPMIntegrator {
             pixels.push(pixel);
         }
         let inv_sqrt_spp: Float = 1.0 as Float / (self.n_iterations as Float).sqrt();
+        // continue an interrupted render
+        let checkpoint_header: CheckpointHeader = CheckpointHeader {
+            kind: CheckpointKind::Sppm,
+            pixel_bounds,
+            target: self.n_iterations as i64,
+        };
+        let mut first_iteration: i32 = 0;
+        let mut some_timer: Option<CheckpointTimer> = None;
+        if let Some(ref checkpoint) = film.checkpoint {
+            if can_resume(checkpoint) {
+                println!("Resuming from checkpoint {:?} ...", checkpoint.filename);
+                match read_checkpoint(&checkpoint.filename, &checkpoint_header, |reader| {
+                    self.read_checkpoint_state(reader, &mut pixels)
+                }) {
+                    Ok(iteration) => first_iteration = iteration,
+                    Err(e) => {
+                        println!(
+                            "ERROR: Unable to resume from checkpoint {:?}: {} (starting from scratch)",
+                            checkpoint.filename, e
+                        );
+                        // the pixels might have been read partially
+                        for pixel in pixels.iter_mut() {
+                            *pixel = SPPMPixel::default();
+                            pixel.radius = self.initial_search_radius;
+                        }
+                    }
+                }
+            }
+            some_timer = Some(CheckpointTimer::new(checkpoint.interval));
+        }
         // TODO: let pixel_memory_bytes: usize = n_pixels as usize * std::mem::size_of::<SPPMPixel>();
 
         // compute _light_distr_ for sampling lights proportional to power
@@ -101,11 +148,12 @@ impl SPPMIntegrator {
                 x: (pixel_extent.x + tile_size - 1) / tile_size,
                 y: (pixel_extent.y + tile_size - 1) / tile_size,
             };
-            // TODO: ProgressReporter progress(2 * nIterations, "Rendering");
-            for iteration in pbr::PbIter::new(0..self.n_iterations) {
+            let progress = ProgressReporter::new(2 * self.n_iterations as u64, "Rendering");
+            progress.update(2 * first_iteration as u64);
+            for iteration in first_iteration..self.n_iterations {
                 // generate SPPM visible points
                 {
-                    // TODO: ProfilePhase _(Prof::SPPMCameraPass);
+                    let _prof = ProfilePhase::new(Prof::SppmCameraPass);
                     // println!("Generate SPPM visible points ...");
                     {
                         let block_queue = BlockQueue::new(
@@ -183,8 +231,8 @@ impl SPPMIntegrator {
                                                 VisiblePoint::default(),
                                             );
                                             let mut specular_bounce: bool = false;
+                                            let mut spectral_channel: Option<usize> = None;
                                             for depth in 0..integrator.max_depth {
-                                                // TODO: ++totalPhotonSurfaceInteractions;
                                                 let mut isect: SurfaceInteraction =
                                                     SurfaceInteraction::default();
                                                 if scene.intersect(&mut ray, &mut isect) {
@@ -193,6 +241,8 @@ impl SPPMIntegrator {
                                                     // compute BSDF at SPPM camera ray intersection
                                                     let mode: TransportMode =
                                                         TransportMode::Radiance;
+                                                    // visible point paths keep their (dispersive) spectral channel
+                                                    isect.spectral_channel = spectral_channel;
                                                     isect.compute_scattering_functions(
                                                         &ray, true, mode,
                                                     );
@@ -245,14 +295,16 @@ impl SPPMIntegrator {
                                                                 BxdfType::BsdfAll as u8;
                                                             let mut sampled_type: u8 =
                                                                 u8::max_value(); // != 0
-                                                            let f: Spectrum = bsdf.sample_f(
-                                                                &wo,
-                                                                &mut wi,
-                                                                &tile_sampler.get_2d(),
-                                                                &mut pdf,
-                                                                bsdf_flags,
-                                                                &mut sampled_type,
-                                                            );
+                                                            let f: Spectrum = bsdf
+                                                                .sample_f_channel(
+                                                                    &wo,
+                                                                    &mut wi,
+                                                                    &tile_sampler.get_2d(),
+                                                                    &mut pdf,
+                                                                    bsdf_flags,
+                                                                    &mut sampled_type,
+                                                                    &mut spectral_channel,
+                                                                );
                                                             if pdf == 0.0 as Float || f.is_black() {
                                                                 break;
                                                             }
@@ -329,7 +381,7 @@ impl SPPMIntegrator {
                     grid_once.push(AtomSetOnce::empty());
                 }
                 {
-                    // TODO: ProfilePhase _(Prof::SPPMGridConstruction);
+                    let _prof = ProfilePhase::new(Prof::SppmGridConstruction);
 
                     // compute grid bounds for SPPM visible points
                     let mut max_radius: Float = 0.0 as Float;
@@ -416,9 +468,11 @@ impl SPPMIntegrator {
                                                     }
                                                 }
                                             }
-                                            // ReportValue(grid_cells_per_visible_point,
-                                            //             (1 + pMax.x - pMin.x) * (1 + pMax.y - pMin.y) *
-                                            //                 (1 + pMax.z - pMin.z));
+                                            GRID_CELLS_PER_VISIBLE_POINT.report_value(
+                                                (1 + p_max.x - p_min.x) as i64
+                                                    * (1 + p_max.y - p_min.y) as i64
+                                                    * (1 + p_max.z - p_min.z) as i64,
+                                            );
                                         }
                                     }
                                 });
@@ -437,6 +491,7 @@ impl SPPMIntegrator {
                         .unwrap();
                     }
                 }
+                progress.update(1);
                 // trace photons and accumulate contributions
                 for h in 0..hash_size {
                     // take
@@ -447,7 +502,7 @@ impl SPPMIntegrator {
                 }
                 std::mem::drop(grid);
                 {
-                    // TODO: ProfilePhase _(Prof::SPPMPhotonPass);
+                    let _prof = ProfilePhase::new(Prof::SppmPhotonPass);
                     // println!("Trace photons and accumulate contributions ...");
                     let chunk_size: usize =
                         (self.photons_per_iteration / num_cores as i32) as usize;
@@ -471,6 +526,7 @@ impl SPPMIntegrator {
                                         * integrator.photons_per_iteration as u64
                                         + *photon_index as u64;
                                     let mut halton_dim: i32 = 0;
+                                    PHOTON_PATHS.inc();
                                     // choose light to shoot photon from
                                     let mut light_pdf_opt: Option<Float> = Some(0.0 as Float);
                                     let light_sample: Float =
@@ -540,10 +596,11 @@ impl SPPMIntegrator {
                                             break;
                                         }
                                         // follow photon path through scene and record intersections
+                                        let mut spectral_channel: Option<usize> = None;
                                         for depth in 0..integrator.max_depth {
 					    let mut isect: SurfaceInteraction = SurfaceInteraction::default();
 					    if scene.intersect(&mut photon_ray, &mut isect) {
-                                                // TODO: ++totalPhotonSurfaceInteractions;
+                                                VISIBLE_POINTS_CHECKED.add_denom(1);
                                                 if depth > 0 {
                                                     // add photon contribution to nearby visible points
                                                     let mut photon_grid_index: Point3i =
@@ -569,6 +626,7 @@ impl SPPMIntegrator {
                                                             let mut opt = grid_once[h].get();
                                                             while let Some(node) = opt {
                                                                 // deal with linked list
+                                                                VISIBLE_POINTS_CHECKED.add_num(1);
                                                                 let pixel = node.pixel;
                                                                 let radius: Float = pixel.radius;
                                                                     if pnt3_distance_squaredf(
@@ -601,19 +659,9 @@ impl SPPMIntegrator {
                                                                                     &wi,
                                                                                     bsdf_flags,
                                                                                 );
-                                                                            for i in 0..3 {
-                                                                                let rgb_i: RGBEnum =
-                                                                                    match i {
-                                                                                        0 =>
-                                                                                            RGBEnum::Red,
-                                                                                        1 =>
-                                                                                            RGBEnum::Green,
-                                                                                        _ =>
-                                                                                            RGBEnum::Blue,
-                                                                                    };
-                                                                                let phi_i: Float = phi[rgb_i];
+                                                                            for i in 0..Spectrum::N_SAMPLES {
                                                                                 pixel.phi[i]
-                                                                                    .add(phi_i);
+                                                                                    .add(phi[i]);
                                                                             }
                                                                             pixel.m.fetch_add(
                                                                                 1_i32,
@@ -631,6 +679,8 @@ impl SPPMIntegrator {
 
                                                 // compute BSDF at photon intersection point
                                                 let mode: TransportMode = TransportMode::Importance;
+                                                // photons keep the spectral channel a dispersive event picked
+                                                isect.spectral_channel = spectral_channel;
 						isect.compute_scattering_functions(&photon_ray, true, mode);
                                                 if let Some(ref photon_bsdf) = isect.bsdf {
                                                     // sample BSDF _fr_ and direction _wi_ for reflected photon
@@ -651,13 +701,14 @@ impl SPPMIntegrator {
                                                         ),
                                                     };
                                                     halton_dim += 2;
-                                                    let fr: Spectrum = photon_bsdf.sample_f(
+                                                    let fr: Spectrum = photon_bsdf.sample_f_channel(
                                                         &wo,
                                                         &mut wi,
                                                         &bsdf_sample,
                                                         &mut pdf,
                                                         bsdf_flags,
                                                         &mut sampled_type,
+                                                        &mut spectral_channel,
                                                     );
                                                     if fr.is_black() || pdf == 0.0 as Float {
                                                         break;
@@ -707,7 +758,7 @@ impl SPPMIntegrator {
                 }
                 // update pixel values from this pass's photons
                 {
-                    // TODO: ProfilePhase _(Prof::SPPMStatsUpdate);
+                    let _prof = ProfilePhase::new(Prof::SppmStatsUpdate);
                     // println!("Update pixel values from this pass's photons ...");
                     let chunk_size: usize = (n_pixels / num_cores as i32) as usize;
                     {
@@ -728,26 +779,15 @@ impl SPPMIntegrator {
                                             let r_new: Float =
                                                 p.radius * (n_new / (p.n + p_m as Float)).sqrt();
                                             let mut phi: Spectrum = Spectrum::default();
-                                            for j in 0..3 {
-                                                match j {
-                                                    0 => {
-                                                        phi[RGBEnum::Red] = Float::from(&p.phi[j]);
-                                                    }
-                                                    1 => {
-                                                        phi[RGBEnum::Green] =
-                                                            Float::from(&p.phi[j]);
-                                                    }
-                                                    _ => {
-                                                        phi[RGBEnum::Blue] = Float::from(&p.phi[j]);
-                                                    }
-                                                }
+                                            for j in 0..Spectrum::N_SAMPLES {
+                                                phi[j] = Float::from(&p.phi[j]);
                                             }
                                             p.tau = (p.tau + p.vp.beta * phi) * (r_new * r_new)
                                                 / (p.radius * p.radius);
                                             p.n = n_new;
                                             p.radius = r_new;
                                             p.m.store(0, atomic::Ordering::Relaxed);
-                                            for j in 0..3 {
+                                            for j in 0..Spectrum::N_SAMPLES {
                                                 p.phi[j] = AtomicFloat::new(0.0 as Float);
                                             }
                                         }
@@ -771,27 +811,18 @@ impl SPPMIntegrator {
                         .unwrap();
                     }
                 }
+                progress.update(1);
+                // store the photon statistics of all finished iterations
+                if let Some(ref mut timer) = some_timer {
+                    if timer.due() || iteration + 1 == self.n_iterations {
+                        self.write_checkpoint(&checkpoint_header, iteration + 1, &pixels);
+                    }
+                }
                 // periodically store SPPM image in film and write image
                 if iteration + 1 == self.n_iterations
                     || ((iteration + 1) % self.write_frequency) == 0
                 {
-                    let x0: i32 = pixel_bounds.p_min.x;
-                    let x1: i32 = pixel_bounds.p_max.x;
-                    let np: u64 = (iteration + 1) as u64 * self.photons_per_iteration as u64;
-                    let mut image: Vec<Spectrum> = Vec::with_capacity(pixel_bounds.area() as usize);
-                    for y in (pixel_bounds.p_min.y as usize)..(pixel_bounds.p_max.y as usize) {
-                        for x in (x0 as usize)..(x1 as usize) {
-                            // compute radiance _L_ for SPPM pixel _pixel_
-                            let pixel = &pixels[(y - pixel_bounds.p_min.y as usize)
-                                * (x1 as usize - x0 as usize)
-                                + (x - x0 as usize)];
-                            let mut l: Spectrum = pixel.ld / (iteration + 1) as Float;
-                            l += pixel.tau / (np as Float * PI * pixel.radius * pixel.radius);
-                            image.push(l);
-                        }
-                    }
-                    film.set_image(&image[..]);
-                    film.write_image(1.0 as Float);
+                    self.write_image(&film, &pixels, iteration + 1);
                     // TODO: write SPPM radius image, if requested
                     // if (getenv("SPPM_RADIUS")) {
                     //     std::unique_ptr<Float[]> rimg(
@@ -827,12 +858,83 @@ impl SPPMIntegrator {
                     // }
                 }
             }
-            // TODO: progress.Done();
+            if first_iteration == self.n_iterations {
+                // the checkpoint of a finished render
+                self.write_image(&film, &pixels, self.n_iterations);
+            }
+            progress.done();
         }
     }
+    /// Store the SPPM image after *n_iterations* in the film and write
+    /// it to file.
+    fn write_image(&self, film: &Film, pixels: &[SPPMPixel], n_iterations: i32) {
+        let pixel_bounds: Bounds2i = film.cropped_pixel_bounds;
+        let x0: i32 = pixel_bounds.p_min.x;
+        let x1: i32 = pixel_bounds.p_max.x;
+        let np: u64 = n_iterations as u64 * self.photons_per_iteration as u64;
+        let mut image: Vec<Spectrum> = Vec::with_capacity(pixel_bounds.area() as usize);
+        for y in (pixel_bounds.p_min.y as usize)..(pixel_bounds.p_max.y as usize) {
+            for x in (x0 as usize)..(x1 as usize) {
+                // compute radiance _L_ for SPPM pixel _pixel_
+                let pixel = &pixels[(y - pixel_bounds.p_min.y as usize)
+                    * (x1 as usize - x0 as usize)
+                    + (x - x0 as usize)];
+                let mut l: Spectrum = pixel.ld / n_iterations as Float;
+                l += pixel.tau / (np as Float * PI * pixel.radius * pixel.radius);
+                image.push(l);
+            }
+        }
+        film.set_image(&image[..]);
+        film.write_image(1.0 as Float);
+    }
     pub fn get_camera(&self) -> Arc<Camera> {
         self.camera.clone()
     }
+    /// Store the statistics of all pixels after *n_iterations*
+    /// (visible points and photons get recomputed each iteration).
+    fn write_checkpoint(&self, header: &CheckpointHeader, n_iterations: i32, pixels: &[SPPMPixel]) {
+        if let Some(ref checkpoint) = self.camera.get_film().checkpoint {
+            let result: Result<()> = write_checkpoint(&checkpoint.filename, header, |writer| {
+                writer.write_i32::<LittleEndian>(n_iterations)?;
+                writer.write_i32::<LittleEndian>(self.photons_per_iteration)?;
+                write_len(writer, pixels.len())?;
+                for pixel in pixels {
+                    pixel.write_checkpoint_state(writer)?;
+                }
+                Ok(())
+            });
+            if let Err(e) = result {
+                println!(
+                    "ERROR: Unable to write checkpoint {:?}: {}",
+                    checkpoint.filename, e
+                );
+            }
+        }
+    }
+    /// Restore the pixel statistics and return the number of finished
+    /// iterations.
+    fn read_checkpoint_state<R: Read>(
+        &self,
+        reader: &mut R,
+        pixels: &mut [SPPMPixel],
+    ) -> Result<i32> {
+        let n_iterations: i32 = reader.read_i32::<LittleEndian>()?;
+        let photons_per_iteration: i32 = reader.read_i32::<LittleEndian>()?;
+        if photons_per_iteration != self.photons_per_iteration {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "checkpoint was written for {} photons per iteration (expected {})",
+                    photons_per_iteration, self.photons_per_iteration
+                ),
+            ));
+        }
+        read_len(reader, pixels.len())?;
+        for pixel in pixels.iter_mut() {
+            pixel.read_checkpoint_state(reader)?;
+        }
+        Ok(n_iterations)
+    }
 }
 
 #[derive(Default)]
@@ -843,17 +945,55 @@ pub struct VisiblePoint {
     pub beta: Spectrum,
 }
 
-#[derive(Default)]
 pub struct SPPMPixel {
     pub radius: Float,
     pub ld: Spectrum,
     pub vp: VisiblePoint,
-    pub phi: [AtomicFloat; 3],
+    pub phi: Vec<AtomicFloat>,
     pub m: Atomic<i32>,
     pub n: Float,
     pub tau: Spectrum,
 }
 
+impl SPPMPixel {
+    fn write_checkpoint_state<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_floats(writer, &[self.radius, self.n])?;
+        for j in 0..Spectrum::N_SAMPLES {
+            write_floats(writer, &[self.ld[j], self.tau[j]])?;
+        }
+        Ok(())
+    }
+    fn read_checkpoint_state<R: Read>(&mut self, reader: &mut R) -> Result<()> {
+        let mut values: [Float; 2] = [0.0 as Float; 2];
+        read_floats(reader, &mut values)?;
+        self.radius = values[0];
+        self.n = values[1];
+        for j in 0..Spectrum::N_SAMPLES {
+            read_floats(reader, &mut values)?;
+            self.ld[j] = values[0];
+            self.tau[j] = values[1];
+        }
+        Ok(())
+    }
+}
+
+impl Default for SPPMPixel {
+    fn default() -> Self {
+        SPPMPixel {
+            radius: 0.0 as Float,
+            ld: Spectrum::default(),
+            vp: VisiblePoint::default(),
+            // one entry per spectral sample
+            phi: (0..Spectrum::N_SAMPLES)
+                .map(|_| AtomicFloat::default())
+                .collect(),
+            m: Atomic::default(),
+            n: 0.0 as Float,
+            tau: Spectrum::default(),
+        }
+    }
+}
+
 pub struct SPPMPixelListNode<'p> {
     pub pixel: &'p SPPMPixel,
     pub next: AtomSetOnce<Arc<SPPMPixelListNode<'p>>>,
@@ -17,9 +17,13 @@ use crate::core::reflection::BxdfType;
 use crate::core::sampler::Sampler;
 use crate::core::sampling::Distribution1D;
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase, StatCounter};
 
 // see volpath.h
 
+static VOLUME_INTERACTIONS: StatCounter = StatCounter::new("Integrator/Volume interactions");
+static SURFACE_INTERACTIONS: StatCounter = StatCounter::new("Integrator/Surface interactions");
+
 /// Accounts for scattering and attenuation from participating media
 /// as well as scattering from surfaces - uses the render loop of a
 /// [SamplerIntegrator](../../core/integrator/enum.SamplerIntegrator.html)
@@ -66,7 +70,7 @@ impl VolPathIntegrator {
         // arena: &mut Arena,
         _depth: i32,
     ) -> Spectrum {
-        // TODO: ProfilePhase p(Prof::SamplerIntegratorLi);
+        let _prof = ProfilePhase::new(Prof::SamplerIntegratorLi);
         let mut l: Spectrum = Spectrum::default();
         let mut beta: Spectrum = Spectrum::new(1.0 as Float);
         let mut ray: Ray = Ray {
@@ -88,6 +92,8 @@ impl VolPathIntegrator {
         // refracted rays that are about to be refracted back out of a
         // medium and thus have their beta value increased.
         let mut eta_scale: Float = 1.0;
+        // spectral channel a dispersive event restricted the path to
+        let mut spectral_channel: Option<usize> = None;
         loop {
             let mut mi_opt: Option<MediumInteraction> = None;
             // intersect _ray_ with scene and store intersection in _isect_
@@ -113,7 +119,7 @@ impl VolPathIntegrator {
                     let mi_p = mi.common.p;
                     // if mi.is_valid() {...}
                     if let Some(phase) = mi.clone().phase {
-                        // TODO: ++volumeInteractions;
+                        VOLUME_INTERACTIONS.inc();
                         // handle scattering at point in medium for volumetric path tracer
                         if let Some(ref light_distribution) = self.light_distribution {
                             let distrib: Arc<Distribution1D> = light_distribution.lookup(&mi_p);
@@ -132,7 +138,7 @@ impl VolPathIntegrator {
                         }
                     }
                 } else {
-                    // TODO: ++surfaceInteractions;
+                    SURFACE_INTERACTIONS.inc();
                     // possibly add emitted light at intersection
                     if bounces == 0 || specular_bounce {
                         // add emitted light at path vertex
@@ -144,6 +150,8 @@ impl VolPathIntegrator {
                     }
                     // compute scattering functions and skip over medium boundaries
                     let mode: TransportMode = TransportMode::Radiance;
+                    // stay on the spectral channel a dispersive event picked
+                    isect.spectral_channel = spectral_channel;
                     isect.compute_scattering_functions(&ray, true, mode);
                     if let Some(ref _bsdf) = isect.bsdf {
                         // we are fine (for below)
@@ -173,13 +181,14 @@ impl VolPathIntegrator {
                             let mut pdf: Float = 0.0 as Float;
                             let bsdf_flags: u8 = BxdfType::BsdfAll as u8;
                             let mut sampled_type: u8 = u8::max_value(); // != 0
-                            let f: Spectrum = bsdf.sample_f(
+                            let f: Spectrum = bsdf.sample_f_channel(
                                 &wo,
                                 &mut wi,
                                 &sampler.get_2d(),
                                 &mut pdf,
                                 bsdf_flags,
                                 &mut sampled_type,
+                                &mut spectral_channel,
                             );
                             if f.is_black() || pdf == 0.0 as Float {
                                 break;
@@ -217,17 +226,7 @@ impl VolPathIntegrator {
                                     // importance sample the BSSRDF
                                     let s2: Point2f = sampler.get_2d();
                                     let s1: Float = sampler.get_1d();
-                                    let (s, pi_opt) = bssrdf.sample_s(
-                                        // the next three (extra) parameters are used for SeparableBssrdfAdapter
-                                        bssrdf.clone(),
-                                        bssrdf.mode,
-                                        bssrdf.eta,
-                                        // done
-                                        scene,
-                                        s1,
-                                        s2,
-                                        &mut pdf,
-                                    );
+                                    let (s, pi_opt) = bssrdf.sample_s(scene, s1, s2, &mut pdf);
                                     if s.is_black() || pdf == 0.0 as Float {
                                         break;
                                     }
@@ -314,7 +313,7 @@ impl VolPathIntegrator {
                     let mi_p = mi.common.p;
                     // if mi.is_valid() {...}
                     if let Some(phase) = mi.clone().phase {
-                        // TODO: ++volumeInteractions;
+                        VOLUME_INTERACTIONS.inc();
                         // handle scattering at point in medium for volumetric path tracer
                         if let Some(ref light_distribution) = self.light_distribution {
                             let distrib: Arc<Distribution1D> = light_distribution.lookup(&mi_p);
@@ -33,6 +33,7 @@ pub mod integrators;
 pub mod lights;
 pub mod materials;
 pub mod media;
+pub mod parser;
 pub mod samplers;
 pub mod shapes;
 pub mod textures;
@@ -12,6 +12,7 @@ use crate::core::rng::FLOAT_ONE_MINUS_EPSILON;
 use crate::core::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
 use crate::core::scene::Scene;
 use crate::core::shape::Shape;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::core::transform::Transform;
 
 // see diffuse.h
@@ -70,7 +71,7 @@ impl DiffuseAreaLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester<'a, 'b>,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
         *light_intr = self.shape.sample_with_ref_point(iref, u, pdf);
         // TODO: iref.mediumInterface = mediumInterface;
         if *pdf == 0.0 as Float || (light_intr.p - iref.p).length_squared() == 0.0 as Float {
@@ -98,7 +99,7 @@ impl DiffuseAreaLight {
         Spectrum::default()
     }
     pub fn pdf_li(&self, iref: &dyn Interaction, wi: &Vector3f) -> Float {
-        // TODO: ProfilePhase _(Prof::LightPdf);
+        let _prof = ProfilePhase::new(Prof::LightPdf);
         self.shape.pdf_with_ref_point(iref, &wi)
     }
     pub fn sample_le(
@@ -111,7 +112,7 @@ impl DiffuseAreaLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
 
         // sample a point on the area light's _Shape_, _p_shape_
         let ic: InteractionCommon = self.shape.sample(u1, pdf_pos);
@@ -11,6 +11,7 @@ use crate::core::medium::MediumInterface;
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::sampling::concentric_sample_disk;
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::core::transform::Transform;
 
 // see distant.h
@@ -53,7 +54,7 @@ impl DistantLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester<'a, 'b>,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
         *wi = self.w_light;
         *pdf = 1.0 as Float;
         let p_outside: Point3f =
@@ -102,7 +103,7 @@ impl DistantLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
 
         // choose point on disk oriented toward infinite light direction
         let mut v1: Vector3f = Vector3f::default();
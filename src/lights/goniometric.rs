@@ -19,6 +19,7 @@ use crate::core::pbrt::{Float, Spectrum};
 use crate::core::pbrt::{INV_2_PI, INV_PI};
 use crate::core::sampling::{uniform_sample_sphere, uniform_sphere_pdf};
 use crate::core::scene::Scene;
+use crate::core::spectrum::SpectrumType;
 use crate::core::transform::Transform;
 
 // see https://stackoverflow.com/questions/36008434/how-can-i-decode-f16-to-f32-using-only-the-stable-standard-library
@@ -116,10 +117,13 @@ impl GonioPhotometricLight {
                     let mut texels: Vec<Spectrum> = Vec::new();
                     for idx in 0..(resolution.x * resolution.y) {
                         let (r, g, b) = pixel_data[idx as usize];
-                        texels.push(Spectrum::rgb(
-                            decode_f16(r.to_bits()),
-                            decode_f16(g.to_bits()),
-                            decode_f16(b.to_bits()),
+                        texels.push(Spectrum::from_rgb_typed(
+                            &[
+                                decode_f16(r.to_bits()),
+                                decode_f16(g.to_bits()),
+                                decode_f16(b.to_bits()),
+                            ],
+                            SpectrumType::Illuminant,
                         ));
                     }
                     // create _MipMap_ from converted texels (see above)
@@ -186,7 +190,10 @@ impl GonioPhotometricLight {
                     let img_result = hdr.read_image_transform(
                         |p| {
                             let rgb = p.to_hdr();
-                            Spectrum::rgb(rgb[0], rgb[1], rgb[2])
+                            Spectrum::from_rgb_typed(
+                                &[rgb[0], rgb[1], rgb[2]],
+                                SpectrumType::Illuminant,
+                            )
                         },
                         &mut texels,
                     );
@@ -20,6 +20,8 @@ use crate::core::pbrt::{INV_2_PI, INV_PI};
 use crate::core::sampling::concentric_sample_disk;
 use crate::core::sampling::Distribution2D;
 use crate::core::scene::Scene;
+use crate::core::spectrum::SpectrumType;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::core::transform::Transform;
 
 // see https://stackoverflow.com/questions/36008434/how-can-i-decode-f16-to-f32-using-only-the-stable-standard-library
@@ -109,10 +111,13 @@ impl InfiniteAreaLight {
                     for i in 0..(resolution.x * resolution.y) {
                         let (r, g, b) = pixel_data[i as usize];
                         texels.push(
-                            Spectrum::rgb(
-                                decode_f16(r.to_bits()),
-                                decode_f16(g.to_bits()),
-                                decode_f16(b.to_bits()),
+                            Spectrum::from_rgb_typed(
+                                &[
+                                    decode_f16(r.to_bits()),
+                                    decode_f16(g.to_bits()),
+                                    decode_f16(b.to_bits()),
+                                ],
+                                SpectrumType::Illuminant,
                             ) * *l,
                         );
                     }
@@ -194,7 +199,10 @@ impl InfiniteAreaLight {
                     let img_result = hdr.read_image_transform(
                         |p| {
                             let rgb = p.to_hdr();
-                            Spectrum::rgb(rgb[0], rgb[1], rgb[2]) * *l
+                            Spectrum::from_rgb_typed(
+                                &[rgb[0], rgb[1], rgb[2]],
+                                SpectrumType::Illuminant,
+                            ) * *l
                         },
                         &mut texels,
                     );
@@ -304,7 +312,7 @@ impl InfiniteAreaLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester<'a, 'b>,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
         // find $(u,v)$ sample coordinates in infinite light texture
         let mut map_pdf: Float = 0.0 as Float;
         let uv: Point2f = self.distribution.sample_continuous(u, &mut map_pdf);
@@ -331,7 +339,6 @@ impl InfiniteAreaLight {
         }
         // return radiance value for infinite light direction
         let world_radius: Float = *self.world_radius.read().unwrap();
-        // TODO: SpectrumType::Illuminant
         light_intr.p = iref.p + *wi * (2.0 as Float * world_radius);
         light_intr.time = iref.time;
         vis.p0 = Some(&iref);
@@ -344,7 +351,6 @@ impl InfiniteAreaLight {
     pub fn power(&self) -> Spectrum {
         let p: Point2f = Point2f { x: 0.5, y: 0.5 };
         let world_radius: Float = *self.world_radius.read().unwrap();
-        // TODO: SpectrumType::Illuminant
         self.lmap.lookup_pnt_flt(p, 0.5 as Float) * Spectrum::new(PI * world_radius * world_radius)
     }
     /// Like **DistanceLights**, **InfiniteAreaLights** also need the
@@ -372,11 +378,10 @@ impl InfiniteAreaLight {
             x: spherical_phi(&w) * INV_2_PI,
             y: spherical_theta(&w) * INV_PI,
         };
-        // TODO: SpectrumType::Illuminant
         self.lmap.lookup_pnt_flt(st, 0.0 as Float)
     }
     pub fn pdf_li(&self, _iref: &dyn Interaction, w: &Vector3f) -> Float {
-        // TODO: ProfilePhase _(Prof::LightPdf);
+        let _prof = ProfilePhase::new(Prof::LightPdf);
         let wi: Vector3f = self.world_to_light.transform_vector(&w);
         let theta: Float = spherical_theta(&wi);
         let phi: Float = spherical_phi(&wi);
@@ -400,7 +405,7 @@ impl InfiniteAreaLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
 
         // find $(u,v)$ sample coordinates in infinite light texture
         let mut map_pdf: Float = 0.0 as Float;
@@ -443,7 +448,6 @@ impl InfiniteAreaLight {
             *pdf_dir = map_pdf / (2.0 as Float * PI * PI * sin_theta);
         }
         *pdf_pos = 1.0 as Float / (PI * world_radius * world_radius);
-        // TODO: return Spectrum(Lmap->Lookup(uv), SpectrumType::Illuminant);
         self.lmap.lookup_pnt_flt(uv, 0.0 as Float)
     }
     pub fn pdf_le(&self, ray: &Ray, _n_light: &Normal3f, pdf_pos: &mut Float, pdf_dir: &mut Float) {
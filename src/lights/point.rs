@@ -11,6 +11,7 @@ use crate::core::medium::{Medium, MediumInterface};
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::sampling::{uniform_sample_sphere, uniform_sphere_pdf};
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::core::transform::Transform;
 
 // see point.h
@@ -58,7 +59,7 @@ impl PointLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester<'a, 'b>,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
         *wi = (self.p_light - iref.p).normalize();
         *pdf = 1.0 as Float;
         light_intr.p = self.p_light;
@@ -89,7 +90,7 @@ impl PointLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
         *ray = Ray {
             o: self.p_light,
             d: uniform_sample_sphere(u1),
@@ -13,6 +13,7 @@ use crate::core::pbrt::{Float, Spectrum};
 use crate::core::reflection::cos_theta;
 use crate::core::sampling::{uniform_cone_pdf, uniform_sample_cone};
 use crate::core::scene::Scene;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::core::transform::Transform;
 
 // see spot.h
@@ -83,7 +84,7 @@ impl SpotLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester<'a, 'b>,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
         *wi = (self.p_light - iref.p).normalize();
         *pdf = 1.0 as Float;
         // medium_interface2
@@ -129,7 +130,7 @@ impl SpotLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _prof = ProfilePhase::new(Prof::LightSample);
         let w: Vector3f = uniform_sample_cone(u1, self.cos_total_width);
         let mut inside: Option<Arc<Medium>> = None;
         if let Some(ref mi_inside) = self.medium_interface.inside {
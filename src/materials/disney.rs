@@ -3,6 +3,7 @@ use std::sync::Arc;
 
 use num::Zero;
 
+use crate::core::bssrdf::{Bssrdf, DisneyBssrdf};
 use crate::core::geometry::{spherical_direction, vec3_abs_dot_vec3f, vec3_dot_vec3f};
 use crate::core::geometry::{Point2f, Vector3f, XYEnum};
 use crate::core::interaction::SurfaceInteraction;
@@ -83,7 +84,7 @@ impl DisneyMaterial {
         si: &mut SurfaceInteraction,
         mode: TransportMode,
         _allow_multiple_lobes: bool,
-        _material: Option<Arc<Material>>,
+        material: Option<Arc<Material>>,
         scale_opt: Option<Spectrum>,
     ) {
         let mut use_scale: bool = false;
@@ -123,6 +124,7 @@ impl DisneyMaterial {
         let spec_tint = self.specular_tint.evaluate(si);
         let cc = self.clearcoat.evaluate(si);
         let gloss: Float = lerp(self.clearcoat_gloss.evaluate(si), 0.1, 0.001);
+        let mut use_bssrdf: bool = false;
         si.bsdf = Some(Bsdf::new(si, 1.0));
         if let Some(bsdf) = &mut si.bsdf {
             if diffuse_weight > 0.0 {
@@ -179,7 +181,7 @@ impl DisneyMaterial {
                             None,
                         )));
                     }
-                    // TODO: BSSRDF
+                    use_bssrdf = true;
                 }
 
                 // Retro-reflection.
@@ -320,6 +322,16 @@ impl DisneyMaterial {
                 }
             }
         }
+        if use_bssrdf {
+            // Burley's normalized diffusion for the diffuse lobe
+            let mut r: Spectrum = c * diffuse_weight;
+            if use_scale {
+                r *= sc;
+            }
+            si.bssrdf = Some(Bssrdf::Disney(DisneyBssrdf::new(
+                &r, &sd, si, e, material, mode,
+            )));
+        }
     }
 }
 // DisneyDiffuse
@@ -7,9 +7,10 @@ use crate::core::microfacet::{MicrofacetDistribution, TrowbridgeReitzDistributio
 use crate::core::paramset::TextureParams;
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::reflection::{
-    Bsdf, Bxdf, Fresnel, FresnelDielectric, FresnelSpecular, MicrofacetReflection,
-    MicrofacetTransmission, SpecularReflection, SpecularTransmission,
+    Bsdf, Bxdf, DispersiveSpecular, Fresnel, FresnelDielectric, FresnelSpecular,
+    MicrofacetReflection, MicrofacetTransmission, SpecularReflection, SpecularTransmission,
 };
+use crate::core::spectrum::interpolate_spectrum_samples;
 use crate::core::texture::Texture;
 
 // see glass.h
@@ -24,6 +25,8 @@ pub struct GlassMaterial {
     pub index: Arc<dyn Texture<Float> + Sync + Send>,
     pub bump_map: Option<Arc<dyn Texture<Float> + Send + Sync>>,
     pub remap_roughness: bool,
+    /// index of refraction per spectral channel (dispersive glass)
+    pub dispersive_eta: Option<Spectrum>,
 }
 
 impl GlassMaterial {
@@ -35,6 +38,7 @@ impl GlassMaterial {
         index: Arc<dyn Texture<Float> + Send + Sync>,
         bump_map: Option<Arc<dyn Texture<Float> + Sync + Send>>,
         remap_roughness: bool,
+        dispersive_eta: Option<Spectrum>,
     ) -> Self {
         GlassMaterial {
             kr,
@@ -44,8 +48,74 @@ impl GlassMaterial {
             index,
             bump_map,
             remap_roughness,
+            dispersive_eta,
         }
     }
+    /// The index of refraction per spectral channel can be given by
+    /// Cauchy's equation ("cauchy" [A B], with B in $\mu m^2$), the
+    /// Sellmeier equation ("sellmeierB" [B1 B2 B3] and "sellmeierC"
+    /// [C1 C2 C3], with C in $\mu m^2$) or tabulated ("etaspectrum"
+    /// [lambda0 eta0 lambda1 eta1 ...], with lambda in nm). Only
+    /// smooth glass is dispersive, rough glass uses the average index
+    /// of refraction.
+    pub fn get_dispersive_eta(mp: &mut TextureParams) -> Option<Spectrum> {
+        let cauchy: Vec<Float> = mp.find_floats("cauchy");
+        let sellmeier_b: Vec<Float> = mp.find_floats("sellmeierB");
+        let sellmeier_c: Vec<Float> = mp.find_floats("sellmeierC");
+        let eta_spectrum: Vec<Float> = mp.find_floats("etaspectrum");
+        let ior = |lambda: Float| -> Option<Float> {
+            // wavelength in micrometers
+            let l: Float = lambda / 1000.0 as Float;
+            if !cauchy.is_empty() {
+                if cauchy.len() != 2 {
+                    return None;
+                }
+                Some(cauchy[0] + cauchy[1] / (l * l))
+            } else if !sellmeier_b.is_empty() || !sellmeier_c.is_empty() {
+                if sellmeier_b.len() != sellmeier_c.len() {
+                    return None;
+                }
+                let mut n2: Float = 1.0 as Float;
+                for (b, c) in sellmeier_b.iter().zip(sellmeier_c.iter()) {
+                    n2 += b * l * l / (l * l - c);
+                }
+                Some(n2.sqrt())
+            } else if !eta_spectrum.is_empty() {
+                if eta_spectrum.len() % 2 != 0 {
+                    return None;
+                }
+                let lambdas: Vec<Float> = eta_spectrum.iter().step_by(2).copied().collect();
+                let etas: Vec<Float> = eta_spectrum.iter().skip(1).step_by(2).copied().collect();
+                Some(interpolate_spectrum_samples(
+                    &lambdas,
+                    &etas,
+                    lambdas.len() as i32,
+                    lambda,
+                ))
+            } else {
+                None
+            }
+        };
+        if cauchy.is_empty()
+            && sellmeier_b.is_empty()
+            && sellmeier_c.is_empty()
+            && eta_spectrum.is_empty()
+        {
+            return None;
+        }
+        let mut eta: Spectrum = Spectrum::default();
+        for c in 0..Spectrum::N_SAMPLES {
+            if let Some(n) = ior(Spectrum::wavelength(c)) {
+                eta[c] = n;
+            } else {
+                println!(
+                    "ERROR: Invalid \"cauchy\", \"sellmeierB\"/\"sellmeierC\" or \"etaspectrum\" values for glass. Ignoring dispersion."
+                );
+                return None;
+            }
+        }
+        Some(eta)
+    }
     pub fn create(mp: &mut TextureParams) -> Arc<Material> {
         let kr = mp.get_spectrum_texture("Kr", Spectrum::new(1.0 as Float));
         let kt = mp.get_spectrum_texture("Kt", Spectrum::new(1.0 as Float));
@@ -53,6 +123,19 @@ impl GlassMaterial {
         let roughv = mp.get_float_texture("vroughness", 0.0 as Float);
         let bump_map = mp.get_float_texture_or_null("bumpmap");
         let remap_roughness: bool = mp.find_bool("remaproughness", true);
+        let dispersive_eta: Option<Spectrum> = GlassMaterial::get_dispersive_eta(mp);
+        if dispersive_eta.is_some() {
+            let is_rough: bool = ["uroughness", "vroughness"].iter().any(|name| {
+                mp.find_float(name, 0.0 as Float) != 0.0 as Float
+                    || !mp.geom_params.find_texture(name).is_empty()
+                    || !mp.material_params.find_texture(name).is_empty()
+            });
+            if is_rough {
+                println!(
+                    "WARNING: Dispersion is only supported for smooth glass. Using the average index of refraction for rough glass."
+                );
+            }
+        }
         let eta_option: Option<Arc<dyn Texture<Float> + Send + Sync>> =
             mp.get_float_texture_or_null("eta");
         if let Some(ref eta) = eta_option {
@@ -64,6 +147,7 @@ impl GlassMaterial {
                 eta.clone(),
                 bump_map,
                 remap_roughness,
+                dispersive_eta,
             ))))
         } else {
             let eta: Arc<dyn Texture<Float> + Send + Sync> =
@@ -76,6 +160,7 @@ impl GlassMaterial {
                 eta,
                 bump_map,
                 remap_roughness,
+                dispersive_eta,
             ))))
         }
     }
@@ -109,11 +194,31 @@ impl GlassMaterial {
             .evaluate(si)
             .clamp(0.0 as Float, std::f32::INFINITY as Float);
         let is_specular: bool = urough == 0.0 as Float && vrough == 0.0 as Float;
-        let eta: Float = self.index.evaluate(si);
+        let eta: Float = if let Some(dispersive_eta) = self.dispersive_eta {
+            // average index of refraction
+            let mut sum: Float = 0.0 as Float;
+            for c in 0..Spectrum::N_SAMPLES {
+                sum += dispersive_eta[c];
+            }
+            sum / Spectrum::N_SAMPLES as Float
+        } else {
+            self.index.evaluate(si)
+        };
+        let spectral_channel: Option<usize> = si.spectral_channel;
         si.bsdf = Some(Bsdf::new(si, eta));
         if let Some(bsdf) = &mut si.bsdf {
             if is_specular && allow_multiple_lobes {
-                if use_scale {
+                if let Some(dispersive_eta) = self.dispersive_eta {
+                    bsdf.add(Bxdf::DispersiveSpec(DispersiveSpecular::new(
+                        r,
+                        t,
+                        1.0 as Float,
+                        dispersive_eta,
+                        mode,
+                        spectral_channel,
+                        scale_opt,
+                    )));
+                } else if use_scale {
                     bsdf.add(Bxdf::FresnelSpec(FresnelSpecular::new(
                         r,
                         t,
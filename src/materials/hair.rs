@@ -307,17 +307,17 @@ impl HairBSDF {
     }
     pub fn sigma_a_from_reflectance(c: Spectrum, beta_n: Float) -> Spectrum {
         let mut sigma_a: Spectrum = Spectrum::default();
-        for i in 0..3 {
+        for i in 0..Spectrum::N_SAMPLES {
             let sqr: Float = beta_n * beta_n;
             let pow3: Float = sqr * beta_n;
             let pow4: Float = pow3 * beta_n;
             let pow5: Float = pow4 * beta_n;
-            let f: Float = c.c[i].ln()
+            let f: Float = c[i].ln()
                 / (5.969 as Float - 0.215 as Float * beta_n + 2.532 as Float * sqr
                     - 10.73 as Float * pow3
                     + 5.574 as Float * pow4
                     + 0.245 as Float * pow5);
-            sigma_a.c[i] = f * f;
+            sigma_a[i] = f * f;
         }
         sigma_a
     }
@@ -9,9 +9,10 @@ use crate::core::microfacet::{
 };
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::reflection::{
-    Bxdf, FourierBSDF, Fresnel, FresnelBlend, FresnelConductor, FresnelDielectric, FresnelNoOp,
-    FresnelSpecular, LambertianReflection, LambertianTransmission, MicrofacetReflection,
-    MicrofacetTransmission, OrenNayar, SpecularReflection, SpecularTransmission,
+    Bxdf, DispersiveSpecular, FourierBSDF, Fresnel, FresnelBlend, FresnelConductor,
+    FresnelDielectric, FresnelNoOp, FresnelSpecular, LambertianReflection, LambertianTransmission,
+    MicrofacetReflection, MicrofacetTransmission, OrenNayar, SpecularReflection,
+    SpecularTransmission,
 };
 use crate::core::texture::Texture;
 use crate::materials::disney::{
@@ -67,6 +68,8 @@ impl MixMaterial {
             si.common.time,
             si.shape,
         );
+        si2.face_index = si.face_index;
+        si2.spectral_channel = si.spectral_channel;
         self.m1
             .compute_scattering_functions(si, mode, allow_multiple_lobes, None, Some(s1));
         self.m2
@@ -119,6 +122,17 @@ impl MixMaterial {
                                 bxdf.sc_opt,
                             )))
                         }
+                        Bxdf::DispersiveSpec(bxdf) => {
+                            bsdf1.add(Bxdf::DispersiveSpec(DispersiveSpecular::new(
+                                bxdf.r,
+                                bxdf.t,
+                                bxdf.eta_a,
+                                bxdf.eta_b,
+                                bxdf.mode,
+                                bxdf.channel,
+                                bxdf.sc_opt,
+                            )))
+                        }
                         Bxdf::LambertianRefl(bxdf) => bsdf1.add(Bxdf::LambertianRefl(
                             LambertianReflection::new(bxdf.r, bxdf.sc_opt),
                         )),
@@ -4,6 +4,7 @@ use std::sync::Arc;
 // use time::PreciseTime;
 // pbrt
 use crate::core::bssrdf::compute_beam_diffusion_bssrdf;
+use crate::core::bssrdf::Bssrdf;
 use crate::core::bssrdf::BssrdfTable;
 use crate::core::bssrdf::TabulatedBssrdf;
 use crate::core::interaction::SurfaceInteraction;
@@ -264,7 +265,7 @@ impl SubsurfaceMaterial {
                     .sigma_s
                     .evaluate(si)
                     .clamp(0.0 as Float, std::f32::INFINITY as Float);
-            si.bssrdf = Some(TabulatedBssrdf::new(
+            si.bssrdf = Some(Bssrdf::Tabulated(TabulatedBssrdf::new(
                 si,
                 material,
                 mode,
@@ -272,7 +273,7 @@ impl SubsurfaceMaterial {
                 &sig_a,
                 &sig_s,
                 self.table.clone(),
-            ));
+            )));
         }
     }
 }
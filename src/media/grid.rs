@@ -8,11 +8,13 @@ use crate::core::medium::{HenyeyGreenstein, Medium};
 use crate::core::pbrt::lerp;
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::sampler::Sampler;
-use crate::core::spectrum::RGBEnum;
+use crate::core::stats::{Prof, ProfilePhase, StatRatio};
 use crate::core::transform::Transform;
 
 // see grid.h
 
+static N_TR_STEPS: StatRatio = StatRatio::new("Media/Grid steps per Tr() call");
+
 pub struct GridDensityMedium {
     pub sigma_a: Spectrum,
     pub sigma_s: Spectrum,
@@ -50,7 +52,7 @@ impl GridDensityMedium {
             nz,
             world_to_medium: Transform::inverse(medium_to_world),
             density: d,
-            sigma_t: (*sigma_s + *sigma_a)[RGBEnum::Red],
+            sigma_t: (*sigma_s + *sigma_a)[0],
             inv_max_density: 1.0 as Float / max_density,
         }
     }
@@ -153,8 +155,8 @@ impl GridDensityMedium {
     }
     // Medium
     pub fn tr(&self, r_world: &Ray, sampler: &mut Sampler) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::MediumTr);
-        // TODO: ++nTrCalls;
+        let _prof = ProfilePhase::new(Prof::MediumTr);
+        N_TR_STEPS.add_denom(1);
         let mut in_ray: Ray = Ray::default();
         in_ray.o = r_world.o;
         in_ray.d = r_world.d.normalize();
@@ -183,7 +185,7 @@ impl GridDensityMedium {
         let mut tr: Float = 1.0;
         let mut t: Float = t_min;
         loop {
-            // TODO: ++nTrSteps;
+            N_TR_STEPS.add_num(1);
             t -= (1.0 as Float - sampler.get_1d()).ln() * self.inv_max_density / self.sigma_t;
             if t >= t_max {
                 break;
@@ -209,7 +211,7 @@ impl GridDensityMedium {
         r_world: &Ray,
         sampler: &mut Sampler,
     ) -> (Spectrum, Option<MediumInteraction>) {
-        // TODO: ProfilePhase _(Prof::MediumSample);
+        let _prof = ProfilePhase::new(Prof::MediumSample);
         let mut in_ray: Ray = Ray::default();
         in_ray.o = r_world.o;
         in_ray.d = r_world.d.normalize();
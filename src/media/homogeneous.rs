@@ -1,15 +1,13 @@
 // std
 use std::f32;
 use std::sync::Arc;
-// others
-use strum::IntoEnumIterator;
 // pbrt
 use crate::core::geometry::Ray;
 use crate::core::interaction::MediumInteraction;
 use crate::core::medium::{HenyeyGreenstein, Medium};
 use crate::core::pbrt::{Float, Spectrum};
 use crate::core::sampler::Sampler;
-use crate::core::spectrum::RGBEnum;
+use crate::core::stats::{Prof, ProfilePhase};
 
 // see homogeneous.h
 
@@ -31,7 +29,7 @@ impl HomogeneousMedium {
     }
     // Medium
     pub fn tr(&self, ray: &Ray, _sampler: &mut Sampler) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::MediumTr);
+        let _prof = ProfilePhase::new(Prof::MediumTr);
         (-self.sigma_t * (ray.t_max.get() * ray.d.length()).min(f32::MAX)).exp()
     }
     pub fn sample(
@@ -39,15 +37,11 @@ impl HomogeneousMedium {
         ray: &Ray,
         sampler: &mut Sampler,
     ) -> (Spectrum, Option<MediumInteraction>) {
-        // TODO: ProfilePhase _(Prof::MediumSample);
+        let _prof = ProfilePhase::new(Prof::MediumSample);
         // sample a channel and distance along the ray
-        let channel: usize = ((sampler.get_1d() * 3.0 as Float) as usize).min(2_usize);
-        let channel_rgb: RGBEnum = match channel {
-            0 => RGBEnum::Red,
-            1 => RGBEnum::Green,
-            _ => RGBEnum::Blue,
-        };
-        let dist: Float = -((1.0 as Float - sampler.get_1d()).ln()) / self.sigma_t[channel_rgb];
+        let channel: usize = ((sampler.get_1d() * Spectrum::N_SAMPLES as Float) as usize)
+            .min(Spectrum::N_SAMPLES - 1);
+        let dist: Float = -((1.0 as Float - sampler.get_1d()).ln()) / self.sigma_t[channel];
         let t: Float = (dist / ray.d.length()).min(ray.t_max.get());
         let sampled_medium: bool = t < ray.t_max.get();
         let mi_opt = if sampled_medium {
@@ -74,11 +68,10 @@ impl HomogeneousMedium {
             tr
         };
         let mut pdf: Float = 0.0 as Float;
-        for i in RGBEnum::iter() {
-            // TODO: Spectrum::nSamples
+        for i in 0..Spectrum::N_SAMPLES {
             pdf += density[i];
         }
-        pdf *= 1.0 as Float / 3.0 as Float; // TODO: Spectrum::nSamples
+        pdf *= 1.0 as Float / Spectrum::N_SAMPLES as Float;
         if pdf == 0.0 as Float {
             assert!(tr.is_black());
             pdf = 1.0 as Float;
@@ -0,0 +1,1448 @@
+//! Parser for the scene description files (extension .pbrt) of
+//! [pbrt-v3](https://www.pbrt.org/fileformat-v3.html), calling the
+//! `pbrt_*` functions of the [api](../core/api/index.html) for each
+//! statement.
+//!
+//! Errors are returned as a **ParseError** (file name, line, column
+//! and the offending token), non-fatal problems (e.g. unknown
+//! parameters or plugin names) are printed to standard error and
+//! collected as **ParseWarning**s.
+//! Scenes written for pbrt-v4 are translated by the [v4](v4/index.html)
+//! module.
+//!
+//! ```rust
+//! use rs_pbrt::core::api::pbrt_init;
+//! use rs_pbrt::core::pbrt::Options;
+//! use rs_pbrt::parser::parse_str;
+//!
+//! fn main() {
+//!     let (mut api_state, mut bsdf_state) = pbrt_init(Options::default());
+//!     let scene: &str = "LookAt 0 0 5  0 0 0  0 1 0\n\
+//!                        Camera \"perspective\" \"float fov\" [ 45 ]\n\
+//!                        Sampler \"halton\" \"flaot pixelsamples\" [ 4 ]\n";
+//!     let warnings = parse_str(scene, &mut api_state, &mut bsdf_state).unwrap();
+//!     assert_eq!(warnings.len(), 1);
+//!     assert_eq!(warnings[0].line, 3);
+//!     let error = parse_str("LookAt 0 0 5\n", &mut api_state, &mut bsdf_state).unwrap_err();
+//!     assert_eq!(error.line, 1);
+//! }
+//! ```
+
+pub mod v4;
+
+// std
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+// others
+use pest::Parser;
+use pest_derive::*;
+// pbrt
+use crate::core::api::{
+    pbrt_accelerator, pbrt_active_transform_all, pbrt_active_transform_end_time,
+    pbrt_active_transform_start_time, pbrt_area_light_source, pbrt_attribute_begin,
+    pbrt_attribute_end, pbrt_camera, pbrt_cleanup, pbrt_concat_transform, pbrt_coord_sys_transform,
+    pbrt_coordinate_system, pbrt_film, pbrt_identity, pbrt_integrator, pbrt_light_source,
+    pbrt_look_at, pbrt_make_named_material, pbrt_make_named_medium, pbrt_material,
+    pbrt_medium_interface, pbrt_named_material, pbrt_object_begin, pbrt_object_end,
+    pbrt_object_instance, pbrt_pixel_filter, pbrt_reverse_orientation, pbrt_rotate, pbrt_sampler,
+    pbrt_scale, pbrt_shape, pbrt_texture, pbrt_transform, pbrt_transform_begin, pbrt_transform_end,
+    pbrt_transform_times, pbrt_translate, pbrt_world_begin,
+};
+use crate::core::api::{ApiState, BsdfState};
+use crate::core::geometry::{Normal3f, Point2f, Point3f, Vector3f};
+use crate::core::paramset::ParamSet;
+use crate::core::pbrt::Float;
+use crate::core::transform::Transform;
+
+#[derive(Parser)]
+#[grammar = "parser/rs_pbrt.pest"]
+struct PbrtParser;
+
+/// Error while parsing a scene file, pointing to the offending
+/// token.
+#[derive(Debug)]
+pub struct ParseError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub message: String,
+    /// files (and lines) which (recursively) included the file
+    pub include_stack: Vec<(String, usize)>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: ERROR: {}", self.file, self.message)?;
+        } else {
+            write!(
+                f,
+                "{}:{}:{}: ERROR: {}",
+                self.file, self.line, self.column, self.message
+            )?;
+        }
+        if !self.token.is_empty() {
+            write!(f, " (found {:?})", self.token)?;
+        }
+        for (file, line) in self.include_stack.iter().rev() {
+            write!(f, "\n    included from {}:{}", file, line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Non-fatal problem found while parsing (e.g. an unknown parameter
+/// or plugin name), which gets reported but does not stop rendering.
+#[derive(Debug)]
+pub struct ParseWarning {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.file.is_empty() {
+            // e.g. parameters set via the API instead of a scene file
+            write!(f, "WARNING: {}", self.message)
+        } else {
+            write!(
+                f,
+                "{}:{}:{}: WARNING: {}",
+                self.file, self.line, self.column, self.message
+            )
+        }
+    }
+}
+
+/// Error within a single statement (offset into the statement's text).
+struct StatementError {
+    pos: usize,
+    token: String,
+    message: String,
+}
+
+impl StatementError {
+    fn new(pair: &pest::iterators::Pair<Rule>, message: &str) -> Self {
+        StatementError {
+            pos: pair.as_span().start(),
+            token: String::from(pair.as_str()),
+            message: String::from(message),
+        }
+    }
+}
+
+/// A statement (identifier and parameters) collected from one or
+/// more lines. Remembers where each piece started in the file.
+#[derive(Default)]
+struct Statement {
+    text: String,
+    // offset into text, line, column
+    pieces: Vec<(usize, usize, usize)>,
+}
+
+impl Statement {
+    fn new(identifier: &str, line_col: (usize, usize)) -> Self {
+        Statement {
+            text: String::from(identifier),
+            pieces: vec![(0_usize, line_col.0, line_col.1)],
+        }
+    }
+    fn identifier(&self) -> &str {
+        match self.text.find(' ') {
+            Some(end) => &self.text[..end],
+            None => &self.text,
+        }
+    }
+    fn params(&self) -> &str {
+        match self.text.find(' ') {
+            Some(end) => &self.text[end + 1..],
+            None => "",
+        }
+    }
+    fn append(&mut self, piece: &str, line_col: (usize, usize)) {
+        if piece.is_empty() {
+            return;
+        }
+        self.text += " ";
+        self.pieces.push((self.text.len(), line_col.0, line_col.1));
+        self.text += piece;
+    }
+    fn line_col(&self, pos: usize) -> (usize, usize) {
+        let mut line_col: (usize, usize) = (0_usize, 0_usize);
+        for (offset, line, column) in &self.pieces {
+            if *offset > pos {
+                break;
+            }
+            line_col = (*line, column + pos - offset);
+        }
+        line_col
+    }
+}
+
+/// Keeps track of included files and collects warnings.
+#[derive(Default)]
+struct ParserState {
+    /// file names and line of the statement being processed
+    include_stack: Vec<(String, usize)>,
+    warnings: Vec<ParseWarning>,
+    /// location of a `WorldBegin` still waiting for its `WorldEnd`
+    world_begin: Option<(String, usize, usize)>,
+}
+
+impl ParserState {
+    fn current_file(&self) -> String {
+        match self.include_stack.last() {
+            Some((file, _line)) => file.clone(),
+            None => String::new(),
+        }
+    }
+    /// Relative file names are resolved relative to the including file.
+    fn resolve_filename(&self, api_state: &ApiState, name: &str) -> String {
+        resolve_filename(&self.current_file(), api_state, name)
+    }
+    fn error(&self, statement: &Statement, error: StatementError) -> ParseError {
+        let (line, column) = statement.line_col(error.pos);
+        let mut include_stack: Vec<(String, usize)> = self.include_stack.clone();
+        include_stack.pop();
+        ParseError {
+            file: self.current_file(),
+            line,
+            column,
+            token: error.token,
+            message: error.message,
+            include_stack,
+        }
+    }
+    fn warning(&mut self, statement: &Statement, pos: usize, message: String) {
+        let (line, column) = statement.line_col(pos);
+        let warning = ParseWarning {
+            file: self.current_file(),
+            line,
+            column,
+            message,
+        };
+        eprintln!("{}", warning);
+        self.warnings.push(warning);
+    }
+}
+
+/// Resolves *name* relative to the directory of *current_file* (or
+/// the search directory for scenes given as a string).
+fn resolve_filename(current_file: &str, api_state: &ApiState, name: &str) -> String {
+    let path: &Path = Path::new(name);
+    if path.is_absolute() {
+        return String::from(name);
+    }
+    let current_path: &Path = Path::new(current_file);
+    let directory: PathBuf = if current_file != "<string>" && !current_file.is_empty() {
+        match current_path.parent() {
+            Some(parent) => PathBuf::from(parent),
+            None => PathBuf::new(),
+        }
+    } else if let Some(ref search_directory) = api_state.search_directory {
+        PathBuf::from(search_directory.as_ref())
+    } else {
+        PathBuf::new()
+    };
+    String::from(directory.join(path).to_str().unwrap())
+}
+
+// parameter types of the grammar (see rs_pbrt.pest)
+const PARAM_TYPE_NAMES: &[&str] = &[
+    "bool",
+    "blackbody",
+    "float",
+    "integer",
+    "point2",
+    "point",
+    "vector",
+    "normal",
+    "rgb",
+    "color",
+    "spectrum",
+    "string",
+    "texture",
+];
+
+// known plugin names (see api.rs)
+const ACCELERATORS: &[&str] = &["bvh", "kdtree"];
+const CAMERAS: &[&str] = &["perspective", "orthographic", "realistic", "environment"];
+const FILMS: &[&str] = &["image"];
+const FILTERS: &[&str] = &["box", "gaussian", "mitchell", "sinc", "triangle"];
+const INTEGRATORS: &[&str] = &[
+    "whitted",
+    "directlighting",
+    "path",
+    "volpath",
+    "bdpt",
+    "mlt",
+    "ambientocclusion",
+    "sppm",
+];
+const LIGHTS: &[&str] = &[
+    "point",
+    "spot",
+    "goniometric",
+    "projection",
+    "distant",
+    "infinite",
+    "exinfinite",
+];
+const AREA_LIGHTS: &[&str] = &["area", "diffuse"];
+const MATERIALS: &[&str] = &[
+    "",
+    "none",
+    "matte",
+    "plastic",
+    "translucent",
+    "glass",
+    "mirror",
+    "hair",
+    "mix",
+    "metal",
+    "substrate",
+    "uber",
+    "subsurface",
+    "kdsubsurface",
+    "fourier",
+    "disney",
+];
+const MEDIA: &[&str] = &["homogeneous", "heterogeneous"];
+const SAMPLERS: &[&str] = &[
+    "lowdiscrepancy",
+    "02sequence",
+    "maxmindist",
+    "halton",
+    "sobol",
+    "random",
+    "stratified",
+];
+const SHAPES: &[&str] = &[
+    "sphere",
+    "cylinder",
+    "disk",
+    "cone",
+    "paraboloid",
+    "hyperboloid",
+    "curve",
+    "trianglemesh",
+    "plymesh",
+    "heightfield",
+    "loopsubdiv",
+    "nurbs",
+];
+const TEXTURES: &[&str] = &[
+    "constant",
+    "scale",
+    "mix",
+    "bilerp",
+    "imagemap",
+    "uv",
+    "checkerboard",
+    "dots",
+    "fbm",
+    "wrinkled",
+    "marble",
+    "windy",
+    "ptex",
+];
+
+/// Returns a warning message if a plugin name isn't known.
+fn check_plugin_name(identifier: &str, params: &ParamSet) -> Option<String> {
+    let (kind, name, known): (&str, String, &[&str]) = match identifier {
+        "Accelerator" => ("accelerator", params.name.clone(), ACCELERATORS),
+        "AreaLightSource" => ("area light", params.name.clone(), AREA_LIGHTS),
+        "Camera" => ("camera", params.name.clone(), CAMERAS),
+        "Film" => ("film", params.name.clone(), FILMS),
+        "Integrator" => ("integrator", params.name.clone(), INTEGRATORS),
+        "LightSource" => ("light", params.name.clone(), LIGHTS),
+        "MakeNamedMaterial" => (
+            "material",
+            params.find_one_string("type", String::new()),
+            MATERIALS,
+        ),
+        "MakeNamedMedium" => {
+            let medium_type: String = params.find_one_string("type", String::new());
+            if medium_type.is_empty() {
+                // reported by pbrt_make_named_medium()
+                return None;
+            }
+            ("medium", medium_type, MEDIA)
+        }
+        "Material" => ("material", params.name.clone(), MATERIALS),
+        "PixelFilter" => ("filter", params.name.clone(), FILTERS),
+        "Sampler" => ("sampler", params.name.clone(), SAMPLERS),
+        "Shape" => ("shape", params.name.clone(), SHAPES),
+        "Texture" => {
+            if params.tex_type != "float"
+                && params.tex_type != "color"
+                && params.tex_type != "spectrum"
+            {
+                return Some(format!("Texture type {:?} unknown", params.tex_type));
+            }
+            ("texture", params.tex_name.clone(), TEXTURES)
+        }
+        _ => return None,
+    };
+    if known.contains(&name.as_str()) {
+        None
+    } else {
+        Some(format!("{} {:?} unknown", kind, name))
+    }
+}
+
+fn pbrt_float(pair: &pest::iterators::Pair<Rule>) -> Result<Float, StatementError> {
+    f32::from_str(pair.as_span().as_str())
+        .map_err(|_| StatementError::new(pair, "expected a floating point number"))
+}
+
+fn pbrt_integer(pair: &pest::iterators::Pair<Rule>) -> Result<i32, StatementError> {
+    i32::from_str(pair.as_span().as_str())
+        .map_err(|_| StatementError::new(pair, "expected an integer"))
+}
+
+fn pbrt_bool_parameter(pairs: &mut pest::iterators::Pairs<Rule>) -> (String, bool) {
+    // single string with or without brackets
+    let ident = pairs.next();
+    let string: String = String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+    let option = pairs.next();
+    let lbrack = option.clone().unwrap();
+    let string2 = if lbrack.as_str() == "[" {
+        // check for brackets
+        let string = pairs.next();
+        let pair = string.unwrap().clone();
+        let ident = pair.into_inner().next();
+        String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap()
+    } else {
+        // no brackets
+        let string = option.clone();
+        let pair = string.unwrap().clone();
+        let ident = pair.into_inner().next();
+        String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap()
+    };
+    // return boolean (instead of string)
+    let b: bool;
+    if string2 == "true" {
+        b = true;
+    } else if string2 == "false" {
+        b = false
+    } else {
+        println!(
+            "WARNING: parameter {:?} not well defined, defaulting to false",
+            string
+        );
+        b = false
+    }
+    (string, b)
+}
+
+fn pbrt_float_parameter(
+    pairs: &mut pest::iterators::Pairs<Rule>,
+) -> Result<(String, Vec<Float>), StatementError> {
+    let mut floats: Vec<Float> = Vec::new();
+    // single float or several floats using brackets
+    let ident = pairs.next();
+    let string: String = String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+    let option = pairs.next();
+    let lbrack = option.clone().unwrap();
+    if lbrack.as_str() == "[" {
+        // check for brackets
+        let mut number = pairs.next();
+        while number.is_some() {
+            let pair = number.unwrap().clone();
+            if pair.as_str() == "]" {
+                // closing bracket found
+                break;
+            } else {
+                let float: Float = pbrt_float(&pair)?;
+                floats.push(float);
+            }
+            number = pairs.next();
+        }
+    } else {
+        // no brackets
+        let mut number = option.clone();
+        while number.is_some() {
+            let pair = number.unwrap().clone();
+            let float: Float = pbrt_float(&pair)?;
+            floats.push(float);
+            number = pairs.next();
+        }
+    }
+    Ok((string, floats))
+}
+
+fn pbrt_integer_parameter(
+    pairs: &mut pest::iterators::Pairs<Rule>,
+) -> Result<(String, Vec<i32>), StatementError> {
+    let mut integers: Vec<i32> = Vec::new();
+    // single integer or several integers using brackets
+    let ident = pairs.next();
+    let string: String = String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+    let option = pairs.next();
+    let lbrack = option.clone().unwrap();
+    if lbrack.as_str() == "[" {
+        // check for brackets
+        let mut number = pairs.next();
+        while number.is_some() {
+            let pair = number.unwrap().clone();
+            if pair.as_str() == "]" {
+                // closing bracket found
+                break;
+            } else {
+                let integer: i32 = pbrt_integer(&pair)?;
+                integers.push(integer);
+            }
+            number = pairs.next();
+        }
+    } else {
+        // no brackets
+        let mut number = option.clone();
+        while number.is_some() {
+            let pair = number.unwrap().clone();
+            let integer: i32 = pbrt_integer(&pair)?;
+            integers.push(integer);
+            number = pairs.next();
+        }
+    }
+    Ok((string, integers))
+}
+
+fn pbrt_string_parameter(pairs: &mut pest::iterators::Pairs<Rule>) -> (String, Vec<String>) {
+    let mut strings: Vec<String> = Vec::new();
+    // single string or several strings using brackets
+    let ident = pairs.next();
+    let string1: String = String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+    let option = pairs.next();
+    let lbrack = option.clone().unwrap();
+    if lbrack.as_str() == "[" {
+        // check for brackets
+        let mut string = pairs.next();
+        while string.is_some() {
+            let pair = string.unwrap().clone();
+            if pair.as_str() == "]" {
+                // closing bracket found
+                break;
+            } else {
+                let ident = pair.into_inner().next();
+                strings.push(String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap());
+            }
+            string = pairs.next();
+        }
+    } else {
+        // no brackets
+        let string = option.clone();
+        let pair = string.unwrap().clone();
+        let ident = pair.into_inner().next();
+        strings.push(String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap());
+    }
+    (string1, strings)
+}
+
+fn pbrt_texture_parameter(pairs: &mut pest::iterators::Pairs<Rule>) -> (String, String) {
+    // single string with or without brackets
+    let ident = pairs.next();
+    let string1: String = String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+    let option = pairs.next();
+    let lbrack = option.clone().unwrap();
+    let string2 = if lbrack.as_str() == "[" {
+        // check for brackets
+        let string = pairs.next();
+        let pair = string.unwrap().clone();
+        let ident = pair.into_inner().next();
+        String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap()
+    } else {
+        // no brackets
+        let string = option.clone();
+        let pair = string.unwrap().clone();
+        let ident = pair.into_inner().next();
+        String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap()
+    };
+    (string1, string2)
+}
+
+fn extract_params(
+    key_word: String,
+    pairs: pest::iterators::Pair<Rule>,
+) -> Result<ParamSet, StatementError> {
+    let mut params: ParamSet = ParamSet {
+        key_word,
+        ..Default::default()
+    };
+    let mut counter: u8 = 0_u8;
+    for pair in pairs.into_inner() {
+        // let span = pair.clone().as_span();
+        // println!("Rule:    {:?}", pair.as_rule());
+        // println!("Span:    {:?}", span);
+        // println!("Text:    {}", span.as_str());
+        match pair.as_rule() {
+            Rule::identifier => {
+                // ignore (was added above)
+            }
+            Rule::empty_string => {}
+            Rule::string => {
+                match counter {
+                    0 => {
+                        // name
+                        let mut string_pairs = pair.into_inner();
+                        let ident = string_pairs.next();
+                        params.name =
+                            String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+                    }
+                    1 => {
+                        // tex_type
+                        let mut string_pairs = pair.into_inner();
+                        let ident = string_pairs.next();
+                        params.tex_type =
+                            String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+                    }
+                    2 => {
+                        // tex_name
+                        let mut string_pairs = pair.into_inner();
+                        let ident = string_pairs.next();
+                        params.tex_name =
+                            String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+                    }
+                    _ => unreachable!(),
+                };
+                counter += 1_u8;
+            }
+            Rule::type_name => {
+                // name
+                let mut string_pairs = pair.into_inner();
+                let ident = string_pairs.next();
+                params.name = String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+            }
+            Rule::file_name => {
+                // name
+                let mut string_pairs = pair.into_inner();
+                let ident = string_pairs.next();
+                params.name = String::from_str(ident.unwrap().clone().as_span().as_str()).unwrap();
+            }
+            Rule::parameter => {
+                for parameter_pair in pair.into_inner() {
+                    // println!("DEBUG: {:?}", parameter_pair.as_rule());
+                    match parameter_pair.as_rule() {
+                        Rule::bool_param => {
+                            let tuple: (String, bool) =
+                                pbrt_bool_parameter(&mut parameter_pair.into_inner());
+                            let string: String = tuple.0;
+                            let b: bool = tuple.1;
+                            params.add_bool(string, b);
+                        }
+                        Rule::blackbody_param => {
+                            let tuple: (String, Vec<Float>) =
+                                pbrt_float_parameter(&mut parameter_pair.into_inner())?;
+                            let string: String = tuple.0;
+                            let floats: Vec<Float> = tuple.1;
+                            params.add_blackbody_spectrum(string, floats);
+                        }
+                        Rule::float_param => {
+                            let tuple: (String, Vec<Float>) =
+                                pbrt_float_parameter(&mut parameter_pair.into_inner())?;
+                            let string: String = tuple.0;
+                            let floats: Vec<Float> = tuple.1;
+                            if floats.len() == 1 {
+                                params.add_float(string, floats[0]);
+                            } else {
+                                params.add_floats(string, floats);
+                            }
+                        }
+                        Rule::integer_param => {
+                            let tuple: (String, Vec<i32>) =
+                                pbrt_integer_parameter(&mut parameter_pair.into_inner())?;
+                            let string: String = tuple.0;
+                            let integers: Vec<i32> = tuple.1;
+                            if integers.len() == 1 {
+                                params.add_int(string, integers[0]);
+                            } else {
+                                params.add_ints(string, integers);
+                            }
+                        }
+                        Rule::point_param => {
+                            let tuple: (String, Vec<Float>) =
+                                pbrt_float_parameter(&mut parameter_pair.into_inner())?;
+                            let string: String = tuple.0;
+                            let floats: Vec<Float> = tuple.1;
+                            if floats.len() == 3 {
+                                params.add_point3f(
+                                    string,
+                                    Point3f {
+                                        x: floats[0],
+                                        y: floats[1],
+                                        z: floats[2],
+                                    },
+                                );
+                            } else {
+                                params.add_point3fs(string, floats);
+                            }
+                        }
+                        Rule::point2_param => {
+                            let tuple: (String, Vec<Float>) =
+                                pbrt_float_parameter(&mut parameter_pair.into_inner())?;
+                            let string: String = tuple.0;
+                            let floats: Vec<Float> = tuple.1;
+                            if floats.len() == 2 {
+                                params.add_point2f(
+                                    string,
+                                    Point2f {
+                                        x: floats[0],
+                                        y: floats[1],
+                                    },
+                                );
+                            } else {
+                                params.add_point2fs(string, floats);
+                            }
+                        }
+                        Rule::normal_param => {
+                            let tuple: (String, Vec<Float>) =
+                                pbrt_float_parameter(&mut parameter_pair.into_inner())?;
+                            let string: String = tuple.0;
+                            let floats: Vec<Float> = tuple.1;
+                            if floats.len() == 3 {
+                                params.add_normal3f(
+                                    string,
+                                    Normal3f {
+                                        x: floats[0],
+                                        y: floats[1],
+                                        z: floats[2],
+                                    },
+                                );
+                            } else {
+                                params.add_normal3fs(string, floats);
+                            }
+                        }
+                        Rule::rgb_param => {
+                            let tuple: (String, Vec<Float>) =
+                                pbrt_float_parameter(&mut parameter_pair.into_inner())?;
+                            let string: String = tuple.0;
+                            let floats: Vec<Float> = tuple.1;
+                            params.add_rgb(string, [floats[0], floats[1], floats[2]]);
+                        }
+                        Rule::spectrum_param => {
+                            // TODO: "spectrum Kd" [ 300 .3  400 .6   410 .65  415 .8  500 .2  600 .1 ]
+                            // let tuple: (String, Vec<Float>) =
+                            //     pbrt_float_parameter(&mut parameter_pair.into_inner());
+                            // let string: String = tuple.0;
+                            // let floats: Vec<Float> = tuple.1;
+                            // params.add_rgb_spectrum(
+                            //     string,
+                            //     Spectrum {
+                            //         c: [floats[0], floats[1], floats[2]],
+                            //     },
+                            // );
+                            // or
+                            // "spectrum Kd" "filename"
+                            let tuple: (String, Vec<String>) =
+                                pbrt_string_parameter(&mut parameter_pair.into_inner());
+                            let string1: String = tuple.0;
+                            let strings: Vec<String> = tuple.1;
+                            params.add_sampled_spectrum_files(string1, strings);
+                        }
+                        Rule::string_param => {
+                            let tuple: (String, Vec<String>) =
+                                pbrt_string_parameter(&mut parameter_pair.into_inner());
+                            let string1: String = tuple.0;
+                            let strings: Vec<String> = tuple.1;
+                            params.add_strings(string1, strings);
+                        }
+                        Rule::texture_param => {
+                            let tuple: (String, String) =
+                                pbrt_texture_parameter(&mut parameter_pair.into_inner());
+                            let string1: String = tuple.0;
+                            let string2: String = tuple.1;
+                            params.add_texture(string1, string2);
+                        }
+                        Rule::vector_param => {
+                            let tuple: (String, Vec<Float>) =
+                                pbrt_float_parameter(&mut parameter_pair.into_inner())?;
+                            let string: String = tuple.0;
+                            let floats: Vec<Float> = tuple.1;
+                            if floats.len() == 3 {
+                                params.add_vector3f(
+                                    string,
+                                    Vector3f {
+                                        x: floats[0],
+                                        y: floats[1],
+                                        z: floats[2],
+                                    },
+                                );
+                            } else {
+                                params.add_vector3fs(string, floats);
+                            }
+                        }
+                        _ => unreachable!(),
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(params)
+}
+
+fn parse_line(
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    parser_state: &mut ParserState,
+    statement_buf: &Statement,
+) -> Result<(), ParseError> {
+    let identifier: &str = statement_buf.identifier();
+    if statement_buf.params() == "" {
+        // no additional arguments
+        match identifier {
+            "AttributeBegin" => {
+                // AttributeBegin
+                // println!("{} {}", identifier, str_buf);
+                pbrt_attribute_begin(api_state);
+            }
+            "AttributeEnd" => {
+                // AttributeEnd
+                // println!("{} {}", identifier, str_buf);
+                pbrt_attribute_end(api_state);
+            }
+            "ObjectEnd" => {
+                // ObjectEnd
+                // println!("{} {}", identifier, str_buf);
+                pbrt_object_end(api_state);
+            }
+            "ReverseOrientation" => {
+                // ReverseOrientation
+                // println!("{} {}", identifier, str_buf);
+                pbrt_reverse_orientation(api_state);
+            }
+            "TransformBegin" => {
+                // TransformBegin
+                pbrt_transform_begin(api_state);
+            }
+            "TransformEnd" => {
+                // TransformEnd
+                pbrt_transform_end(api_state);
+            }
+            "WorldBegin" => {
+                // WorldBegin
+                // println!("{} {}", identifier, str_buf);
+                let (line, column) = statement_buf.line_col(0_usize);
+                parser_state.world_begin = Some((parser_state.current_file(), line, column));
+                pbrt_world_begin(api_state);
+            }
+            "WorldEnd" => {
+                // WorldEnd
+                // println!("{} {}", identifier, str_buf);
+                parser_state.world_begin = None;
+                pbrt_cleanup(api_state);
+            }
+            "Identity" => {
+                // Identity
+                pbrt_identity(api_state);
+            }
+            _ => {
+                return Err(parser_state.error(
+                    statement_buf,
+                    StatementError {
+                        pos: 0_usize,
+                        token: String::from(identifier),
+                        message: String::from("missing parameters"),
+                    },
+                ));
+            }
+        }
+    } else {
+        let statement: &str = statement_buf.text.as_str();
+        // println!("DEBUG: {:?}", &statement);
+        let pairs = match PbrtParser::parse(Rule::name_and_or_params, statement) {
+            Ok(mut pairs) => pairs.next().unwrap(),
+            Err(_) => {
+                return Err(parser_state.error(
+                    statement_buf,
+                    StatementError {
+                        pos: 0_usize,
+                        token: String::from(identifier),
+                        message: String::from("unsuccessful parse"),
+                    },
+                ));
+            }
+        };
+        // anything left over could not be parsed
+        let end: usize = pairs.as_span().end();
+        let remaining: &str = statement[end..].trim_start();
+        if !remaining.is_empty() {
+            let pos: usize = statement.len() - remaining.len();
+            let token: &str = if let Some(stripped) = remaining.strip_prefix('"') {
+                // e.g. "float radius"
+                match stripped.find('"') {
+                    Some(end) => &remaining[..end + 2],
+                    None => remaining,
+                }
+            } else {
+                remaining.split_whitespace().next().unwrap_or("")
+            };
+            let param_type: &str = token
+                .trim_start_matches('"')
+                .split_whitespace()
+                .next()
+                .unwrap_or("");
+            if PARAM_TYPE_NAMES.contains(&param_type) {
+                // a known type, so the values must be wrong
+                return Err(parser_state.error(
+                    statement_buf,
+                    StatementError {
+                        pos,
+                        token: String::from(token),
+                        message: String::from("malformed parameter value(s)"),
+                    },
+                ));
+            }
+            parser_state.warning(
+                statement_buf,
+                pos,
+                format!("ignoring unknown parameter(s) starting with {}", token),
+            );
+        }
+        for inner_pair in pairs.into_inner() {
+            // println!("DEBUG: {:?}", inner_pair.as_rule());
+            match inner_pair.as_rule() {
+                Rule::type_params => {
+                    // identifier "type" parameter-list
+                    let for_printing = inner_pair.as_str();
+                    // println!("DEBUG: {}", for_printing);
+                    let mut params = extract_params(String::from(identifier), inner_pair)
+                        .map_err(|e| parser_state.error(statement_buf, e))?;
+                    let (line, column) = statement_buf.line_col(0_usize);
+                    params.location = Some((parser_state.current_file(), line, column));
+                    if let Some(message) = check_plugin_name(identifier, &params) {
+                        parser_state.warning(statement_buf, identifier.len() + 1, message);
+                    }
+                    match identifier {
+                        "Accelerator" => {
+                            // Accelerator
+                            pbrt_accelerator(api_state, params);
+                        }
+                        "AreaLightSource" => {
+                            // AreaLightSource
+                            pbrt_area_light_source(api_state, params);
+                        }
+                        "Camera" => {
+                            // Camera
+                            pbrt_camera(api_state, params);
+                        }
+                        "CoordinateSystem" => {
+                            // CoordinateSystem
+                            pbrt_coordinate_system(api_state, params);
+                        }
+                        "CoordSysTransform" => {
+                            // CoordSysTransform
+                            pbrt_coord_sys_transform(api_state, params);
+                        }
+                        "Film" => {
+                            // Film
+                            pbrt_film(api_state, params);
+                        }
+                        "Include" => {
+                            // Include
+                            let filename: String =
+                                parser_state.resolve_filename(api_state, &params.name);
+                            let todo: Vec<&str> = for_printing.splitn(3, '"').collect();
+                            if !api_state.writes_scene() {
+                                println!("Include {:?}", filename);
+                            }
+                            include_file(filename, api_state, bsdf_state, parser_state, todo[2])?;
+                        }
+                        "Integrator" => {
+                            // Integrator
+                            pbrt_integrator(api_state, params);
+                        }
+                        "LightSource" => {
+                            // LightSource
+                            pbrt_light_source(api_state, params);
+                        }
+                        "MakeNamedMaterial" => {
+                            // MakeNamedMaterial
+                            pbrt_make_named_material(api_state, bsdf_state, params);
+                        }
+                        "MakeNamedMedium" => {
+                            // MakeNamedMedium
+                            pbrt_make_named_medium(api_state, params);
+                        }
+                        "Material" => {
+                            // Material
+                            pbrt_material(api_state, params);
+                        }
+                        "NamedMaterial" => {
+                            // NamedMaterial
+                            pbrt_named_material(api_state, params);
+                        }
+                        "ObjectBegin" => {
+                            // ObjectBegin
+                            pbrt_object_begin(api_state, params);
+                        }
+                        "ObjectInstance" => {
+                            // ObjectInstance
+                            pbrt_object_instance(api_state, params);
+                        }
+                        "PixelFilter" => {
+                            // PixelFilter
+                            pbrt_pixel_filter(api_state, params);
+                        }
+                        "Sampler" => {
+                            // Sampler
+                            pbrt_sampler(api_state, params);
+                        }
+                        "Shape" => {
+                            // Shape
+                            pbrt_shape(api_state, bsdf_state, params);
+                        }
+                        "Texture" => {
+                            // Texture
+                            pbrt_texture(api_state, params);
+                        }
+                        _ => parser_state.warning(
+                            statement_buf,
+                            0_usize,
+                            format!("ignoring unexpected parameters of {}", identifier),
+                        ),
+                    }
+                }
+                Rule::active_transform => {
+                    // ActiveTransform
+                    for rule_pair in inner_pair.into_inner() {
+                        match rule_pair.as_rule() {
+                            Rule::all => {
+                                pbrt_active_transform_all(api_state);
+                            }
+                            Rule::start_time => {
+                                pbrt_active_transform_start_time(api_state);
+                            }
+                            Rule::end_time => {
+                                pbrt_active_transform_end_time(api_state);
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+                }
+                Rule::concat_transform => {
+                    // ConcatTransform m00 .. m33
+                    let mut m: Vec<Float> = Vec::new();
+                    for rule_pair in inner_pair.into_inner() {
+                        // ignore brackets
+                        let not_opening: bool = rule_pair.as_str() != "[";
+                        let not_closing: bool = rule_pair.as_str() != "]";
+                        if not_opening && not_closing {
+                            let number: Float = pbrt_float(&rule_pair)
+                                .map_err(|e| parser_state.error(statement_buf, e))?;
+                            m.push(number);
+                        }
+                    }
+                    let m00: Float = m[0];
+                    let m01: Float = m[1];
+                    let m02: Float = m[2];
+                    let m03: Float = m[3];
+                    let m10: Float = m[4];
+                    let m11: Float = m[5];
+                    let m12: Float = m[6];
+                    let m13: Float = m[7];
+                    let m20: Float = m[8];
+                    let m21: Float = m[9];
+                    let m22: Float = m[10];
+                    let m23: Float = m[11];
+                    let m30: Float = m[12];
+                    let m31: Float = m[13];
+                    let m32: Float = m[14];
+                    let m33: Float = m[15];
+                    let tr: Transform = Transform::new(
+                        m00, m10, m20, m30, m01, m11, m21, m31, m02, m12, m22, m32, m03, m13, m23,
+                        m33,
+                    );
+                    pbrt_concat_transform(api_state, &tr);
+                }
+                Rule::look_at => {
+                    // LookAt eye_x eye_y eye_z look_x look_y look_z up_x up_y up_z
+                    let mut v: Vec<Float> = Vec::new();
+                    for rule_pair in inner_pair.into_inner() {
+                        let number: Float = pbrt_float(&rule_pair)
+                            .map_err(|e| parser_state.error(statement_buf, e))?;
+                        v.push(number);
+                    }
+                    // println!(
+                    //     "LookAt {} {} {} {} {} {} {} {} {}",
+                    //     v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7], v[8],
+                    // );
+                    pbrt_look_at(
+                        api_state, v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7], v[8],
+                    );
+                }
+                Rule::medium_interface => {
+                    // MediumInterface
+                    let mut strings: Vec<String> = Vec::new();
+                    for rule_pair in inner_pair.into_inner() {
+                        match rule_pair.as_rule() {
+                            Rule::empty_string => {
+                                strings.push(String::from(""));
+                            }
+                            Rule::string => {
+                                let ident = rule_pair.into_inner().next();
+                                let string: String =
+                                    String::from_str(ident.unwrap().clone().as_span().as_str())
+                                        .unwrap();
+                                strings.push(string);
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+                    assert!(
+                        strings.len() == 2_usize,
+                        "ERROR: expected two strings, found {:?}",
+                        strings.len()
+                    );
+                    pbrt_medium_interface(api_state, &strings[0], &strings[1]);
+                }
+                Rule::rotate => {
+                    // Rotate angle x y z
+                    let mut v: Vec<Float> = Vec::new();
+                    for rule_pair in inner_pair.into_inner() {
+                        let number: Float = pbrt_float(&rule_pair)
+                            .map_err(|e| parser_state.error(statement_buf, e))?;
+                        v.push(number);
+                    }
+                    // println!("Rotate {} {} {} {}", v[0], v[1], v[2], v[3]);
+                    pbrt_rotate(api_state, v[0], v[1], v[2], v[3]);
+                }
+                Rule::scale => {
+                    // Scale x y z
+                    let mut v: Vec<Float> = Vec::new();
+                    for rule_pair in inner_pair.into_inner() {
+                        let number: Float = pbrt_float(&rule_pair)
+                            .map_err(|e| parser_state.error(statement_buf, e))?;
+                        v.push(number);
+                    }
+                    // println!("Scale {} {} {}", v[0], v[1], v[2]);
+                    pbrt_scale(api_state, v[0], v[1], v[2]);
+                }
+                Rule::transform => {
+                    // Transform m00 .. m33
+                    let mut m: Vec<Float> = Vec::new();
+                    for rule_pair in inner_pair.into_inner() {
+                        // ignore brackets
+                        let not_opening: bool = rule_pair.as_str() != "[";
+                        let not_closing: bool = rule_pair.as_str() != "]";
+                        if not_opening && not_closing {
+                            let number: Float = pbrt_float(&rule_pair)
+                                .map_err(|e| parser_state.error(statement_buf, e))?;
+                            m.push(number);
+                        }
+                    }
+                    let m00: Float = m[0];
+                    let m01: Float = m[1];
+                    let m02: Float = m[2];
+                    let m03: Float = m[3];
+                    let m10: Float = m[4];
+                    let m11: Float = m[5];
+                    let m12: Float = m[6];
+                    let m13: Float = m[7];
+                    let m20: Float = m[8];
+                    let m21: Float = m[9];
+                    let m22: Float = m[10];
+                    let m23: Float = m[11];
+                    let m30: Float = m[12];
+                    let m31: Float = m[13];
+                    let m32: Float = m[14];
+                    let m33: Float = m[15];
+                    let tr: Transform = Transform::new(
+                        m00, m10, m20, m30, m01, m11, m21, m31, m02, m12, m22, m32, m03, m13, m23,
+                        m33,
+                    );
+                    pbrt_transform(api_state, &tr);
+                }
+                Rule::transform_times => {
+                    // TransformTimes start end
+                    let mut v: Vec<Float> = Vec::new();
+                    for rule_pair in inner_pair.into_inner() {
+                        let number: Float = pbrt_float(&rule_pair)
+                            .map_err(|e| parser_state.error(statement_buf, e))?;
+                        v.push(number);
+                    }
+                    pbrt_transform_times(api_state, v[0], v[1]);
+                }
+                Rule::translate => {
+                    // Translate x y z
+                    let mut v: Vec<Float> = Vec::new();
+                    for rule_pair in inner_pair.into_inner() {
+                        let number: Float = pbrt_float(&rule_pair)
+                            .map_err(|e| parser_state.error(statement_buf, e))?;
+                        v.push(number);
+                    }
+                    // println!("Translate {} {} {}", v[0], v[1], v[2]);
+                    pbrt_translate(api_state, v[0], v[1], v[2]);
+                }
+                Rule::remaining_line => {
+                    // predetermined number of arguments of predetermined type
+                    let params: &str = statement_buf.params();
+                    let first: &str = params.split_whitespace().next().unwrap_or("");
+                    let error = if first.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        // lines not starting with a known identifier get
+                        // appended to the previous statement
+                        StatementError {
+                            pos: identifier.len() + 1,
+                            token: String::from(first),
+                            message: String::from("unknown directive"),
+                        }
+                    } else {
+                        StatementError {
+                            pos: identifier.len() + 1,
+                            token: String::from(params),
+                            message: format!("unexpected parameters for {}", identifier),
+                        }
+                    };
+                    return Err(parser_state.error(statement_buf, error));
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse a scene file (and recursively all included files). Textures,
+/// meshes, etc. are searched relative to the directory of the file.
+/// Scenes in the pbrt-v4 format are detected and handed over to
+/// [v4::parse_file()](v4/fn.parse_file.html).
+pub fn parse_file(
+    filename: &str,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<Vec<ParseWarning>, ParseError> {
+    if let Ok(input) = std::fs::read_to_string(filename) {
+        if v4::is_pbrt_v4(&input) {
+            if !api_state.writes_scene() {
+                println!("Reading {:?} as pbrt-v4 scene", filename);
+            }
+            return v4::parse_file(filename, api_state, bsdf_state);
+        }
+    }
+    let ip: &Path = Path::new(filename);
+    let pb: PathBuf = if ip.is_relative() {
+        let cp: PathBuf = env::current_dir().unwrap();
+        cp.join(ip)
+    } else {
+        PathBuf::from(ip)
+    };
+    if let Some(search_directory) = pb.as_path().parent() {
+        // println!("search_directory is {}", search_directory.display());
+        api_state.search_directory = Some(Box::new(PathBuf::from(search_directory)));
+    }
+    let mut parser_state: ParserState = ParserState::default();
+    include_file(
+        String::from(filename),
+        api_state,
+        bsdf_state,
+        &mut parser_state,
+        "",
+    )?;
+    if let Some((file, line, column)) = parser_state.world_begin.take() {
+        let warning = ParseWarning {
+            file,
+            line,
+            column,
+            message: String::from("WorldBegin without WorldEnd, nothing was rendered"),
+        };
+        eprintln!("{}", warning);
+        parser_state.warnings.push(warning);
+    }
+    Ok(parser_state.warnings)
+}
+
+/// Parse a scene description given as a string. Included files are
+/// searched relative to the search directory (if set) or the current
+/// working directory.
+pub fn parse_str(
+    input: &str,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<Vec<ParseWarning>, ParseError> {
+    let mut parser_state: ParserState = ParserState::default();
+    parse_input(
+        String::from("<string>"),
+        String::from(input),
+        api_state,
+        bsdf_state,
+        &mut parser_state,
+    )?;
+    Ok(parser_state.warnings)
+}
+
+fn include_file(
+    filename: String,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    parser_state: &mut ParserState,
+    append: &str,
+) -> Result<(), ParseError> {
+    // println!("FILE = {}", x);
+    let f = match File::open(filename.clone()) {
+        Ok(f) => f,
+        Err(e) => {
+            return Err(ParseError {
+                file: filename,
+                line: 0_usize,
+                column: 0_usize,
+                token: String::new(),
+                message: format!("could not open file ({})", e),
+                include_stack: parser_state.include_stack.clone(),
+            });
+        }
+    };
+    let mut reader = BufReader::new(f);
+    let mut str_buf: String = String::default();
+    if let Err(e) = reader.read_to_string(&mut str_buf) {
+        return Err(ParseError {
+            file: filename,
+            line: 0_usize,
+            column: 0_usize,
+            token: String::new(),
+            message: format!("could not read file ({})", e),
+            include_stack: parser_state.include_stack.clone(),
+        });
+    }
+    if !append.is_empty() {
+        str_buf += append;
+        str_buf += "\n";
+    }
+    parse_input(filename, str_buf, api_state, bsdf_state, parser_state)
+}
+
+fn parse_input(
+    filename: String,
+    str_buf: String,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    parser_state: &mut ParserState,
+) -> Result<(), ParseError> {
+    let mut str_buf: String = str_buf;
+    if !str_buf.ends_with('\n') {
+        // every line (including the last one) needs a newline
+        str_buf += "\n";
+    }
+    let pairs = match PbrtParser::parse(Rule::pbrt, &str_buf) {
+        Ok(mut pairs) => pairs.next().unwrap(),
+        Err(e) => {
+            let (line, column) = match e.line_col {
+                pest::error::LineColLocation::Pos(line_col) => line_col,
+                pest::error::LineColLocation::Span(line_col, _) => line_col,
+            };
+            return Err(ParseError {
+                file: filename,
+                line,
+                column,
+                token: String::new(),
+                message: String::from("unsuccessful parse"),
+                include_stack: parser_state.include_stack.clone(),
+            });
+        }
+    };
+    parser_state.include_stack.push((filename, 0_usize));
+    let mut statement: Option<Statement> = None;
+    // let mut comment_count: u64 = 0;
+    // let mut empty_count: u64 = 0;
+    // let mut todo_count: u64 = 0;
+    // first parse file line by line
+    for inner_pair in pairs.into_inner() {
+        match inner_pair.as_rule() {
+            // comment lines (starting with '#')
+            Rule::comment_line => {
+                // comment_count += 1;
+            }
+            Rule::statement_line => {
+                for statement_pair in inner_pair.into_inner() {
+                    let line_col: (usize, usize) = statement_pair.as_span().start_pos().line_col();
+                    match statement_pair.as_rule() {
+                        Rule::identifier => {
+                            if let Some(ref previous) = statement {
+                                process_statement(api_state, bsdf_state, parser_state, previous)?;
+                            }
+                            statement = Some(Statement::new(statement_pair.as_str(), line_col));
+                        }
+                        Rule::remaining_line => {
+                            if let Some(ref mut current) = statement {
+                                current.append(statement_pair.as_str(), line_col);
+                            }
+                        }
+                        Rule::trailing_comment => {
+                            // ignore (only if there are no '"' chars)
+                            if statement_pair.as_str().contains('\"') {
+                                if let Some(ref mut current) = statement {
+                                    current.append(statement_pair.as_str(), line_col);
+                                }
+                            }
+                        }
+                        _ => unreachable!(),
+                    }
+                }
+            }
+            Rule::empty_line => {
+                // empty_count += 1;
+            }
+            Rule::todo_line => {
+                // todo_count += 1;
+                for params_pair in inner_pair.into_inner() {
+                    let line_col: (usize, usize) = params_pair.as_span().start_pos().line_col();
+                    match params_pair.as_rule() {
+                        Rule::remaining_params => {
+                            if let Some(ref mut current) = statement {
+                                current.append(params_pair.as_str(), line_col);
+                            } else if !params_pair.as_str().trim().is_empty() {
+                                let unknown = Statement::new(params_pair.as_str(), line_col);
+                                return Err(parser_state.error(
+                                    &unknown,
+                                    StatementError {
+                                        pos: 0_usize,
+                                        token: String::from(unknown.identifier()),
+                                        message: String::from("unknown directive"),
+                                    },
+                                ));
+                            }
+                        }
+                        Rule::trailing_comment => {
+                            // ignore
+                        }
+                        _ => unreachable!(),
+                    }
+                }
+            }
+            Rule::EOI => {
+                if let Some(ref last) = statement {
+                    process_statement(api_state, bsdf_state, parser_state, last)?;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    // println!("Number of comment line(s):   {}", comment_count);
+    // println!("Number of parameter line(s): {}", todo_count);
+    // println!("Number of empty line(s):     {}", empty_count);
+    parser_state.include_stack.pop();
+    Ok(())
+}
+
+fn process_statement(
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    parser_state: &mut ParserState,
+    statement: &Statement,
+) -> Result<(), ParseError> {
+    // remember the line for error messages of included files
+    if let Some(current) = parser_state.include_stack.last_mut() {
+        current.1 = statement.line_col(0_usize).0;
+    }
+    let result = parse_line(api_state, bsdf_state, parser_state, statement);
+    // e.g. unused parameters, already printed by the API
+    parser_state.warnings.append(&mut api_state.take_warnings());
+    result
+}
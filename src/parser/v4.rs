@@ -0,0 +1,1965 @@
+//! Importer for scene files written for
+//! [pbrt-v4](https://pbrt.org/fileformat-v4). Each statement gets
+//! translated into the closest pbrt-v3 equivalent and passed on to the
+//! `pbrt_*` functions of the [api](../../core/api/index.html), just
+//! like the statements of the pbrt-v3 [parser](../index.html).
+//!
+//! On top of the pbrt-v3 syntax pbrt-v4 adds `ColorSpace`, `Option`,
+//! `Attribute` and `Import`, parameter types like "point3" or
+//! "spectrum" with named spectra (e.g. "metal-Au-eta" or
+//! "glass-BK7"), and a new set of materials. There is no `WorldEnd`,
+//! rendering starts at the end of the main file. The most important
+//! translations are:
+//!
+//! | pbrt-v4                     | pbrt-v3                                      |
+//! |-----------------------------|----------------------------------------------|
+//! | `diffuse`                   | `matte` (reflectance → Kd)                   |
+//! | `coateddiffuse`             | `substrate` (Ks from the coating's eta)      |
+//! | `conductor`                 | `metal` (reflectance → eta and k)            |
+//! | `dielectric`                | `glass` (named glasses → Sellmeier or eta)   |
+//! | `diffusetransmission`       | `translucent`                                |
+//! | `mix` "materials"           | `mix` "namedmaterial1"/"namedmaterial2"      |
+//! | "displacement"              | "bumpmap"                                    |
+//! | `zsobol`, `pmj02bn`, ...    | `sobol`, `independent` → `random`            |
+//! | `rgb` film                  | `image` film ("iso" → "scale")               |
+//! | filter "xradius"/"yradius"  | "xwidth"/"ywidth" (gaussian "sigma" → alpha) |
+//! | light "filename"            | "mapname"                                    |
+//! | `bilinearmesh`              | `trianglemesh`                               |
+//! | `uniformgrid` medium        | `heterogeneous`                              |
+//!
+//! Everything without a pbrt-v3 counterpart (other color spaces,
+//! `Option`s, photometric light "power", ...) is reported as a
+//! **ParseWarning** and ignored.
+//!
+//! ```rust
+//! use rs_pbrt::core::api::pbrt_init;
+//! use rs_pbrt::core::pbrt::Options;
+//! use rs_pbrt::parser::v4::{is_pbrt_v4, parse_str};
+//!
+//! fn main() {
+//!     let (mut api_state, mut bsdf_state) = pbrt_init(Options::default());
+//!     let scene: &str = "ColorSpace \"aces2065-1\"\n\
+//!                        LookAt 0 0 5  0 0 0  0 1 0\n\
+//!                        Camera \"perspective\" \"float fov\" 45\n\
+//!                        Sampler \"zsobol\" \"integer pixelsamples\" 4\n";
+//!     assert!(is_pbrt_v4(scene));
+//!     let warnings = parse_str(scene, &mut api_state, &mut bsdf_state).unwrap();
+//!     assert_eq!(warnings.len(), 1);
+//!     assert_eq!(warnings[0].line, 1);
+//! }
+//! ```
+
+// std
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+// pbrt
+use crate::core::api::{
+    pbrt_accelerator, pbrt_active_transform_all, pbrt_active_transform_end_time,
+    pbrt_active_transform_start_time, pbrt_area_light_source, pbrt_attribute_begin,
+    pbrt_attribute_end, pbrt_camera, pbrt_cleanup, pbrt_concat_transform, pbrt_coord_sys_transform,
+    pbrt_coordinate_system, pbrt_film, pbrt_identity, pbrt_integrator, pbrt_light_source,
+    pbrt_look_at, pbrt_make_named_material, pbrt_make_named_medium, pbrt_material,
+    pbrt_medium_interface, pbrt_named_material, pbrt_object_begin, pbrt_object_end,
+    pbrt_object_instance, pbrt_pixel_filter, pbrt_reverse_orientation, pbrt_rotate, pbrt_sampler,
+    pbrt_scale, pbrt_shape, pbrt_texture, pbrt_transform, pbrt_transform_begin, pbrt_transform_end,
+    pbrt_transform_times, pbrt_translate, pbrt_world_begin,
+};
+use crate::core::api::{ApiState, BsdfState};
+use crate::core::floatfile::read_float_file;
+use crate::core::geometry::{Normal3f, Point2f, Point3f, Vector3f};
+use crate::core::paramset::ParamSet;
+use crate::core::pbrt::{Float, Spectrum};
+use crate::core::spectrum::{blackbody_normalized, CIE_LAMBDA, N_CIE_SAMPLES};
+use crate::core::transform::Transform;
+use crate::materials::metal::{COPPER_K, COPPER_N, COPPER_SAMPLES, COPPER_WAVELENGTHS};
+use crate::parser::{check_plugin_name, resolve_filename, ParseError, ParseWarning};
+
+// materials which only exist in pbrt-v4
+const V4_MATERIALS: &[&str] = &[
+    "coateddiffuse",
+    "coatedconductor",
+    "conductor",
+    "dielectric",
+    "thindielectric",
+    "diffuse",
+    "diffusetransmission",
+    "measured",
+    "interface",
+];
+// parameter types which only exist in pbrt-v4
+const V4_TYPES: &[&str] = &["point3", "vector3", "normal3"];
+// plugin names which only exist in pbrt-v4
+const V4_PLUGINS: &[(&str, &[&str])] = &[
+    ("Film", &["rgb", "gbuffer", "spectral"]),
+    (
+        "Integrator",
+        &["lightpath", "randomwalk", "simplepath", "simplevolpath"],
+    ),
+    (
+        "Sampler",
+        &["independent", "paddedsobol", "pmj02bn", "zsobol"],
+    ),
+    ("Shape", &["bilinearmesh"]),
+];
+// targets of the Attribute directive
+const ATTRIBUTE_TARGETS: &[&str] = &["shape", "light", "material", "medium", "texture"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    /// directive, number or bare true/false
+    Word,
+    /// quoted string (without the quotes)
+    Quoted,
+    OpenBracket,
+    CloseBracket,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn is_directive(&self) -> bool {
+        self.kind == TokenKind::Word && self.text.starts_with(|c: char| c.is_ascii_uppercase())
+    }
+}
+
+/// Splits the input into tokens, skipping whitespace and comments.
+/// Returns line, column and message of the first error.
+fn tokenize(input: &str) -> Result<Vec<Token>, (usize, usize, String)> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut chars = input.chars().peekable();
+    let (mut line, mut column): (usize, usize) = (1_usize, 1_usize);
+    while let Some(c) = chars.next() {
+        let (start_line, start_column): (usize, usize) = (line, column);
+        column += 1;
+        match c {
+            '\n' => {
+                line += 1;
+                column = 1;
+            }
+            '#' => {
+                // comment until the end of the line
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+            '[' | ']' => tokens.push(Token {
+                kind: if c == '[' {
+                    TokenKind::OpenBracket
+                } else {
+                    TokenKind::CloseBracket
+                },
+                text: c.to_string(),
+                line: start_line,
+                column: start_column,
+            }),
+            '"' => {
+                let mut text: String = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            column += 1;
+                            match chars.next() {
+                                Some('n') => text.push('\n'),
+                                Some('t') => text.push('\t'),
+                                Some(escaped) => text.push(escaped),
+                                None => {}
+                            }
+                        }
+                        Some('\n') | None => {
+                            return Err((
+                                start_line,
+                                start_column,
+                                String::from("unterminated string"),
+                            ));
+                        }
+                        Some(other) => text.push(other),
+                    }
+                    column += 1;
+                }
+                column += 1;
+                tokens.push(Token {
+                    kind: TokenKind::Quoted,
+                    text,
+                    line: start_line,
+                    column: start_column,
+                });
+            }
+            _ if c.is_whitespace() => {}
+            _ => {
+                let mut text: String = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace()
+                        || next == '['
+                        || next == ']'
+                        || next == '"'
+                        || next == '#'
+                    {
+                        break;
+                    }
+                    text.push(next);
+                    chars.next();
+                    column += 1;
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Word,
+                    text,
+                    line: start_line,
+                    column: start_column,
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns true if *input* uses syntax which only exists in pbrt-v4:
+/// one of the new directives, parameter types, materials or other
+/// plugin names. Everything else (including files which can't be
+/// tokenized) is left to the pbrt-v3 parser, which reports errors
+/// more precisely.
+pub fn is_pbrt_v4(input: &str) -> bool {
+    let tokens: Vec<Token> = match tokenize(input) {
+        Ok(tokens) => tokens,
+        Err(_) => return false,
+    };
+    let v4_plugin = |directive: &str, token: Option<&Token>| -> bool {
+        match token {
+            Some(token) => {
+                token.kind == TokenKind::Quoted
+                    && V4_PLUGINS.iter().any(|(name, plugins)| {
+                        *name == directive && plugins.contains(&token.text.as_str())
+                    })
+            }
+            None => false,
+        }
+    };
+    let v4_material = |token: Option<&Token>| -> bool {
+        match token {
+            Some(token) => {
+                token.kind == TokenKind::Quoted && V4_MATERIALS.contains(&token.text.as_str())
+            }
+            None => false,
+        }
+    };
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Word => match token.text.as_str() {
+                "ColorSpace" | "Option" | "Attribute" | "Import" => return true,
+                "Material" if v4_material(tokens.get(i + 1)) => return true,
+                directive if v4_plugin(directive, tokens.get(i + 1)) => return true,
+                _ => {}
+            },
+            TokenKind::Quoted => {
+                let words: Vec<&str> = token.text.split_whitespace().collect();
+                if words.len() == 2 {
+                    if V4_TYPES.contains(&words[0]) {
+                        return true;
+                    }
+                    if words[0] == "string" && words[1] == "type" {
+                        let mut value: Option<&Token> = tokens.get(i + 1);
+                        if let Some(next) = value {
+                            if next.kind == TokenKind::OpenBracket {
+                                value = tokens.get(i + 2);
+                            }
+                        }
+                        if v4_material(value) {
+                            return true;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(Float),
+    Str(String),
+    Bool(bool),
+}
+
+/// A parameter of a pbrt-v4 statement (before translation).
+#[derive(Debug, Clone)]
+struct Param {
+    param_type: String,
+    name: String,
+    values: Vec<Value>,
+    line: usize,
+    column: usize,
+    /// defaults from an Attribute statement don't have to be used
+    may_be_unused: bool,
+}
+
+impl Param {
+    /// A new parameter at the position of *origin*.
+    fn new(param_type: &str, name: &str, values: Vec<Value>, origin: &Param) -> Self {
+        Param {
+            param_type: String::from(param_type),
+            name: String::from(name),
+            values,
+            line: origin.line,
+            column: origin.column,
+            may_be_unused: origin.may_be_unused,
+        }
+    }
+    fn numbers(values: &[Float]) -> Vec<Value> {
+        values.iter().map(|v| Value::Number(*v)).collect()
+    }
+    fn floats(&self) -> Option<Vec<Float>> {
+        self.values
+            .iter()
+            .map(|v| match v {
+                Value::Number(f) => Some(*f),
+                _ => None,
+            })
+            .collect()
+    }
+    fn strings(&self) -> Option<Vec<String>> {
+        self.values
+            .iter()
+            .map(|v| match v {
+                Value::Str(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+    fn string(&self) -> Option<String> {
+        match self.values.first() {
+            Some(Value::Str(s)) => Some(s.clone()),
+            _ => None,
+        }
+    }
+    fn float(&self) -> Option<Float> {
+        match self.values.first() {
+            Some(Value::Number(f)) if self.values.len() == 1 => Some(*f),
+            _ => None,
+        }
+    }
+    fn bool(&self) -> Option<bool> {
+        match self.values.first() {
+            Some(Value::Bool(b)) => Some(*b),
+            Some(Value::Str(s)) if s == "true" || s == "false" => Some(s == "true"),
+            _ => None,
+        }
+    }
+    /// Three values for RGB parameters, one value is used for all
+    /// channels.
+    fn rgb(&self) -> Option<[Float; 3]> {
+        if self.param_type != "rgb" && self.param_type != "color" && self.param_type != "float" {
+            return None;
+        }
+        match self.floats() {
+            Some(ref v) if v.len() == 3 => Some([v[0], v[1], v[2]]),
+            Some(ref v) if v.len() == 1 => Some([v[0], v[0], v[0]]),
+            _ => None,
+        }
+    }
+}
+
+fn find<'a>(params: &'a [Param], name: &str) -> Option<&'a Param> {
+    params.iter().find(|p| p.name == name)
+}
+
+fn take(params: &mut Vec<Param>, name: &str) -> Option<Param> {
+    let index: usize = params.iter().position(|p| p.name == name)?;
+    Some(params.remove(index))
+}
+
+fn rename(params: &mut [Param], from: &str, to: &str) {
+    for p in params.iter_mut() {
+        if p.name == from {
+            p.name = String::from(to);
+        }
+    }
+}
+
+/// pbrt-v3 expects spectra for some parameters pbrt-v4 also accepts
+/// as a single float.
+fn floats_to_rgb(params: &mut [Param], names: &[&str]) {
+    for p in params.iter_mut() {
+        if p.param_type == "float" && names.contains(&p.name.as_str()) {
+            if let Some(v) = p.float() {
+                p.param_type = String::from("rgb");
+                p.values = Param::numbers(&[v, v, v]);
+            }
+        }
+    }
+}
+
+fn blackbody(temperature: Float) -> Spectrum {
+    let mut v: Vec<Float> = Vec::with_capacity(N_CIE_SAMPLES as usize);
+    blackbody_normalized(&CIE_LAMBDA, N_CIE_SAMPLES as usize, temperature, &mut v);
+    Spectrum::from_sampled(&CIE_LAMBDA, &v, N_CIE_SAMPLES as i32)
+}
+
+/// Index of refraction (at the d-line) of pbrt-v4's named glasses.
+fn glass_ior(name: &str) -> Option<Float> {
+    match name {
+        "glass-BK7" => Some(1.5168 as Float),
+        "glass-BAF10" => Some(1.6700 as Float),
+        "glass-FK51A" => Some(1.4866 as Float),
+        "glass-LASF9" => Some(1.8503 as Float),
+        // the SF5, SF10 and SF11 glasses
+        "glass-F5" => Some(1.6727 as Float),
+        "glass-F10" => Some(1.7283 as Float),
+        "glass-F11" => Some(1.7847 as Float),
+        _ => None,
+    }
+}
+
+/// pbrt-v4's named spectra, as far as they can be approximated (metals
+/// by their RGB values, glasses by a constant index of refraction).
+fn named_spectrum(name: &str) -> Option<Spectrum> {
+    match name {
+        "metal-Cu-eta" => Some(Spectrum::from_sampled(
+            &COPPER_WAVELENGTHS,
+            &COPPER_N,
+            COPPER_SAMPLES as i32,
+        )),
+        "metal-Cu-k" => Some(Spectrum::from_sampled(
+            &COPPER_WAVELENGTHS,
+            &COPPER_K,
+            COPPER_SAMPLES as i32,
+        )),
+        "metal-Ag-eta" => Some(Spectrum::rgb(0.155, 0.117, 0.138)),
+        "metal-Ag-k" => Some(Spectrum::rgb(4.828, 3.122, 2.147)),
+        "metal-Al-eta" => Some(Spectrum::rgb(1.657, 0.880, 0.521)),
+        "metal-Al-k" => Some(Spectrum::rgb(9.224, 6.270, 4.837)),
+        "metal-Au-eta" => Some(Spectrum::rgb(0.143, 0.375, 1.442)),
+        "metal-Au-k" => Some(Spectrum::rgb(3.983, 2.386, 1.603)),
+        "metal-CuZn-eta" => Some(Spectrum::rgb(0.444, 0.527, 1.094)),
+        "metal-CuZn-k" => Some(Spectrum::rgb(3.695, 2.765, 1.829)),
+        "stdillum-A" => Some(blackbody(2856.0 as Float)),
+        "stdillum-D50" => Some(blackbody(5003.0 as Float)),
+        "stdillum-D65" => Some(Spectrum::new(1.0 as Float)),
+        _ => glass_ior(name).map(Spectrum::new),
+    }
+}
+
+/// Cursor into the tokens of one file.
+struct Cursor<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+    fn next(&mut self) -> Option<&'a Token> {
+        let token: Option<&'a Token> = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+}
+
+/// Keeps track of included files, the graphics state which only
+/// exists in pbrt-v4 and collects warnings.
+struct V4Parser {
+    /// file names and line of the statement being processed
+    include_stack: Vec<(String, usize)>,
+    warnings: Vec<ParseWarning>,
+    /// parameter defaults set by Attribute, per AttributeBegin level
+    attributes: Vec<HashMap<String, Vec<Param>>>,
+    /// the integrator gets created at WorldBegin (when the number of
+    /// pixel samples is known)
+    integrator: Option<(Token, String, Vec<Param>)>,
+    pixel_samples: i32,
+    pixel_filter: bool,
+    world_begin: bool,
+}
+
+impl V4Parser {
+    fn new() -> Self {
+        V4Parser {
+            include_stack: Vec::new(),
+            warnings: Vec::new(),
+            attributes: vec![HashMap::new()],
+            integrator: None,
+            pixel_samples: 16_i32,
+            pixel_filter: false,
+            world_begin: false,
+        }
+    }
+    fn current_file(&self) -> String {
+        match self.include_stack.last() {
+            Some((file, _line)) => file.clone(),
+            None => String::new(),
+        }
+    }
+    fn error(&self, token: &Token, message: &str) -> ParseError {
+        let mut include_stack: Vec<(String, usize)> = self.include_stack.clone();
+        include_stack.pop();
+        ParseError {
+            file: self.current_file(),
+            line: token.line,
+            column: token.column,
+            token: token.text.clone(),
+            message: String::from(message),
+            include_stack,
+        }
+    }
+    fn warning(&mut self, line: usize, column: usize, message: String) {
+        let warning = ParseWarning {
+            file: self.current_file(),
+            line,
+            column,
+            message,
+        };
+        eprintln!("{}", warning);
+        self.warnings.push(warning);
+    }
+    fn unsupported(&mut self, params: &mut Vec<Param>, names: &[&str], what: &str) {
+        for name in names {
+            if let Some(p) = take(params, name) {
+                self.warning(
+                    p.line,
+                    p.column,
+                    format!(
+                        "parameter {:?} of {} is not supported (ignored)",
+                        name, what
+                    ),
+                );
+            }
+        }
+    }
+    fn include_file(
+        &mut self,
+        filename: String,
+        api_state: &mut ApiState,
+        bsdf_state: &mut BsdfState,
+    ) -> Result<(), ParseError> {
+        let input: String = match fs::read_to_string(&filename) {
+            Ok(input) => input,
+            Err(e) => {
+                return Err(ParseError {
+                    file: filename,
+                    line: 0_usize,
+                    column: 0_usize,
+                    token: String::new(),
+                    message: format!("could not read file ({})", e),
+                    include_stack: self.include_stack.clone(),
+                });
+            }
+        };
+        self.parse_input(filename, &input, api_state, bsdf_state)
+    }
+    fn parse_input(
+        &mut self,
+        filename: String,
+        input: &str,
+        api_state: &mut ApiState,
+        bsdf_state: &mut BsdfState,
+    ) -> Result<(), ParseError> {
+        let tokens: Vec<Token> = match tokenize(input) {
+            Ok(tokens) => tokens,
+            Err((line, column, message)) => {
+                return Err(ParseError {
+                    file: filename,
+                    line,
+                    column,
+                    token: String::new(),
+                    message,
+                    include_stack: self.include_stack.clone(),
+                });
+            }
+        };
+        self.include_stack.push((filename, 0_usize));
+        let mut cursor = Cursor {
+            tokens: &tokens,
+            pos: 0_usize,
+        };
+        while let Some(token) = cursor.next() {
+            // remember the line for error messages of included files
+            if let Some(current) = self.include_stack.last_mut() {
+                current.1 = token.line;
+            }
+            if !token.is_directive() {
+                return Err(self.error(token, "expected a directive"));
+            }
+            let result = self.statement(token, &mut cursor, api_state, bsdf_state);
+            // e.g. unused parameters, already printed by the API
+            self.warnings.append(&mut api_state.take_warnings());
+            result?;
+        }
+        self.include_stack.pop();
+        Ok(())
+    }
+    fn string_arg(&self, directive: &Token, cursor: &mut Cursor) -> Result<String, ParseError> {
+        match cursor.next() {
+            Some(token) if token.kind == TokenKind::Quoted => Ok(token.text.clone()),
+            Some(token) => Err(self.error(
+                token,
+                &format!("expected a quoted string after {}", directive.text),
+            )),
+            None => Err(self.error(
+                directive,
+                &format!("expected a quoted string after {}", directive.text),
+            )),
+        }
+    }
+    /// Exactly *n* numbers, optionally enclosed in brackets.
+    fn float_args(
+        &self,
+        directive: &Token,
+        cursor: &mut Cursor,
+        n: usize,
+    ) -> Result<Vec<Float>, ParseError> {
+        let bracket: bool = match cursor.peek() {
+            Some(token) if token.kind == TokenKind::OpenBracket => {
+                cursor.next();
+                true
+            }
+            _ => false,
+        };
+        let mut v: Vec<Float> = Vec::with_capacity(n);
+        for _i in 0..n {
+            match cursor.next() {
+                Some(token) if token.kind == TokenKind::Word => match f32::from_str(&token.text) {
+                    Ok(number) => v.push(number),
+                    Err(_) => {
+                        return Err(self.error(token, "expected a floating point number"));
+                    }
+                },
+                Some(token) => {
+                    return Err(self.error(token, "expected a floating point number"));
+                }
+                None => {
+                    return Err(self.error(
+                        directive,
+                        &format!("expected {} numbers after {}", n, directive.text),
+                    ));
+                }
+            }
+        }
+        if bracket {
+            match cursor.next() {
+                Some(token) if token.kind == TokenKind::CloseBracket => {}
+                Some(token) => return Err(self.error(token, "expected ']'")),
+                None => return Err(self.error(directive, "expected ']'")),
+            }
+        }
+        Ok(v)
+    }
+    fn value(&self, token: &Token) -> Result<Value, ParseError> {
+        match token.kind {
+            TokenKind::Quoted => Ok(Value::Str(token.text.clone())),
+            TokenKind::Word => match token.text.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                text => match f32::from_str(text) {
+                    Ok(number) => Ok(Value::Number(number)),
+                    Err(_) => Err(self.error(token, "expected a parameter value")),
+                },
+            },
+            _ => Err(self.error(token, "expected a parameter value")),
+        }
+    }
+    /// Parameter list of a statement: `"type name" value` or `"type
+    /// name" [ values ]`.
+    fn params(&self, cursor: &mut Cursor) -> Result<Vec<Param>, ParseError> {
+        let mut params: Vec<Param> = Vec::new();
+        while let Some(declaration) = cursor.peek() {
+            if declaration.kind != TokenKind::Quoted {
+                break;
+            }
+            cursor.next();
+            let words: Vec<&str> = declaration.text.split_whitespace().collect();
+            if words.len() != 2 {
+                return Err(self.error(
+                    declaration,
+                    "expected a parameter declaration (\"type name\")",
+                ));
+            }
+            let mut values: Vec<Value> = Vec::new();
+            match cursor.next() {
+                Some(token) if token.kind == TokenKind::OpenBracket => loop {
+                    match cursor.next() {
+                        Some(token) if token.kind == TokenKind::CloseBracket => break,
+                        Some(token) => values.push(self.value(token)?),
+                        None => return Err(self.error(token, "unterminated '['")),
+                    }
+                },
+                Some(token) if !token.is_directive() => values.push(self.value(token)?),
+                _ => {
+                    return Err(self.error(
+                        declaration,
+                        &format!("missing value of parameter {:?}", words[1]),
+                    ));
+                }
+            }
+            params.push(Param {
+                param_type: String::from(words[0]),
+                name: String::from(words[1]),
+                values,
+                line: declaration.line,
+                column: declaration.column,
+                may_be_unused: false,
+            });
+        }
+        Ok(params)
+    }
+    /// Adds the defaults of an Attribute *target* for all parameters
+    /// which were not given explicitly.
+    fn with_attributes(&self, target: &str, mut params: Vec<Param>) -> Vec<Param> {
+        if let Some(defaults) = self.attributes.last().and_then(|a| a.get(target)) {
+            for p in defaults {
+                if find(&params, &p.name).is_none() {
+                    let mut default: Param = p.clone();
+                    default.may_be_unused = true;
+                    params.push(default);
+                }
+            }
+        }
+        params
+    }
+    /// Reads the values of a sampled spectrum file.
+    fn spectrum_file(&mut self, p: &Param, api_state: &ApiState, name: &str) -> Option<Spectrum> {
+        let filename: String = resolve_filename(&self.current_file(), api_state, name);
+        let mut vals: Vec<Float> = Vec::new();
+        if !read_float_file(&filename, &mut vals) || vals.len() < 2 {
+            self.warning(
+                p.line,
+                p.column,
+                format!("spectrum {:?} unknown (not a named spectrum or file)", name),
+            );
+            return None;
+        }
+        let lambdas: Vec<Float> = vals.iter().step_by(2).copied().collect();
+        let v: Vec<Float> = vals.iter().skip(1).step_by(2).copied().collect();
+        let n: usize = lambdas.len().min(v.len());
+        Some(Spectrum::from_sampled(&lambdas[..n], &v[..n], n as i32))
+    }
+    /// Converts a (translated) parameter into the ParamSet. Spectra of
+    /// light sources (*illuminant*) get normalized to a luminance of
+    /// one, like pbrt-v4 does.
+    fn add_param(
+        &mut self,
+        param_set: &mut ParamSet,
+        p: &Param,
+        illuminant: bool,
+        api_state: &ApiState,
+    ) {
+        let name: String = p.name.clone();
+        let malformed = |parser: &mut V4Parser| {
+            parser.warning(
+                p.line,
+                p.column,
+                format!(
+                    "values of {:?} parameter {:?} malformed (ignored)",
+                    p.param_type, name
+                ),
+            );
+        };
+        match p.param_type.as_str() {
+            "integer" => match p.floats() {
+                Some(floats) => {
+                    let ints: Vec<i32> = floats.iter().map(|f| *f as i32).collect();
+                    if ints.len() == 1 {
+                        param_set.add_int(name, ints[0]);
+                    } else {
+                        param_set.add_ints(name, ints);
+                    }
+                }
+                None => malformed(self),
+            },
+            "float" => match p.floats() {
+                Some(floats) => {
+                    if floats.len() == 1 {
+                        param_set.add_float(name, floats[0]);
+                    } else {
+                        param_set.add_floats(name, floats);
+                    }
+                }
+                None => malformed(self),
+            },
+            "point2" => match p.floats() {
+                Some(floats) if floats.len() == 2 => param_set.add_point2f(
+                    name,
+                    Point2f {
+                        x: floats[0],
+                        y: floats[1],
+                    },
+                ),
+                Some(floats) if floats.len() % 2 == 0 => param_set.add_point2fs(name, floats),
+                _ => malformed(self),
+            },
+            "point3" | "point" => match p.floats() {
+                Some(floats) if floats.len() == 3 => param_set.add_point3f(
+                    name,
+                    Point3f {
+                        x: floats[0],
+                        y: floats[1],
+                        z: floats[2],
+                    },
+                ),
+                Some(floats) if floats.len() % 3 == 0 => param_set.add_point3fs(name, floats),
+                _ => malformed(self),
+            },
+            "vector3" | "vector" => match p.floats() {
+                Some(floats) if floats.len() == 3 => param_set.add_vector3f(
+                    name,
+                    Vector3f {
+                        x: floats[0],
+                        y: floats[1],
+                        z: floats[2],
+                    },
+                ),
+                Some(floats) if floats.len() % 3 == 0 => param_set.add_vector3fs(name, floats),
+                _ => malformed(self),
+            },
+            "normal3" | "normal" => match p.floats() {
+                Some(floats) if floats.len() == 3 => param_set.add_normal3f(
+                    name,
+                    Normal3f {
+                        x: floats[0],
+                        y: floats[1],
+                        z: floats[2],
+                    },
+                ),
+                Some(floats) if floats.len() % 3 == 0 => param_set.add_normal3fs(name, floats),
+                _ => malformed(self),
+            },
+            "rgb" | "color" => match p.floats() {
+                Some(floats) if floats.len() == 3 => {
+                    param_set.add_rgb(name, [floats[0], floats[1], floats[2]])
+                }
+                _ => malformed(self),
+            },
+            "blackbody" => match p.floats() {
+                // pbrt-v3 used [ temperature scale ]
+                Some(floats) if floats.len() == 1 || floats.len() == 2 => {
+                    let mut s: Spectrum = blackbody(floats[0]);
+                    if illuminant && s.y() > 0.0 as Float {
+                        s = s / s.y();
+                    }
+                    if floats.len() == 2 {
+                        s = s * floats[1];
+                    }
+                    param_set.add_rgb_spectrum(name, s);
+                }
+                _ => malformed(self),
+            },
+            "spectrum" => {
+                let spectrum: Option<Spectrum> = if let Some(floats) = p.floats() {
+                    if floats.len() >= 2 && floats.len() % 2 == 0 {
+                        let lambdas: Vec<Float> = floats.iter().step_by(2).copied().collect();
+                        let v: Vec<Float> = floats.iter().skip(1).step_by(2).copied().collect();
+                        Some(Spectrum::from_sampled(&lambdas, &v, lambdas.len() as i32))
+                    } else {
+                        malformed(self);
+                        None
+                    }
+                } else if let Some(spectrum_name) = p.string() {
+                    match named_spectrum(&spectrum_name) {
+                        Some(s) => Some(s),
+                        None => self.spectrum_file(p, api_state, &spectrum_name),
+                    }
+                } else {
+                    malformed(self);
+                    None
+                };
+                if let Some(mut s) = spectrum {
+                    if illuminant && s.y() > 0.0 as Float {
+                        s = s / s.y();
+                    }
+                    param_set.add_rgb_spectrum(name, s);
+                }
+            }
+            "string" => match p.strings() {
+                Some(strings) => param_set.add_strings(name, strings),
+                None => malformed(self),
+            },
+            "bool" => match p.bool() {
+                Some(b) => param_set.add_bool(name, b),
+                None => malformed(self),
+            },
+            "texture" => match p.string() {
+                Some(texture) => param_set.add_texture(name, texture),
+                None => malformed(self),
+            },
+            _ => self.warning(
+                p.line,
+                p.column,
+                format!(
+                    "parameter type {:?} of {:?} is not supported (ignored)",
+                    p.param_type, name
+                ),
+            ),
+        }
+    }
+    fn param_set(
+        &mut self,
+        directive: &Token,
+        name: &str,
+        params: &[Param],
+        illuminant: bool,
+        api_state: &ApiState,
+    ) -> ParamSet {
+        let mut param_set: ParamSet = ParamSet {
+            key_word: directive.text.clone(),
+            name: String::from(name),
+            location: Some((self.current_file(), directive.line, directive.column)),
+            ..Default::default()
+        };
+        for p in params {
+            self.add_param(&mut param_set, p, illuminant, api_state);
+            if p.may_be_unused {
+                param_set.mark_used(&p.name);
+            }
+        }
+        param_set
+    }
+    fn check_plugin_name(&mut self, directive: &Token, param_set: &ParamSet) {
+        if let Some(message) = check_plugin_name(&directive.text, param_set) {
+            self.warning(directive.line, directive.column, message);
+        }
+    }
+    fn translate_camera(
+        &mut self,
+        directive: &Token,
+        name: &str,
+        params: &mut Vec<Param>,
+    ) -> String {
+        match name {
+            "realistic" => {
+                self.unsupported(params, &["aperture"], "camera \"realistic\"");
+                String::from(name)
+            }
+            "spherical" => {
+                let mapping: String = take(params, "mapping")
+                    .and_then(|p| p.string())
+                    .unwrap_or_else(|| String::from("equalarea"));
+                if mapping != "equirectangular" {
+                    self.warning(
+                        directive.line,
+                        directive.column,
+                        format!(
+                            "spherical camera mapping {:?} is not supported, using \"equirectangular\"",
+                            mapping
+                        ),
+                    );
+                }
+                String::from("environment")
+            }
+            _ => String::from(name),
+        }
+    }
+    fn translate_film(&mut self, directive: &Token, name: &str, params: &mut Vec<Param>) -> String {
+        if name == "gbuffer" || name == "spectral" {
+            self.warning(
+                directive.line,
+                directive.column,
+                format!("film {:?} is not supported, writing an RGB image", name),
+            );
+        }
+        rename(params, "maxcomponentvalue", "maxsampleluminance");
+        if let Some(iso) = take(params, "iso") {
+            // the imaging ratio of pbrt-v4's default sensor
+            match iso.float() {
+                Some(value) => params.push(Param::new(
+                    "float",
+                    "scale",
+                    Param::numbers(&[value / 100.0 as Float]),
+                    &iso,
+                )),
+                None => self.unsupported(&mut vec![iso], &["iso"], "film"),
+            }
+        }
+        if let Some(sensor) = find(params, "sensor") {
+            if sensor.string().as_deref() == Some("cie1931") {
+                take(params, "sensor");
+            }
+        }
+        self.unsupported(params, &["sensor", "whitebalance"], "film");
+        take(params, "savefp16");
+        String::from(match name {
+            "rgb" | "gbuffer" | "spectral" => "image",
+            _ => name,
+        })
+    }
+    fn translate_sampler(
+        &mut self,
+        directive: &Token,
+        name: &str,
+        params: &mut Vec<Param>,
+    ) -> String {
+        if let Some(pixel_samples) = find(params, "pixelsamples").and_then(|p| p.float()) {
+            self.pixel_samples = pixel_samples as i32;
+        }
+        take(params, "randomization");
+        self.unsupported(params, &["seed"], "sampler");
+        match name {
+            "zsobol" | "paddedsobol" => String::from("sobol"),
+            "pmj02bn" => {
+                self.warning(
+                    directive.line,
+                    directive.column,
+                    String::from("sampler \"pmj02bn\" is not supported, using \"sobol\""),
+                );
+                String::from("sobol")
+            }
+            "independent" => String::from("random"),
+            _ => String::from(name),
+        }
+    }
+    fn translate_integrator(
+        &mut self,
+        directive: &Token,
+        name: &str,
+        params: &mut Vec<Param>,
+    ) -> String {
+        if let Some(light_sampler) = take(params, "lightsampler") {
+            let strategy: &str = match light_sampler.string().as_deref() {
+                Some("uniform") => "uniform",
+                Some("power") => "power",
+                _ => "spatial",
+            };
+            params.push(Param::new(
+                "string",
+                "lightsamplestrategy",
+                vec![Value::Str(String::from(strategy))],
+                &light_sampler,
+            ));
+        }
+        if let Some(regularize) = find(params, "regularize") {
+            if regularize.bool() == Some(false) {
+                take(params, "regularize");
+            }
+        }
+        self.unsupported(params, &["regularize"], "integrator");
+        let approximation = |parser: &mut V4Parser, v3_name: &str| -> String {
+            parser.warning(
+                directive.line,
+                directive.column,
+                format!(
+                    "integrator {:?} is not supported, using {:?}",
+                    name, v3_name
+                ),
+            );
+            String::from(v3_name)
+        };
+        match name {
+            "simplepath" | "randomwalk" => approximation(self, "path"),
+            "simplevolpath" => approximation(self, "volpath"),
+            "lightpath" => approximation(self, "bdpt"),
+            "aov" => approximation(self, "path"),
+            "sppm" => {
+                // pbrt-v4 runs one iteration per pixel sample
+                if find(params, "iterations").is_none() && find(params, "numiterations").is_none() {
+                    let origin: Param = Param {
+                        param_type: String::new(),
+                        name: String::new(),
+                        values: Vec::new(),
+                        line: directive.line,
+                        column: directive.column,
+                        may_be_unused: false,
+                    };
+                    params.push(Param::new(
+                        "integer",
+                        "numiterations",
+                        Param::numbers(&[self.pixel_samples as Float]),
+                        &origin,
+                    ));
+                }
+                String::from(name)
+            }
+            _ => String::from(name),
+        }
+    }
+    fn translate_pixel_filter(&mut self, name: &str, params: &mut Vec<Param>) -> String {
+        self.pixel_filter = true;
+        rename(params, "xradius", "xwidth");
+        rename(params, "yradius", "ywidth");
+        if name == "gaussian" {
+            if let Some(sigma) = take(params, "sigma") {
+                match sigma.float() {
+                    Some(s) if s > 0.0 as Float => params.push(Param::new(
+                        "float",
+                        "alpha",
+                        Param::numbers(&[1.0 as Float / (2.0 as Float * s * s)]),
+                        &sigma,
+                    )),
+                    _ => self.unsupported(&mut vec![sigma], &["sigma"], "filter \"gaussian\""),
+                }
+            }
+            // pbrt-v4's default radius
+            for width in &["xwidth", "ywidth"] {
+                if find(params, width).is_none() {
+                    params.push(Param {
+                        param_type: String::from("float"),
+                        name: String::from(*width),
+                        values: Param::numbers(&[1.5 as Float]),
+                        line: 0_usize,
+                        column: 0_usize,
+                        may_be_unused: false,
+                    });
+                }
+            }
+        }
+        String::from(match name {
+            "lanczos" => "sinc",
+            _ => name,
+        })
+    }
+    fn translate_light(&mut self, directive: &Token, name: &str, params: &mut Vec<Param>) {
+        let what: String = format!("light {:?}", name);
+        self.unsupported(params, &["power", "illuminance", "portal"], &what);
+        floats_to_rgb(params, &["scale"]);
+        if name == "infinite" || name == "goniometric" || name == "projection" {
+            rename(params, "filename", "mapname");
+            if name != "projection" && find(params, "mapname").is_some() {
+                self.warning(
+                    directive.line,
+                    directive.column,
+                    format!(
+                        "{} maps use an equal-area parameterization in pbrt-v4, the image is read as latitude-longitude map",
+                        what
+                    ),
+                );
+            }
+        }
+    }
+    fn translate_area_light(&mut self, name: &str, params: &mut Vec<Param>) {
+        let what: String = format!("area light {:?}", name);
+        self.unsupported(params, &["power", "filename"], &what);
+        floats_to_rgb(params, &["scale"]);
+    }
+    /// Translates a pbrt-v4 material into the closest pbrt-v3 one and
+    /// returns its name.
+    fn translate_material(
+        &mut self,
+        directive: &Token,
+        name: &str,
+        params: &mut Vec<Param>,
+    ) -> String {
+        let what: String = format!("material {:?}", name);
+        rename(params, "displacement", "bumpmap");
+        self.unsupported(params, &["normalmap"], &what);
+        let approximation = |parser: &mut V4Parser, message: &str| {
+            parser.warning(
+                directive.line,
+                directive.column,
+                format!("{} {}", what, message),
+            );
+        };
+        let v3_name: &str = match name {
+            "diffuse" => {
+                rename(params, "reflectance", "Kd");
+                "matte"
+            }
+            "coateddiffuse" => {
+                rename(params, "reflectance", "Kd");
+                self.split_roughness(params, 0.0 as Float);
+                // specular reflectance of the coating at normal incidence
+                let eta: Float = match take(params, "eta") {
+                    Some(p) => match p.float() {
+                        Some(eta) => eta,
+                        None => match p.string().and_then(|s| glass_ior(&s)) {
+                            Some(eta) => eta,
+                            None => {
+                                self.unsupported(&mut vec![p], &["eta"], &what);
+                                1.5 as Float
+                            }
+                        },
+                    },
+                    None => 1.5 as Float,
+                };
+                let r0: Float = ((eta - 1.0) / (eta + 1.0)) * ((eta - 1.0) / (eta + 1.0));
+                params.push(Param {
+                    param_type: String::from("rgb"),
+                    name: String::from("Ks"),
+                    values: Param::numbers(&[r0, r0, r0]),
+                    line: directive.line,
+                    column: directive.column,
+                    may_be_unused: false,
+                });
+                self.unsupported(params, &["thickness", "albedo", "g"], &what);
+                take(params, "maxdepth");
+                take(params, "nsamples");
+                "substrate"
+            }
+            "conductor" | "coatedconductor" => {
+                if name == "coatedconductor" {
+                    approximation(self, "is approximated without its coating");
+                    params.retain(|p| !p.name.starts_with("interface."));
+                    for p in params.iter_mut() {
+                        if let Some(conductor_name) = p.name.strip_prefix("conductor.") {
+                            p.name = String::from(conductor_name);
+                        }
+                    }
+                    take(params, "thickness");
+                    take(params, "albedo");
+                    take(params, "g");
+                    take(params, "maxdepth");
+                    take(params, "nsamples");
+                }
+                self.conductor_reflectance(params, &what);
+                if find(params, "roughness").is_none() {
+                    params.push(Param {
+                        param_type: String::from("float"),
+                        name: String::from("roughness"),
+                        values: Param::numbers(&[0.0 as Float]),
+                        line: directive.line,
+                        column: directive.column,
+                        may_be_unused: false,
+                    });
+                }
+                floats_to_rgb(params, &["eta", "k"]);
+                "metal"
+            }
+            "dielectric" | "thindielectric" => {
+                if name == "thindielectric" {
+                    approximation(self, "is approximated by a solid dielectric");
+                }
+                self.dielectric_eta(params, &what);
+                rename(params, "roughness", "uroughness");
+                if let Some(u) = find(params, "uroughness") {
+                    if find(params, "vroughness").is_none() {
+                        let v: Param = Param::new(&u.param_type, "vroughness", u.values.clone(), u);
+                        params.push(v);
+                    }
+                }
+                "glass"
+            }
+            "diffusetransmission" => {
+                let scale: Float = match take(params, "scale") {
+                    Some(p) => match p.float() {
+                        Some(scale) => scale,
+                        None => {
+                            self.unsupported(&mut vec![p], &["scale"], &what);
+                            1.0 as Float
+                        }
+                    },
+                    None => 1.0 as Float,
+                };
+                rename(params, "reflectance", "reflect");
+                rename(params, "transmittance", "transmit");
+                // pbrt-v4's defaults
+                for p in &["reflect", "transmit"] {
+                    if find(params, p).is_none() {
+                        params.push(Param {
+                            param_type: String::from("float"),
+                            name: String::from(*p),
+                            values: Param::numbers(&[0.25 as Float]),
+                            line: directive.line,
+                            column: directive.column,
+                            may_be_unused: false,
+                        });
+                    }
+                }
+                for (p, value) in &[("Kd", scale), ("Ks", 0.0 as Float)] {
+                    params.push(Param {
+                        param_type: String::from("float"),
+                        name: String::from(*p),
+                        values: Param::numbers(&[*value]),
+                        line: directive.line,
+                        column: directive.column,
+                        may_be_unused: false,
+                    });
+                }
+                "translucent"
+            }
+            "mix" => {
+                if let Some(materials) = take(params, "materials") {
+                    match materials.strings() {
+                        Some(ref names) if names.len() == 2 => {
+                            // pbrt-v3 picks the first material for an
+                            // amount of one, pbrt-v4 the second one
+                            params.push(Param::new(
+                                "string",
+                                "namedmaterial1",
+                                vec![Value::Str(names[1].clone())],
+                                &materials,
+                            ));
+                            params.push(Param::new(
+                                "string",
+                                "namedmaterial2",
+                                vec![Value::Str(names[0].clone())],
+                                &materials,
+                            ));
+                        }
+                        _ => self.unsupported(&mut vec![materials], &["materials"], &what),
+                    }
+                }
+                if let Some(amount) = find(params, "amount") {
+                    if amount.param_type == "texture" {
+                        // pbrt-v3 expects a spectrum texture
+                        self.unsupported(params, &["amount"], &what);
+                    }
+                }
+                "mix"
+            }
+            "hair" => {
+                self.unsupported(params, &["reflectance"], &what);
+                "hair"
+            }
+            "subsurface" => {
+                self.unsupported(params, &["g"], &what);
+                if find(params, "reflectance").is_some() {
+                    rename(params, "reflectance", "Kd");
+                    take(params, "sigma_a");
+                    take(params, "sigma_s");
+                    take(params, "name");
+                    self.split_roughness(params, 0.0 as Float);
+                    "kdsubsurface"
+                } else {
+                    self.split_roughness(params, 0.0 as Float);
+                    "subsurface"
+                }
+            }
+            "interface" => "none",
+            "measured" => {
+                approximation(self, "is not supported, using \"diffuse\"");
+                params.clear();
+                "matte"
+            }
+            _ => name,
+        };
+        floats_to_rgb(
+            params,
+            &[
+                "Kd", "Ks", "Kr", "Kt", "reflect", "transmit", "amount", "mfp", "sigma_a",
+                "sigma_s",
+            ],
+        );
+        String::from(v3_name)
+    }
+    /// pbrt-v4's "roughness" sets both "uroughness" and "vroughness"
+    /// (which default to *default*).
+    fn split_roughness(&mut self, params: &mut Vec<Param>, default: Float) {
+        let roughness: Option<Param> = take(params, "roughness");
+        for name in &["uroughness", "vroughness"] {
+            if find(params, name).is_none() {
+                let (param_type, values): (String, Vec<Value>) = match roughness {
+                    Some(ref p) => (p.param_type.clone(), p.values.clone()),
+                    None => (String::from("float"), Param::numbers(&[default])),
+                };
+                params.push(Param {
+                    param_type,
+                    name: String::from(*name),
+                    values,
+                    line: 0_usize,
+                    column: 0_usize,
+                    may_be_unused: false,
+                });
+            }
+        }
+    }
+    /// A conductor given by its "reflectance" gets the eta and k of a
+    /// metal with that reflectance at normal incidence.
+    fn conductor_reflectance(&mut self, params: &mut Vec<Param>, what: &str) {
+        if let Some(reflectance) = take(params, "reflectance") {
+            match reflectance.rgb() {
+                Some(r) => {
+                    let mut k: [Float; 3] = [0.0 as Float; 3];
+                    for (ki, ri) in k.iter_mut().zip(r.iter()) {
+                        let ri: Float = ri.max(0.0 as Float).min(0.9999 as Float);
+                        *ki = 2.0 as Float * ri.sqrt() / (1.0 as Float - ri).sqrt();
+                    }
+                    params.retain(|p| p.name != "eta" && p.name != "k");
+                    params.push(Param::new(
+                        "rgb",
+                        "eta",
+                        Param::numbers(&[1.0 as Float, 1.0 as Float, 1.0 as Float]),
+                        &reflectance,
+                    ));
+                    params.push(Param::new("rgb", "k", Param::numbers(&k), &reflectance));
+                }
+                None => self.unsupported(&mut vec![reflectance], &["reflectance"], what),
+            }
+        }
+    }
+    /// Named glasses become (dispersive) Sellmeier coefficients or a
+    /// constant index of refraction, sampled spectra an "etaspectrum".
+    fn dielectric_eta(&mut self, params: &mut Vec<Param>, what: &str) {
+        let eta: Param = match take(params, "eta") {
+            Some(eta) => eta,
+            None => return,
+        };
+        if eta.param_type == "float" || eta.param_type == "texture" {
+            params.push(eta);
+            return;
+        }
+        if eta.param_type == "spectrum" {
+            if let Some(floats) = eta.floats() {
+                params.push(Param::new(
+                    "float",
+                    "etaspectrum",
+                    Param::numbers(&floats),
+                    &eta,
+                ));
+                return;
+            }
+            if let Some(name) = eta.string() {
+                if name == "glass-BK7" {
+                    params.push(Param::new(
+                        "float",
+                        "sellmeierB",
+                        Param::numbers(&[1.039_612, 0.231_792_34, 1.010_469_4]),
+                        &eta,
+                    ));
+                    params.push(Param::new(
+                        "float",
+                        "sellmeierC",
+                        Param::numbers(&[0.006_000_699, 0.020_017_914, 103.560_65]),
+                        &eta,
+                    ));
+                }
+                if let Some(ior) = glass_ior(&name) {
+                    params.push(Param::new("float", "eta", Param::numbers(&[ior]), &eta));
+                    return;
+                }
+            }
+        }
+        self.unsupported(&mut vec![eta], &["eta"], what);
+    }
+    fn translate_texture(
+        &mut self,
+        directive: &Token,
+        class: &str,
+        params: &mut Vec<Param>,
+    ) -> String {
+        let what: String = format!("texture {:?}", class);
+        match class {
+            "imagemap" => {
+                if let Some(encoding) = take(params, "encoding") {
+                    let gamma: Option<bool> = match encoding.string().as_deref() {
+                        Some("sRGB") => Some(true),
+                        Some("linear") => Some(false),
+                        Some(other) if other.starts_with("gamma ") => {
+                            self.warning(
+                                encoding.line,
+                                encoding.column,
+                                format!("encoding {:?} is approximated by \"sRGB\"", other),
+                            );
+                            Some(true)
+                        }
+                        _ => None,
+                    };
+                    match gamma {
+                        Some(gamma) => params.push(Param::new(
+                            "bool",
+                            "gamma",
+                            vec![Value::Bool(gamma)],
+                            &encoding,
+                        )),
+                        None => self.unsupported(&mut vec![encoding], &["encoding"], &what),
+                    }
+                }
+                if let Some(filter) = take(params, "filter") {
+                    let trilinear: bool =
+                        !matches!(filter.string().as_deref(), Some("ewa") | Some("EWA"));
+                    params.push(Param::new(
+                        "bool",
+                        "trilinear",
+                        vec![Value::Bool(trilinear)],
+                        &filter,
+                    ));
+                }
+                if let Some(invert) = find(params, "invert") {
+                    if invert.bool() == Some(false) {
+                        take(params, "invert");
+                    }
+                }
+                self.unsupported(params, &["invert"], &what);
+                String::from(class)
+            }
+            "scale" => {
+                rename(params, "tex", "tex1");
+                rename(params, "scale", "tex2");
+                String::from(class)
+            }
+            "directionmix" => {
+                self.warning(
+                    directive.line,
+                    directive.column,
+                    format!("{} is approximated by an even \"mix\"", what),
+                );
+                take(params, "dir");
+                String::from("mix")
+            }
+            _ => String::from(class),
+        }
+    }
+    /// Translates the medium type, returns None for media without a
+    /// pbrt-v3 counterpart.
+    fn translate_medium(&mut self, directive: &Token, params: &mut Vec<Param>) -> Option<()> {
+        let medium_type: String = find(params, "type")
+            .and_then(|p| p.string())
+            .unwrap_or_default();
+        let what: String = format!("medium {:?}", medium_type);
+        match medium_type.as_str() {
+            "homogeneous" => {
+                self.unsupported(params, &["Le", "Lescale"], &what);
+            }
+            "uniformgrid" => {
+                self.unsupported(params, &["Le", "Lescale", "temperature"], &what);
+                for p in params.iter_mut() {
+                    if p.name == "type" {
+                        p.values = vec![Value::Str(String::from("heterogeneous"))];
+                    }
+                }
+            }
+            "rgbgrid" | "cloud" | "nanovdb" => {
+                self.warning(
+                    directive.line,
+                    directive.column,
+                    format!("{} is not supported (ignored)", what),
+                );
+                return None;
+            }
+            _ => {}
+        }
+        Some(())
+    }
+    fn translate_shape(&mut self, name: &str, params: &mut Vec<Param>) -> String {
+        let what: String = format!("shape {:?}", name);
+        self.unsupported(params, &["emissionfilename"], &what);
+        match name {
+            "bilinearmesh" => {
+                // each patch (p00 p10 p01 p11) becomes two triangles
+                if let Some(indices) = take(params, "indices") {
+                    if let Some(v) = indices.floats() {
+                        let mut triangles: Vec<Float> = Vec::with_capacity(v.len() / 4 * 6);
+                        for patch in v.chunks_exact(4) {
+                            triangles.extend_from_slice(&[
+                                patch[0], patch[1], patch[3], patch[0], patch[3], patch[2],
+                            ]);
+                        }
+                        params.push(Param::new(
+                            "integer",
+                            "indices",
+                            Param::numbers(&triangles),
+                            &indices,
+                        ));
+                    }
+                } else if let Some(p) = find(params, "P") {
+                    if p.values.len() == 12 {
+                        let indices: Param = Param::new(
+                            "integer",
+                            "indices",
+                            Param::numbers(&[0.0, 1.0, 3.0, 0.0, 3.0, 2.0]),
+                            p,
+                        );
+                        params.push(indices);
+                    }
+                }
+                String::from("trianglemesh")
+            }
+            "trianglemesh" => {
+                // pbrt-v4 allows to omit the indices of a single triangle
+                if find(params, "indices").is_none() {
+                    if let Some(p) = find(params, "P") {
+                        if p.values.len() == 9 {
+                            let indices: Param = Param::new(
+                                "integer",
+                                "indices",
+                                Param::numbers(&[0.0, 1.0, 2.0]),
+                                p,
+                            );
+                            params.push(indices);
+                        }
+                    }
+                }
+                String::from(name)
+            }
+            "plymesh" => {
+                self.unsupported(params, &["displacement", "edgelength"], &what);
+                String::from(name)
+            }
+            _ => String::from(name),
+        }
+    }
+    /// Creates the integrator (and pbrt-v4's default filter) at the end
+    /// of the options block.
+    fn world_begin(&mut self, directive: &Token, api_state: &mut ApiState) {
+        if !self.pixel_filter {
+            let mut params: Vec<Param> = Vec::new();
+            let name: String = self.translate_pixel_filter("gaussian", &mut params);
+            let pixel_filter: Token = Token {
+                kind: TokenKind::Word,
+                text: String::from("PixelFilter"),
+                line: directive.line,
+                column: directive.column,
+            };
+            let param_set: ParamSet =
+                self.param_set(&pixel_filter, &name, &params, false, api_state);
+            pbrt_pixel_filter(api_state, param_set);
+        }
+        if let Some((directive, name, mut params)) = self.integrator.take() {
+            let name: String = self.translate_integrator(&directive, &name, &mut params);
+            let param_set: ParamSet = self.param_set(&directive, &name, &params, false, api_state);
+            self.check_plugin_name(&directive, &param_set);
+            pbrt_integrator(api_state, param_set);
+        }
+        self.world_begin = true;
+        pbrt_world_begin(api_state);
+    }
+    fn statement(
+        &mut self,
+        directive: &Token,
+        cursor: &mut Cursor,
+        api_state: &mut ApiState,
+        bsdf_state: &mut BsdfState,
+    ) -> Result<(), ParseError> {
+        match directive.text.as_str() {
+            "Accelerator" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, false, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_accelerator(api_state, param_set);
+            }
+            "ActiveTransform" => match cursor.next() {
+                Some(token) if token.text == "All" => pbrt_active_transform_all(api_state),
+                Some(token) if token.text == "StartTime" => {
+                    pbrt_active_transform_start_time(api_state)
+                }
+                Some(token) if token.text == "EndTime" => pbrt_active_transform_end_time(api_state),
+                Some(token) => {
+                    return Err(self.error(token, "expected All, StartTime or EndTime"));
+                }
+                None => return Err(self.error(directive, "expected All, StartTime or EndTime")),
+            },
+            "AreaLightSource" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                let mut params: Vec<Param> = self.with_attributes("light", params);
+                self.translate_area_light(&name, &mut params);
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, true, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_area_light_source(api_state, param_set);
+            }
+            "Attribute" => {
+                let target: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                if ATTRIBUTE_TARGETS.contains(&target.as_str()) {
+                    let attributes: &mut HashMap<String, Vec<Param>> =
+                        self.attributes.last_mut().unwrap();
+                    let defaults: &mut Vec<Param> = attributes.entry(target).or_default();
+                    for p in params {
+                        defaults.retain(|d| d.name != p.name);
+                        defaults.push(p);
+                    }
+                } else {
+                    self.warning(
+                        directive.line,
+                        directive.column,
+                        format!("Attribute target {:?} unknown (ignored)", target),
+                    );
+                }
+            }
+            "AttributeBegin" => {
+                let current: HashMap<String, Vec<Param>> =
+                    self.attributes.last().cloned().unwrap_or_default();
+                self.attributes.push(current);
+                pbrt_attribute_begin(api_state);
+            }
+            "AttributeEnd" => {
+                if self.attributes.len() > 1 {
+                    self.attributes.pop();
+                }
+                pbrt_attribute_end(api_state);
+            }
+            "Camera" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let mut params: Vec<Param> = self.params(cursor)?;
+                let name: String = self.translate_camera(directive, &name, &mut params);
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, false, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_camera(api_state, param_set);
+            }
+            "ColorSpace" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                if name != "srgb" {
+                    self.warning(
+                        directive.line,
+                        directive.column,
+                        format!("color space {:?} is not supported, using \"srgb\"", name),
+                    );
+                }
+            }
+            "ConcatTransform" | "Transform" => {
+                let m: Vec<Float> = self.float_args(directive, cursor, 16)?;
+                let tr: Transform = Transform::new(
+                    m[0], m[4], m[8], m[12], m[1], m[5], m[9], m[13], m[2], m[6], m[10], m[14],
+                    m[3], m[7], m[11], m[15],
+                );
+                if directive.text == "Transform" {
+                    pbrt_transform(api_state, &tr);
+                } else {
+                    pbrt_concat_transform(api_state, &tr);
+                }
+            }
+            "CoordinateSystem" | "CoordSysTransform" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let param_set: ParamSet = self.param_set(directive, &name, &[], false, api_state);
+                if directive.text == "CoordinateSystem" {
+                    pbrt_coordinate_system(api_state, param_set);
+                } else {
+                    pbrt_coord_sys_transform(api_state, param_set);
+                }
+            }
+            "Film" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let mut params: Vec<Param> = self.params(cursor)?;
+                let name: String = self.translate_film(directive, &name, &mut params);
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, false, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_film(api_state, param_set);
+            }
+            "Identity" => pbrt_identity(api_state),
+            "Import" | "Include" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let filename: String = resolve_filename(&self.current_file(), api_state, &name);
+                if !api_state.writes_scene() {
+                    println!("{} {:?}", directive.text, filename);
+                }
+                self.include_file(filename, api_state, bsdf_state)?;
+            }
+            "Integrator" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                self.integrator = Some((directive.clone(), name, params));
+            }
+            "LightSource" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                let mut params: Vec<Param> = self.with_attributes("light", params);
+                self.translate_light(directive, &name, &mut params);
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, true, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_light_source(api_state, param_set);
+            }
+            "LookAt" => {
+                let v: Vec<Float> = self.float_args(directive, cursor, 9)?;
+                pbrt_look_at(
+                    api_state, v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7], v[8],
+                );
+            }
+            "MakeNamedMaterial" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                let mut params: Vec<Param> = self.with_attributes("material", params);
+                let material_type: Param = match take(&mut params, "type") {
+                    Some(p) => p,
+                    None => {
+                        return Err(self.error(
+                            directive,
+                            "no parameter string \"type\" found in MakeNamedMaterial",
+                        ));
+                    }
+                };
+                let v4_name: String = material_type.string().unwrap_or_default();
+                let v3_name: String = self.translate_material(directive, &v4_name, &mut params);
+                params.push(Param::new(
+                    "string",
+                    "type",
+                    vec![Value::Str(v3_name)],
+                    &material_type,
+                ));
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, false, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_make_named_material(api_state, bsdf_state, param_set);
+            }
+            "MakeNamedMedium" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                let mut params: Vec<Param> = self.with_attributes("medium", params);
+                if self.translate_medium(directive, &mut params).is_some() {
+                    let param_set: ParamSet =
+                        self.param_set(directive, &name, &params, false, api_state);
+                    self.check_plugin_name(directive, &param_set);
+                    pbrt_make_named_medium(api_state, param_set);
+                }
+            }
+            "Material" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                let mut params: Vec<Param> = self.with_attributes("material", params);
+                let name: String = self.translate_material(directive, &name, &mut params);
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, false, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_material(api_state, param_set);
+            }
+            "MediumInterface" => {
+                // a single name is used for both sides
+                let inside: String = self.string_arg(directive, cursor)?;
+                let outside: String = match cursor.peek() {
+                    Some(token) if token.kind == TokenKind::Quoted => {
+                        cursor.next();
+                        token.text.clone()
+                    }
+                    _ => inside.clone(),
+                };
+                pbrt_medium_interface(api_state, &inside, &outside);
+            }
+            "NamedMaterial" | "ObjectBegin" | "ObjectInstance" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let param_set: ParamSet = self.param_set(directive, &name, &[], false, api_state);
+                match directive.text.as_str() {
+                    "NamedMaterial" => pbrt_named_material(api_state, param_set),
+                    "ObjectBegin" => {
+                        let current: HashMap<String, Vec<Param>> =
+                            self.attributes.last().cloned().unwrap_or_default();
+                        self.attributes.push(current);
+                        pbrt_object_begin(api_state, param_set);
+                    }
+                    _ => pbrt_object_instance(api_state, param_set),
+                }
+            }
+            "ObjectEnd" => {
+                if self.attributes.len() > 1 {
+                    self.attributes.pop();
+                }
+                pbrt_object_end(api_state);
+            }
+            "Option" => {
+                for p in self.params(cursor)? {
+                    self.warning(
+                        p.line,
+                        p.column,
+                        format!("Option {:?} is not supported (ignored)", p.name),
+                    );
+                }
+            }
+            "PixelFilter" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let mut params: Vec<Param> = self.params(cursor)?;
+                let name: String = self.translate_pixel_filter(&name, &mut params);
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, false, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_pixel_filter(api_state, param_set);
+            }
+            "ReverseOrientation" => pbrt_reverse_orientation(api_state),
+            "Rotate" => {
+                let v: Vec<Float> = self.float_args(directive, cursor, 4)?;
+                pbrt_rotate(api_state, v[0], v[1], v[2], v[3]);
+            }
+            "Sampler" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let mut params: Vec<Param> = self.params(cursor)?;
+                let name: String = self.translate_sampler(directive, &name, &mut params);
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, false, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_sampler(api_state, param_set);
+            }
+            "Scale" => {
+                let v: Vec<Float> = self.float_args(directive, cursor, 3)?;
+                pbrt_scale(api_state, v[0], v[1], v[2]);
+            }
+            "Shape" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                let mut params: Vec<Param> = self.with_attributes("shape", params);
+                let name: String = self.translate_shape(&name, &mut params);
+                let param_set: ParamSet =
+                    self.param_set(directive, &name, &params, false, api_state);
+                self.check_plugin_name(directive, &param_set);
+                pbrt_shape(api_state, bsdf_state, param_set);
+            }
+            "Texture" => {
+                let name: String = self.string_arg(directive, cursor)?;
+                let tex_type: String = self.string_arg(directive, cursor)?;
+                let class: String = self.string_arg(directive, cursor)?;
+                let params: Vec<Param> = self.params(cursor)?;
+                let mut params: Vec<Param> = self.with_attributes("texture", params);
+                let class: String = self.translate_texture(directive, &class, &mut params);
+                let mut param_set: ParamSet =
+                    self.param_set(directive, &name, &params, false, api_state);
+                param_set.tex_type = tex_type;
+                param_set.tex_name = class;
+                self.check_plugin_name(directive, &param_set);
+                pbrt_texture(api_state, param_set);
+            }
+            "TransformBegin" => pbrt_transform_begin(api_state),
+            "TransformEnd" => pbrt_transform_end(api_state),
+            "TransformTimes" => {
+                let v: Vec<Float> = self.float_args(directive, cursor, 2)?;
+                pbrt_transform_times(api_state, v[0], v[1]);
+            }
+            "Translate" => {
+                let v: Vec<Float> = self.float_args(directive, cursor, 3)?;
+                pbrt_translate(api_state, v[0], v[1], v[2]);
+            }
+            "WorldBegin" => self.world_begin(directive, api_state),
+            "WorldEnd" => {
+                self.warning(
+                    directive.line,
+                    directive.column,
+                    String::from("WorldEnd is not part of pbrt-v4 (ignored)"),
+                );
+            }
+            _ => return Err(self.error(directive, "unknown directive")),
+        }
+        Ok(())
+    }
+}
+
+/// Parse a pbrt-v4 scene file (and recursively all included and
+/// imported files), rendering starts at the end of the file.
+pub fn parse_file(
+    filename: &str,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<Vec<ParseWarning>, ParseError> {
+    let ip: &Path = Path::new(filename);
+    let pb: PathBuf = if ip.is_relative() {
+        let cp: PathBuf = env::current_dir().unwrap();
+        cp.join(ip)
+    } else {
+        PathBuf::from(ip)
+    };
+    if let Some(search_directory) = pb.as_path().parent() {
+        api_state.search_directory = Some(Box::new(PathBuf::from(search_directory)));
+    }
+    let mut parser: V4Parser = V4Parser::new();
+    parser.include_file(String::from(filename), api_state, bsdf_state)?;
+    if parser.world_begin {
+        pbrt_cleanup(api_state);
+        parser.warnings.append(&mut api_state.take_warnings());
+    }
+    Ok(parser.warnings)
+}
+
+/// Parse a pbrt-v4 scene description given as a string, rendering
+/// starts at the end of the string (if it contains `WorldBegin`).
+pub fn parse_str(
+    input: &str,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<Vec<ParseWarning>, ParseError> {
+    let mut parser: V4Parser = V4Parser::new();
+    parser.parse_input(String::from("<string>"), input, api_state, bsdf_state)?;
+    if parser.world_begin {
+        pbrt_cleanup(api_state);
+        parser.warnings.append(&mut api_state.take_warnings());
+    }
+    Ok(parser.warnings)
+}
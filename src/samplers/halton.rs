@@ -1,5 +1,6 @@
 // std
 use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+use std::sync::Arc;
 // others
 use strum::IntoEnumIterator;
 // pbrt
@@ -11,9 +12,10 @@ use crate::core::lowdiscrepancy::{
 use crate::core::lowdiscrepancy::{PRIME_SUMS, PRIME_TABLE_SIZE};
 use crate::core::paramset::ParamSet;
 use crate::core::pbrt::mod_t;
-use crate::core::pbrt::Float;
+use crate::core::pbrt::{mix_bits, Float};
 use crate::core::rng::Rng;
-use crate::core::sampler::Sampler;
+use crate::core::sampler::{AdaptiveSampling, Sampler};
+use crate::core::stats::{Prof, ProfilePhase};
 
 // Generate random digit permutations for Halton sampler
 lazy_static::lazy_static! {
@@ -60,6 +62,8 @@ pub struct HaltonSampler {
     pixel_for_offset_y: AtomicI32,
     offset_for_current_pixel: AtomicU64,
     sample_at_pixel_center: bool, // default: false
+    // digit permutations for a user given seed (otherwise the global ones are used)
+    radical_inverse_permutations: Option<Arc<Vec<u16>>>,
     // inherited from class GlobalSampler (see sampler.h)
     dimension: i64,
     interval_sample_index: u64,
@@ -74,6 +78,8 @@ pub struct HaltonSampler {
     sample_array_2d: Vec<Vec<Point2f>>,
     array_1d_offset: usize,
     array_2d_offset: usize,
+    /// see [AdaptiveSampling](../../core/sampler/struct.AdaptiveSampling.html)
+    pub adaptive: Option<AdaptiveSampling>,
 }
 
 impl HaltonSampler {
@@ -116,6 +122,7 @@ impl HaltonSampler {
             pixel_for_offset_y: AtomicI32::new(0_i32),
             offset_for_current_pixel: AtomicU64::new(0_u64),
             sample_at_pixel_center,
+            radical_inverse_permutations: None,
             dimension: 0_i64,
             interval_sample_index: 0_u64,
             array_start_dim: 5_i64, // static const int arrayStartDim = 5;
@@ -128,6 +135,7 @@ impl HaltonSampler {
             sample_array_2d: Vec::new(),
             array_1d_offset: 0_usize,
             array_2d_offset: 0_usize,
+            adaptive: None,
         }
     }
     pub fn clone_with_seed(&self, _seed: u64) -> Box<Sampler> {
@@ -144,6 +152,7 @@ impl HaltonSampler {
             pixel_for_offset_y: AtomicI32::new(pixel_for_offset_y),
             offset_for_current_pixel: AtomicU64::new(offset_for_current_pixel),
             sample_at_pixel_center: self.sample_at_pixel_center,
+            radical_inverse_permutations: self.radical_inverse_permutations.clone(),
             dimension: self.dimension,
             interval_sample_index: self.interval_sample_index,
             array_start_dim: self.array_start_dim,
@@ -156,19 +165,24 @@ impl HaltonSampler {
             sample_array_2d: self.sample_array_2d.to_vec(),
             array_1d_offset: self.array_1d_offset,
             array_2d_offset: self.array_2d_offset,
+            adaptive: self.adaptive,
         };
         let sampler = Sampler::Halton(halton_sampler);
         Box::new(sampler)
     }
     pub fn create(params: &ParamSet, sample_bounds: &Bounds2i) -> Box<Sampler> {
         let nsamp: i32 = params.find_one_int("pixelsamples", 16);
-        // TODO: if (PbrtOptions.quickRender) nsamp = 1;
         let sample_at_center: bool = params.find_one_bool("samplepixelcenter", false);
-        Box::new(Sampler::Halton(HaltonSampler::new(
-            nsamp as i64,
-            sample_bounds,
-            sample_at_center,
-        )))
+        let seed: i32 = params.find_one_int("seed", 0);
+        let mut halton_sampler = HaltonSampler::new(nsamp as i64, sample_bounds, sample_at_center);
+        if seed != 0 {
+            let mut rng: Rng = Rng::new();
+            rng.set_sequence(mix_bits(seed as u64));
+            halton_sampler.radical_inverse_permutations =
+                Some(Arc::new(compute_radical_inverse_permutations(&mut rng)));
+        }
+        halton_sampler.adaptive = AdaptiveSampling::create(params);
+        Box::new(Sampler::Halton(halton_sampler))
     }
     pub fn get_index_for_sample(&self, sample_num: u64) -> u64 {
         let pixel_for_offset_x: i32 = self.pixel_for_offset_x.load(Ordering::Relaxed);
@@ -231,11 +245,15 @@ impl HaltonSampler {
                 PRIME_TABLE_SIZE, dim
             );
         }
-        &RADICAL_INVERSE_PERMUTATIONS[PRIME_SUMS[dim as usize] as usize..]
+        if let Some(ref radical_inverse_permutations) = self.radical_inverse_permutations {
+            &radical_inverse_permutations[PRIME_SUMS[dim as usize] as usize..]
+        } else {
+            &RADICAL_INVERSE_PERMUTATIONS[PRIME_SUMS[dim as usize] as usize..]
+        }
     }
     // Sampler
     pub fn start_pixel(&mut self, p: Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _prof = ProfilePhase::new(Prof::StartPixel);
         // Sampler::StartPixel(p);
         self.current_pixel = p;
         self.current_pixel_sample_index = 0_i64;
@@ -273,7 +291,7 @@ impl HaltonSampler {
         assert!(self.array_end_dim == dim);
     }
     pub fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         if self.dimension >= self.array_start_dim && self.dimension < self.array_end_dim {
             self.dimension = self.array_end_dim;
         }
@@ -284,7 +302,7 @@ impl HaltonSampler {
         ret
     }
     pub fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         if self.dimension + 1 >= self.array_start_dim && self.dimension < self.array_end_dim {
             self.dimension = self.array_end_dim;
         }
@@ -3,11 +3,12 @@ use crate::core::geometry::{Point2f, Point2i};
 use crate::core::lowdiscrepancy::C_MAX_MIN_DIST;
 use crate::core::lowdiscrepancy::{sample_generator_matrix, sobol_2d, van_der_corput};
 use crate::core::paramset::ParamSet;
-use crate::core::pbrt::Float;
 use crate::core::pbrt::{is_power_of_2, log_2_int_i64, round_up_pow2_32, round_up_pow2_64};
+use crate::core::pbrt::{mix_bits, Float};
 use crate::core::rng::Rng;
 use crate::core::sampler::Sampler;
 use crate::core::sampling::shuffle;
+use crate::core::stats::{Prof, ProfilePhase};
 
 pub struct MaxMinDistSampler {
     pub samples_per_pixel: i64,
@@ -18,6 +19,7 @@ pub struct MaxMinDistSampler {
     current_1d_dimension: i32,
     current_2d_dimension: i32,
     rng: Rng,
+    seed: u64,
     // inherited from class Sampler (see sampler.h)
     current_pixel: Point2i,
     current_pixel_sample_index: i64,
@@ -63,6 +65,7 @@ impl MaxMinDistSampler {
             current_1d_dimension: 0_i32,
             current_2d_dimension: 0_i32,
             rng: Rng::default(),
+            seed: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -90,6 +93,7 @@ impl MaxMinDistSampler {
             current_1d_dimension: self.current_1d_dimension,
             current_2d_dimension: self.current_2d_dimension,
             rng: self.rng,
+            seed: self.seed,
             current_pixel: self.current_pixel,
             current_pixel_sample_index: self.current_pixel_sample_index,
             samples_1d_array_sizes: self.samples_1d_array_sizes.to_vec(),
@@ -106,15 +110,14 @@ impl MaxMinDistSampler {
     pub fn create(params: &ParamSet) -> Box<Sampler> {
         let nsamp: i32 = params.find_one_int("pixelsamples", 16);
         let sd: i32 = params.find_one_int("dimensions", 4);
-        // TODO: if (PbrtOptions.quickRender) nsamp = 1;
-        Box::new(Sampler::MaxMinDist(MaxMinDistSampler::new(
-            nsamp as i64,
-            sd as i64,
-        )))
+        let seed: i32 = params.find_one_int("seed", 0);
+        let mut mmds = MaxMinDistSampler::new(nsamp as i64, sd as i64);
+        mmds.seed = seed as u64;
+        Box::new(Sampler::MaxMinDist(mmds))
     }
     // Sampler
     pub fn start_pixel(&mut self, p: Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _prof = ProfilePhase::new(Prof::StartPixel);
         let inv_spp: Float = 1.0 as Float / self.samples_per_pixel as Float;
         for i in 0..self.samples_per_pixel as usize {
             self.samples_2d[0_usize][i] = Point2f {
@@ -159,7 +162,7 @@ impl MaxMinDistSampler {
         self.array_2d_offset = 0_usize;
     }
     pub fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         if self.current_1d_dimension < self.samples_1d.len() as i32 {
             let sample: Float = self.samples_1d[self.current_1d_dimension as usize]
@@ -171,7 +174,7 @@ impl MaxMinDistSampler {
         }
     }
     pub fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         if self.current_2d_dimension < self.samples_2d.len() as i32 {
             let sample: Point2f = self.samples_2d[self.current_2d_dimension as usize]
@@ -231,7 +234,7 @@ impl MaxMinDistSampler {
         self.current_pixel_sample_index < self.samples_per_pixel
     }
     pub fn reseed(&mut self, seed: u64) {
-        self.rng.set_sequence(seed);
+        self.rng.set_sequence(seed ^ mix_bits(self.seed));
     }
     pub fn get_current_pixel(&self) -> Point2i {
         self.current_pixel
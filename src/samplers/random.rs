@@ -1,15 +1,17 @@
 // pbrt
 use crate::core::geometry::{Point2f, Point2i};
 use crate::core::paramset::ParamSet;
-use crate::core::pbrt::Float;
+use crate::core::pbrt::{mix_bits, Float};
 use crate::core::rng::Rng;
 use crate::core::sampler::Sampler;
+use crate::core::stats::{Prof, ProfilePhase};
 
 // see random.h
 
 pub struct RandomSampler {
     pub samples_per_pixel: i64,
     rng: Rng,
+    seed: u64,
     // inherited from class Sampler (see sampler.h)
     current_pixel: Point2i,
     current_pixel_sample_index: i64,
@@ -26,6 +28,7 @@ impl RandomSampler {
         RandomSampler {
             samples_per_pixel,
             rng: Rng::default(),
+            seed: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -38,7 +41,8 @@ impl RandomSampler {
     }
     pub fn clone_with_seed(&self, seed: u64) -> Box<Sampler> {
         let mut random_sampler = RandomSampler::new(self.samples_per_pixel);
-        random_sampler.rng.set_sequence(seed);
+        random_sampler.seed = self.seed;
+        random_sampler.reseed(seed);
         // manually copy remaining bits
         random_sampler.current_pixel = self.current_pixel;
         random_sampler.current_pixel_sample_index = self.current_pixel_sample_index;
@@ -53,12 +57,14 @@ impl RandomSampler {
     }
     pub fn create(params: &ParamSet) -> Box<Sampler> {
         let nsamp: i32 = params.find_one_int("pixelsamples", 4);
-        // TODO: if (PbrtOptions.quickRender) nsamp = 1;
-        Box::new(Sampler::Random(RandomSampler::new(nsamp as i64)))
+        let seed: i32 = params.find_one_int("seed", 0);
+        let mut random_sampler = RandomSampler::new(nsamp as i64);
+        random_sampler.seed = seed as u64;
+        Box::new(Sampler::Random(random_sampler))
     }
     // Sampler
     pub fn start_pixel(&mut self, p: Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _prof = ProfilePhase::new(Prof::StartPixel);
         for i in 0..self.sample_array_1d.len() {
             for j in 0..self.sample_array_1d[i].len() {
                 self.sample_array_1d[i][j] = self.rng.uniform_float();
@@ -79,12 +85,12 @@ impl RandomSampler {
         self.array_2d_offset = 0_usize;
     }
     pub fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         self.rng.uniform_float()
     }
     pub fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         // C++: call x first
         let x = self.rng.uniform_float();
@@ -95,7 +101,7 @@ impl RandomSampler {
         self.sample_array_2d[array_idx][idx]
     }
     pub fn reseed(&mut self, seed: u64) {
-        self.rng.set_sequence(seed);
+        self.rng.set_sequence(seed ^ mix_bits(self.seed));
     }
     pub fn request_2d_array(&mut self, n: i32) {
         assert_eq!(self.round_count(n), n);
@@ -136,6 +142,13 @@ impl RandomSampler {
         self.current_pixel_sample_index += 1_i64;
         self.current_pixel_sample_index < self.samples_per_pixel
     }
+    pub fn set_sample_number(&mut self, sample_num: i64) -> bool {
+        // reset array offsets for next pixel sample
+        self.array_1d_offset = 0_usize;
+        self.array_2d_offset = 0_usize;
+        self.current_pixel_sample_index = sample_num;
+        self.current_pixel_sample_index < self.samples_per_pixel
+    }
     pub fn get_current_pixel(&self) -> Point2i {
         self.current_pixel
     }
@@ -2,13 +2,14 @@
 use crate::core::geometry::{Bounds2i, Point2f, Point2i, Vector2i, XYEnum};
 use crate::core::lowdiscrepancy::{sobol_interval_to_index, sobol_sample};
 use crate::core::paramset::ParamSet;
-use crate::core::pbrt::Float;
 use crate::core::pbrt::{
     clamp_t, is_power_of_2, log_2_int_u32, round_up_pow2_32, round_up_pow2_64,
 };
+use crate::core::pbrt::{mix_bits, Float};
 use crate::core::rng::FLOAT_ONE_MINUS_EPSILON;
-use crate::core::sampler::Sampler;
+use crate::core::sampler::{AdaptiveSampling, Sampler};
 use crate::core::sobolmatrices::NUM_SOBOL_DIMENSIONS;
+use crate::core::stats::{Prof, ProfilePhase};
 
 // see sobol.h
 
@@ -17,6 +18,7 @@ pub struct SobolSampler {
     sample_bounds: Bounds2i,
     resolution: i32,
     log_2_resolution: i32,
+    scramble: u64,
     // inherited from class GlobalSampler (see sampler.h)
     dimension: i64,
     interval_sample_index: u64,
@@ -31,6 +33,8 @@ pub struct SobolSampler {
     sample_array_2d: Vec<Vec<Point2f>>,
     array_1d_offset: usize,
     array_2d_offset: usize,
+    /// see [AdaptiveSampling](../../core/sampler/struct.AdaptiveSampling.html)
+    pub adaptive: Option<AdaptiveSampling>,
 }
 
 impl SobolSampler {
@@ -63,6 +67,7 @@ impl SobolSampler {
             },
             resolution,
             log_2_resolution,
+            scramble: 0_u64,
             dimension: 0_i64,
             interval_sample_index: 0_u64,
             array_start_dim: 5_i64, // static const int arrayStartDim = 5;
@@ -75,6 +80,7 @@ impl SobolSampler {
             sample_array_2d: Vec::new(),
             array_1d_offset: 0_usize,
             array_2d_offset: 0_usize,
+            adaptive: None,
         }
     }
     pub fn clone_with_seed(&self, _seed: u64) -> Box<Sampler> {
@@ -83,6 +89,7 @@ impl SobolSampler {
             sample_bounds: self.sample_bounds,
             resolution: self.resolution,
             log_2_resolution: self.log_2_resolution,
+            scramble: self.scramble,
             dimension: self.dimension,
             interval_sample_index: self.interval_sample_index,
             array_start_dim: self.array_start_dim,
@@ -95,17 +102,21 @@ impl SobolSampler {
             sample_array_2d: self.sample_array_2d.to_vec(),
             array_1d_offset: self.array_1d_offset,
             array_2d_offset: self.array_2d_offset,
+            adaptive: self.adaptive,
         };
         let sampler = Sampler::Sobol(sobol_sampler);
         Box::new(sampler)
     }
     pub fn create(params: &ParamSet, sample_bounds: &Bounds2i) -> Box<Sampler> {
         let nsamp: i32 = params.find_one_int("pixelsamples", 16);
-        // TODO: if (PbrtOptions.quickRender) nsamp = 1;
-        Box::new(Sampler::Sobol(SobolSampler::new(
-            nsamp as i64,
-            sample_bounds,
-        )))
+        let seed: i32 = params.find_one_int("seed", 0);
+        let mut sobol_sampler = SobolSampler::new(nsamp as i64, sample_bounds);
+        if seed != 0 {
+            // random XOR scrambling of the sample values
+            sobol_sampler.scramble = mix_bits(seed as u64) & 0xffff_ffff;
+        }
+        sobol_sampler.adaptive = AdaptiveSampling::create(params);
+        Box::new(Sampler::Sobol(sobol_sampler))
     }
     pub fn get_index_for_sample(&self, sample_num: u64) -> u64 {
         let v: Vector2i = self.current_pixel - self.sample_bounds.p_min;
@@ -122,7 +133,13 @@ impl SobolSampler {
                 NUM_SOBOL_DIMENSIONS
             );
         }
-        let mut s: Float = sobol_sample(index as i64, dim as i32, 0_u64);
+        // don't scramble the first two dimensions (see get_index_for_sample())
+        let scramble: u64 = if dim == 0 || dim == 1 {
+            0_u64
+        } else {
+            self.scramble
+        };
+        let mut s: Float = sobol_sample(index as i64, dim as i32, scramble);
         // remap Sobol$'$ dimensions used for pixel samples
         if dim == 0 || dim == 1 {
             let dim_i: XYEnum = match dim {
@@ -140,7 +157,7 @@ impl SobolSampler {
     }
     // Sampler
     pub fn start_pixel(&mut self, p: Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _prof = ProfilePhase::new(Prof::StartPixel);
         // Sampler::StartPixel(p);
         self.current_pixel = p;
         self.current_pixel_sample_index = 0_i64;
@@ -178,7 +195,7 @@ impl SobolSampler {
         assert!(self.array_end_dim == dim);
     }
     pub fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         if self.dimension >= self.array_start_dim && self.dimension < self.array_end_dim {
             self.dimension = self.array_end_dim;
         }
@@ -189,7 +206,7 @@ impl SobolSampler {
         ret
     }
     pub fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         if self.dimension + 1 >= self.array_start_dim && self.dimension < self.array_end_dim {
             self.dimension = self.array_end_dim;
         }
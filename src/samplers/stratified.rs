@@ -1,10 +1,11 @@
 // pbrt
 use crate::core::geometry::{Point2f, Point2i};
 use crate::core::paramset::ParamSet;
-use crate::core::pbrt::Float;
+use crate::core::pbrt::{mix_bits, Float};
 use crate::core::rng::Rng;
 use crate::core::sampler::Sampler;
 use crate::core::sampling::{latin_hypercube, shuffle, stratified_sample_1d, stratified_sample_2d};
+use crate::core::stats::{Prof, ProfilePhase};
 
 pub struct StratifiedSampler {
     pub samples_per_pixel: i64,
@@ -17,6 +18,7 @@ pub struct StratifiedSampler {
     current_1d_dimension: i32,
     current_2d_dimension: i32,
     rng: Rng,
+    seed: u64,
     // inherited from class Sampler (see sampler.h)
     current_pixel: Point2i,
     current_pixel_sample_index: i64,
@@ -45,6 +47,7 @@ impl StratifiedSampler {
             current_1d_dimension: 0_i32,
             current_2d_dimension: 0_i32,
             rng: Rng::default(),
+            seed: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -74,6 +77,7 @@ impl StratifiedSampler {
             current_1d_dimension: self.current_1d_dimension,
             current_2d_dimension: self.current_2d_dimension,
             rng: self.rng,
+            seed: self.seed,
             current_pixel: self.current_pixel,
             current_pixel_sample_index: self.current_pixel_sample_index,
             samples_1d_array_sizes: self.samples_1d_array_sizes.to_vec(),
@@ -92,14 +96,14 @@ impl StratifiedSampler {
         let xsamp: i32 = params.find_one_int("xsamples", 4);
         let ysamp: i32 = params.find_one_int("ysamples", 4);
         let sd: i32 = params.find_one_int("dimensions", 4);
-        // TODO: if (PbrtOptions.quickRender) nsamp = 1;
-        Box::new(Sampler::Stratified(StratifiedSampler::new(
-            xsamp, ysamp, jitter, sd as i64,
-        )))
+        let seed: i32 = params.find_one_int("seed", 0);
+        let mut ss = StratifiedSampler::new(xsamp, ysamp, jitter, sd as i64);
+        ss.seed = seed as u64;
+        Box::new(Sampler::Stratified(ss))
     }
     // Sampler
     pub fn start_pixel(&mut self, p: Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _prof = ProfilePhase::new(Prof::StartPixel);
         // generate single stratified samples for the pixel
         for i in 0..self.samples_1d.len() {
             let samples: &mut [Float] = self.samples_1d[i].as_mut_slice();
@@ -161,7 +165,7 @@ impl StratifiedSampler {
         self.array_2d_offset = 0_usize;
     }
     pub fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         if self.current_1d_dimension < self.samples_1d.len() as i32 {
             let sample: Float = self.samples_1d[self.current_1d_dimension as usize]
@@ -173,7 +177,7 @@ impl StratifiedSampler {
         }
     }
     pub fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         if self.current_2d_dimension < self.samples_2d.len() as i32 {
             let sample: Point2f = self.samples_2d[self.current_2d_dimension as usize]
@@ -233,7 +237,7 @@ impl StratifiedSampler {
         self.current_pixel_sample_index < self.samples_per_pixel
     }
     pub fn reseed(&mut self, seed: u64) {
-        self.rng.set_sequence(seed);
+        self.rng.set_sequence(seed ^ mix_bits(self.seed));
     }
     pub fn get_current_pixel(&self) -> Point2i {
         self.current_pixel
@@ -3,9 +3,10 @@ use crate::core::geometry::{Point2f, Point2i};
 use crate::core::lowdiscrepancy::{sobol_2d, van_der_corput};
 use crate::core::paramset::ParamSet;
 use crate::core::pbrt::round_up_pow2_32;
-use crate::core::pbrt::Float;
+use crate::core::pbrt::{mix_bits, Float};
 use crate::core::rng::Rng;
 use crate::core::sampler::Sampler;
+use crate::core::stats::{Prof, ProfilePhase};
 
 // see zerotwosequence.h
 
@@ -18,6 +19,7 @@ pub struct ZeroTwoSequenceSampler {
     current_1d_dimension: i32,
     current_2d_dimension: i32,
     rng: Rng,
+    seed: u64,
     // inherited from class Sampler (see sampler.h)
     current_pixel: Point2i,
     current_pixel_sample_index: i64,
@@ -39,6 +41,7 @@ impl Default for ZeroTwoSequenceSampler {
             current_1d_dimension: 0_i32,
             current_2d_dimension: 0_i32,
             rng: Rng::default(),
+            seed: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -69,6 +72,7 @@ impl ZeroTwoSequenceSampler {
             current_1d_dimension: 0_i32,
             current_2d_dimension: 0_i32,
             rng: Rng::default(),
+            seed: 0_u64,
             current_pixel: Point2i::default(),
             current_pixel_sample_index: 0_i64,
             samples_1d_array_sizes: Vec::new(),
@@ -96,6 +100,7 @@ impl ZeroTwoSequenceSampler {
             current_1d_dimension: self.current_1d_dimension,
             current_2d_dimension: self.current_2d_dimension,
             rng: self.rng,
+            seed: self.seed,
             current_pixel: self.current_pixel,
             current_pixel_sample_index: self.current_pixel_sample_index,
             samples_1d_array_sizes: self.samples_1d_array_sizes.to_vec(),
@@ -112,15 +117,14 @@ impl ZeroTwoSequenceSampler {
     pub fn create(params: &ParamSet) -> Box<Sampler> {
         let nsamp: i32 = params.find_one_int("pixelsamples", 16);
         let sd: i32 = params.find_one_int("dimensions", 4);
-        // TODO: if (PbrtOptions.quickRender) nsamp = 1;
-        Box::new(Sampler::ZeroTwoSequence(ZeroTwoSequenceSampler::new(
-            nsamp as i64,
-            sd as i64,
-        )))
+        let seed: i32 = params.find_one_int("seed", 0);
+        let mut lds = ZeroTwoSequenceSampler::new(nsamp as i64, sd as i64);
+        lds.seed = seed as u64;
+        Box::new(Sampler::ZeroTwoSequence(lds))
     }
     // Sampler
     pub fn start_pixel(&mut self, p: Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _prof = ProfilePhase::new(Prof::StartPixel);
         // generate 1D and 2D pixel sample components using $(0,2)$-sequence
         for samples in &mut self.samples_1d {
             van_der_corput(1, self.samples_per_pixel as i32, samples, &mut self.rng);
@@ -155,7 +159,7 @@ impl ZeroTwoSequenceSampler {
         self.array_2d_offset = 0_usize;
     }
     pub fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         if self.current_1d_dimension < self.samples_1d.len() as i32 {
             let sample: Float = self.samples_1d[self.current_1d_dimension as usize]
@@ -167,7 +171,7 @@ impl ZeroTwoSequenceSampler {
         }
     }
     pub fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _prof = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         if self.current_2d_dimension < self.samples_2d.len() as i32 {
             let sample: Point2f = self.samples_2d[self.current_2d_dimension as usize]
@@ -227,7 +231,7 @@ impl ZeroTwoSequenceSampler {
         self.current_pixel_sample_index < self.samples_per_pixel
     }
     pub fn reseed(&mut self, seed: u64) {
-        self.rng.set_sequence(seed);
+        self.rng.set_sequence(seed ^ mix_bits(self.seed));
     }
     pub fn get_current_pixel(&self) -> Point2i {
         self.current_pixel
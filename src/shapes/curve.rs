@@ -13,10 +13,19 @@ use crate::core::paramset::ParamSet;
 use crate::core::pbrt::Float;
 use crate::core::pbrt::{clamp_t, float_to_bits, lerp};
 use crate::core::shape::Shape;
+use crate::core::stats::{Prof, ProfilePhase};
+use crate::core::stats::{StatCounter, StatIntDistribution, StatMemoryCounter, StatPercent};
 use crate::core::transform::Transform;
 
 // see curve.h
 
+static CURVE_BYTES: StatMemoryCounter = StatMemoryCounter::new("Memory/Curves");
+static N_TESTS: StatPercent = StatPercent::new("Intersections/Ray-curve intersection tests");
+static REFINEMENT_LEVEL: StatIntDistribution =
+    StatIntDistribution::new("Intersections/Curve refinement level");
+static N_CURVES: StatCounter = StatCounter::new("Scene/Curves");
+static N_SPLIT_CURVES: StatCounter = StatCounter::new("Scene/Split curves");
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum CurveType {
     Flat,
@@ -42,6 +51,7 @@ impl CurveCommon {
         curve_type: CurveType,
         norm: Option<[Normal3f; 2]>,
     ) -> Self {
+        N_CURVES.inc();
         if let Some(norm) = norm {
             let n0: Normal3f = norm[0].normalize();
             let n1: Normal3f = norm[1].normalize();
@@ -132,9 +142,11 @@ impl Curve {
                 u_max,
             )));
             segments.push(curve.clone());
-            // TODO: ++nSplitCurves;
+            N_SPLIT_CURVES.inc();
         }
-        // TODO: curveBytes += sizeof(CurveCommon) + n_segments * sizeof(Curve);
+        CURVE_BYTES.add(
+            std::mem::size_of::<CurveCommon>() + segments.len() * std::mem::size_of::<Curve>(),
+        );
         segments
     }
     pub fn recursive_intersect(
@@ -338,7 +350,7 @@ impl Curve {
             //     isect.shape = Some(shape.clone());
             // }
             // }
-            // TODO: ++n_hits;
+            N_TESTS.inc_num();
             true
         }
     }
@@ -365,8 +377,8 @@ impl Curve {
         self.object_to_world.transform_bounds(&self.object_bound())
     }
     pub fn intersect(&self, r: &Ray, t_hit: &mut Float, isect: &mut SurfaceInteraction) -> bool {
-        // TODO: ProfilePhase p(isect ? Prof::CurveIntersect : Prof::CurveIntersectP);
-        // TODO: ++nTests;
+        let _prof = ProfilePhase::new(Prof::CurveIntersect);
+        N_TESTS.inc_denom();
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
@@ -463,7 +475,7 @@ impl Curve {
         let r0: i32 =
             log2(1.414_213_562_37 as Float * 6.0 as Float * l0 / (8.0 as Float * eps)) / 2_i32;
         let max_depth: i32 = clamp_t(r0, 0_i32, 10_i32);
-        // TODO: ReportValue(refinementLevel, maxDepth);
+        REFINEMENT_LEVEL.report_value(max_depth as i64);
         self.recursive_intersect(
             &ray,
             &[cp[0], cp[1], cp[2], cp[3]],
@@ -476,6 +488,7 @@ impl Curve {
         )
     }
     pub fn intersect_p(&self, r: &Ray) -> bool {
+        let _prof = ProfilePhase::new(Prof::CurveIntersectP);
         let mut t_hit: Float = 0.0;
         let mut isect_light: SurfaceInteraction = SurfaceInteraction::default();
         self.intersect(r, &mut t_hit, &mut isect_light)
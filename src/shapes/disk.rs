@@ -9,6 +9,7 @@ use crate::core::material::Material;
 use crate::core::pbrt::Float;
 use crate::core::pbrt::{clamp_t, radians};
 use crate::core::sampling::concentric_sample_disk;
+use crate::core::stats::{Prof, ProfilePhase};
 use crate::core::transform::Transform;
 
 // see disk.h
@@ -90,7 +91,7 @@ impl Disk {
         self.object_to_world.transform_bounds(&self.object_bound())
     }
     pub fn intersect(&self, r: &Ray, t_hit: &mut Float, isect: &mut SurfaceInteraction) -> bool {
-        // TODO: ProfilePhase p(Prof::ShapeIntersect);
+        let _prof = ProfilePhase::new(Prof::ShapeIntersect);
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
@@ -161,7 +162,7 @@ impl Disk {
         true
     }
     pub fn intersect_p(&self, r: &Ray) -> bool {
-        // TODO: ProfilePhase p(Prof::ShapeIntersectP);
+        let _prof = ProfilePhase::new(Prof::ShapeIntersectP);
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
@@ -0,0 +1,130 @@
+// std
+use std::convert::TryInto;
+use std::sync::Arc;
+// pbrt
+use crate::core::geometry::{Normal3f, Point2f, Point3f, Vector3f};
+use crate::core::paramset::ParamSet;
+use crate::core::pbrt::Float;
+use crate::core::shape::Shape;
+use crate::core::transform::Transform;
+use crate::shapes::triangle::{Triangle, TriangleMesh};
+
+// see heightfield.cpp
+
+/// Turns a regular grid of height values (*nu* by *nv* values given
+/// by *Pz*) over the unit square in $(x, y)$ into a triangle mesh. The
+/// mesh gets $(u, v)$ coordinates matching $(x, y)$ and smooth shading
+/// normals estimated from the neighbouring heights. Missing or
+/// inconsistent parameters are returned as an error message.
+pub fn create_heightfield(
+    o2w: &Transform,
+    w2o: &Transform,
+    reverse_orientation: bool,
+    params: &ParamSet,
+) -> Result<Vec<Arc<Shape>>, String> {
+    let nx: i32 = params.find_one_int("nu", -1);
+    let ny: i32 = params.find_one_int("nv", -1);
+    let z: Vec<Float> = params.find_float("Pz");
+    if nx == -1_i32 || ny == -1_i32 || z.is_empty() {
+        return Err(String::from(
+            "Must provide \"nu\", \"nv\" and \"Pz\" with heightfield shape (ignored)",
+        ));
+    }
+    if nx < 2_i32 || ny < 2_i32 {
+        return Err(format!(
+            "Heightfield needs at least 2x2 heights, not {}x{} (ignored)",
+            nx, ny
+        ));
+    }
+    if z.len() != (nx * ny) as usize {
+        return Err(format!(
+            "Number of \"Pz\" values {} doesn't match \"nu\" * \"nv\" = {} (heightfield ignored)",
+            z.len(),
+            nx * ny
+        ));
+    }
+    let nx: usize = nx as usize;
+    let ny: usize = ny as usize;
+    let n_triangles: usize = 2 * (nx - 1) * (ny - 1);
+    let n_vertices: usize = nx * ny;
+    // compute heightfield vertex positions
+    let mut p: Vec<Point3f> = Vec::with_capacity(n_vertices);
+    let mut uvs: Vec<Point2f> = Vec::with_capacity(n_vertices);
+    for y in 0..ny {
+        for x in 0..nx {
+            let u: Float = x as Float / (nx - 1) as Float;
+            let v: Float = y as Float / (ny - 1) as Float;
+            p.push(Point3f {
+                x: u,
+                y: v,
+                z: z[x + y * nx],
+            });
+            uvs.push(Point2f { x: u, y: v });
+        }
+    }
+    // compute shading normals from (one-sided at the border)
+    // central differences of the heights
+    let mut n: Vec<Normal3f> = Vec::with_capacity(n_vertices);
+    for y in 0..ny {
+        for x in 0..nx {
+            let x0: usize = if x > 0 { x - 1 } else { x };
+            let x1: usize = if x < nx - 1 { x + 1 } else { x };
+            let y0: usize = if y > 0 { y - 1 } else { y };
+            let y1: usize = if y < ny - 1 { y + 1 } else { y };
+            let dzdx: Float = (z[x1 + y * nx] - z[x0 + y * nx]) / (p[x1].x - p[x0].x);
+            let dzdy: Float = (z[x + y1 * nx] - z[x + y0 * nx]) / (p[y1 * nx].y - p[y0 * nx].y);
+            n.push(Normal3f {
+                x: -dzdx,
+                y: -dzdy,
+                z: 1.0,
+            });
+        }
+    }
+    // fill in heightfield vertex offset array
+    let vert = |x: usize, y: usize| -> u32 { (x + y * nx) as u32 };
+    let mut vertex_indices: Vec<u32> = Vec::with_capacity(3 * n_triangles);
+    for y in 0..(ny - 1) {
+        for x in 0..(nx - 1) {
+            vertex_indices.push(vert(x, y));
+            vertex_indices.push(vert(x + 1, y));
+            vertex_indices.push(vert(x + 1, y + 1));
+
+            vertex_indices.push(vert(x, y));
+            vertex_indices.push(vert(x + 1, y + 1));
+            vertex_indices.push(vert(x, y + 1));
+        }
+    }
+    // CreateTriangleMesh
+    // transform mesh vertices and normals to world space
+    let mut p_ws: Vec<Point3f> = Vec::with_capacity(n_vertices);
+    for item in p.iter() {
+        p_ws.push(o2w.transform_point(item));
+    }
+    let mut n_ws: Vec<Normal3f> = Vec::with_capacity(n_vertices);
+    for item in n.iter() {
+        n_ws.push(o2w.transform_normal(item).normalize());
+    }
+    let s_ws: Vec<Vector3f> = Vec::new();
+    let mesh = Arc::new(TriangleMesh::new(
+        *o2w,
+        *w2o,
+        reverse_orientation,
+        n_triangles.try_into().unwrap(),
+        vertex_indices,
+        n_vertices.try_into().unwrap(),
+        p_ws, // in world space
+        s_ws, // in world space
+        n_ws, // in world space
+        uvs,
+        None,
+        None,
+        Vec::new(),
+        Vec::new(),
+    ));
+    let mut shapes: Vec<Arc<Shape>> = Vec::new();
+    for id in 0..mesh.n_triangles {
+        let triangle = Arc::new(Shape::Trngl(Triangle::new(mesh.clone(), id)));
+        shapes.push(triangle.clone());
+    }
+    Ok(shapes)
+}
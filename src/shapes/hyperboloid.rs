@@ -0,0 +1,473 @@
+// std
+use std::f32::consts::PI;
+use std::sync::Arc;
+// pbrt
+use crate::core::efloat::quadratic_efloat;
+use crate::core::efloat::EFloat;
+use crate::core::geometry::{
+    nrm_abs_dot_vec3f, pnt3_distance_squaredf, vec3_cross_vec3, vec3_dot_vec3f,
+};
+use crate::core::geometry::{Bounds3f, Normal3f, Point2f, Point3f, Ray, Vector3f, XYEnum};
+use crate::core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
+use crate::core::material::Material;
+use crate::core::pbrt::Float;
+use crate::core::pbrt::{clamp_t, gamma, radians};
+use crate::core::stats::{Prof, ProfilePhase};
+use crate::core::transform::Transform;
+
+// see hyperboloid.h
+
+#[derive(Clone)]
+pub struct Hyperboloid {
+    pub p1: Point3f,
+    pub p2: Point3f,
+    pub z_min: Float,
+    pub z_max: Float,
+    pub phi_max: Float,
+    pub r_max: Float,
+    /// implicit form $x^2 + y^2 = a z^2 + b z + c$
+    pub a: Float,
+    pub b: Float,
+    pub c: Float,
+    // inherited from class Shape (see shape.h)
+    pub object_to_world: Transform,
+    pub world_to_object: Transform,
+    pub reverse_orientation: bool,
+    pub transform_swaps_handedness: bool,
+    pub material: Option<Arc<Material>>,
+}
+
+impl Default for Hyperboloid {
+    fn default() -> Self {
+        Hyperboloid::new(
+            Transform::default(),
+            Transform::default(),
+            false,
+            Point3f {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Point3f {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+            360.0,
+        )
+    }
+}
+
+impl Hyperboloid {
+    /// The line from *point1* to *point2* gets rotated around the z
+    /// axis, so both points need different heights (the API skips
+    /// hyperboloids with `p1.z == p2.z`).
+    pub fn new(
+        object_to_world: Transform,
+        world_to_object: Transform,
+        reverse_orientation: bool,
+        point1: Point3f,
+        point2: Point3f,
+        phi_max: Float,
+    ) -> Self {
+        let p1: Point3f = point1;
+        let p2: Point3f = point2;
+        let radius1: Float = (p1.x * p1.x + p1.y * p1.y).sqrt();
+        let radius2: Float = (p2.x * p2.x + p2.y * p2.y).sqrt();
+        let r_max: Float = radius1.max(radius2);
+        let z_min: Float = p1.z.min(p2.z);
+        let z_max: Float = p1.z.max(p2.z);
+        // compute implicit function coefficients for hyperboloid; the
+        // squared distance of the generating line to the z axis is a
+        // quadratic function of z (this does not require the waist
+        // of the hyperboloid to be at z = 0)
+        let dx: Float = p2.x - p1.x;
+        let dy: Float = p2.y - p1.y;
+        let dz: Float = p2.z - p1.z;
+        let s: Float = (dx * dx + dy * dy) / (dz * dz);
+        let m: Float = (p1.x * dx + p1.y * dy) / dz;
+        let a: Float = s;
+        let b: Float = 2.0 as Float * (m - s * p1.z);
+        let c: Float = s * p1.z * p1.z - 2.0 as Float * m * p1.z + radius1 * radius1;
+        Hyperboloid {
+            // Shape
+            object_to_world,
+            world_to_object,
+            reverse_orientation,
+            transform_swaps_handedness: object_to_world.swaps_handedness(),
+            // Hyperboloid
+            p1,
+            p2,
+            z_min,
+            z_max,
+            phi_max: radians(clamp_t(phi_max, 0.0, 360.0)),
+            r_max,
+            a,
+            b,
+            c,
+            material: None,
+        }
+    }
+    // Shape
+    pub fn object_bound(&self) -> Bounds3f {
+        Bounds3f {
+            p_min: Point3f {
+                x: -self.r_max,
+                y: -self.r_max,
+                z: self.z_min,
+            },
+            p_max: Point3f {
+                x: self.r_max,
+                y: self.r_max,
+                z: self.z_max,
+            },
+        }
+    }
+    pub fn world_bound(&self) -> Bounds3f {
+        // in C++: Bounds3f Shape::WorldBound() const { return (*ObjectToWorld)(ObjectBound()); }
+        self.object_to_world.transform_bounds(&self.object_bound())
+    }
+    /// Rotation angle $\phi$ between the hit point and the generating
+    /// line at the same height.
+    fn phi(&self, p_hit: &Point3f) -> Float {
+        let v: Float = (p_hit.z - self.p1.z) / (self.p2.z - self.p1.z);
+        let pr: Point3f = self.p1 * (1.0 as Float - v) + self.p2 * v;
+        let mut phi: Float =
+            (pr.x * p_hit.y - p_hit.x * pr.y).atan2(p_hit.x * pr.x + p_hit.y * pr.y);
+        if phi < 0.0 as Float {
+            phi += 2.0 as Float * PI;
+        }
+        phi
+    }
+    /// Returns the parametric distance and the object space hit point
+    /// (plus its $\phi$ value) of the closest valid intersection.
+    fn intersect_shape(
+        &self,
+        ray: &Ray,
+        o_err: &Vector3f,
+        d_err: &Vector3f,
+    ) -> Option<(EFloat, Point3f, Float)> {
+        // compute quadratic hyperboloid coefficients
+
+        // initialize _EFloat_ ray coordinate values
+        let ox = EFloat::new(ray.o.x, o_err.x);
+        let oy = EFloat::new(ray.o.y, o_err.y);
+        let oz = EFloat::new(ray.o.z, o_err.z);
+        let dx = EFloat::new(ray.d.x, d_err.x);
+        let dy = EFloat::new(ray.d.y, d_err.y);
+        let dz = EFloat::new(ray.d.z, d_err.z);
+        let ha: EFloat = EFloat::new(self.a, 0.0);
+        let hb: EFloat = EFloat::new(self.b, 0.0);
+        let hc: EFloat = EFloat::new(self.c, 0.0);
+        let a: EFloat = dx * dx + dy * dy - ha * dz * dz;
+        let b: EFloat = (dx * ox + dy * oy - ha * dz * oz) * 2.0f32 - hb * dz;
+        let c: EFloat = ox * ox + oy * oy - ha * oz * oz - hb * oz - hc;
+
+        // solve quadratic equation for _t_ values
+        let mut t0: EFloat = EFloat::default();
+        let mut t1: EFloat = EFloat::default();
+        if !quadratic_efloat(a, b, c, &mut t0, &mut t1) {
+            return None;
+        }
+        // check quadric shape _t0_ and _t1_ for nearest intersection
+        if t0.upper_bound() > ray.t_max.get() || t1.lower_bound() <= 0.0f32 {
+            return None;
+        }
+        let mut t_shape_hit: EFloat = t0;
+        if t_shape_hit.lower_bound() <= 0.0f32 {
+            t_shape_hit = t1;
+            if t_shape_hit.upper_bound() > ray.t_max.get() {
+                return None;
+            }
+        }
+        // compute hyperboloid inverse mapping
+        let mut p_hit: Point3f = ray.position(t_shape_hit.v);
+        let mut phi: Float = self.phi(&p_hit);
+        // test hyperboloid intersection against clipping parameters
+        if p_hit.z < self.z_min || p_hit.z > self.z_max || phi > self.phi_max {
+            if t_shape_hit == t1 {
+                return None;
+            }
+            t_shape_hit = t1;
+            if t1.upper_bound() > ray.t_max.get() {
+                return None;
+            }
+            // compute hyperboloid inverse mapping
+            p_hit = ray.position(t_shape_hit.v);
+            phi = self.phi(&p_hit);
+            if p_hit.z < self.z_min || p_hit.z > self.z_max || phi > self.phi_max {
+                return None;
+            }
+        }
+        Some((t_shape_hit, p_hit, phi))
+    }
+    pub fn intersect(&self, r: &Ray, t_hit: &mut Float, isect: &mut SurfaceInteraction) -> bool {
+        let _prof = ProfilePhase::new(Prof::ShapeIntersect);
+        // transform _Ray_ to object space
+        let mut o_err: Vector3f = Vector3f::default();
+        let mut d_err: Vector3f = Vector3f::default();
+        let ray: Ray = self
+            .world_to_object
+            .transform_ray_with_error(r, &mut o_err, &mut d_err);
+        let (t_shape_hit, p_hit, phi) = match self.intersect_shape(&ray, &o_err, &d_err) {
+            Some(hit) => hit,
+            None => return false,
+        };
+        // find parametric representation of hyperboloid hit
+        let u: Float = phi / self.phi_max;
+        let v: Float = (p_hit.z - self.p1.z) / (self.p2.z - self.p1.z);
+        // compute hyperboloid $\dpdu$ and $\dpdv$
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let dpdu: Vector3f = Vector3f {
+            x: -self.phi_max * p_hit.y,
+            y: self.phi_max * p_hit.x,
+            z: 0.0,
+        };
+        let dpdv: Vector3f = Vector3f {
+            x: (self.p2.x - self.p1.x) * cos_phi - (self.p2.y - self.p1.y) * sin_phi,
+            y: (self.p2.x - self.p1.x) * sin_phi + (self.p2.y - self.p1.y) * cos_phi,
+            z: self.p2.z - self.p1.z,
+        };
+        // compute hyperboloid $\dndu$ and $\dndv$
+        let d2_p_duu: Vector3f = Vector3f {
+            x: p_hit.x,
+            y: p_hit.y,
+            z: 0.0,
+        } * -self.phi_max
+            * self.phi_max;
+        let d2_p_duv: Vector3f = Vector3f {
+            x: -dpdv.y,
+            y: dpdv.x,
+            z: 0.0,
+        } * self.phi_max;
+        let d2_p_dvv: Vector3f = Vector3f {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        // compute coefficients for fundamental forms
+        let ec: Float = vec3_dot_vec3f(&dpdu, &dpdu);
+        let fc: Float = vec3_dot_vec3f(&dpdu, &dpdv);
+        let gc: Float = vec3_dot_vec3f(&dpdv, &dpdv);
+        let nc: Vector3f = vec3_cross_vec3(&dpdu, &dpdv).normalize();
+        let el: Float = vec3_dot_vec3f(&nc, &d2_p_duu);
+        let fl: Float = vec3_dot_vec3f(&nc, &d2_p_duv);
+        let gl: Float = vec3_dot_vec3f(&nc, &d2_p_dvv);
+        // compute $\dndu$ and $\dndv$ from fundamental form coefficients
+        let inv_egf2: Float = 1.0 / (ec * gc - fc * fc);
+        let dndu = dpdu * (fl * fc - el * gc) * inv_egf2 + dpdv * (el * fc - fl * ec) * inv_egf2;
+        let dndu = Normal3f {
+            x: dndu.x,
+            y: dndu.y,
+            z: dndu.z,
+        };
+        let dndv = dpdu * (gl * fc - fl * gc) * inv_egf2 + dpdv * (fl * fc - gl * ec) * inv_egf2;
+        let dndv = Normal3f {
+            x: dndv.x,
+            y: dndv.y,
+            z: dndv.z,
+        };
+        // compute error bounds for hyperboloid intersection
+
+        // compute error bounds for intersection computed with ray equation
+        let px: EFloat =
+            EFloat::new(ray.o.x, o_err.x) + t_shape_hit * EFloat::new(ray.d.x, d_err.x);
+        let py: EFloat =
+            EFloat::new(ray.o.y, o_err.y) + t_shape_hit * EFloat::new(ray.d.y, d_err.y);
+        let pz: EFloat =
+            EFloat::new(ray.o.z, o_err.z) + t_shape_hit * EFloat::new(ray.d.z, d_err.z);
+        let p_error: Vector3f = Vector3f {
+            x: px.get_absolute_error(),
+            y: py.get_absolute_error(),
+            z: pz.get_absolute_error(),
+        };
+        // initialize _SurfaceInteraction_ from parametric information
+        let uv_hit: Point2f = Point2f { x: u, y: v };
+        let wo: Vector3f = -ray.d;
+        *isect = SurfaceInteraction::new(
+            &p_hit, &p_error, uv_hit, &wo, &dpdu, &dpdv, &dndu, &dndv, ray.time, None,
+        );
+        self.object_to_world.transform_surface_interaction(isect);
+        *t_hit = t_shape_hit.v as Float;
+        true
+    }
+    pub fn intersect_p(&self, r: &Ray) -> bool {
+        let _prof = ProfilePhase::new(Prof::ShapeIntersectP);
+        // transform _Ray_ to object space
+        let mut o_err: Vector3f = Vector3f::default();
+        let mut d_err: Vector3f = Vector3f::default();
+        let ray: Ray = self
+            .world_to_object
+            .transform_ray_with_error(r, &mut o_err, &mut d_err);
+        self.intersect_shape(&ray, &o_err, &d_err).is_some()
+    }
+    pub fn get_reverse_orientation(&self) -> bool {
+        self.reverse_orientation
+    }
+    pub fn get_transform_swaps_handedness(&self) -> bool {
+        self.transform_swaps_handedness
+    }
+    pub fn get_object_to_world(&self) -> Transform {
+        self.object_to_world
+    }
+    /// The surface area swept by the generating line between its
+    /// start and the parameter value $v$ is $\phi_{max} \int_0^v
+    /// \sqrt{a t^2 + b t + c} dt$, which has a closed form solution.
+    fn area_up_to(&self, v: Float) -> Float {
+        let (a, b, c) = self.area_coefficients();
+        let v: f64 = v as f64;
+        let q: f64 = (a * v * v + b * v + c).max(0.0);
+        let integral: f64 = if a <= 0.0 {
+            c.max(0.0).sqrt() * v
+        } else {
+            let disc: f64 = 4.0 * a * c - b * b;
+            let lin: f64 = 2.0 * a * v + b;
+            let mut result: f64 = lin * q.sqrt() / (4.0 * a);
+            if disc > 0.0 {
+                // numerically stable form of $\ln(2 \sqrt{a q} + 2 a v + b)$
+                let root: f64 = 2.0 * (a * q).sqrt();
+                let log_arg: f64 = if lin >= 0.0 {
+                    root + lin
+                } else {
+                    disc / (root - lin)
+                };
+                result += disc / (8.0 * a * a.sqrt()) * log_arg.ln();
+            }
+            result
+        };
+        (self.phi_max as f64 * integral) as Float
+    }
+    /// Coefficients of the quadratic under the square root of
+    /// $|\partial p / \partial u \times \partial p / \partial v| /
+    /// \phi_{max}$ as a function of $v$.
+    fn area_coefficients(&self) -> (f64, f64, f64) {
+        let (x1, y1, z1) = (self.p1.x as f64, self.p1.y as f64, self.p1.z as f64);
+        let dx: f64 = self.p2.x as f64 - x1;
+        let dy: f64 = self.p2.y as f64 - y1;
+        let dz: f64 = self.p2.z as f64 - z1;
+        let s: f64 = dx * dx + dy * dy;
+        let m: f64 = x1 * dx + y1 * dy;
+        let dz2: f64 = dz * dz;
+        (
+            s * (dz2 + s),
+            2.0 * m * (dz2 + s),
+            dz2 * (x1 * x1 + y1 * y1) + m * m,
+        )
+    }
+    pub fn area(&self) -> Float {
+        self.area_up_to(1.0) - self.area_up_to(0.0)
+    }
+    pub fn sample(&self, u: Point2f, pdf: &mut Float) -> InteractionCommon {
+        // invert the cumulative surface area to find $v$ (Newton-Bisection)
+        let (a, b, c) = self.area_coefficients();
+        let a0: Float = self.area_up_to(0.0);
+        let target: Float = u[XYEnum::X] * (self.area_up_to(1.0) - a0);
+        let mut v_min: Float = 0.0;
+        let mut v_max: Float = 1.0;
+        let mut v: Float = u[XYEnum::X];
+        for _ in 0..32 {
+            if !(v >= v_min && v <= v_max) {
+                v = 0.5 as Float * (v_min + v_max);
+            }
+            let f: Float = self.area_up_to(v) - a0 - target;
+            if f.abs() < 1e-6 as Float * (1.0 as Float + target) {
+                break;
+            }
+            if f < 0.0 as Float {
+                v_min = v;
+            } else {
+                v_max = v;
+            }
+            let vd: f64 = v as f64;
+            let df: Float =
+                (self.phi_max as f64 * (a * vd * vd + b * vd + c).max(0.0).sqrt()) as Float;
+            v -= f / df;
+        }
+        let v: Float = clamp_t(v, 0.0 as Float, 1.0 as Float);
+        let phi: Float = u[XYEnum::Y] * self.phi_max;
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let pr: Point3f = self.p1 * (1.0 as Float - v) + self.p2 * v;
+        let p_obj: Point3f = Point3f {
+            x: pr.x * cos_phi - pr.y * sin_phi,
+            y: pr.x * sin_phi + pr.y * cos_phi,
+            z: pr.z,
+        };
+        let dpdu: Vector3f = Vector3f {
+            x: -self.phi_max * p_obj.y,
+            y: self.phi_max * p_obj.x,
+            z: 0.0,
+        };
+        let dpdv: Vector3f = Vector3f {
+            x: (self.p2.x - self.p1.x) * cos_phi - (self.p2.y - self.p1.y) * sin_phi,
+            y: (self.p2.x - self.p1.x) * sin_phi + (self.p2.y - self.p1.y) * cos_phi,
+            z: self.p2.z - self.p1.z,
+        };
+        let n: Vector3f = vec3_cross_vec3(&dpdu, &dpdv);
+        let mut it: InteractionCommon = InteractionCommon {
+            n: self
+                .object_to_world
+                .transform_normal(&Normal3f {
+                    x: n.x,
+                    y: n.y,
+                    z: n.z,
+                })
+                .normalize(),
+            ..Default::default()
+        };
+        if self.reverse_orientation {
+            it.n *= -1.0 as Float;
+        }
+        let p_obj_error: Vector3f = Vector3f {
+            x: p_obj.x,
+            y: p_obj.y,
+            z: p_obj.z,
+        }
+        .abs()
+            * gamma(5_i32);
+        it.p = self.object_to_world.transform_point_with_abs_error(
+            &p_obj,
+            &p_obj_error,
+            &mut it.p_error,
+        );
+        *pdf = 1.0 as Float / self.area();
+        it
+    }
+    pub fn sample_with_ref_point(
+        &self,
+        iref: &InteractionCommon,
+        u: Point2f,
+        pdf: &mut Float,
+    ) -> InteractionCommon {
+        let intr: InteractionCommon = self.sample(u, pdf);
+        let mut wi: Vector3f = intr.p - iref.p;
+        if wi.length_squared() == 0.0 as Float {
+            *pdf = 0.0 as Float;
+        } else {
+            wi = wi.normalize();
+            // convert from area measure, as returned by the Sample()
+            // call above, to solid angle measure.
+            *pdf *= pnt3_distance_squaredf(&iref.p, &intr.p) / nrm_abs_dot_vec3f(&intr.n, &-wi);
+            if (*pdf).is_infinite() {
+                *pdf = 0.0 as Float;
+            }
+        }
+        intr
+    }
+    pub fn pdf_with_ref_point(&self, iref: &dyn Interaction, wi: &Vector3f) -> Float {
+        // intersect sample ray with area light geometry
+        let ray: Ray = iref.spawn_ray(wi);
+        let mut t_hit: Float = 0.0;
+        let mut isect_light: SurfaceInteraction = SurfaceInteraction::default();
+        if self.intersect(&ray, &mut t_hit, &mut isect_light) {
+            // convert light sample weight to solid angle measure
+            let mut pdf: Float = pnt3_distance_squaredf(iref.get_p(), &isect_light.common.p)
+                / (nrm_abs_dot_vec3f(&isect_light.common.n, &-(*wi)) * self.area());
+            if pdf.is_infinite() {
+                pdf = 0.0 as Float;
+            }
+            pdf
+        } else {
+            0.0 as Float
+        }
+    }
+}
@@ -666,6 +666,8 @@ pub fn loop_subdivide(
         Vec::new(),
         None,
         None,
+        Vec::new(),
+        Vec::new(),
     ))
 }
 
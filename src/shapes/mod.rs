@@ -17,7 +17,9 @@
 //!
 //! ## Cones
 //!
-//! TODO
+//! Cones are centered around the z axis with their base at z = 0 and
+//! their apex at the given height. Like the other quadrics they
+//! support a partial sweep via a maximum $\phi$ angle.
 //!
 //! ## Curves
 //!
@@ -52,18 +54,31 @@
 //!
 //! ## Hyperboloids
 //!
-//! TODO
+//! A hyperboloid is the surface of revolution created by sweeping a
+//! line segment (between two points) around the z axis. Cylinders and
+//! cones are special cases.
 //!
 //! ## Paraboloids
 //!
-//! TODO
+//! Paraboloids are centered around the z axis and are bounded by a
+//! minimum and maximum z value.
+//!
+//! ## Heightfields
+//!
+//! A heightfield is a regular grid of z values over the unit square
+//! in x and y. It is converted into a triangle mesh (two triangles
+//! per grid cell) with uv coordinates and smooth shading normals.
 //!
 
+pub mod cone;
 pub mod curve;
 pub mod cylinder;
 pub mod disk;
+pub mod heightfield;
+pub mod hyperboloid;
 pub mod loopsubdiv;
 pub mod nurbs;
+pub mod paraboloid;
 pub mod plymesh;
 pub mod sphere;
 pub mod triangle;
@@ -18,8 +18,8 @@ use crate::core::pbrt::Float;
 use crate::core::shape::Shape;
 use crate::core::texture::Texture;
 use crate::core::transform::Transform;
-use crate::shapes::triangle::{Triangle, TriangleMesh};
-use crate::textures::constant::ConstantTexture;
+use crate::shapes::triangle::{get_alpha_texture, Triangle, TriangleMesh};
+use crate::shapes::triangle::{FACE_UVS_QUAD, FACE_UVS_TRIANGLE};
 
 pub fn create_ply_mesh<S: BuildHasher>(
     o2w: &Transform,
@@ -63,6 +63,8 @@ pub fn create_ply_mesh<S: BuildHasher>(
     let mut has_normals: bool = false;
     let mut has_uvs: bool = false;
     let mut tm_vertex_indices: Vec<u32> = Vec::new();
+    let mut face_indices: Vec<i32> = Vec::new();
+    let mut face_uvs: Vec<Point2f> = Vec::new();
     for (name, list) in payload.into_iter() {
         match name.as_ref() {
             "vertex" => {
@@ -135,6 +137,8 @@ pub fn create_ply_mesh<S: BuildHasher>(
             "face" => {
                 for elem in list.into_iter() {
                     let mut nrm: Normal3f = Normal3f::default();
+                    let n_indices_before: usize = tm_vertex_indices.len();
+                    let mut face_index: Option<i32> = None;
                     for (name2, list2) in elem.into_iter() {
                         match name2.as_ref() {
                             "vertex_indices" => {
@@ -206,9 +210,28 @@ pub fn create_ply_mesh<S: BuildHasher>(
                                     nrm.z = z;
                                 }
                             }
+                            "face_indices" => match list2 {
+                                ply::Property::Int(i) => face_index = Some(i),
+                                ply::Property::UInt(i) => face_index = Some(i as i32),
+                                _ => {}
+                            },
                             _ => unreachable!(),
                         }
                     }
+                    if let Some(face_index) = face_index {
+                        // one face index per triangle (two for quads)
+                        let n_triangles: usize = (tm_vertex_indices.len() - n_indices_before) / 3;
+                        for _ in 0..n_triangles {
+                            face_indices.push(face_index);
+                        }
+                        // and the (u, v) values within that face
+                        if n_triangles == 2 {
+                            face_uvs.extend_from_slice(&FACE_UVS_QUAD[0]);
+                            face_uvs.extend_from_slice(&FACE_UVS_QUAD[1]);
+                        } else {
+                            face_uvs.extend_from_slice(&FACE_UVS_TRIANGLE);
+                        }
+                    }
                 }
             }
             _ => unreachable!(),
@@ -234,39 +257,20 @@ pub fn create_ply_mesh<S: BuildHasher>(
         p_ws.push(o2w.transform_point(item));
     }
     let s_ws: Vec<Vector3f> = Vec::new();
-    // look up an alpha texture, if applicable
-    let mut alpha_tex: Option<Arc<dyn Texture<Float> + Send + Sync>> = None;
-    let alpha_tex_name: String = params.find_texture("alpha");
-    if alpha_tex_name != "" {
-        alpha_tex = match float_textures.get(alpha_tex_name.as_str()) {
-            Some(float_texture) => Some(float_texture.clone()),
-            None => {
-                println!(
-                    "Couldn't find float texture {:?} for \"alpha\" parameter",
-                    alpha_tex_name.as_str()
-                );
-                None
-            }
-        }
-    } else if params.find_one_float("alpha", 1.0 as Float) == 0.0 as Float {
-        alpha_tex = Some(Arc::new(ConstantTexture::new(0.0 as Float)));
-    }
-    let mut shadow_alpha_tex: Option<Arc<dyn Texture<Float> + Send + Sync>> = None;
-    let shadow_alpha_tex_name: String = params.find_texture("shadowalpha");
-    if shadow_alpha_tex_name != "" {
-        shadow_alpha_tex = match float_textures.get(shadow_alpha_tex_name.as_str()) {
-            Some(float_texture) => Some(float_texture.clone()),
-            None => {
-                println!(
-                    "Couldn't find float texture {:?} for \"shadowalpha\" parameter",
-                    shadow_alpha_tex_name.as_str()
-                );
-                None
-            }
-        }
-    } else if params.find_one_float("shadowalpha", 1.0 as Float) == 0.0 as Float {
-        shadow_alpha_tex = Some(Arc::new(ConstantTexture::new(0.0 as Float)));
+    if !face_indices.is_empty() && face_indices.len() != tm_vertex_indices.len() / 3 {
+        println!(
+            "ERROR: plymesh: Number of face indices, {}, doesn't match number of faces, {}",
+            face_indices.len(),
+            tm_vertex_indices.len() / 3
+        );
+        face_indices.clear();
+        face_uvs.clear();
     }
+    // look up an alpha texture, if applicable
+    let alpha_tex: Option<Arc<dyn Texture<Float> + Send + Sync>> =
+        get_alpha_texture(params, &float_textures, "alpha");
+    let shadow_alpha_tex: Option<Arc<dyn Texture<Float> + Send + Sync>> =
+        get_alpha_texture(params, &float_textures, "shadowalpha");
     let mesh = Arc::new(TriangleMesh::new(
         *o2w,
         *w2o,
@@ -280,6 +284,8 @@ pub fn create_ply_mesh<S: BuildHasher>(
         uvs,
         alpha_tex,
         shadow_alpha_tex,
+        face_indices,
+        face_uvs,
     ));
     let mut shapes: Vec<Arc<Shape>> = Vec::new();
     for id in 0..mesh.n_triangles {
@@ -1,5 +1,7 @@
 // std
 use std::cell::Cell;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
 use std::mem;
 use std::sync::Arc;
 // pbrt
@@ -12,14 +14,44 @@ use crate::core::geometry::{
     Bounds3f, Normal3f, Point2f, Point3f, Ray, Vector2f, Vector3f, XYEnum,
 };
 use crate::core::interaction::{Interaction, InteractionCommon, Shading, SurfaceInteraction};
+use crate::core::paramset::ParamSet;
 use crate::core::pbrt::gamma;
+use crate::core::pbrt::hash_float;
 use crate::core::pbrt::Float;
 // use crate::core::sampling::uniform_sample_triangle;
+use crate::core::stats::{Prof, ProfilePhase, StatMemoryCounter, StatPercent, StatRatio};
 use crate::core::texture::Texture;
 use crate::core::transform::Transform;
+use crate::textures::constant::ConstantTexture;
 
 // see triangle.h
 
+static TRI_MESH_BYTES: StatMemoryCounter = StatMemoryCounter::new("Memory/Triangle meshes");
+static N_TESTS: StatPercent = StatPercent::new("Intersections/Ray-triangle intersection tests");
+static N_TRIS: StatRatio = StatRatio::new("Scene/Triangles per triangle mesh");
+
+/// Per-face $(u,v)$ coordinates of a triangle which is a (Ptex) face
+/// on its own.
+pub const FACE_UVS_TRIANGLE: [Point2f; 3] = [
+    Point2f { x: 0.0, y: 0.0 },
+    Point2f { x: 1.0, y: 0.0 },
+    Point2f { x: 0.0, y: 1.0 },
+];
+/// Per-face $(u,v)$ coordinates of the two triangles a quad (v0, v1,
+/// v2, v3) gets split into, (v0, v1, v2) and (v3, v0, v2).
+pub const FACE_UVS_QUAD: [[Point2f; 3]; 2] = [
+    [
+        Point2f { x: 0.0, y: 0.0 },
+        Point2f { x: 1.0, y: 0.0 },
+        Point2f { x: 1.0, y: 1.0 },
+    ],
+    [
+        Point2f { x: 0.0, y: 1.0 },
+        Point2f { x: 0.0, y: 0.0 },
+        Point2f { x: 1.0, y: 1.0 },
+    ],
+];
+
 #[derive(Clone)]
 pub struct TriangleMesh {
     /// the total number of triangles in the mesh
@@ -38,6 +70,13 @@ pub struct TriangleMesh {
     pub uv: Vec<Point2f>,
     pub alpha_mask: Option<Arc<dyn Texture<Float> + Send + Sync>>,
     pub shadow_alpha_mask: Option<Arc<dyn Texture<Float> + Send + Sync>>,
+    /// an optional vector of per-triangle face indices, e.g. for Ptex
+    /// lookups (can be empty)
+    pub face_indices: Vec<i32>,
+    /// an optional vector of per-face (u, v) values, three per
+    /// triangle, used if there are no texture coordinates (can be
+    /// empty)
+    pub face_uvs: Vec<Point2f>,
     // inherited from class Shape (see shape.h)
     pub object_to_world: Transform, // TODO: not pub?
     pub world_to_object: Transform, // TODO: not pub?
@@ -59,7 +98,20 @@ impl TriangleMesh {
         uv: Vec<Point2f>,
         alpha_mask: Option<Arc<dyn Texture<Float> + Send + Sync>>,
         shadow_alpha_mask: Option<Arc<dyn Texture<Float> + Send + Sync>>,
+        face_indices: Vec<i32>,
+        face_uvs: Vec<Point2f>,
     ) -> Self {
+        N_TRIS.add_num(n_triangles as u64);
+        N_TRIS.add_denom(1);
+        TRI_MESH_BYTES.add(
+            vertex_indices.len() * mem::size_of::<u32>()
+                + p.len() * mem::size_of::<Point3f>()
+                + n.len() * mem::size_of::<Normal3f>()
+                + s.len() * mem::size_of::<Vector3f>()
+                + uv.len() * mem::size_of::<Point2f>()
+                + face_indices.len() * mem::size_of::<i32>()
+                + face_uvs.len() * mem::size_of::<Point2f>(),
+        );
         TriangleMesh {
             // Shape
             object_to_world,
@@ -76,6 +128,8 @@ impl TriangleMesh {
             uv,
             alpha_mask,
             shadow_alpha_mask,
+            face_indices,
+            face_uvs,
         }
     }
 }
@@ -93,13 +147,28 @@ impl Triangle {
             id: tri_number,
         }
     }
+    pub fn get_face_index(&self) -> i32 {
+        if self.mesh.face_indices.is_empty() {
+            0_i32
+        } else {
+            self.mesh.face_indices[self.id as usize]
+        }
+    }
     pub fn get_uvs(&self) -> [Point2f; 3] {
         if self.mesh.uv.is_empty() {
-            [
-                Point2f { x: 0.0, y: 0.0 },
-                Point2f { x: 1.0, y: 0.0 },
-                Point2f { x: 1.0, y: 1.0 },
-            ]
+            if self.mesh.face_uvs.is_empty() {
+                [
+                    Point2f { x: 0.0, y: 0.0 },
+                    Point2f { x: 1.0, y: 0.0 },
+                    Point2f { x: 1.0, y: 1.0 },
+                ]
+            } else {
+                [
+                    self.mesh.face_uvs[(self.id * 3) as usize],
+                    self.mesh.face_uvs[(self.id * 3) as usize + 1],
+                    self.mesh.face_uvs[(self.id * 3) as usize + 2],
+                ]
+            }
         } else {
             [
                 self.mesh.uv[self.mesh.vertex_indices[(self.id * 3) as usize] as usize],
@@ -132,6 +201,17 @@ impl Triangle {
         bnd3_union_pnt3f(&Bounds3f::new(*p0, *p1), p2)
     }
     pub fn intersect(&self, ray: &Ray, t_hit: &mut Float, isect: &mut SurfaceInteraction) -> bool {
+        self.intersect_alpha(ray, t_hit, isect, true)
+    }
+    fn intersect_alpha(
+        &self,
+        ray: &Ray,
+        t_hit: &mut Float,
+        isect: &mut SurfaceInteraction,
+        test_alpha_texture: bool,
+    ) -> bool {
+        let _prof = ProfilePhase::new(Prof::TriIntersect);
+        N_TESTS.inc_denom();
         // get triangle vertices in _p0_, _p1_, and _p2_
         let idx1: usize = (self.id * 3) as usize;
         let idx = &self.mesh.vertex_indices[idx1..(idx1 + 3)];
@@ -309,10 +389,9 @@ impl Triangle {
         let p_hit: Point3f = *p0 * b0 + *p1 * b1 + *p2 * b2;
         let uv_hit: Point2f = uv[0] * b0 + uv[1] * b1 + uv[2] * b2;
         // test intersection against alpha texture, if present
-        // TODO: testAlphaTexture
-        if let Some(alpha_mask) = &self.mesh.alpha_mask {
+        if let Some(alpha_mask) = self.mesh.alpha_mask.as_ref().filter(|_| test_alpha_texture) {
             let wo: Vector3f = -ray.d;
-            let isect_local: SurfaceInteraction = SurfaceInteraction::new(
+            let mut isect_local: SurfaceInteraction = SurfaceInteraction::new(
                 &p_hit,
                 &Vector3f::default(),
                 uv_hit,
@@ -324,7 +403,8 @@ impl Triangle {
                 ray.time,
                 None,
             );
-            if alpha_mask.evaluate(&isect_local) == 0.0 as Float {
+            isect_local.face_index = self.get_face_index();
+            if alpha_rejects_hit(alpha_mask.evaluate(&isect_local), ray) {
                 return false;
             }
         }
@@ -441,12 +521,14 @@ impl Triangle {
         isect.bsdf = None;
         // isect.bssrdf = None;
         isect.shape = None;
+        isect.face_index = self.get_face_index();
         *t_hit = t;
+        N_TESTS.inc_num();
         true
     }
     pub fn intersect_p(&self, ray: &Ray) -> bool {
-        // TODO: ProfilePhase p(Prof::TriIntersectP);
-        // TODO: ++nTests;
+        let _prof = ProfilePhase::new(Prof::TriIntersectP);
+        N_TESTS.inc_denom();
         // get triangle vertices in _p0_, _p1_, and _p2_
         let idx1: usize = (self.id * 3) as usize;
         let idx = &self.mesh.vertex_indices[idx1..(idx1 + 3)];
@@ -586,7 +668,7 @@ impl Triangle {
         if t <= delta_t {
             return false;
         }
-        // TODO: if (testAlphaTexture && (mesh->alphaMask || mesh->shadowAlphaMask)) { ... }
+        // test shadow ray intersection against alpha texture, if present
         if self.mesh.alpha_mask.is_some() || self.mesh.shadow_alpha_mask.is_some() {
             // compute triangle partial derivatives
             let mut dpdu: Vector3f = Vector3f::default();
@@ -626,7 +708,7 @@ impl Triangle {
             let p_hit: Point3f = *p0 * b0 + *p1 * b1 + *p2 * b2;
             let uv_hit: Point2f = uv[0] * b0 + uv[1] * b1 + uv[2] * b2;
             let wo: Vector3f = -ray.d;
-            let isect_local: SurfaceInteraction = SurfaceInteraction::new(
+            let mut isect_local: SurfaceInteraction = SurfaceInteraction::new(
                 &p_hit,
                 &Vector3f::default(),
                 uv_hit,
@@ -638,18 +720,19 @@ impl Triangle {
                 ray.time,
                 None,
             );
+            isect_local.face_index = self.get_face_index();
             if let Some(alpha_mask) = &self.mesh.alpha_mask {
-                if alpha_mask.evaluate(&isect_local) == 0.0 as Float {
+                if alpha_rejects_hit(alpha_mask.evaluate(&isect_local), ray) {
                     return false;
                 }
             }
             if let Some(shadow_alpha_mask) = &self.mesh.shadow_alpha_mask {
-                if shadow_alpha_mask.evaluate(&isect_local) == 0.0 as Float {
+                if alpha_rejects_hit(shadow_alpha_mask.evaluate(&isect_local), ray) {
                     return false;
                 }
             }
         }
-        // TODO: ++nHits;
+        N_TESTS.inc_num();
         true
     }
     pub fn get_reverse_orientation(&self) -> bool {
@@ -747,7 +830,7 @@ impl Triangle {
         // scene, where this is used to make an invisible area light.
         let mut t_hit: Float = 0.0;
         let mut isect_light: SurfaceInteraction = SurfaceInteraction::default();
-        if self.intersect(&ray, &mut t_hit, &mut isect_light) {
+        if self.intersect_alpha(&ray, &mut t_hit, &mut isect_light, false) {
             // convert light sample weight to solid angle measure
             let mut pdf: Float = pnt3_distance_squaredf(&iref.get_p(), &isect_light.common.p)
                 / (nrm_abs_dot_vec3f(&isect_light.common.n, &-(*wi)) * self.area());
@@ -760,3 +843,48 @@ impl Triangle {
         }
     }
 }
+
+/// Decide whether an alpha value cuts a hit away. Zero alpha always
+/// does, partial alpha does so stochastically, with a probability of
+/// one minus alpha driven by a hash of the ray.
+fn alpha_rejects_hit(alpha: Float, ray: &Ray) -> bool {
+    if alpha >= 1.0 as Float {
+        false
+    } else if alpha <= 0.0 as Float {
+        true
+    } else {
+        hash_float(&[ray.o.x, ray.o.y, ray.o.z, ray.d.x, ray.d.y, ray.d.z]) > alpha
+    }
+}
+
+/// Looks up the alpha texture of a triangle mesh given by the *name*
+/// parameter ("alpha" or "shadowalpha"), either as a float texture or
+/// as a constant value (partial alpha values are honored
+/// stochastically).
+pub fn get_alpha_texture<S: BuildHasher>(
+    params: &ParamSet,
+    float_textures: &HashMap<String, Arc<dyn Texture<Float> + Send + Sync>, S>,
+    name: &str,
+) -> Option<Arc<dyn Texture<Float> + Send + Sync>> {
+    let tex_name: String = params.find_texture(name);
+    if !tex_name.is_empty() {
+        match float_textures.get(tex_name.as_str()) {
+            Some(float_texture) => Some(float_texture.clone()),
+            None => {
+                println!(
+                    "ERROR: Couldn't find float texture {:?} for \"{}\" parameter",
+                    tex_name.as_str(),
+                    name
+                );
+                None
+            }
+        }
+    } else {
+        let alpha: Float = params.find_one_float(name, 1.0 as Float);
+        if alpha < 1.0 as Float {
+            Some(Arc::new(ConstantTexture::new(alpha)))
+        } else {
+            None
+        }
+    }
+}
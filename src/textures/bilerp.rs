@@ -0,0 +1,46 @@
+// std
+use std::ops::{Add, Mul};
+// pbrt
+use crate::core::geometry::{Point2f, Vector2f};
+use crate::core::interaction::SurfaceInteraction;
+use crate::core::pbrt::Float;
+use crate::core::texture::{Texture, TextureMapping2D};
+
+// see bilerp.h
+
+pub struct BilerpTexture<T> {
+    pub mapping: Box<TextureMapping2D>,
+    pub v00: T,
+    pub v01: T,
+    pub v10: T,
+    pub v11: T,
+}
+
+impl<T: Copy> BilerpTexture<T> {
+    pub fn new(mapping: Box<TextureMapping2D>, v00: T, v01: T, v10: T, v11: T) -> Self {
+        BilerpTexture {
+            mapping,
+            v00,
+            v01,
+            v10,
+            v11,
+        }
+    }
+}
+
+impl<T: Copy> Texture<T> for BilerpTexture<T>
+where
+    T: Add<Output = T>,
+    T: Mul<Output = T>,
+    T: From<Float>,
+{
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        let mut dstdx: Vector2f = Vector2f::default();
+        let mut dstdy: Vector2f = Vector2f::default();
+        let st: Point2f = self.mapping.map(si, &mut dstdx, &mut dstdy);
+        self.v00 * T::from((1.0 as Float - st.x) * (1.0 as Float - st.y))
+            + self.v01 * T::from((1.0 as Float - st.x) * st.y)
+            + self.v10 * T::from(st.x * (1.0 as Float - st.y))
+            + self.v11 * T::from(st.x * st.y)
+    }
+}
@@ -1,40 +1,125 @@
 // std
+use std::ops::{Add, Mul};
 use std::sync::Arc;
 // pbrt
-use crate::core::geometry::{Point2f, Vector2f};
+use crate::core::geometry::{Point2f, Point3f, Vector2f, Vector3f};
 use crate::core::interaction::SurfaceInteraction;
-use crate::core::texture::{Texture, TextureMapping2D};
+use crate::core::pbrt::Float;
+use crate::core::texture::{Texture, TextureMapping2D, TextureMapping3D};
 
 // checkerboard.h
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AAMethod {
+    None,
+    ClosedForm,
+}
+
 pub struct Checkerboard2DTexture<T> {
     pub tex1: Arc<dyn Texture<T> + Send + Sync>,
     pub tex2: Arc<dyn Texture<T> + Send + Sync>,
     pub mapping: Box<TextureMapping2D>,
-    // TODO: const AAMethod aaMethod;
+    pub aa_method: AAMethod,
 }
 
 impl<T: Copy> Checkerboard2DTexture<T> {
     pub fn new(
         mapping: Box<TextureMapping2D>,
         tex1: Arc<dyn Texture<T> + Send + Sync>,
-        tex2: Arc<dyn Texture<T> + Send + Sync>, // , TODO: aaMethod
+        tex2: Arc<dyn Texture<T> + Send + Sync>,
+        aa_method: AAMethod,
     ) -> Self {
         Checkerboard2DTexture {
             tex1,
             tex2,
             mapping,
+            aa_method,
         }
     }
 }
 
-impl<T: Copy> Texture<T> for Checkerboard2DTexture<T> {
+impl<T: Copy> Texture<T> for Checkerboard2DTexture<T>
+where
+    T: Add<Output = T>,
+    T: Mul<Output = T>,
+    T: From<Float>,
+{
     fn evaluate(&self, si: &SurfaceInteraction) -> T {
         let mut dstdx: Vector2f = Vector2f::default();
         let mut dstdy: Vector2f = Vector2f::default();
         let st: Point2f = self.mapping.map(si, &mut dstdx, &mut dstdy);
-        // TODO: if (aaMethod == AAMethod::None) {
-        if (st.x.floor() as u32 + st.y.floor() as u32) % 2 == 0 {
+        if self.aa_method == AAMethod::ClosedForm {
+            // compute closed-form box-filtered _Checkerboard2DTexture_ value
+
+            // evaluate single check if filter is entirely inside one of them
+            let ds: Float = dstdx.x.abs().max(dstdy.x.abs());
+            let dt: Float = dstdx.y.abs().max(dstdy.y.abs());
+            if (st.x - ds).floor() != (st.x + ds).floor()
+                || (st.y - dt).floor() != (st.y + dt).floor()
+            {
+                // apply box filter to checkerboard region
+                let bump_int = |x: Float| -> Float {
+                    (x / 2.0 as Float).floor()
+                        + 2.0 as Float
+                            * (x / 2.0 as Float - (x / 2.0 as Float).floor() - 0.5 as Float)
+                                .max(0.0 as Float)
+                };
+                // average of the odd checks over [x - d, x + d] (point
+                // sampled for a degenerate filter width)
+                let average = |x: Float, d: Float| -> Float {
+                    if d > 0.0 as Float {
+                        (bump_int(x + d) - bump_int(x - d)) / (2.0 as Float * d)
+                    } else if x.floor() as i32 % 2 == 0 {
+                        0.0 as Float
+                    } else {
+                        1.0 as Float
+                    }
+                };
+                let s_int: Float = average(st.x, ds);
+                let t_int: Float = average(st.y, dt);
+                let mut area2: Float = s_int + t_int - 2.0 as Float * s_int * t_int;
+                if ds > 1.0 as Float || dt > 1.0 as Float {
+                    area2 = 0.5 as Float;
+                }
+                return self.tex1.evaluate(si) * T::from(1.0 as Float - area2)
+                    + self.tex2.evaluate(si) * T::from(area2);
+            }
+        }
+        // point sample _Checkerboard2DTexture_
+        if (st.x.floor() as i32 + st.y.floor() as i32) % 2 == 0 {
+            self.tex1.evaluate(si)
+        } else {
+            self.tex2.evaluate(si)
+        }
+    }
+}
+
+pub struct Checkerboard3DTexture<T> {
+    pub tex1: Arc<dyn Texture<T> + Send + Sync>,
+    pub tex2: Arc<dyn Texture<T> + Send + Sync>,
+    pub mapping: Box<TextureMapping3D>,
+}
+
+impl<T: Copy> Checkerboard3DTexture<T> {
+    pub fn new(
+        mapping: Box<TextureMapping3D>,
+        tex1: Arc<dyn Texture<T> + Send + Sync>,
+        tex2: Arc<dyn Texture<T> + Send + Sync>,
+    ) -> Self {
+        Checkerboard3DTexture {
+            tex1,
+            tex2,
+            mapping,
+        }
+    }
+}
+
+impl<T: Copy> Texture<T> for Checkerboard3DTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        let mut dpdx: Vector3f = Vector3f::default();
+        let mut dpdy: Vector3f = Vector3f::default();
+        let p: Point3f = self.mapping.map(si, &mut dpdx, &mut dpdy);
+        if (p.x.floor() as i32 + p.y.floor() as i32 + p.z.floor() as i32) % 2 == 0 {
             self.tex1.evaluate(si)
         } else {
             self.tex2.evaluate(si)
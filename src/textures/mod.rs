@@ -38,10 +38,122 @@
 //!
 //! ![WindyTexture](/doc/img/windy_pbrt_rust.png)
 //!
+//! ## PtexTexture
+//!
+//! Per-face textures read from Ptex files. Triangle meshes provide
+//! the face index for each triangle via "faceIndices" (or the
+//! "face_indices" property of PLY files).
+//!
+//! The following writes a small Ptex file with the layout of
+//! PtexIO.h (a 64 byte header, followed by the extended header, the
+//! zipped face info and constant data, the level info and the zipped
+//! data of level 0) and reads it back:
+//!
+//! ```rust
+//! use miniz_oxide::deflate::compress_to_vec_zlib;
+//! use rs_pbrt::core::geometry::{Normal3f, Point2f, Point3f, Vector3f};
+//! use rs_pbrt::core::interaction::SurfaceInteraction;
+//! use rs_pbrt::core::pbrt::Float;
+//! use rs_pbrt::core::texture::Texture;
+//! use rs_pbrt::textures::imagemap::convert_to_float;
+//! use rs_pbrt::textures::ptex::PtexTexture;
+//!
+//! fn main() {
+//!     // two quad faces with one uint8 channel, the first one is
+//!     // constant, the second one has 2x2 texels
+//!     let mut face_info: Vec<u8> = Vec::new();
+//!     for (log2_res, flags) in [(0_u8, 1_u8), (1_u8, 0_u8)].iter() {
+//!         face_info.extend_from_slice(&[*log2_res, *log2_res, 0, *flags]);
+//!         for _ in 0..4 {
+//!             face_info.extend_from_slice(&(-1_i32).to_le_bytes());
+//!         }
+//!     }
+//!     let face_info: Vec<u8> = compress_to_vec_zlib(&face_info, 6);
+//!     let const_data: Vec<u8> = compress_to_vec_zlib(&[51, 0], 6);
+//!     let face_data: Vec<u8> = compress_to_vec_zlib(&[0, 255, 102, 204], 6);
+//!     // face data headers: block size and encoding (constant, zipped)
+//!     let mut headers: Vec<u8> = Vec::new();
+//!     headers.extend_from_slice(&0_u32.to_le_bytes());
+//!     headers.extend_from_slice(&(face_data.len() as u32 | 1 << 30).to_le_bytes());
+//!     let headers: Vec<u8> = compress_to_vec_zlib(&headers, 6);
+//!     let level_data_size: u64 = (headers.len() + face_data.len()) as u64;
+//!     let mut level_info: Vec<u8> = Vec::new();
+//!     level_info.extend_from_slice(&level_data_size.to_le_bytes());
+//!     level_info.extend_from_slice(&(headers.len() as u32).to_le_bytes());
+//!     level_info.extend_from_slice(&2_u32.to_le_bytes()); // nfaces
+//!     let ext_header: Vec<u8> = vec![0_u8; 48];
+//!     let mut ptex: Vec<u8> = Vec::new();
+//!     for value in [
+//!         0x7865_7450, // magic
+//!         1,           // version
+//!         1,           // mesh type (quads)
+//!         0,           // data type (uint8)
+//!     ]
+//!     .iter()
+//!     {
+//!         ptex.extend_from_slice(&(*value as u32).to_le_bytes());
+//!     }
+//!     ptex.extend_from_slice(&(-1_i32).to_le_bytes()); // alpha channel
+//!     ptex.extend_from_slice(&1_u16.to_le_bytes()); // channels
+//!     ptex.extend_from_slice(&1_u16.to_le_bytes()); // levels
+//!     for value in [
+//!         2, // faces
+//!         ext_header.len(),
+//!         face_info.len(),
+//!         const_data.len(),
+//!         level_info.len(),
+//!         4, // minor version
+//!     ]
+//!     .iter()
+//!     {
+//!         ptex.extend_from_slice(&(*value as u32).to_le_bytes());
+//!     }
+//!     ptex.extend_from_slice(&level_data_size.to_le_bytes());
+//!     ptex.extend_from_slice(&0_u32.to_le_bytes()); // meta data zip size
+//!     ptex.extend_from_slice(&0_u32.to_le_bytes()); // meta data mem size
+//!     assert_eq!(ptex.len(), 64);
+//!     for block in [ext_header, face_info, const_data, level_info, headers, face_data].iter() {
+//!         ptex.extend_from_slice(block);
+//!     }
+//!     let filename = std::env::temp_dir().join("ptex_doctest.ptx");
+//!     std::fs::write(&filename, &ptex).unwrap();
+//!     let texture: PtexTexture<Float> = PtexTexture::new(
+//!         String::from(filename.to_str().unwrap()),
+//!         1.0 as Float,
+//!         false,
+//!         8.0 as Float,
+//!         convert_to_float,
+//!     );
+//!     let lookup = |face_index: i32, u: Float, v: Float| -> Float {
+//!         let mut si: SurfaceInteraction = SurfaceInteraction::new(
+//!             &Point3f::default(),
+//!             &Vector3f::default(),
+//!             Point2f { x: u, y: v },
+//!             &Vector3f { x: 0.0, y: 0.0, z: 1.0 },
+//!             &Vector3f { x: 1.0, y: 0.0, z: 0.0 },
+//!             &Vector3f { x: 0.0, y: 1.0, z: 0.0 },
+//!             &Normal3f::default(),
+//!             &Normal3f::default(),
+//!             0.0 as Float,
+//!             None,
+//!         );
+//!         si.face_index = face_index;
+//!         texture.evaluate(&si)
+//!     };
+//!     assert!((lookup(0, 0.5, 0.5) - 0.2).abs() < 1e-3);
+//!     assert!((lookup(1, 0.25, 0.25) - 0.0).abs() < 1e-3);
+//!     assert!((lookup(1, 0.75, 0.25) - 1.0).abs() < 1e-3);
+//!     assert!((lookup(1, 0.25, 0.75) - 0.4).abs() < 1e-3);
+//!     assert!((lookup(1, 0.75, 0.75) - 0.8).abs() < 1e-3);
+//!     std::fs::remove_file(filename).unwrap();
+//! }
+//! ```
+//!
 //! ## WrinkledTexture
 //!
 //! ![WrinkledTexture](/doc/img/wrinkled_pbrt_rust.png)
 
+pub mod bilerp;
 pub mod checkerboard;
 pub mod constant;
 pub mod dots;
@@ -49,6 +161,8 @@ pub mod fbm;
 pub mod imagemap;
 pub mod marble;
 pub mod mix;
+pub mod ptex;
 pub mod scale;
+pub mod uv;
 pub mod windy;
 pub mod wrinkled;
@@ -0,0 +1,561 @@
+// std
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+// others
+use byteorder::{LittleEndian, ReadBytesExt};
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+// pbrt
+use crate::core::geometry::{Point2f, Point2i, Vector2f};
+use crate::core::interaction::SurfaceInteraction;
+use crate::core::mipmap::{ImageWrap, MipMap};
+use crate::core::pbrt::{clamp_t, lerp, Float, Spectrum};
+use crate::core::spectrum::RGBSpectrum;
+use crate::core::texture::Texture;
+
+// see ptex.h
+
+const PTEX_MAGIC: u32 = 0x7865_7450; // "Ptex"
+const PTEX_HEADER_SIZE: u64 = 64;
+const PTEX_FACE_INFO_SIZE: usize = 20;
+const PTEX_LEVEL_INFO_SIZE: usize = 16;
+// largest (log2) face resolution we accept
+const PTEX_MAX_LOG2_RES: i8 = 15;
+// FaceInfo flags
+const PTEX_FLAG_CONSTANT: u8 = 1;
+// FaceDataHeader encodings
+const PTEX_ENC_CONSTANT: u32 = 0;
+const PTEX_ENC_ZIPPED: u32 = 1;
+const PTEX_ENC_DIFFZIPPED: u32 = 2;
+const PTEX_ENC_TILED: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PtexDataType {
+    UInt8,
+    UInt16,
+    Half,
+    Float,
+}
+
+impl PtexDataType {
+    fn size(self) -> usize {
+        match self {
+            PtexDataType::UInt8 => 1,
+            PtexDataType::UInt16 | PtexDataType::Half => 2,
+            PtexDataType::Float => 4,
+        }
+    }
+    /// Reads the *i*th value from *data* and normalizes integer types
+    /// to $[0, 1]$.
+    fn value(self, data: &[u8], i: usize) -> Float {
+        match self {
+            PtexDataType::UInt8 => data[i] as Float / 255.0 as Float,
+            PtexDataType::UInt16 => {
+                u16::from_le_bytes([data[2 * i], data[2 * i + 1]]) as Float / 65535.0 as Float
+            }
+            PtexDataType::Half => half_to_float(u16::from_le_bytes([data[2 * i], data[2 * i + 1]])),
+            PtexDataType::Float => f32::from_le_bytes([
+                data[4 * i],
+                data[4 * i + 1],
+                data[4 * i + 2],
+                data[4 * i + 3],
+            ]) as Float,
+        }
+    }
+}
+
+fn half_to_float(h: u16) -> Float {
+    let sign: Float = if h & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent: i32 = ((h >> 10) & 0x1f) as i32;
+    let mantissa: Float = (h & 0x3ff) as Float;
+    if exponent == 0 {
+        // zero or subnormal
+        sign * mantissa * (2.0 as Float).powi(-24)
+    } else if exponent == 0x1f {
+        if mantissa == 0.0 as Float {
+            sign * Float::INFINITY
+        } else {
+            Float::NAN
+        }
+    } else {
+        sign * (1.0 as Float + mantissa / 1024.0 as Float) * (2.0 as Float).powi(exponent - 15)
+    }
+}
+
+/// Face data of a Ptex file (most detailed level only), converted to
+/// (interleaved) floats.
+struct PtexFaceData {
+    res_u: usize,
+    res_v: usize,
+    texels: Vec<Float>,
+}
+
+/// Minimal native reader for the Ptex file format. It reads the
+/// highest resolution level of all faces; the reductions stored in
+/// the file are not used (they get recomputed by the _MipMap_ of each
+/// face).
+struct PtexFile {
+    triangles: bool,
+    n_channels: usize,
+    faces: Vec<PtexFaceData>,
+}
+
+impl PtexFile {
+    fn open(filename: &str) -> std::io::Result<PtexFile> {
+        let mut buffer: Vec<u8> = Vec::new();
+        File::open(filename)?.read_to_end(&mut buffer)?;
+        let mut reader = Cursor::new(&buffer[..]);
+        // header
+        if reader.read_u32::<LittleEndian>()? != PTEX_MAGIC {
+            return Err(invalid_data("not a ptex file"));
+        }
+        let version: u32 = reader.read_u32::<LittleEndian>()?;
+        if version != 1 {
+            return Err(invalid_data(&format!(
+                "unsupported ptex version {}",
+                version
+            )));
+        }
+        let mesh_type: u32 = reader.read_u32::<LittleEndian>()?;
+        let data_type: PtexDataType = match reader.read_u32::<LittleEndian>()? {
+            0 => PtexDataType::UInt8,
+            1 => PtexDataType::UInt16,
+            2 => PtexDataType::Half,
+            3 => PtexDataType::Float,
+            dt => return Err(invalid_data(&format!("unknown data type {}", dt))),
+        };
+        let _alpha_chan: i32 = reader.read_i32::<LittleEndian>()?;
+        let n_channels: usize = reader.read_u16::<LittleEndian>()? as usize;
+        let n_levels: usize = reader.read_u16::<LittleEndian>()? as usize;
+        let n_faces: usize = reader.read_u32::<LittleEndian>()? as usize;
+        let ext_header_size: u64 = reader.read_u32::<LittleEndian>()? as u64;
+        let face_info_size: usize = reader.read_u32::<LittleEndian>()? as usize;
+        let const_data_size: usize = reader.read_u32::<LittleEndian>()? as usize;
+        let level_info_size: usize = reader.read_u32::<LittleEndian>()? as usize;
+        let _minor_version: u32 = reader.read_u32::<LittleEndian>()?;
+        let _level_data_size: u64 = reader.read_u64::<LittleEndian>()?;
+        let _meta_data_zip_size: u32 = reader.read_u32::<LittleEndian>()?;
+        let _meta_data_mem_size: u32 = reader.read_u32::<LittleEndian>()?;
+        if n_channels == 0 || n_levels == 0 {
+            return Err(invalid_data("no channels or levels"));
+        }
+        let pixel_size: usize = data_type.size() * n_channels;
+        // skip the extended header
+        reader.seek(SeekFrom::Start(PTEX_HEADER_SIZE + ext_header_size))?;
+        // face info, constant data and level info
+        let face_info: Vec<u8> =
+            read_zip_block(&mut reader, face_info_size, n_faces * PTEX_FACE_INFO_SIZE)?;
+        let const_data: Vec<u8> =
+            read_zip_block(&mut reader, const_data_size, n_faces * pixel_size)?;
+        let mut level_info: Vec<u8> = vec![0_u8; level_info_size];
+        reader.read_exact(&mut level_info)?;
+        if level_info.len() < PTEX_LEVEL_INFO_SIZE {
+            return Err(invalid_data("missing level info"));
+        }
+        let mut level_info_reader = Cursor::new(&level_info[..]);
+        let _level_data_size: u64 = level_info_reader.read_u64::<LittleEndian>()?;
+        let level_header_size: usize = level_info_reader.read_u32::<LittleEndian>()? as usize;
+        let level_n_faces: usize = level_info_reader.read_u32::<LittleEndian>()? as usize;
+        if level_n_faces != n_faces {
+            return Err(invalid_data("level 0 doesn't contain all faces"));
+        }
+        // level 0 (highest resolution) starts right after the level info
+        let face_data_headers: Vec<u8> =
+            read_zip_block(&mut reader, level_header_size, 4 * n_faces)?;
+        let mut faces: Vec<PtexFaceData> = Vec::with_capacity(n_faces);
+        for f in 0..n_faces {
+            let info: &[u8] = &face_info[f * PTEX_FACE_INFO_SIZE..(f + 1) * PTEX_FACE_INFO_SIZE];
+            let res_u: usize = res_from_log2(info[0] as i8)?;
+            let res_v: usize = res_from_log2(info[1] as i8)?;
+            let flags: u8 = info[3];
+            let fdh: u32 = u32::from_le_bytes([
+                face_data_headers[4 * f],
+                face_data_headers[4 * f + 1],
+                face_data_headers[4 * f + 2],
+                face_data_headers[4 * f + 3],
+            ]);
+            let block_size: usize = (fdh & 0x3fff_ffff) as usize;
+            let encoding: u32 = fdh >> 30;
+            let block_start: u64 = reader.position();
+            if flags & PTEX_FLAG_CONSTANT != 0 {
+                let pixel: &[u8] = &const_data[f * pixel_size..(f + 1) * pixel_size];
+                let texels: Vec<Float> =
+                    (0..n_channels).map(|c| data_type.value(pixel, c)).collect();
+                faces.push(PtexFaceData {
+                    res_u: 1,
+                    res_v: 1,
+                    texels,
+                });
+            } else {
+                let texels: Vec<Float> = read_face_data(
+                    &mut reader,
+                    res_u,
+                    res_v,
+                    encoding,
+                    block_size,
+                    data_type,
+                    n_channels,
+                )?;
+                faces.push(PtexFaceData {
+                    res_u,
+                    res_v,
+                    texels,
+                });
+            }
+            reader.seek(SeekFrom::Start(block_start + block_size as u64))?;
+        }
+        Ok(PtexFile {
+            triangles: mesh_type == 0,
+            n_channels,
+            faces,
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Converts a (log2) resolution of the file into a number of texels,
+/// rejecting values which would overflow.
+fn res_from_log2(log2: i8) -> std::io::Result<usize> {
+    if log2 > PTEX_MAX_LOG2_RES {
+        return Err(invalid_data(&format!("invalid resolution 2^{}", log2)));
+    }
+    Ok(1_usize << log2.max(0))
+}
+
+/// Reads *zip_size* bytes of zlib compressed data, which are expected
+/// to inflate to *mem_size* bytes.
+fn read_zip_block(
+    reader: &mut Cursor<&[u8]>,
+    zip_size: usize,
+    mem_size: usize,
+) -> std::io::Result<Vec<u8>> {
+    let mut zipped: Vec<u8> = vec![0_u8; zip_size];
+    reader.read_exact(&mut zipped)?;
+    let data: Vec<u8> = decompress_to_vec_zlib(&zipped)
+        .map_err(|e| invalid_data(&format!("unable to inflate data ({:?})", e)))?;
+    if data.len() != mem_size {
+        return Err(invalid_data("unexpected size of inflated data"));
+    }
+    Ok(data)
+}
+
+/// Reads the data of a single face (or tile), stored in *block_size*
+/// bytes, and returns *res_u* x *res_v* pixels with *n_channels*
+/// interleaved floats each.
+fn read_face_data(
+    reader: &mut Cursor<&[u8]>,
+    res_u: usize,
+    res_v: usize,
+    encoding: u32,
+    block_size: usize,
+    data_type: PtexDataType,
+    n_channels: usize,
+) -> std::io::Result<Vec<Float>> {
+    let pixel_size: usize = data_type.size() * n_channels;
+    let n_pixels: usize = res_u * res_v;
+    match encoding {
+        PTEX_ENC_CONSTANT => {
+            let mut pixel: Vec<u8> = vec![0_u8; pixel_size];
+            reader.read_exact(&mut pixel)?;
+            let mut texels: Vec<Float> = Vec::with_capacity(n_pixels * n_channels);
+            for _ in 0..n_pixels {
+                for c in 0..n_channels {
+                    texels.push(data_type.value(&pixel, c));
+                }
+            }
+            Ok(texels)
+        }
+        PTEX_ENC_ZIPPED | PTEX_ENC_DIFFZIPPED => {
+            let mut data: Vec<u8> = read_zip_block(reader, block_size, n_pixels * pixel_size)?;
+            if encoding == PTEX_ENC_DIFFZIPPED {
+                decode_difference(&mut data, data_type);
+            }
+            // channels are stored as separate planes; interleave them
+            let mut texels: Vec<Float> = vec![0.0 as Float; n_pixels * n_channels];
+            for c in 0..n_channels {
+                for i in 0..n_pixels {
+                    texels[i * n_channels + c] = data_type.value(&data, c * n_pixels + i);
+                }
+            }
+            Ok(texels)
+        }
+        PTEX_ENC_TILED => {
+            let tile_res_u: usize = res_from_log2(reader.read_i8()?)?;
+            let tile_res_v: usize = res_from_log2(reader.read_i8()?)?;
+            let tile_header_size: usize = reader.read_u32::<LittleEndian>()? as usize;
+            let tile_res_u: usize = tile_res_u.min(res_u);
+            let tile_res_v: usize = tile_res_v.min(res_v);
+            let n_tiles_u: usize = res_u / tile_res_u;
+            let n_tiles_v: usize = res_v / tile_res_v;
+            let n_tiles: usize = n_tiles_u * n_tiles_v;
+            let tile_headers: Vec<u8> = read_zip_block(reader, tile_header_size, 4 * n_tiles)?;
+            let mut texels: Vec<Float> = vec![0.0 as Float; n_pixels * n_channels];
+            for tile in 0..n_tiles {
+                let fdh: u32 = u32::from_le_bytes([
+                    tile_headers[4 * tile],
+                    tile_headers[4 * tile + 1],
+                    tile_headers[4 * tile + 2],
+                    tile_headers[4 * tile + 3],
+                ]);
+                let block_size: usize = (fdh & 0x3fff_ffff) as usize;
+                let tile_encoding: u32 = fdh >> 30;
+                if tile_encoding == PTEX_ENC_TILED {
+                    return Err(invalid_data("nested tiles"));
+                }
+                let block_start: u64 = reader.position();
+                let tile_texels: Vec<Float> = read_face_data(
+                    reader,
+                    tile_res_u,
+                    tile_res_v,
+                    tile_encoding,
+                    block_size,
+                    data_type,
+                    n_channels,
+                )?;
+                reader.seek(SeekFrom::Start(block_start + block_size as u64))?;
+                // copy tile into face
+                let u0: usize = (tile % n_tiles_u) * tile_res_u;
+                let v0: usize = (tile / n_tiles_u) * tile_res_v;
+                for v in 0..tile_res_v {
+                    let src: usize = v * tile_res_u * n_channels;
+                    let dst: usize = ((v0 + v) * res_u + u0) * n_channels;
+                    texels[dst..dst + tile_res_u * n_channels]
+                        .copy_from_slice(&tile_texels[src..src + tile_res_u * n_channels]);
+                }
+            }
+            Ok(texels)
+        }
+        _ => Err(invalid_data(&format!("unknown face encoding {}", encoding))),
+    }
+}
+
+/// Undoes the difference encoding (only used for integer data types).
+fn decode_difference(data: &mut [u8], data_type: PtexDataType) {
+    match data_type {
+        PtexDataType::UInt8 => {
+            let mut prev: u8 = 0;
+            for value in data.iter_mut() {
+                *value = value.wrapping_add(prev);
+                prev = *value;
+            }
+        }
+        PtexDataType::UInt16 => {
+            let mut prev: u16 = 0;
+            for pair in data.chunks_exact_mut(2) {
+                let value: u16 = u16::from_le_bytes([pair[0], pair[1]]).wrapping_add(prev);
+                pair.copy_from_slice(&value.to_le_bytes());
+                prev = value;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// MIP pyramid of a Ptex triangle face. The texels of the upright
+/// and inverted sub-triangles are stored in the lower left and
+/// (mirrored) upper right half of a square grid.
+struct PtexTriangleMipMap {
+    /// (resolution, texels) per level, the highest resolution first
+    pyramid: Vec<(usize, Vec<RGBSpectrum>)>,
+}
+
+impl PtexTriangleMipMap {
+    fn new(res: usize, texels: Vec<RGBSpectrum>) -> PtexTriangleMipMap {
+        let mut pyramid: Vec<(usize, Vec<RGBSpectrum>)> = vec![(res, texels)];
+        while pyramid[pyramid.len() - 1].0 > 1 {
+            // each coarser sub-triangle covers four finer ones
+            let (res, texels) = &pyramid[pyramid.len() - 1];
+            let res: usize = *res;
+            let upright = |u: usize, v: usize| -> RGBSpectrum { texels[v * res + u] };
+            let inverted =
+                |u: usize, v: usize| -> RGBSpectrum { texels[(res - 1 - u) * res + res - 1 - v] };
+            let res2: usize = res / 2;
+            let mut texels2: Vec<RGBSpectrum> = vec![RGBSpectrum::default(); res2 * res2];
+            for v in 0..res2 {
+                for u in 0..(res2 - v) {
+                    let (u1, v1): (usize, usize) = (2 * u, 2 * v);
+                    texels2[v * res2 + u] = (upright(u1, v1)
+                        + inverted(u1, v1)
+                        + upright(u1 + 1, v1)
+                        + upright(u1, v1 + 1))
+                        * 0.25 as Float;
+                    if u + v + 2 <= res2 {
+                        texels2[(res2 - 1 - u) * res2 + res2 - 1 - v] = (inverted(u1 + 1, v1)
+                            + inverted(u1, v1 + 1)
+                            + upright(u1 + 1, v1 + 1)
+                            + inverted(u1 + 1, v1 + 1))
+                            * 0.25 as Float;
+                    }
+                }
+            }
+            pyramid.push((res2, texels2));
+        }
+        PtexTriangleMipMap { pyramid }
+    }
+    fn levels(&self) -> usize {
+        self.pyramid.len()
+    }
+    /// Returns the sub-triangle of the given level containing $(u,v)$.
+    fn texel(&self, level: usize, uv: Point2f) -> RGBSpectrum {
+        let (res, texels) = &self.pyramid[level];
+        let res: usize = *res;
+        let res_f: Float = res as Float;
+        let mut u: Float = clamp_t(uv.x, 0.0 as Float, 1.0 as Float);
+        let mut v: Float = clamp_t(uv.y, 0.0 as Float, 1.0 as Float);
+        if u + v > 1.0 as Float {
+            // project onto the triangle
+            let inv_sum: Float = 1.0 as Float / (u + v);
+            u *= inv_sum;
+            v *= inv_sum;
+        }
+        u *= res_f;
+        v *= res_f;
+        let ui: usize = (u.floor() as usize).min(res - 1);
+        let vi: usize = (v.floor() as usize).min(res - 1 - ui);
+        if (u - ui as Float) + (v - vi as Float) <= 1.0 as Float || ui + vi + 1 >= res {
+            texels[vi * res + ui]
+        } else {
+            texels[(res - 1 - ui) * res + res - 1 - vi]
+        }
+    }
+    /// Interpolates between the two levels matching the filter
+    /// _width_ (see **MipMap::lookup_pnt_flt()**).
+    fn lookup(&self, uv: Point2f, width: Float) -> RGBSpectrum {
+        let level: Float = self.levels() as Float - 1.0 as Float + width.max(1e-8 as Float).log2();
+        if level < 0.0 as Float {
+            self.texel(0_usize, uv)
+        } else if level >= self.levels() as Float - 1.0 as Float {
+            self.texel(self.levels() - 1, uv)
+        } else {
+            let i_level: usize = level.floor() as usize;
+            let delta: Float = level - i_level as Float;
+            lerp(
+                delta,
+                self.texel(i_level, uv),
+                self.texel(i_level + 1_usize, uv),
+            )
+        }
+    }
+}
+
+enum PtexFace {
+    /// quad faces are filtered by a (clamped) _MipMap_ per face
+    Quad(Box<MipMap<RGBSpectrum>>),
+    /// triangle faces are filtered by their own pyramid
+    Triangle(Box<PtexTriangleMipMap>),
+}
+
+/// Per-face textures (see http://ptex.us). The face is selected by
+/// the face index of the _SurfaceInteraction_ (e.g. from the
+/// "faceIndices" of a triangle mesh), the $(u,v)$ coordinates are
+/// used within the face and the ray differentials pick the filter
+/// width. The texels are kept as RGB values, like in pbrt-v3 gamma
+/// (and the conversion into a _Spectrum_) gets applied to the
+/// filtered value.
+pub struct PtexTexture<T> {
+    pub filename: String,
+    pub gamma: Float,
+    faces: Vec<PtexFace>,
+    convert: fn(&Spectrum) -> T,
+}
+
+impl<T> PtexTexture<T> {
+    pub fn new(
+        filename: String,
+        gamma: Float,
+        do_trilinear: bool,
+        max_aniso: Float,
+        convert: fn(&Spectrum) -> T,
+    ) -> PtexTexture<T> {
+        let mut faces: Vec<PtexFace> = Vec::new();
+        match PtexFile::open(&filename) {
+            Ok(ptex) => {
+                if ptex.n_channels != 1 && ptex.n_channels != 3 && ptex.n_channels != 4 {
+                    println!(
+                        "WARNING: {}: Ptex file has {} channels, using the first one(s).",
+                        filename, ptex.n_channels
+                    );
+                }
+                let nc: usize = ptex.n_channels;
+                for face in ptex.faces.iter() {
+                    let texels: Vec<RGBSpectrum> = face
+                        .texels
+                        .chunks(nc)
+                        .map(|c| {
+                            let mut rgb: [Float; 3] = [c[0]; 3];
+                            if nc >= 3 {
+                                rgb.copy_from_slice(&c[0..3]);
+                            }
+                            RGBSpectrum::from_rgb(&rgb)
+                        })
+                        .collect();
+                    if ptex.triangles {
+                        faces.push(PtexFace::Triangle(Box::new(PtexTriangleMipMap::new(
+                            face.res_u, texels,
+                        ))));
+                    } else {
+                        faces.push(PtexFace::Quad(Box::new(MipMap::new(
+                            Point2i {
+                                x: face.res_u as i32,
+                                y: face.res_v as i32,
+                            },
+                            &texels[..],
+                            do_trilinear,
+                            max_aniso,
+                            ImageWrap::Clamp,
+                        ))));
+                    }
+                }
+            }
+            Err(e) => {
+                println!("ERROR: Unable to read Ptex file {:?}: {}", filename, e);
+            }
+        }
+        PtexTexture {
+            filename,
+            gamma,
+            faces,
+            convert,
+        }
+    }
+}
+
+impl<T> Texture<T> for PtexTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        if si.face_index < 0 || si.face_index as usize >= self.faces.len() {
+            return (self.convert)(&Spectrum::default());
+        }
+        let mut dstdx: Vector2f = Vector2f {
+            x: si.dudx.get(),
+            y: si.dvdx.get(),
+        };
+        let mut dstdy: Vector2f = Vector2f {
+            x: si.dudy.get(),
+            y: si.dvdy.get(),
+        };
+        let filtered: RGBSpectrum = match &self.faces[si.face_index as usize] {
+            PtexFace::Quad(mipmap) => mipmap.lookup_pnt_vec_vec(si.uv, &mut dstdx, &mut dstdy),
+            PtexFace::Triangle(mipmap) => {
+                let width: Float = dstdx
+                    .x
+                    .abs()
+                    .max(dstdx.y.abs())
+                    .max(dstdy.x.abs().max(dstdy.y.abs()));
+                mipmap.lookup(si.uv, width)
+            }
+        };
+        // convert the filtered RGB value only once
+        let mut rgb: [Float; 3] = [0.0 as Float; 3];
+        filtered.to_rgb(&mut rgb);
+        if self.gamma != 1.0 as Float {
+            for value in rgb.iter_mut() {
+                if *value >= 0.0 as Float && *value <= 1.0 as Float {
+                    *value = value.powf(self.gamma);
+                }
+            }
+        }
+        (self.convert)(&Spectrum::from_rgb(&rgb))
+    }
+}
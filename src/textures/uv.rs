@@ -0,0 +1,39 @@
+// pbrt
+use crate::core::geometry::{Point2f, Vector2f};
+use crate::core::interaction::SurfaceInteraction;
+use crate::core::pbrt::{Float, Spectrum};
+use crate::core::texture::{Texture, TextureMapping2D};
+
+// see uv.h
+
+/// Visualizes the texture coordinates: the fractional parts of $s$
+/// and $t$ are returned as red and green. The **Float** version
+/// returns the luminance of that color.
+pub struct UVTexture {
+    pub mapping: Box<TextureMapping2D>,
+}
+
+impl UVTexture {
+    pub fn new(mapping: Box<TextureMapping2D>) -> Self {
+        UVTexture { mapping }
+    }
+    fn rgb(&self, si: &SurfaceInteraction) -> Spectrum {
+        let mut dstdx: Vector2f = Vector2f::default();
+        let mut dstdy: Vector2f = Vector2f::default();
+        let st: Point2f = self.mapping.map(si, &mut dstdx, &mut dstdy);
+        let rgb: [Float; 3] = [st.x - st.x.floor(), st.y - st.y.floor(), 0.0 as Float];
+        Spectrum::from_rgb(&rgb)
+    }
+}
+
+impl Texture<Spectrum> for UVTexture {
+    fn evaluate(&self, si: &SurfaceInteraction) -> Spectrum {
+        self.rgb(si)
+    }
+}
+
+impl Texture<Float> for UVTexture {
+    fn evaluate(&self, si: &SurfaceInteraction) -> Float {
+        self.rgb(si).y()
+    }
+}